@@ -7,9 +7,11 @@ use std::time::Duration;
 use crate::maailma::kappale::Kappale;
 use crate::maailma::kappale::Muoto::*;
 use crate::maailma::kappale::Tagi::*;
+use crate::maailma::luku::luku;
 use crate::maailma::vektori::Vektori;
 use crate::paivitys::Paivitysaika;
-use crate::piirtaja::{Piirtotapa, ValiaikainenPiirrettavaKappale};
+use crate::piirtaja::{Piirtokerros, Piirtotapa, ValiaikainenPiirrettavaKappale};
+use crate::tiedosto::Tietue;
 
 type Peliaika = Duration;
 
@@ -37,6 +39,11 @@ impl Animaatiot {
         self.animaatiot.push(animaatio);
     }
 
+    /// Antaa kaikki animaatiot kuolinaikoineen
+    pub fn kaikki(&self) -> &[Kuolevainen<Box<Animaatio>>] {
+        &self.animaatiot
+    }
+
     /// Päivittää kaikkien animaatioiden tilaa luoden tarvittavan graafisen esityksen valmiiksi.
     /// Tarvittaessa myös tuhoaa kaikki vanhentuneet animaatiot.
     /// # Arguments
@@ -70,6 +77,13 @@ pub trait Animaatio {
     /// Antaa animaation aloitushetken esittäen sen pelin käynnistymisestä kuluneessa ajasta eli kuinka
     /// paljon aikaa on kulunut pelin käynnistymisestä.
     fn animaation_alku(&self) -> &Peliaika;
+    /// Antaa animaation lajin nimen, jolla animaatio tunnistetaan tallennettaessa ja ladattaessa
+    fn nimi(&self) -> &'static str;
+    /// Tallentaa animaation tiedot annettuun tietueeseen. Tiedot luetaan latauksessa
+    /// takaisin lajin nimellä rekisteröidyn lataajan avulla.
+    /// # Arguments
+    /// * `tietue` - Tietue, johon tiedot tallennetaan
+    fn tallenna(&self, tietue: &mut Tietue);
 }
 
 /// Animaatio, joka animoi laatikon, joka muuttuu alkukoosta loppukooksi annetulla aikavälillä
@@ -119,6 +133,20 @@ impl KatoamisAnimaatio {
     pub fn sijainti_mut(&mut self) -> &mut Vektori {
         &mut self.sijainti
     }
+
+    /// Lataa katoamisanimaation tietueesta
+    /// # Arguments
+    /// * `tietue` - Tietue, johon animaatio on tallennettu
+    pub fn lataa(tietue: &Tietue) -> Result<Box<dyn Animaatio>, String> {
+        Ok(Box::new(KatoamisAnimaatio::new(
+            tietue.vektori("sijainti")?,
+            tietue.kesto("alku")?,
+            tietue.arvo("alkukoko")?,
+            tietue.arvo("loppukoko")?,
+            tietue.kesto("kesto")?,
+            tietue.vari("vari")?,
+        )))
+    }
 }
 
 impl Animaatio for KatoamisAnimaatio {
@@ -137,9 +165,9 @@ impl Animaatio for KatoamisAnimaatio {
             frame_sekunteina,
         );
         //println!("{:?} {:?}", koko, frame_sekunteina);
-        let a = ValiaikainenPiirrettavaKappale::new(
+        let a = ValiaikainenPiirrettavaKappale::new_kerroksella(
             Kappale::new_keskipisteella(
-                Nelio(koko, koko),
+                Nelio(luku(koko), luku(koko)),
                 self.sijainti.x,
                 self.sijainti.y,
                 Partikkeli,
@@ -147,6 +175,7 @@ impl Animaatio for KatoamisAnimaatio {
             Piirtotapa::Yksivarinen {
                 vari: self.kappaleen_vari,
             },
+            Piirtokerros::Efektit,
         );
 
         palat.push(a);
@@ -157,6 +186,19 @@ impl Animaatio for KatoamisAnimaatio {
     fn animaation_alku(&self) -> &Peliaika {
         &self.animaation_alku
     }
+
+    fn nimi(&self) -> &'static str {
+        "katoaminen"
+    }
+
+    fn tallenna(&self, tietue: &mut Tietue) {
+        tietue.lisaa_vektori("sijainti", self.sijainti);
+        tietue.lisaa_kesto("alku", self.animaation_alku);
+        tietue.lisaa("alkukoko", self.alkukoko);
+        tietue.lisaa("loppukoko", self.loppukoko);
+        tietue.lisaa_kesto("kesto", self.muutoksen_kesto);
+        tietue.lisaa_vari("vari", self.kappaleen_vari);
+    }
 }
 
 /// Animaation ammusten tuhoutumiselle
@@ -201,6 +243,19 @@ impl AmmusAnimaatio {
     pub fn sijainti_mut(&mut self) -> &mut Vektori {
         &mut self.sijainti
     }
+
+    /// Lataa ammusanimaation tietueesta
+    /// # Arguments
+    /// * `tietue` - Tietue, johon animaatio on tallennettu
+    pub fn lataa(tietue: &Tietue) -> Result<Box<dyn Animaatio>, String> {
+        Ok(Box::new(AmmusAnimaatio::new(
+            tietue.vektori("sijainti")?,
+            tietue.kesto("alku")?,
+            tietue.vektori("suunta")?,
+            tietue.kesto("kesto")?,
+            tietue.vari("vari")?,
+        )))
+    }
 }
 
 impl Animaatio for AmmusAnimaatio {
@@ -225,11 +280,12 @@ impl Animaatio for AmmusAnimaatio {
         let koko =
             lineaarinen_interpolaatio(0.0, 10.0, muutoksen_kesto_sekunteina, 0.1, frame_sekunteina);
 
-        let a = ValiaikainenPiirrettavaKappale::new(
-            Kappale::new_keskipisteella(Nelio(koko, koko), sijainti.x, sijainti.y, Partikkeli),
+        let a = ValiaikainenPiirrettavaKappale::new_kerroksella(
+            Kappale::new_keskipisteella(Nelio(luku(koko), luku(koko)), sijainti.x, sijainti.y, Partikkeli),
             Piirtotapa::Yksivarinen {
                 vari: self.kappaleen_vari,
             },
+            Piirtokerros::Efektit,
         );
         palat.push(a);
     }
@@ -239,6 +295,18 @@ impl Animaatio for AmmusAnimaatio {
     fn animaation_alku(&self) -> &Peliaika {
         &self.animaation_alku
     }
+
+    fn nimi(&self) -> &'static str {
+        "ammus"
+    }
+
+    fn tallenna(&self, tietue: &mut Tietue) {
+        tietue.lisaa_vektori("sijainti", self.sijainti);
+        tietue.lisaa_kesto("alku", self.animaation_alku);
+        tietue.lisaa_vektori("suunta", self.suunta);
+        tietue.lisaa_kesto("kesto", self.muutoksen_kesto);
+        tietue.lisaa_vari("vari", self.kappaleen_vari);
+    }
 }
 
 /// Sisältää jotakin joka kuolee, annettuna ajanhetkenä.
@@ -268,6 +336,11 @@ impl<T> Kuolevainen<T> {
     pub fn kuoleeko(&self, peliaika: &Peliaika) -> bool {
         self.kuolin_aika <= *peliaika
     }
+
+    /// Antaa ajanhetken, jolloin sisältö tulee poistaa
+    pub fn kuolin_aika(&self) -> Peliaika {
+        self.kuolin_aika
+    }
 }
 
 impl<T> Deref for Kuolevainen<T> {
@@ -2,12 +2,22 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use std::time::Duration;
 
-use super::maailma::kappale::{Kappale, Muoto, Tagi};
+use super::laajavaihe::{KaikkiParit, Laajavaihe, Pyyhkaisyvaihe};
+use super::maailma::kappale::{Kappale, Muoto, Rajaus, Tagi};
+use super::maailma::luku::{luku, sekunteina, Luku, Skalaari};
+use super::maailma::tagit::Tagit;
 use super::maailma::vektori::Vektori;
 use crate::maailma::Lisaosa;
 
 type RcKappale = Rc<RefCell<Kappale>>;
 
+/// Kuinka monta kertaa päällekkäiset kappaleet erotetaan päivityksessä. Useampi kierros
+/// erottaa myös kulmiin ja useamman kappaleen väliin jääneet kappaleet.
+const EROTTELUKIERROKSIA: usize = 4;
+
+/// Kuinka monta kertaa nopean kappaleen osumahetki puolitetaan osuman löydyttyä
+const OSUMAHETKEN_PUOLITUKSIA: usize = 10;
+
 /// Kohde, jolle voidaan laskea fysiikkaan liittyviä laskuja.
 pub trait Fysiikallinen {
     /// Antaa kohteen nopeuden
@@ -32,7 +42,177 @@ pub trait Fysiikallinen {
     /// Laskee kohteen uuden sijainnin ja palauttaa sen
     /// # Arguments
     /// * `paivitysaika` - Aika, jonka verran kappaleen sijaintia päivitetään
-    fn laske_uusi_sijainti(&self, paivitysaika: &Duration) -> Vektori<f32>;
+    fn laske_uusi_sijainti(&self, paivitysaika: &Duration) -> Vektori;
+
+    /// Antaa kohteen kulmanopeuden radiaaneina sekunnissa
+    fn anna_kulmanopeus(&self) -> Luku;
+
+    /// Asettaa kohteen kulmanopeuden
+    /// # Arguments
+    /// * `kulmanopeus` - Kohteen uusi kulmanopeus radiaaneina sekunnissa
+    fn aseta_kulmanopeus(&mut self, kulmanopeus: Luku);
+
+    /// Antaa kohteen asennon radiaaneina
+    fn anna_kulma(&self) -> Luku;
+
+    /// Asettaa kohteen asennon
+    /// # Arguments
+    /// * `kulma` - Kohteen uusi asento radiaaneina
+    fn aseta_kulma(&mut self, kulma: Luku);
+
+    /// Laskee kohteen uuden asennon ja palauttaa sen
+    /// # Arguments
+    /// * `paivitysaika` - Aika, jonka verran kappaleen asentoa päivitetään
+    fn laske_uusi_kulma(&self, paivitysaika: &Duration) -> Luku;
+
+    /// Antaa kohteen massan
+    fn anna_massa(&self) -> Luku;
+
+    /// Asettaa kohteen massan. Painavampi kohde siirtyy törmäyksissä vähemmän.
+    /// # Arguments
+    /// * `massa` - Kohteen uusi massa. Pitää olla positiivinen.
+    fn aseta_massa(&mut self, massa: Luku);
+
+    /// Antaa kohteen kimmoisuuden
+    fn anna_kimmoisuus(&self) -> Luku;
+
+    /// Asettaa kohteen kimmoisuuden eli kuinka suuri osa törmäysnopeudesta säilyy kimmotessa
+    /// # Arguments
+    /// * `kimmoisuus` - Kohteen uusi kimmoisuus väliltä 0..1
+    fn aseta_kimmoisuus(&mut self, kimmoisuus: Luku);
+
+    /// Antaa kohteen kitkakertoimen
+    fn anna_kitka(&self) -> Luku;
+
+    /// Asettaa kohteen kitkakertoimen, joka jarruttaa pintaa pitkin liukumista törmäyksissä
+    /// # Arguments
+    /// * `kitka` - Kohteen uusi kitkakerroin
+    fn aseta_kitka(&mut self, kitka: Luku);
+
+    /// Antaa kohteeseen seuraavassa fysiikan päivityksessä vaikuttavien voimien summan
+    fn anna_voima(&self) -> Vektori;
+
+    /// Lisää kohteeseen voiman, joka vaikuttaa seuraavan fysiikan päivityksen ajan
+    /// # Arguments
+    /// * `voima` - Lisättävä voima
+    fn lisaa_voima(&mut self, voima: Vektori);
+
+    /// Poistaa kohteeseen lisätyt voimat. Kutsutaan, kun voimat on otettu huomioon.
+    fn nollaa_voima(&mut self);
+
+    /// Antaa kohteen vaimennuksen
+    fn anna_vaimennus(&self) -> Luku;
+
+    /// Asettaa kohteen vaimennuksen eli kuinka nopeasti kohteen nopeus hiipuu itsestään
+    /// # Arguments
+    /// * `vaimennus` - Kohteen uusi vaimennus sekunnin käänteislukuna
+    fn aseta_vaimennus(&mut self, vaimennus: Luku);
+
+    /// Antaa kohteen suurimman sallitun nopeuden, jos sellainen on
+    fn anna_maksiminopeus(&self) -> Option<Luku>;
+
+    /// Asettaa kohteen suurimman sallitun nopeuden
+    /// # Arguments
+    /// * `maksiminopeus` - Suurin sallittu nopeus tai ei mitään, jos nopeutta ei rajata
+    fn aseta_maksiminopeus(&mut self, maksiminopeus: Option<Luku>);
+
+    /// Laskee kohteen uuden nopeuden voimien, vaimennuksen ja suurimman nopeuden mukaan ja
+    /// palauttaa sen
+    /// # Arguments
+    /// * `paivitysaika` - Aika, jonka verran kappaleen nopeutta päivitetään
+    fn laske_uusi_nopeus(&self, paivitysaika: &Duration) -> Vektori;
+}
+
+/// Kertoo, miten fysiikka saa liikuttaa kappaletta
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Liikkuvuus {
+    /// Kappale ei liiku lainkaan, eikä sen törmäyksiä toisiin staattisiin tarkisteta. Esim. seinät.
+    Staattinen,
+    /// Kappale liikkuu nopeutensa mukaan, mutta törmäykset eivät siirrä sitä. Voimat eivät
+    /// vaikuta siihen, mutta sitä voi ohjata tavoitenopeudella.
+    Kinemaattinen,
+    /// Kappale liikkuu nopeutensa mukaan, ja törmäykset siirtävät sitä
+    #[default]
+    Dynaaminen,
+}
+
+/// Tapa, jolla fysiikka laskee kappaleiden uudet nopeudet ja sijainnit
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Integrointi {
+    /// Nopeus päivitetään ensin ja sijainti uudella nopeudella. Yksinkertainen ja vakaa.
+    #[default]
+    SemiimplisiittinenEuler,
+    /// Sijainti päivitetään vanhan ja uuden nopeuden keskiarvolla (nopeus-Verlet). Tarkempi
+    /// kiihtyvässä liikkeessä.
+    Verlet,
+}
+
+/// Törmäysmatriisi, joka kertoo, mitkä törmäyskerrokset törmäävät toisiinsa. Kerroksina
+/// käytetään tageja. Matriisi on symmetrinen, ja oletuksena kaikki kerrokset törmäävät.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Tormaysmatriisi {
+    /// Kunkin kerroksen rivi eli kerrokset, joihin se törmää, tagin järjestysnumeron mukaan
+    rivit: [Tagit; 64],
+}
+
+impl Default for Tormaysmatriisi {
+    fn default() -> Self {
+        Tormaysmatriisi {
+            rivit: [Tagit::kaikki(); 64],
+        }
+    }
+}
+
+impl Tormaysmatriisi {
+    /// Luo matriisin, jossa kaikki kerrokset törmäävät toisiinsa
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Asettaa, törmäävätkö kaksi kerrosta toisiinsa
+    /// # Arguments
+    /// * `kerros_a` - Ensimmäinen kerros
+    /// * `kerros_b` - Toinen kerros
+    /// * `tormaavat` - Törmäävätkö kerrokset
+    pub fn aseta(&mut self, kerros_a: Tagi, kerros_b: Tagi, tormaavat: bool) {
+        let (a, b) = (kerros_a.bitti() as usize, kerros_b.bitti() as usize);
+        if tormaavat {
+            self.rivit[a].lisaa(kerros_b);
+            self.rivit[b].lisaa(kerros_a);
+        } else {
+            self.rivit[a].poista(kerros_b);
+            self.rivit[b].poista(kerros_a);
+        }
+    }
+
+    /// Asettaa kerroksen koko rivin eli kerrokset, joihin se törmää. Muihin kerroksiin
+    /// kerros ei törmää.
+    /// # Arguments
+    /// * `kerros` - Kerros, jonka rivi asetetaan
+    /// * `kerrokset` - Kerrokset, joihin kerros törmää
+    pub fn aseta_rivi(&mut self, kerros: Tagi, kerrokset: Tagit) {
+        for toinen in Tagit::kaikki().iter() {
+            self.aseta(kerros, toinen, kerrokset.sisaltaa(toinen));
+        }
+    }
+
+    /// Antaa kerrokset, joihin annettu kerros törmää
+    /// # Arguments
+    /// * `kerros` - Kerros, jonka rivi annetaan
+    pub fn rivi(&self, kerros: Tagi) -> Tagit {
+        self.rivit[kerros.bitti() as usize]
+    }
+
+    /// Törmäävätkö annettujen kerrosjoukkojen kappaleet toisiinsa. Riittää, että yksikin
+    /// kerrospari törmää.
+    /// # Arguments
+    /// * `kerrokset_a` - Ensimmäisen kappaleen kerrokset
+    /// * `kerrokset_b` - Toisen kappaleen kerrokset
+    pub fn tormaavat(&self, kerrokset_a: Tagit, kerrokset_b: Tagit) -> bool {
+        kerrokset_a
+            .iter()
+            .any(|x| self.rivi(x).leikkaa(kerrokset_b))
+    }
 }
 
 /// Fysiikka lisäosa tavalliselle kappaleelle. Kertoo käytännössä, että
@@ -42,6 +222,31 @@ pub struct Fysiikkakappale {
     kappale: RcKappale,
     /// Kappaleen nopeus ja suunta
     nopeus: Vektori,
+    /// Kappaleen kulmanopeus radiaaneina sekunnissa
+    kulmanopeus: Luku,
+    /// Tarkistetaanko kappaleen törmäykset koko sen kulkemalta matkalta, jotta se ei mene
+    /// ohuiden kappaleiden läpi suurellakaan nopeudella
+    nopea: bool,
+    /// Liikkuuko kappale ja siirtävätkö törmäykset sitä
+    liikkuvuus: Liikkuvuus,
+    /// Kappaleen massa
+    massa: Luku,
+    /// Kuinka suuri osa törmäysnopeudesta säilyy kimmotessa
+    kimmoisuus: Luku,
+    /// Kitkakerroin, joka jarruttaa pintaa pitkin liukumista törmäyksissä
+    kitka: Luku,
+    /// Seuraavassa päivityksessä vaikuttavien voimien summa
+    voima: Vektori,
+    /// Kuinka nopeasti nopeus hiipuu itsestään sekunnin käänteislukuna
+    vaimennus: Luku,
+    /// Suurin sallittu nopeus
+    maksiminopeus: Option<Luku>,
+    /// Nopeus, jota kohti kappale kiihtyy, ja suurin kiihtyvyys, jolla se kiihtyy
+    tavoitenopeus: Option<(Vektori, Luku)>,
+    /// Törmäyskerrokset, joissa kappale on. Oletuksena kappaleen tagit.
+    kerrokset: Option<Tagit>,
+    /// Kerrokset, joiden kappaleisiin kappale voi törmätä
+    maski: Tagit,
 }
 
 impl Fysiikkakappale {
@@ -53,12 +258,102 @@ impl Fysiikkakappale {
         Fysiikkakappale {
             kappale: kappale,
             nopeus: nopeus,
+            kulmanopeus: Luku::default(),
+            nopea: false,
+            liikkuvuus: Liikkuvuus::default(),
+            massa: luku(1.0),
+            kimmoisuus: Luku::default(),
+            kitka: Luku::default(),
+            voima: Vektori::default(),
+            vaimennus: Luku::default(),
+            maksiminopeus: None,
+            tavoitenopeus: None,
+            kerrokset: None,
+            maski: Tagit::kaikki(),
         }
     }
 
-    /// Antaa fysiikkakappaleen tagin
-    pub fn anna_tagi(&self) -> Tagi {
-        self.kappale.borrow().tagi
+    /// Antaa fysiikkakappaleen tagit
+    pub fn anna_tagit(&self) -> Tagit {
+        self.kappale.borrow().tagit
+    }
+
+    /// Onko kappale nopea eli tarkistetaanko sen törmäykset koko kuljetulta matkalta
+    pub fn onko_nopea(&self) -> bool {
+        self.nopea
+    }
+
+    /// Asettaa kappaleen nopeaksi tai tavalliseksi. Nopean kappaleen törmäykset tarkistetaan
+    /// koko päivityksen aikana kuljetulta matkalta, ja se pysähtyy ensimmäiseen osumaan.
+    /// # Arguments
+    /// * `nopea` - Onko kappale nopea
+    pub fn aseta_nopea(&mut self, nopea: bool) {
+        self.nopea = nopea;
+    }
+
+    /// Antaa kappaleen liikkuvuuden
+    pub fn anna_liikkuvuus(&self) -> Liikkuvuus {
+        self.liikkuvuus
+    }
+
+    /// Asettaa kappaleen liikkuvuuden
+    /// # Arguments
+    /// * `liikkuvuus` - Liikkuuko kappale ja siirtävätkö törmäykset sitä
+    pub fn aseta_liikkuvuus(&mut self, liikkuvuus: Liikkuvuus) {
+        self.liikkuvuus = liikkuvuus;
+    }
+
+    /// Asettaa nopeuden, jota kohti kappale kiihtyy jokaisessa fysiikan päivityksessä enintään
+    /// annetulla kiihtyvyydellä. Kiihtyvyys lasketaan fysiikan päivitysajalla, joten liike on
+    /// sama päivitysnopeudesta ja tavoitteen asettamiskerroista riippumatta.
+    /// # Arguments
+    /// * `nopeus` - Nopeus, jota kohti kappale kiihtyy
+    /// * `kiihtyvyys` - Suurin kiihtyvyys, jolla kappale kiihtyy tai jarruttaa
+    pub fn aseta_tavoitenopeus(&mut self, nopeus: Vektori, kiihtyvyys: Luku) {
+        self.tavoitenopeus = Some((nopeus, kiihtyvyys));
+    }
+
+    /// Antaa nopeuden, jota kohti kappale kiihtyy, ja suurimman kiihtyvyyden, jos ne on asetettu
+    pub fn anna_tavoitenopeus(&self) -> Option<(Vektori, Luku)> {
+        self.tavoitenopeus
+    }
+
+    /// Poistaa kappaleen tavoitenopeuden, jolloin kappaleen nopeutta muuttavat vain voimat
+    pub fn poista_tavoitenopeus(&mut self) {
+        self.tavoitenopeus = None;
+    }
+
+    /// Antaa kappaleen törmäyskerrokset. Jos kerroksia ei ole asetettu, kappale on
+    /// tagiensa kerroksissa.
+    pub fn anna_kerrokset(&self) -> Tagit {
+        self.kerrokset.unwrap_or_else(|| self.anna_tagit())
+    }
+
+    /// Asettaa kappaleen törmäyskerrokset
+    /// # Arguments
+    /// * `kerrokset` - Kerrokset, joissa kappale on
+    pub fn aseta_kerrokset(&mut self, kerrokset: Tagit) {
+        self.kerrokset = Some(kerrokset);
+    }
+
+    /// Antaa kerrokset, joiden kappaleisiin kappale voi törmätä
+    pub fn anna_maski(&self) -> Tagit {
+        self.maski
+    }
+
+    /// Asettaa kerrokset, joiden kappaleisiin kappale voi törmätä. Oletuksena kaikki.
+    /// # Arguments
+    /// * `maski` - Kerrokset, joihin kappale voi törmätä
+    pub fn aseta_maski(&mut self, maski: Tagit) {
+        self.maski = maski;
+    }
+
+    /// Sallivatko kappaleiden kerrokset ja maskit niiden törmätä toisiinsa. Kummankin
+    /// maskin pitää sisältää jokin toisen kappaleen kerroksista.
+    /// # Arguments
+    /// * `toinen` - Kappale, johon verrataan
+    pub fn voi_tormata(&self, toinen: &Fysiikkakappale) -> bool {
+        self.maski.leikkaa(toinen.anna_kerrokset()) && toinen.maski.leikkaa(self.anna_kerrokset())
     }
 }
 
@@ -102,8 +397,141 @@ impl Fysiikallinen for Fysiikkakappale {
     /// Laskee kappaleen uuden sijainnin annetun ajan mukaan
     /// # Arguments
     /// * `paivitysaika` - Päivityksessä käytettävä aika
-    fn laske_uusi_sijainti(&self, paivitysaika: &Duration) -> Vektori<f32> {
-        self.anna_sijainti() + self.anna_nopeus() * (paivitysaika.as_micros() as f32 * 0.000_001)
+    fn laske_uusi_sijainti(&self, paivitysaika: &Duration) -> Vektori {
+        self.anna_sijainti() + self.anna_nopeus() * sekunteina(paivitysaika)
+    }
+
+    /// Antaa kohteen kulmanopeuden radiaaneina sekunnissa
+    fn anna_kulmanopeus(&self) -> Luku {
+        self.kulmanopeus
+    }
+
+    /// Asettaa kohteen kulmanopeuden
+    /// # Arguments
+    /// * `kulmanopeus` - Kohteen uusi kulmanopeus radiaaneina sekunnissa
+    fn aseta_kulmanopeus(&mut self, kulmanopeus: Luku) {
+        self.kulmanopeus = kulmanopeus;
+    }
+
+    /// Antaa kohteen asennon radiaaneina
+    fn anna_kulma(&self) -> Luku {
+        self.kappale.borrow().kulma()
+    }
+
+    /// Asettaa kohteen asennon
+    /// # Arguments
+    /// * `kulma` - Kohteen uusi asento radiaaneina
+    fn aseta_kulma(&mut self, kulma: Luku) {
+        self.kappale.borrow_mut().aseta_kulma(kulma);
+    }
+
+    /// Laskee kappaleen uuden asennon annetun ajan mukaan
+    /// # Arguments
+    /// * `paivitysaika` - Päivityksessä käytettävä aika
+    fn laske_uusi_kulma(&self, paivitysaika: &Duration) -> Luku {
+        self.anna_kulma() + self.anna_kulmanopeus() * sekunteina(paivitysaika)
+    }
+
+    /// Antaa kohteen massan
+    fn anna_massa(&self) -> Luku {
+        self.massa
+    }
+
+    /// Asettaa kohteen massan
+    /// # Arguments
+    /// * `massa` - Kohteen uusi massa. Pitää olla positiivinen.
+    fn aseta_massa(&mut self, massa: Luku) {
+        self.massa = massa;
+    }
+
+    /// Antaa kohteen kimmoisuuden
+    fn anna_kimmoisuus(&self) -> Luku {
+        self.kimmoisuus
+    }
+
+    /// Asettaa kohteen kimmoisuuden
+    /// # Arguments
+    /// * `kimmoisuus` - Kohteen uusi kimmoisuus väliltä 0..1
+    fn aseta_kimmoisuus(&mut self, kimmoisuus: Luku) {
+        self.kimmoisuus = kimmoisuus;
+    }
+
+    /// Antaa kohteen kitkakertoimen
+    fn anna_kitka(&self) -> Luku {
+        self.kitka
+    }
+
+    /// Asettaa kohteen kitkakertoimen
+    /// # Arguments
+    /// * `kitka` - Kohteen uusi kitkakerroin
+    fn aseta_kitka(&mut self, kitka: Luku) {
+        self.kitka = kitka;
+    }
+
+    /// Antaa kohteeseen vaikuttavien voimien summan
+    fn anna_voima(&self) -> Vektori {
+        self.voima
+    }
+
+    /// Lisää kohteeseen voiman
+    /// # Arguments
+    /// * `voima` - Lisättävä voima
+    fn lisaa_voima(&mut self, voima: Vektori) {
+        self.voima += voima;
+    }
+
+    /// Poistaa kohteeseen lisätyt voimat
+    fn nollaa_voima(&mut self) {
+        self.voima = Vektori::default();
+    }
+
+    /// Antaa kohteen vaimennuksen
+    fn anna_vaimennus(&self) -> Luku {
+        self.vaimennus
+    }
+
+    /// Asettaa kohteen vaimennuksen
+    /// # Arguments
+    /// * `vaimennus` - Kohteen uusi vaimennus sekunnin käänteislukuna
+    fn aseta_vaimennus(&mut self, vaimennus: Luku) {
+        self.vaimennus = vaimennus;
+    }
+
+    /// Antaa kohteen suurimman sallitun nopeuden
+    fn anna_maksiminopeus(&self) -> Option<Luku> {
+        self.maksiminopeus
+    }
+
+    /// Asettaa kohteen suurimman sallitun nopeuden
+    /// # Arguments
+    /// * `maksiminopeus` - Suurin sallittu nopeus tai ei mitään
+    fn aseta_maksiminopeus(&mut self, maksiminopeus: Option<Luku>) {
+        self.maksiminopeus = maksiminopeus;
+    }
+
+    /// Laskee kappaleen uuden nopeuden. Voimat vaikuttavat vain dynaamisiin kappaleisiin,
+    /// koska muiden massa on ääretön. Tavoitenopeus, vaimennus ja suurin nopeus vaikuttavat
+    /// myös kinemaattisiin kappaleisiin, joten niitäkin voi ohjata. Staattiset eivät liiku.
+    /// # Arguments
+    /// * `paivitysaika` - Päivityksessä käytettävä aika
+    fn laske_uusi_nopeus(&self, paivitysaika: &Duration) -> Vektori {
+        let aika = sekunteina(paivitysaika);
+        let mut nopeus = match self.liikkuvuus {
+            Liikkuvuus::Staattinen => return self.nopeus,
+            Liikkuvuus::Kinemaattinen => self.nopeus,
+            Liikkuvuus::Dynaaminen => self.nopeus + self.voima * (aika / self.massa),
+        };
+        if let Some((tavoite, kiihtyvyys)) = self.tavoitenopeus {
+            nopeus += (tavoite - nopeus).rajaa_pituus(kiihtyvyys * aika);
+        }
+        if self.vaimennus > 0.0 {
+            // Ei vaihda nopeuden suuntaa pitkälläkään päivitysajalla
+            nopeus /= luku(1.0) + self.vaimennus * aika;
+        }
+        match self.maksiminopeus {
+            Some(maksimi) => nopeus.rajaa_pituus(maksimi),
+            None => nopeus,
+        }
     }
 }
 
@@ -123,25 +551,42 @@ impl Tormaystiedot {
         }
     }
 
-    /// Lisää törmäyksen tägin törmäystietoon ja tarvittaessa luo törmäystiedon
+    /// Lisää törmäyksen tägit törmäystietoon ja tarvittaessa luo törmäystiedon
     /// # Arguments
     /// * `indeksi` - törmänneen kappaleen indeksi
-    /// * `tagi` - Törmätyn kappaleen tagi
-    pub fn lisaa_tormays(&mut self, indeksi: usize, tagi: Tagi) {
+    /// * `kohde` - Törmätyn kappaleen indeksi
+    /// * `tagit` - Törmätyn kappaleen tagit
+    pub fn lisaa_tormays(&mut self, indeksi: usize, kohde: usize, tagit: Tagit) {
+        // Törmäykset lisätään yleensä törmääjän mukaan järjestyksessä, joten etsitään lopusta
         match self
             .tormays_tiedot
             .iter_mut()
+            .rev()
             .find(|x| x.indeksi == indeksi)
         {
-            Some(a) => a.lisaa_tagi(tagi),
+            Some(a) => a.lisaa_kohde(kohde, tagit),
             None => {
                 let mut uusi_tormaystieto = Tormaystieto::new(indeksi);
-                uusi_tormaystieto.lisaa_tagi(tagi);
+                uusi_tormaystieto.lisaa_kohde(kohde, tagit);
                 self.tormays_tiedot.push(uusi_tormaystieto);
             }
         }
     }
 
+    /// Asettaa törmääjän osumahetken, jos törmääjällä on törmäystieto
+    /// # Arguments
+    /// * `indeksi` - Törmänneen kappaleen indeksi
+    /// * `osumahetki` - Aika päivityksen alusta osumaan
+    pub fn aseta_osumahetki(&mut self, indeksi: usize, osumahetki: Duration) {
+        if let Some(tormays) = self
+            .tormays_tiedot
+            .iter_mut()
+            .find(|x| x.indeksi == indeksi)
+        {
+            tormays.osumahetki = Some(osumahetki);
+        }
+    }
+
     /// Antaa tiedot kaikista kerätyistä törmäyksistä
     pub fn anna_tormaykset(&self) -> &[Tormaystieto] {
         &self.tormays_tiedot
@@ -153,8 +598,13 @@ impl Tormaystiedot {
 pub struct Tormaystieto {
     /// Törmänneen kappaleen indeksi
     pub indeksi: usize,
-    /// Lista kohteiden tageista, joihin on törmätty
-    tormatyt_kohteet: Vec<Tagi>,
+    /// Kaikkien kohteiden tagit, joihin on törmätty
+    tormatyt_kohteet: Tagit,
+    /// Kohteiden indeksit, joihin on törmätty
+    kohteet: Vec<usize>,
+    /// Nopean kappaleen osumahetki päivityksen alusta laskettuna. Tavallisille kappaleille
+    /// ei mitään, koska niiden törmäykset tarkistetaan vain päivityksen lopussa.
+    osumahetki: Option<Duration>,
 }
 
 impl Tormaystieto {
@@ -164,38 +614,121 @@ impl Tormaystieto {
     pub fn new(indeksi: usize) -> Self {
         Tormaystieto {
             indeksi: indeksi,
-            tormatyt_kohteet: Vec::new(),
+            tormatyt_kohteet: Tagit::new(),
+            kohteet: Vec::new(),
+            osumahetki: None,
         }
     }
 
-    /// Lisää annetun tagin, jos kyseinen tagi ei jo ole lisätty
+    /// Lisää annetut tagit törmättyjen kohteiden tageihin
     /// # Arguments
-    /// * `lisattava_tagi` - Törmätyn kohteen tagi
-    pub fn lisaa_tagi(&mut self, lisattava_tagi: Tagi) {
-        if !self.tormatyt_kohteet.contains(&lisattava_tagi) {
-            self.tormatyt_kohteet.push(lisattava_tagi)
-        }
+    /// * `lisattavat_tagit` - Törmätyn kohteen tagit
+    pub fn lisaa_tagit(&mut self, lisattavat_tagit: Tagit) {
+        self.tormatyt_kohteet.yhdista(lisattavat_tagit);
+    }
+
+    /// Lisää törmätyn kohteen ja sen tagit
+    /// # Arguments
+    /// * `kohde` - Törmätyn kappaleen indeksi
+    /// * `tagit` - Törmätyn kappaleen tagit
+    pub fn lisaa_kohde(&mut self, kohde: usize, tagit: Tagit) {
+        self.kohteet.push(kohde);
+        self.lisaa_tagit(tagit);
     }
 
     /// Antaa törmättyjen kohteiden tagit
-    pub fn anna_tagit(&self) -> &[Tagi] {
-        &self.tormatyt_kohteet
+    pub fn anna_tagit(&self) -> Tagit {
+        self.tormatyt_kohteet
+    }
+
+    /// Antaa törmättyjen kohteiden indeksit
+    pub fn anna_kohteet(&self) -> &[usize] {
+        &self.kohteet
+    }
+
+    /// Antaa nopean kappaleen osumahetken päivityksen alusta laskettuna
+    pub fn anna_osumahetki(&self) -> Option<Duration> {
+        self.osumahetki
     }
 }
 
 /// Fysiikka otus, joka muistaa jotakin fysiikan päivitysksistä
-#[derive(Default)]
 pub struct Fysiikka {
     /// Viimeisimmän fysiikkapäivityksen aikana tapahtuneet törmäykset
     pub tormaykset: Tormaystiedot,
+    /// Kappaleiden liitosryhmät. Saman ryhmän kappaleet eivät törmää toisiinsa.
+    liitosryhmat: Vec<usize>,
+    /// Laaja vaihe, joka antaa mahdollisesti törmäävät kappaleparit
+    laajavaihe: Box<dyn Laajavaihe>,
+    /// Tapa, jolla uudet nopeudet ja sijainnit lasketaan
+    integrointi: Integrointi,
+    /// Mitkä törmäyskerrokset törmäävät toisiinsa
+    tormaysmatriisi: Tormaysmatriisi,
+}
+
+impl Default for Fysiikka {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Fysiikka {
-    /// Luo uuden fysiikan
+    /// Luo uuden fysiikan, joka etsii törmäysparit pyyhkäisemällä
     pub fn new() -> Self {
         Fysiikka {
             tormaykset: Default::default(),
+            liitosryhmat: Vec::new(),
+            laajavaihe: Box::new(Pyyhkaisyvaihe),
+            integrointi: Integrointi::default(),
+            tormaysmatriisi: Tormaysmatriisi::default(),
+        }
+    }
+
+    /// Asettaa tavan, jolla uudet nopeudet ja sijainnit lasketaan
+    /// # Arguments
+    /// * `integrointi` - Käytettävä integrointitapa
+    pub fn aseta_integrointi(&mut self, integrointi: Integrointi) {
+        self.integrointi = integrointi;
+    }
+
+    /// Asettaa laajan vaiheen, jolla mahdollisesti törmäävät kappaleparit etsitään
+    /// # Arguments
+    /// * `laajavaihe` - Käytettävä laaja vaihe
+    pub fn aseta_laajavaihe(&mut self, laajavaihe: Box<dyn Laajavaihe>) {
+        self.laajavaihe = laajavaihe;
+    }
+
+    /// Asettaa kappaleiden liitosryhmät, jotta toisiinsa liitetyt kappaleet eivät törmää
+    /// toisiinsa. Tyhjä lista tarkoittaa, että kaikki kappaleet voivat törmätä.
+    /// # Arguments
+    /// * `liitosryhmat` - Kunkin kappaleen ryhmä samassa järjestyksessä kuin kappaleet
+    pub fn aseta_liitosryhmat(&mut self, liitosryhmat: Vec<usize>) {
+        self.liitosryhmat = liitosryhmat;
+    }
+
+    /// Asettaa törmäysmatriisin, jonka mukaan törmäämättömien kerrosten kappaleparit
+    /// ohitetaan kokonaan törmäysten tarkistuksessa ja käsittelyssä
+    /// # Arguments
+    /// * `tormaysmatriisi` - Mitkä törmäyskerrokset törmäävät toisiinsa
+    pub fn aseta_tormaysmatriisi(&mut self, tormaysmatriisi: Tormaysmatriisi) {
+        self.tormaysmatriisi = tormaysmatriisi;
+    }
+
+    /// Voivatko annetut kappaleet törmätä toisiinsa. Toisiinsa liitetyt kappaleet eivät
+    /// törmää, eivätkä kappaleet, joiden kerrokset tai maskit eivät sitä salli.
+    /// # Arguments
+    /// * `kappaleet` - Kaikki päivitettävät kappaleet
+    /// * `i` - Ensimmäisen kappaleen indeksi
+    /// * `j` - Toisen kappaleen indeksi
+    fn voivat_tormata(&self, kappaleet: &[Fysiikkakappale], i: usize, j: usize) -> bool {
+        if !self.liitosryhmat.is_empty() && self.liitosryhmat[i] == self.liitosryhmat[j] {
+            return false;
         }
+        let (a, b) = (&kappaleet[i], &kappaleet[j]);
+        a.voi_tormata(b)
+            && self
+                .tormaysmatriisi
+                .tormaavat(a.anna_kerrokset(), b.anna_kerrokset())
     }
 }
 
@@ -209,74 +742,331 @@ impl Fysiikka {
         kappaleet: &mut [Fysiikkakappale],
         paivitysaika: &Duration,
     ) {
-        let mut vanhat_sijainnit = Vec::new();
+        let mut laajavaihe = std::mem::replace(&mut self.laajavaihe, Box::new(KaikkiParit));
+        self.laske_uudet_sijainnit_laajavaiheella(kappaleet, paivitysaika, laajavaihe.as_mut());
+        self.laajavaihe = laajavaihe;
+    }
+
+    /// Laskee kappaleille uudet sijainnit kuten `laske_uudet_sijainnit`, mutta etsii
+    /// mahdollisesti törmäävät parit annetulla laajalla vaiheella. Käytetään esim. maailman
+    /// alueindeksin kanssa, jota fysiikka ei voi omistaa.
+    /// # Arguments
+    /// * `kappaleet` - Päivitettävät kappaleet
+    /// * `paivitysaika` - Päivityksessä käytettävä aika
+    /// * `laajavaihe` - Laaja vaihe, jolla parit etsitään
+    pub fn laske_uudet_sijainnit_laajavaiheella(
+        &mut self,
+        kappaleet: &mut [Fysiikkakappale],
+        paivitysaika: &Duration,
+        laajavaihe: &mut dyn Laajavaihe,
+    ) {
         self.tormaykset = Tormaystiedot::new();
 
-        // Laskee uudet sijainnit
-        for kappale in kappaleet.iter_mut() {
-            vanhat_sijainnit.push(kappale.anna_sijainti());
-            kappale.aseta_sijainti(kappale.laske_uusi_sijainti(paivitysaika));
+        // Laskee uudet sijainnit ja asennot. Staattiset kappaleet eivät liiku.
+        let mut nopeiden_alut = Vec::new();
+        for (i, kappale) in kappaleet.iter_mut().enumerate() {
+            if kappale.anna_liikkuvuus() == Liikkuvuus::Staattinen {
+                // Staattiseen kappaleeseen ohjatut voimat ja tavoitteet hylätään, jotta ne
+                // eivät kerry ja vaikuta myöhemmin, jos kappaleen liikkuvuus muuttuu
+                kappale.nollaa_voima();
+                kappale.poista_tavoitenopeus();
+                continue;
+            }
+            if kappale.onko_nopea() {
+                nopeiden_alut.push((i, kappale.anna_sijainti()));
+            }
+            let vanha_nopeus = kappale.anna_nopeus();
+            let uusi_nopeus = kappale.laske_uusi_nopeus(paivitysaika);
+            kappale.aseta_nopeus(uusi_nopeus);
+            kappale.nollaa_voima();
+            let uusi_sijainti = match self.integrointi {
+                Integrointi::SemiimplisiittinenEuler => kappale.laske_uusi_sijainti(paivitysaika),
+                Integrointi::Verlet => {
+                    let keskinopeus = (vanha_nopeus + uusi_nopeus) * 0.5;
+                    kappale.anna_sijainti() + keskinopeus * sekunteina(paivitysaika)
+                }
+            };
+            kappale.aseta_sijainti(uusi_sijainti);
+            if kappale.anna_kulmanopeus() != 0.0 {
+                kappale.aseta_kulma(kappale.laske_uusi_kulma(paivitysaika));
+            }
         }
 
-        // Tarkistetaan törmäykset uusien sijaintien välillä
-        for i in 0..kappaleet.len() {
-            // Tarkistaa aiheuttaako se törmäyksen
-            for j in 0..kappaleet.len() {
-                if i == j {
-                    // Törmäys itsensä kannssa ei ole järkevä luonnollisestikaan
-                    continue;
-                }
-                if ovatko_paallekkain(
-                    &kappaleet[i].kappale.borrow(),
-                    &kappaleet[j].kappale.borrow(),
-                ) {
-                    // Törmäys tapahtuu
-                    // Merkitään törmäys muistiin
-                    self.tormaykset
-                        .lisaa_tormays(i, kappaleet[j].kappale.borrow().tagi)
-                }
+        // Nopeat kappaleet pysäytetään ensimmäiseen kappaleeseen, johon ne osuvat matkallaan
+        let mut osumahetket = Vec::new();
+        for (i, alku) in nopeiden_alut {
+            if let Some(osuus) = self.osumahetki(kappaleet, i, alku) {
+                osumahetket.push((i, paivitysaika.mul_f32(osuus.liukuluku())));
             }
         }
 
-        // Perutaan kaikkien törmänneiden liike
-        for tormays in self.tormaykset.anna_tormaykset() {
-            let tormaajan_indeksi = tormays.indeksi;
-            kappaleet[tormaajan_indeksi].aseta_sijainti(vanhat_sijainnit[tormaajan_indeksi]);
+        // Tarkistetaan törmäykset uusien sijaintien välillä vain laajan vaiheen antamille pareille
+        let rajaukset: Vec<Rajaus> = kappaleet
+            .iter()
+            .map(|x| x.kappale.borrow().rajaus())
+            .collect();
+        let staattiset: Vec<bool> = kappaleet
+            .iter()
+            .map(|x| x.anna_liikkuvuus() == Liikkuvuus::Staattinen)
+            .collect();
+        let mut tormanneet = Vec::new();
+        for (i, j) in laajavaihe.ehdokasparit(&rajaukset, &staattiset) {
+            if !self.voivat_tormata(kappaleet, i, j) {
+                continue;
+            }
+            if ovatko_paallekkain(
+                &kappaleet[i].kappale.borrow(),
+                &kappaleet[j].kappale.borrow(),
+            ) {
+                // Törmäys merkitään molemmille kappaleille
+                tormanneet.push((i, j));
+                tormanneet.push((j, i));
+            }
+        }
+        // Järjestetään, jotta törmäykset käsitellään samassa järjestyksessä laajasta vaiheesta riippumatta
+        tormanneet.sort_unstable();
+        for &(i, j) in &tormanneet {
+            self.tormaykset
+                .lisaa_tormays(i, j, kappaleet[j].kappale.borrow().tagit);
+        }
+        for (i, osumahetki) in osumahetket {
+            self.tormaykset.aseta_osumahetki(i, osumahetki);
         }
+
+        // Erotetaan törmänneet kappaleet, jolloin ne liukuvat toistensa pintoja pitkin. Vain
+        // pareja, joissa on dynaaminen kappale, voidaan erottaa.
+        let dynaaminen = |i: usize| kappaleet[i].anna_liikkuvuus() == Liikkuvuus::Dynaaminen;
+        let parit: Vec<(usize, usize)> = tormanneet
+            .into_iter()
+            .filter(|&(i, j)| i < j && (dynaaminen(i) || dynaaminen(j)))
+            .collect();
+        for _ in 0..EROTTELUKIERROKSIA {
+            for &(i, j) in &parit {
+                erota(kappaleet, i, j);
+            }
+        }
+    }
+
+    /// Etsii nopean kappaleen ensimmäisen osuman sen kulkiessa annetusta alkusijainnista
+    /// nykyiseen sijaintiinsa. Matka käydään läpi askelin, jotka ovat enintään puolet
+    /// kappaleen pienimmästä mitasta, ja osumahetki tarkennetaan puolittamalla. Jos osuma
+    /// löytyy, kappale siirretään osumakohtaan ja palautetaan osumahetki osuutena
+    /// päivityksestä. Muut kappaleet ovat tarkistuksessa päivityksen lopun sijainneissaan.
+    /// # Arguments
+    /// * `kappaleet` - Kaikki päivitettävät kappaleet
+    /// * `indeksi` - Nopean kappaleen indeksi
+    /// * `alku` - Nopean kappaleen sijainti päivityksen alussa
+    fn osumahetki(
+        &self,
+        kappaleet: &mut [Fysiikkakappale],
+        indeksi: usize,
+        alku: Vektori,
+    ) -> Option<Luku> {
+        let loppu = kappaleet[indeksi].anna_sijainti();
+        let siirto = loppu - alku;
+        let (leveys, korkeus) = kappaleet[indeksi].anna_muoto().koko();
+        let askeleen_pituus = (leveys.min(korkeus) / 2.0).max(luku(1.0));
+        let askelia = (siirto.pituus() / askeleen_pituus).liukuluku().ceil() as usize;
+        if askelia <= 1 {
+            // Lyhyt siirto ei voi ohittaa mitään, joten tavallinen tarkistus riittää
+            return None;
+        }
+
+        // Otetaan mukaan vain kappaleet, jotka ovat kuljetun matkan lähellä ja joiden
+        // kanssa nopea kappale ei ole päällekkäin jo alussa
+        let mut rajaus = kappaleet[indeksi].kappale.borrow().rajaus();
+        kappaleet[indeksi].aseta_sijainti(alku);
+        let alun_rajaus = kappaleet[indeksi].kappale.borrow().rajaus();
+        rajaus.vasen_yla = Vektori::new(
+            rajaus.vasen_yla.x.min(alun_rajaus.vasen_yla.x),
+            rajaus.vasen_yla.y.min(alun_rajaus.vasen_yla.y),
+        );
+        rajaus.oikea_ala = Vektori::new(
+            rajaus.oikea_ala.x.max(alun_rajaus.oikea_ala.x),
+            rajaus.oikea_ala.y.max(alun_rajaus.oikea_ala.y),
+        );
+        let kohteet: Vec<RcKappale> = kappaleet
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != indeksi && self.voivat_tormata(kappaleet, indeksi, *j))
+            .map(|(_, x)| x.anna_kappale())
+            .filter(|x| x.borrow().rajaus().leikkaa(&rajaus))
+            .filter(|x| !ovatko_paallekkain(&kappaleet[indeksi].kappale.borrow(), &x.borrow()))
+            .collect();
+
+        let nopea = &mut kappaleet[indeksi];
+        let mut osuuko = |osuus: Luku| {
+            nopea.aseta_sijainti(alku + siirto * osuus);
+            let kappale = nopea.kappale.borrow();
+            kohteet
+                .iter()
+                .any(|x| ovatko_paallekkain(&kappale, &x.borrow()))
+        };
+        let askel = luku(1.0) / luku(askelia as f32);
+        for k in 1..=askelia {
+            let mut osumaton = askel * luku((k - 1) as f32);
+            let mut osuva = if k == askelia {
+                luku(1.0)
+            } else {
+                askel * luku(k as f32)
+            };
+            if !osuuko(osuva) {
+                continue;
+            }
+            for _ in 0..OSUMAHETKEN_PUOLITUKSIA {
+                let puolivali = (osumaton + osuva) * 0.5;
+                if osuuko(puolivali) {
+                    osuva = puolivali;
+                } else {
+                    osumaton = puolivali;
+                }
+            }
+            // Jätetään kappale hieman kohteen sisään, jotta osuma löytyy törmäystarkistuksessa
+            nopea.aseta_sijainti(alku + siirto * osuva);
+            return Some(osuva);
+        }
+        nopea.aseta_sijainti(loppu);
+        None
+    }
+}
+
+/// Erottaa kaksi päällekkäistä kappaletta lyhintä mahdollista siirtoa pitkin ja muuttaa
+/// niiden nopeuksia törmäysimpulssilla. Vain dynaamisia kappaleita siirretään, ja siirto
+/// jaetaan kappaleiden kesken käänteisten massojen suhteessa, jolloin kevyempi siirtyy enemmän.
+/// # Arguments
+/// * `kappaleet` - Kaikki päivitettävät kappaleet
+/// * `i` - Ensimmäisen kappaleen indeksi
+/// * `j` - Toisen kappaleen indeksi
+fn erota(kappaleet: &mut [Fysiikkakappale], i: usize, j: usize) {
+    let kaanteinen_a = kaanteinen_massa(&kappaleet[i]);
+    let kaanteinen_b = kaanteinen_massa(&kappaleet[j]);
+    let kaanteiset = kaanteinen_a + kaanteinen_b;
+    if kaanteiset <= 0.0 {
+        return;
+    }
+    let kosketus = match kappaleet[i]
+        .kappale
+        .borrow()
+        .runko()
+        .kosketus(&kappaleet[j].kappale.borrow().runko())
+    {
+        Some(kosketus) => kosketus,
+        None => return,
+    };
+    let normaali = kosketus.normaali;
+    let siirto = normaali * (kosketus.syvyys / kaanteiset);
+    let impulssi = tormaysimpulssi(&kappaleet[i], &kappaleet[j], normaali) / kaanteiset;
+    if kaanteinen_a > 0.0 {
+        let kappale = &mut kappaleet[i];
+        kappale.aseta_sijainti(kappale.anna_sijainti() + siirto * kaanteinen_a);
+        kappale.aseta_nopeus(kappale.anna_nopeus() + impulssi * kaanteinen_a);
+    }
+    if kaanteinen_b > 0.0 {
+        let kappale = &mut kappaleet[j];
+        kappale.aseta_sijainti(kappale.anna_sijainti() - siirto * kaanteinen_b);
+        kappale.aseta_nopeus(kappale.anna_nopeus() - impulssi * kaanteinen_b);
+    }
+}
+
+/// Antaa kappaleen käänteisen massan. Staattisten ja kinemaattisten kappaleiden käänteinen
+/// massa on nolla eli niiden massa on ääretön.
+/// # Arguments
+/// * `kappale` - Kappale, jonka käänteinen massa annetaan
+fn kaanteinen_massa(kappale: &Fysiikkakappale) -> Luku {
+    if kappale.anna_liikkuvuus() == Liikkuvuus::Dynaaminen && kappale.anna_massa() > 0.0 {
+        luku(1.0) / kappale.anna_massa()
+    } else {
+        Luku::default()
+    }
+}
+
+/// Laskee ensimmäiseen kappaleeseen kohdistuvan törmäysimpulssin kerrottuna kappaleiden
+/// käänteisten massojen summalla. Toiseen kappaleeseen kohdistuu vastakkainen impulssi.
+/// Normaalin suuntainen osa kimmottaa kappaleet erilleen ja pinnan suuntainen osa on kitkaa,
+/// joka on enintään kitkakertoimen verran normaalin suuntaisesta osasta. Kimmoisuudeksi
+/// otetaan kappaleiden suurempi kimmoisuus ja kitkaksi kitkakertoimien geometrinen keskiarvo.
+/// # Arguments
+/// * `kappale_a` - Ensimmäinen kappale
+/// * `kappale_b` - Toinen kappale
+/// * `normaali` - Yksikkövektori, joka osoittaa toisesta kappaleesta ensimmäiseen
+fn tormaysimpulssi(
+    kappale_a: &Fysiikkakappale,
+    kappale_b: &Fysiikkakappale,
+    normaali: Vektori,
+) -> Vektori {
+    let suhteellinen_nopeus = kappale_a.anna_nopeus() - kappale_b.anna_nopeus();
+    let normaalinopeus = suhteellinen_nopeus.pistetulo(normaali);
+    if normaalinopeus >= 0.0 {
+        // Kappaleet ovat jo erkanemassa
+        return Vektori::default();
     }
+    let kimmoisuus = kappale_a.anna_kimmoisuus().max(kappale_b.anna_kimmoisuus());
+    let normaali_impulssi = -(luku(1.0) + kimmoisuus) * normaalinopeus;
+
+    let tangentti = suhteellinen_nopeus - normaali * normaalinopeus;
+    let kitka = (kappale_a.anna_kitka() * kappale_b.anna_kitka()).sqrt();
+    let kitka_impulssi = match tangentti.normalisoi() {
+        Some(suunta) => suunta * -tangentti.pituus().min(kitka * normaali_impulssi),
+        None => Vektori::default(),
+    };
+    normaali * normaali_impulssi + kitka_impulssi
 }
 
-/// Tarkistaa törmäävätkö kaksi annettua kappaletta toisiinsa
+/// Tarkistaa törmäävätkö kaksi annettua kappaletta toisiinsa. Suorakaiteille ja ympyröille
+/// on omat tarkistuksensa, muut muodot tarkistetaan yleisesti niiden runkojen avulla.
 /// # Arguments
 /// * `kappale_a` - Kappale, joka ns törmää
 /// * `kappale_b` - Kappale, joka ns tulee törmätyksi
 fn ovatko_paallekkain(kappale_a: &Kappale, kappale_b: &Kappale) -> bool {
     match (kappale_a.muoto, kappale_b.muoto) {
-        (Muoto::Nelio(leveys_a, korkeus_a), Muoto::Nelio(leveys_b, korkeus_b)) => {
-            let sijainti_a = kappale_a.kulman_sijainti();
-            let sijainti_b = kappale_b.kulman_sijainti();
-
-            let vasen_a = sijainti_a.x;
-            let oikea_a = sijainti_a.x + leveys_a;
-            let vasen_b = sijainti_b.x;
-            let oikea_b = sijainti_b.x + leveys_b;
-            let ala_a = sijainti_a.y;
-            let yla_a = sijainti_a.y + korkeus_a;
-            let ala_b = sijainti_b.y;
-            let yla_b = sijainti_b.y + korkeus_b;
-            !(oikea_a < vasen_b || oikea_b < vasen_a || yla_a < ala_b || yla_b < ala_a)
-        }
-        // TODO: Epäilen bugia ympyrän törmäyksissä, mutta hankala olla varma, kun ympyrä on oikeasti kuva
+        (Muoto::Nelio(_, _), Muoto::Nelio(_, _)) => suorakaiteiden_tormays(kappale_a, kappale_b),
         (Muoto::Ympyra(sade_a), Muoto::Ympyra(sade_b)) => {
             (kappale_a.keskipisteen_sijainti() - kappale_b.keskipisteen_sijainti()).pituus()
                 < (sade_a + sade_b)
         }
         (Muoto::Ympyra(_), Muoto::Nelio(_, _)) => ympyran_ja_nelion_tormays(kappale_b, kappale_a),
         (Muoto::Nelio(_, _), Muoto::Ympyra(_)) => ympyran_ja_nelion_tormays(kappale_a, kappale_b),
+        _ => kappale_a.runko().ovatko_paallekkain(&kappale_b.runko()),
     }
 }
 
-/// Tarkistaa ovatko annetut neliö ja ympyrä toistensa päällä.
+/// Tarkistaa ovatko annetut suorakaiteet toistensa päällä erottavien akseleiden avulla.
+/// Suorakaiteet eivät ole päällekkäin vain, jos jonkin suorakaiteen sivun suuntaiselle
+/// akselille projisoidut suorakaiteet eivät ole päällekkäin. Reunojen kosketus on törmäys.
+/// # Arguments
+/// * `nelio_a` - Ensimmäinen suorakaide
+/// * `nelio_b` - Toinen suorakaide
+fn suorakaiteiden_tormays(nelio_a: &Kappale, nelio_b: &Kappale) -> bool {
+    let kulmat_a = nelio_a.kulmat();
+    let kulmat_b = nelio_b.kulmat();
+    let akselit = [
+        Vektori::new(luku(1.0), luku(0.0)).kierra(nelio_a.kulma()),
+        Vektori::new(luku(0.0), luku(1.0)).kierra(nelio_a.kulma()),
+        Vektori::new(luku(1.0), luku(0.0)).kierra(nelio_b.kulma()),
+        Vektori::new(luku(0.0), luku(1.0)).kierra(nelio_b.kulma()),
+    ];
+    akselit.iter().all(|akseli| {
+        let (min_a, max_a) = projektio(&kulmat_a, *akseli);
+        let (min_b, max_b) = projektio(&kulmat_b, *akseli);
+        !(max_a < min_b || max_b < min_a)
+    })
+}
+
+/// Antaa pisteiden projektion pienimmän ja suurimman arvon annetulla akselilla
+/// # Arguments
+/// * `pisteet` - Projisoitavat pisteet
+/// * `akseli` - Yksikkövektori, jolle pisteet projisoidaan
+fn projektio(pisteet: &[Vektori], akseli: Vektori) -> (Luku, Luku) {
+    pisteet
+        .iter()
+        .map(|x| x.pistetulo(akseli))
+        .fold((Luku::INFINITY, Luku::NEG_INFINITY), |(min, max), x| {
+            (min.min(x), max.max(x))
+        })
+}
+
+/// Tarkistaa ovatko annetut neliö ja ympyrä toistensa päällä. Ympyrän keskipiste siirretään
+/// neliön omaan koordinaatistoon, jolloin neliötä voidaan käsitellä kiertämättömänä.
 /// Antaa false, jos oletut muodot(ensin neliö ja sitten ympyrä) eivät päde.
 /// # Arguments
 /// * `nelio` - Pitää olla neliö tai false
@@ -284,38 +1074,671 @@ fn ovatko_paallekkain(kappale_a: &Kappale, kappale_b: &Kappale) -> bool {
 fn ympyran_ja_nelion_tormays(nelio: &Kappale, ympyra: &Kappale) -> bool {
     if let Muoto::Nelio(leveys, korkeus) = nelio.muoto {
         if let Muoto::Ympyra(sade) = ympyra.muoto {
-            let ympyra_sijainti = ympyra.keskipisteen_sijainti();
-            let nelio_sijainti = nelio.kulman_sijainti();
-            let vasen = nelio_sijainti.x;
-            let oikea = nelio_sijainti.x + leveys;
-            let ala = nelio_sijainti.y;
-            let yla = nelio_sijainti.y + korkeus;
-            let vasen_yla_kulma = Vektori::new(vasen, yla);
-            let oikea_yla_kulma = Vektori::new(oikea, yla);
-            let vasen_ala_kulma = Vektori::new(vasen, ala);
-            let oikea_ala_kulma = Vektori::new(oikea, ala);
-
-            return !(
-                // Rajataan neliö, jonka ulkopuolella törmäys ei voi tapahtua
-                ympyra_sijainti.x <= vasen - sade
-                || oikea + sade <= ympyra_sijainti.x
-                || ympyra_sijainti.y <= ala - sade
-                || yla + sade <= ympyra_sijainti.y
-                // Tarkistetaan, onko se, neliön kulman muodostaman ympyrän ulkopuolella
-                || (ympyra_sijainti - vasen_yla_kulma).pituus() >= sade
-                    && ympyra_sijainti.x < vasen
-                    && ympyra_sijainti.y > yla
-                || (ympyra_sijainti - oikea_yla_kulma).pituus() >= sade
-                    && ympyra_sijainti.x > oikea
-                    && ympyra_sijainti.y > yla
-                || (ympyra_sijainti - vasen_ala_kulma).pituus() >= sade
-                    && ympyra_sijainti.x < vasen
-                    && ympyra_sijainti.y < ala
-                || (ympyra_sijainti - oikea_ala_kulma).pituus() >= sade
-                    && ympyra_sijainti.x > oikea
-                    && ympyra_sijainti.y < ala
+            let suhteellinen_sijainti = (ympyra.keskipisteen_sijainti()
+                - nelio.keskipisteen_sijainti())
+            .kierra(-nelio.kulma());
+            // Neliön lähin piste ympyrän keskipisteeseen
+            let lahin_piste = Vektori::new(
+                suhteellinen_sijainti.x.max(-leveys / 2.0).min(leveys / 2.0),
+                suhteellinen_sijainti.y.max(-korkeus / 2.0).min(korkeus / 2.0),
             );
+            return (suhteellinen_sijainti - lahin_piste).pituus() < sade;
         }
     }
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    use super::*;
+    use crate::maailma::luku::Skalaari;
+    use crate::maailma::tagit::Tagirekisteri;
+
+    const PAIVITYSAIKA: Duration = Duration::from_millis(16);
+
+    fn kappale(muoto: Muoto, x: f32, y: f32, nopeus: (f32, f32)) -> Fysiikkakappale {
+        let kappale = Kappale::new_keskipisteella(muoto, luku(x), luku(y), Tagi::Vihollinen);
+        Fysiikkakappale::new(
+            Vektori::new(luku(nopeus.0), luku(nopeus.1)),
+            Rc::new(RefCell::new(kappale)),
+        )
+    }
+
+    fn nelio(x: f32, y: f32, nopeus: (f32, f32)) -> Fysiikkakappale {
+        kappale(Muoto::Nelio(luku(20.0), luku(20.0)), x, y, nopeus)
+    }
+
+    fn seina(leveys: f32, korkeus: f32, x: f32, y: f32) -> Fysiikkakappale {
+        let muoto = Muoto::Nelio(luku(leveys), luku(korkeus));
+        let mut seina = kappale(muoto, x, y, (0.0, 0.0));
+        seina.aseta_liikkuvuus(Liikkuvuus::Staattinen);
+        seina
+    }
+
+    /// Vaakasuora seinä, jonka yläreuna on kohdassa y = 0
+    fn lattia() -> Fysiikkakappale {
+        seina(400.0, 20.0, 0.0, 10.0)
+    }
+
+    /// Pystysuora seinä, jonka vasen reuna on kohdassa x = 100
+    fn oikea_seina() -> Fysiikkakappale {
+        seina(20.0, 400.0, 110.0, 0.0)
+    }
+
+    fn keskipiste(kappale: &Fysiikkakappale) -> (f32, f32) {
+        let sijainti = kappale.anna_kappale().borrow().keskipisteen_sijainti();
+        (sijainti.x.liukuluku(), sijainti.y.liukuluku())
+    }
+
+    fn nopeus(kappale: &Fysiikkakappale) -> (f32, f32) {
+        let nopeus = kappale.anna_nopeus();
+        (nopeus.x.liukuluku(), nopeus.y.liukuluku())
+    }
+
+    fn paivita(kappaleet: &mut [Fysiikkakappale]) -> Fysiikka {
+        let mut fysiikka = Fysiikka::new();
+        fysiikka.laske_uudet_sijainnit(kappaleet, &PAIVITYSAIKA);
+        fysiikka
+    }
+
+    fn lahes(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn kappale_liukuu_seinaa_pitkin() {
+        let mut kappaleet = vec![lattia(), nelio(0.0, -9.0, (100.0, 100.0))];
+        let fysiikka = paivita(&mut kappaleet);
+
+        // Seinän suuntainen liike säilyy ja seinän sisään menevä poistuu
+        let (x, y) = keskipiste(&kappaleet[1]);
+        assert!(lahes(x, 1.6), "x = {}", x);
+        assert!(lahes(y, -10.0), "y = {}", y);
+        let (vx, vy) = nopeus(&kappaleet[1]);
+        assert!(
+            lahes(vx, 100.0) && lahes(vy, 0.0),
+            "nopeus = {:?}",
+            (vx, vy)
+        );
+        // Seinä ei liiku ja törmäys ilmoitetaan edelleen
+        assert_eq!(keskipiste(&kappaleet[0]), (0.0, 10.0));
+        assert_eq!(fysiikka.tormaykset.anna_tormaykset().len(), 2);
+    }
+
+    #[test]
+    fn lepaava_kappale_ei_vajoa_eika_ajaudu() {
+        let mut kappaleet = vec![lattia(), nelio(0.0, -10.0, (0.0, 0.0))];
+        for _ in 0..100 {
+            // Kappaletta painetaan joka päivityksessä lattiaa vasten
+            kappaleet[1].aseta_nopeus(Vektori::new(luku(0.0), luku(50.0)));
+            paivita(&mut kappaleet);
+        }
+        let (x, y) = keskipiste(&kappaleet[1]);
+        assert!(lahes(x, 0.0), "x = {}", x);
+        assert!(lahes(y, -10.0), "y = {}", y);
+        assert!(lahes(nopeus(&kappaleet[1]).1, 0.0));
+    }
+
+    #[test]
+    fn kappale_pysahtyy_kulmaan() {
+        let mut kappaleet = vec![lattia(), oikea_seina(), nelio(80.0, -20.0, (200.0, 200.0))];
+        for _ in 0..20 {
+            paivita(&mut kappaleet);
+        }
+        let (x, y) = keskipiste(&kappaleet[2]);
+        assert!(lahes(x, 90.0), "x = {}", x);
+        assert!(lahes(y, -10.0), "y = {}", y);
+        let (vx, vy) = nopeus(&kappaleet[2]);
+        assert!(lahes(vx, 0.0) && lahes(vy, 0.0), "nopeus = {:?}", (vx, vy));
+    }
+
+    #[test]
+    fn ympyra_liukuu_kulman_ohi() {
+        // Ympyrä liikkuu lattian suuntaisesti ja osuu lattian kulmaan vain hieman
+        let mut kappaleet = vec![
+            lattia(),
+            kappale(Muoto::Ympyra(luku(5.0)), -203.0, -4.0, (100.0, 0.0)),
+        ];
+        paivita(&mut kappaleet);
+        let (x, y) = keskipiste(&kappaleet[1]);
+        assert!(y < -4.0, "y = {}", y);
+        let kulmaan = ((x + 200.0).powi(2) + y.powi(2)).sqrt();
+        assert!(kulmaan >= 5.0 - 0.01, "etäisyys kulmaan = {}", kulmaan);
+        assert!(nopeus(&kappaleet[1]).0 > 0.0);
+    }
+
+    #[test]
+    fn liikkuvat_kappaleet_jakavat_erottamisen() {
+        let mut kappaleet = vec![nelio(-9.0, 0.0, (10.0, 0.0)), nelio(9.0, 0.0, (-10.0, 0.0))];
+        paivita(&mut kappaleet);
+        let (xa, _) = keskipiste(&kappaleet[0]);
+        let (xb, _) = keskipiste(&kappaleet[1]);
+        assert!(lahes(xa, -10.0), "xa = {}", xa);
+        assert!(lahes(xb, 10.0), "xb = {}", xb);
+        assert_eq!(nopeus(&kappaleet[0]), (0.0, 0.0));
+        assert_eq!(nopeus(&kappaleet[1]), (0.0, 0.0));
+    }
+
+    /// Ammus lentää ohutta seinää kohti niin hitaalla päivitysnopeudella, että se kulkee
+    /// yhdessä päivityksessä seinän läpi
+    fn ammus_ja_ohut_seina(nopea: bool) -> (Vec<Fysiikkakappale>, Fysiikka) {
+        let mut ammus = kappale(Muoto::Ympyra(luku(5.0)), 0.0, 0.0, (260.0, 0.0));
+        ammus.aseta_nopea(nopea);
+        let mut kappaleet = vec![ammus, seina(4.0, 200.0, 30.0, 0.0)];
+        let mut fysiikka = Fysiikka::new();
+        fysiikka.laske_uudet_sijainnit(&mut kappaleet, &Duration::from_millis(200));
+        (kappaleet, fysiikka)
+    }
+
+    #[test]
+    fn tavallinen_ammus_menee_ohuen_seinan_lapi() {
+        let (kappaleet, fysiikka) = ammus_ja_ohut_seina(false);
+        assert!(lahes(keskipiste(&kappaleet[0]).0, 52.0));
+        assert!(fysiikka.tormaykset.anna_tormaykset().is_empty());
+    }
+
+    #[test]
+    fn nopea_ammus_pysahtyy_ohueen_seinaan() {
+        let (kappaleet, fysiikka) = ammus_ja_ohut_seina(true);
+        // Ammus jää seinän pintaan eikä liiku sen sisään
+        let (x, y) = keskipiste(&kappaleet[0]);
+        assert!((x - 23.0).abs() < 0.05, "x = {}", x);
+        assert!(lahes(y, 0.0), "y = {}", y);
+        assert!(lahes(nopeus(&kappaleet[0]).0, 0.0));
+
+        let tormaykset = fysiikka.tormaykset.anna_tormaykset();
+        let ammuksen = tormaykset.iter().find(|x| x.indeksi == 0).unwrap();
+        assert_eq!(ammuksen.anna_kohteet(), &[1]);
+        // Ammus kulkee 23 / 52 osaa matkastaan ennen osumaa
+        let osumahetki = ammuksen.anna_osumahetki().unwrap().as_secs_f32();
+        assert!((osumahetki - 0.2 * 23.0 / 52.0).abs() < 0.001);
+        let seinan = tormaykset.iter().find(|x| x.indeksi == 1).unwrap();
+        assert_eq!(seinan.anna_osumahetki(), None);
+    }
+
+    #[test]
+    fn nopea_kappale_pysahtyy_ohueen_seinaan_pitkalla_paivitysajalla() {
+        // Kappale kulkee kahden sekunnin päivityksessä 10000 ja seinä on vain 1 paksu
+        let mut kappaleet = vec![
+            kappale(Muoto::Ympyra(luku(5.0)), 0.0, 0.0, (5000.0, 0.0)),
+            seina(1.0, 200.0, 5000.0, 0.0),
+        ];
+        kappaleet[0].aseta_nopea(true);
+        let mut fysiikka = Fysiikka::new();
+        fysiikka.laske_uudet_sijainnit(&mut kappaleet, &Duration::from_secs(2));
+
+        let (x, _) = keskipiste(&kappaleet[0]);
+        assert!((x - 4994.5).abs() < 0.05, "x = {}", x);
+        assert!(lahes(nopeus(&kappaleet[0]).0, 0.0));
+        let tormaykset = fysiikka.tormaykset.anna_tormaykset();
+        let ammuksen = tormaykset.iter().find(|x| x.indeksi == 0).unwrap();
+        let osumahetki = ammuksen.anna_osumahetki().unwrap().as_secs_f32();
+        assert!((osumahetki - 2.0 * 4994.5 / 10000.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn osumahetken_puolitus_jattaa_kappaleen_hieman_kohteen_sisaan() {
+        let mut kappaleet = vec![
+            kappale(Muoto::Ympyra(luku(5.0)), 52.0, 0.0, (260.0, 0.0)),
+            seina(4.0, 200.0, 30.0, 0.0),
+        ];
+        let alku = Vektori::new(luku(-5.0), luku(-5.0));
+        let osuus = Fysiikka::new().osumahetki(&mut kappaleet, 0, alku).unwrap();
+
+        // Matka käydään läpi 11 askeleessa, ja puolitus tarkentaa osuman askeleen
+        // 1024:nteen osaan. Kappale on aina osumahetkellä kohteen sisällä.
+        let tarkkuus = 52.0 / 11.0 / 1024.0;
+        let (x, _) = keskipiste(&kappaleet[0]);
+        let syvyys = x + 5.0 - 28.0;
+        assert!(
+            syvyys > 0.0 && syvyys <= tarkkuus + 0.001,
+            "syvyys = {}",
+            syvyys
+        );
+        assert!(lahes(osuus.liukuluku(), x / 52.0));
+        assert!(ovatko_paallekkain(
+            &kappaleet[0].anna_kappale().borrow(),
+            &kappaleet[1].anna_kappale().borrow()
+        ));
+    }
+
+    #[test]
+    fn nopea_ammus_ilman_osumaa_kulkee_koko_matkan() {
+        let mut kappaleet = vec![
+            kappale(Muoto::Ympyra(luku(5.0)), 0.0, 0.0, (260.0, 0.0)),
+            seina(4.0, 20.0, 30.0, 40.0),
+        ];
+        kappaleet[0].aseta_nopea(true);
+        let mut fysiikka = Fysiikka::new();
+        fysiikka.laske_uudet_sijainnit(&mut kappaleet, &Duration::from_millis(200));
+        assert!(lahes(keskipiste(&kappaleet[0]).0, 52.0));
+        assert!(fysiikka.tormaykset.anna_tormaykset().is_empty());
+    }
+
+    #[test]
+    fn staattinen_kappale_ei_liiku_eika_tormaa_staattisiin() {
+        let mut kappaleet = vec![lattia(), seina(20.0, 20.0, 0.0, 10.0)];
+        kappaleet[0].aseta_nopeus(Vektori::new(luku(100.0), luku(0.0)));
+        let fysiikka = paivita(&mut kappaleet);
+        assert_eq!(keskipiste(&kappaleet[0]), (0.0, 10.0));
+        assert!(fysiikka.tormaykset.anna_tormaykset().is_empty());
+    }
+
+    #[test]
+    fn kinemaattinen_kappale_tyontaa_dynaamista() {
+        let mut tyontaja = nelio(-9.0, 0.0, (50.0, 0.0));
+        tyontaja.aseta_liikkuvuus(Liikkuvuus::Kinemaattinen);
+        let seina = seina(20.0, 20.0, -25.0, 0.0);
+        let mut kappaleet = vec![tyontaja, nelio(11.0, 0.0, (0.0, 0.0)), seina];
+        kappaleet[1].aseta_nopeus(Vektori::new(luku(-10.0), luku(0.0)));
+        let fysiikka = paivita(&mut kappaleet);
+
+        // Kinemaattinen kappale kulkee nopeutensa mukaan, dynaaminen väistää koko matkan
+        let (x_tyontaja, _) = keskipiste(&kappaleet[0]);
+        let (x_dynaaminen, _) = keskipiste(&kappaleet[1]);
+        assert!(lahes(x_tyontaja, -8.2), "x = {}", x_tyontaja);
+        assert!(lahes(x_dynaaminen, 11.8), "x = {}", x_dynaaminen);
+        // Dynaaminen kappale jatkaa kinemaattisen mukana
+        assert_eq!(nopeus(&kappaleet[0]), (50.0, 0.0));
+        assert!(lahes(nopeus(&kappaleet[1]).0, 50.0));
+        // Kinemaattisen ja staattisen kappaleen törmäys ilmoitetaan, vaikka kumpaakaan ei siirretä
+        assert!(fysiikka
+            .tormaykset
+            .anna_tormaykset()
+            .iter()
+            .any(|x| x.indeksi == 0 && x.anna_kohteet().contains(&2)));
+    }
+
+    #[test]
+    fn raskas_kappale_siirtyy_vahemman_ja_liikemaara_sailyy() {
+        let mut kappaleet = vec![nelio(-9.0, 0.0, (10.0, 0.0)), nelio(9.0, 0.0, (-10.0, 0.0))];
+        kappaleet[0].aseta_massa(luku(3.0));
+        paivita(&mut kappaleet);
+
+        // Päällekkäisyys 2.32 jaetaan käänteisten massojen suhteessa 1:3
+        let (xa, _) = keskipiste(&kappaleet[0]);
+        let (xb, _) = keskipiste(&kappaleet[1]);
+        assert!(lahes(xa, -8.84 - 0.58), "xa = {}", xa);
+        assert!(lahes(xb, 8.84 + 1.74), "xb = {}", xb);
+        // Kimmoton törmäys: molemmat jatkavat yhteisellä nopeudella (3 * 10 - 10) / 4
+        assert!(lahes(nopeus(&kappaleet[0]).0, 5.0));
+        assert!(lahes(nopeus(&kappaleet[1]).0, 5.0));
+    }
+
+    #[test]
+    fn aarimmainen_massasuhde_kummassakin_jarjestyksessa() {
+        for raskas_ensin in &[false, true] {
+            let mut kevyt = nelio(-9.0, 0.0, (10.0, 0.0));
+            let mut raskas = nelio(9.0, 0.0, (0.0, 0.0));
+            kevyt.aseta_massa(luku(1.0));
+            raskas.aseta_massa(luku(1000.0));
+            let mut kappaleet = match raskas_ensin {
+                true => vec![raskas, kevyt],
+                false => vec![kevyt, raskas],
+            };
+            paivita(&mut kappaleet);
+            let (kevyt, raskas) = match raskas_ensin {
+                true => (&kappaleet[1], &kappaleet[0]),
+                false => (&kappaleet[0], &kappaleet[1]),
+            };
+
+            // Kevyt kappale väistää lähes koko päällekkäisyyden kuin seinästä
+            let (x_kevyt, _) = keskipiste(kevyt);
+            let (x_raskas, _) = keskipiste(raskas);
+            assert!(lahes(x_kevyt, -11.0), "x = {}", x_kevyt);
+            assert!(lahes(x_raskas, 9.0), "x = {}", x_raskas);
+            // Kimmoton törmäys: yhteinen nopeus ja liikemäärä säilyy
+            let (v_kevyt, v_raskas) = (nopeus(kevyt).0, nopeus(raskas).0);
+            assert!(
+                lahes(v_kevyt, v_raskas),
+                "nopeudet {} {}",
+                v_kevyt,
+                v_raskas
+            );
+            // Kiintoluvuilla raskaan kappaleen käänteinen massa pyöristyy, joten liikemäärä
+            // säilyy vain noin prosentin tarkkuudella
+            let liikemaara = v_kevyt + 1000.0 * v_raskas;
+            assert!((liikemaara - 10.0).abs() < 0.1, "liikemäärä {}", liikemaara);
+        }
+    }
+
+    #[test]
+    fn raskas_kappale_tyontaa_kevytta_lahes_pysahtymatta() {
+        let mut kappaleet = vec![nelio(-9.0, 0.0, (10.0, 0.0)), nelio(9.0, 0.0, (0.0, 0.0))];
+        kappaleet[0].aseta_massa(luku(1000.0));
+        paivita(&mut kappaleet);
+        let (x_raskas, _) = keskipiste(&kappaleet[0]);
+        let (x_kevyt, _) = keskipiste(&kappaleet[1]);
+        assert!(lahes(x_raskas, -8.84), "x = {}", x_raskas);
+        assert!(lahes(x_kevyt, 11.16), "x = {}", x_kevyt);
+        assert!(lahes(nopeus(&kappaleet[0]).0, 9.99));
+        assert!(lahes(nopeus(&kappaleet[1]).0, 9.99));
+    }
+
+    #[test]
+    fn taysin_kimmoisa_tormays_sailyttaa_energian() {
+        let mut kappaleet = vec![nelio(-9.0, 0.0, (10.0, 0.0)), nelio(9.0, 0.0, (-10.0, 0.0))];
+        kappaleet[0].aseta_kimmoisuus(luku(1.0));
+        kappaleet[1].aseta_massa(luku(3.0));
+        paivita(&mut kappaleet);
+
+        // Kimmoisassa törmäyksessä massojen 1 ja 3 nopeudet 10 ja -10 muuttuvat -20:ksi ja 0:ksi
+        let (va, vb) = (nopeus(&kappaleet[0]).0, nopeus(&kappaleet[1]).0);
+        assert!(lahes(va, -20.0) && lahes(vb, 0.0), "nopeudet {} {}", va, vb);
+        let energia = 0.5 * va * va + 0.5 * 3.0 * vb * vb;
+        assert!((energia - 200.0).abs() < 0.1, "energia {}", energia);
+        assert!(lahes(va + 3.0 * vb, 10.0 - 30.0));
+    }
+
+    #[test]
+    fn kimmoisa_pallo_ei_meneta_vauhtia_seinien_valissa() {
+        let mut pallo = kappale(Muoto::Ympyra(luku(5.0)), 0.0, 0.0, (300.0, 0.0));
+        pallo.aseta_kimmoisuus(luku(1.0));
+        let mut kappaleet = vec![seina(20.0, 400.0, -110.0, 0.0), oikea_seina(), pallo];
+        let mut kimpoamisia = 0;
+        for _ in 0..200 {
+            let ennen = nopeus(&kappaleet[2]).0;
+            paivita(&mut kappaleet);
+            let (vx, vy) = nopeus(&kappaleet[2]);
+            if vx * ennen < 0.0 {
+                kimpoamisia += 1;
+            }
+            assert!(
+                lahes(vx.abs(), 300.0) && lahes(vy, 0.0),
+                "nopeus {:?}",
+                (vx, vy)
+            );
+        }
+        assert!(kimpoamisia >= 4, "kimpoamisia {}", kimpoamisia);
+        // Pallo pysyy seinien välissä
+        let (x, _) = keskipiste(&kappaleet[2]);
+        assert!(x > -95.01 && x < 95.01, "x = {}", x);
+    }
+
+    #[test]
+    fn kimmoisa_kappale_kimpoaa_seinasta() {
+        let mut pallo = kappale(Muoto::Ympyra(luku(5.0)), 0.0, -4.0, (30.0, 100.0));
+        pallo.aseta_kimmoisuus(luku(1.0));
+        let mut kappaleet = vec![lattia(), pallo];
+        paivita(&mut kappaleet);
+        let (vx, vy) = nopeus(&kappaleet[1]);
+        assert!(
+            lahes(vx, 30.0) && lahes(vy, -100.0),
+            "nopeus = {:?}",
+            (vx, vy)
+        );
+    }
+
+    #[test]
+    fn kitka_hidastaa_liukumista() {
+        let mut kappaleet = vec![lattia(), nelio(0.0, -9.0, (100.0, 40.0))];
+        kappaleet[0].aseta_kitka(luku(0.5));
+        kappaleet[1].aseta_kitka(luku(0.5));
+        paivita(&mut kappaleet);
+        // Normaalin suuntainen impulssi 40 jarruttaa liukumista enintään 0.5 * 40
+        let (vx, vy) = nopeus(&kappaleet[1]);
+        assert!(lahes(vx, 80.0) && lahes(vy, 0.0), "nopeus = {:?}", (vx, vy));
+
+        // Hidas liukuminen pysähtyy kokonaan
+        kappaleet[1].aseta_nopeus(Vektori::new(luku(5.0), luku(40.0)));
+        paivita(&mut kappaleet);
+        assert_eq!(nopeus(&kappaleet[1]), (0.0, 0.0));
+    }
+
+    fn paivita_sekunti(kappaleet: &mut [Fysiikkakappale], integrointi: Integrointi) {
+        let mut fysiikka = Fysiikka::new();
+        fysiikka.aseta_integrointi(integrointi);
+        fysiikka.laske_uudet_sijainnit(kappaleet, &Duration::from_secs(1));
+    }
+
+    #[test]
+    fn voima_kiihdyttaa_massan_mukaan_ja_nollautuu() {
+        for (integrointi, odotettu_x) in &[
+            (Integrointi::SemiimplisiittinenEuler, 50.0),
+            (Integrointi::Verlet, 25.0),
+        ] {
+            let mut kappaleet = vec![nelio(0.0, 0.0, (0.0, 0.0))];
+            kappaleet[0].aseta_massa(luku(2.0));
+            kappaleet[0].lisaa_voima(Vektori::new(luku(100.0), luku(0.0)));
+            paivita_sekunti(&mut kappaleet, *integrointi);
+            assert!(lahes(nopeus(&kappaleet[0]).0, 50.0));
+            let (x, _) = keskipiste(&kappaleet[0]);
+            assert!(lahes(x, *odotettu_x), "{:?}: x = {}", integrointi, x);
+            assert!(kappaleet[0].anna_voima() == Vektori::default());
+
+            // Voima vaikuttaa vain yhden päivityksen ajan
+            paivita_sekunti(&mut kappaleet, *integrointi);
+            assert!(lahes(nopeus(&kappaleet[0]).0, 50.0));
+        }
+    }
+
+    #[test]
+    fn vaimennus_ja_maksiminopeus_rajaavat_nopeutta() {
+        let mut kappaleet = vec![nelio(0.0, 0.0, (100.0, 0.0))];
+        kappaleet[0].aseta_vaimennus(luku(1.0));
+        paivita_sekunti(&mut kappaleet, Integrointi::default());
+        assert!(lahes(nopeus(&kappaleet[0]).0, 50.0));
+
+        kappaleet[0].aseta_vaimennus(Luku::default());
+        kappaleet[0].aseta_nopeus(Vektori::new(luku(30.0), luku(40.0)));
+        kappaleet[0].aseta_maksiminopeus(Some(luku(10.0)));
+        paivita_sekunti(&mut kappaleet, Integrointi::default());
+        let (vx, vy) = nopeus(&kappaleet[0]);
+        assert!(lahes(vx, 6.0) && lahes(vy, 8.0), "nopeus = {:?}", (vx, vy));
+    }
+
+    #[test]
+    fn tavoitenopeus_saavutetaan_ylittamatta() {
+        let mut kappaleet = vec![nelio(0.0, 0.0, (0.0, 0.0))];
+        let tavoite = Vektori::new(luku(100.0), luku(0.0));
+        kappaleet[0].aseta_tavoitenopeus(tavoite, luku(60.0));
+        paivita_sekunti(&mut kappaleet, Integrointi::default());
+        assert!(lahes(nopeus(&kappaleet[0]).0, 60.0));
+        paivita_sekunti(&mut kappaleet, Integrointi::default());
+        assert!(lahes(nopeus(&kappaleet[0]).0, 100.0));
+    }
+
+    #[test]
+    fn kinemaattista_kappaletta_voi_ohjata_mutta_ei_tyontaa() {
+        let mut kappaleet = vec![nelio(0.0, 0.0, (100.0, 0.0))];
+        kappaleet[0].aseta_liikkuvuus(Liikkuvuus::Kinemaattinen);
+        kappaleet[0].aseta_tavoitenopeus(Vektori::default(), luku(60.0));
+        kappaleet[0].lisaa_voima(Vektori::new(luku(1000.0), luku(0.0)));
+        paivita_sekunti(&mut kappaleet, Integrointi::default());
+        assert!(lahes(nopeus(&kappaleet[0]).0, 40.0));
+        assert!(kappaleet[0].anna_voima() == Vektori::default());
+    }
+
+    #[test]
+    fn tormaysmatriisi_on_symmetrinen() {
+        let mut matriisi = Tormaysmatriisi::new();
+        let rivi = [Tagi::Seina, Tagi::Vihollinen].iter().copied().collect();
+        matriisi.aseta_rivi(Tagi::Ammus, rivi);
+        assert!(matriisi.rivi(Tagi::Seina).sisaltaa(Tagi::Ammus));
+        assert!(!matriisi.rivi(Tagi::Pelaaja).sisaltaa(Tagi::Ammus));
+
+        // Riittää, että yksikin kerrospari törmää
+        let ammus = Tagit::from(Tagi::Ammus);
+        let mut tykki = Tagit::from(Tagi::Vihollinen);
+        tykki.lisaa(Tagirekisteri::new().rekisteroi("tykki").unwrap());
+        assert!(!matriisi.tormaavat(ammus, ammus));
+        assert!(matriisi.tormaavat(ammus, tykki));
+    }
+
+    #[test]
+    fn tormaamattomat_kerrokset_menevat_toistensa_lapi() {
+        let mut kappaleet = vec![nelio(-9.0, 0.0, (10.0, 0.0)), nelio(9.0, 0.0, (-10.0, 0.0))];
+        for kappale in &mut kappaleet {
+            kappale.aseta_kerrokset(Tagit::from(Tagi::Ammus));
+        }
+        let mut matriisi = Tormaysmatriisi::new();
+        matriisi.aseta(Tagi::Ammus, Tagi::Ammus, false);
+        let mut fysiikka = Fysiikka::new();
+        fysiikka.aseta_tormaysmatriisi(matriisi);
+        fysiikka.laske_uudet_sijainnit(&mut kappaleet, &PAIVITYSAIKA);
+
+        // Kappaleita ei erotettu eikä niiden törmäystä merkitty
+        assert!(fysiikka.tormaykset.anna_tormaykset().is_empty());
+        assert!(lahes(keskipiste(&kappaleet[0]).0, -8.84));
+        assert!(lahes(nopeus(&kappaleet[0]).0, 10.0));
+
+        // Muiden kerrosten kappaleet törmäävät edelleen
+        kappaleet[1].aseta_kerrokset(Tagit::from(Tagi::Seina));
+        fysiikka.laske_uudet_sijainnit(&mut kappaleet, &PAIVITYSAIKA);
+        assert!(!fysiikka.tormaykset.anna_tormaykset().is_empty());
+    }
+
+    #[test]
+    fn yksipuolinen_maski_estaa_tormayksen_kummassakin_jarjestyksessa() {
+        for seina_ensin in &[false, true] {
+            let mut ammus = nelio(-9.0, 0.0, (10.0, 0.0));
+            ammus.aseta_kerrokset(Tagit::from(Tagi::Ammus));
+            // Ammus törmäisi seiniin, mutta seinä ei ammuksiin
+            ammus.aseta_maski(Tagit::from(Tagi::Seina));
+            let mut seina = nelio(9.0, 0.0, (0.0, 0.0));
+            seina.aseta_kerrokset(Tagit::from(Tagi::Seina));
+            seina.aseta_maski(Tagit::from(Tagi::Pelaaja));
+            assert!(!ammus.voi_tormata(&seina) && !seina.voi_tormata(&ammus));
+
+            let mut kappaleet = match seina_ensin {
+                true => vec![seina, ammus],
+                false => vec![ammus, seina],
+            };
+            let fysiikka = paivita(&mut kappaleet);
+            assert!(fysiikka.tormaykset.anna_tormaykset().is_empty());
+        }
+    }
+
+    #[test]
+    fn matriisi_ja_maskit_pitaa_sallia_tormays_molemmat() {
+        let mut kappaleet = vec![nelio(-9.0, 0.0, (10.0, 0.0)), nelio(9.0, 0.0, (0.0, 0.0))];
+        kappaleet[0].aseta_kerrokset(Tagit::from(Tagi::Ammus));
+        kappaleet[1].aseta_kerrokset(Tagit::from(Tagi::Seina));
+        let mut matriisi = Tormaysmatriisi::new();
+        matriisi.aseta(Tagi::Ammus, Tagi::Seina, false);
+        let mut fysiikka = Fysiikka::new();
+
+        // Maskit sallivat, mutta matriisi kieltää
+        fysiikka.aseta_tormaysmatriisi(matriisi);
+        fysiikka.laske_uudet_sijainnit(&mut kappaleet, &PAIVITYSAIKA);
+        assert!(fysiikka.tormaykset.anna_tormaykset().is_empty());
+
+        // Matriisi sallii, mutta toisen kappaleen maski kieltää
+        fysiikka.aseta_tormaysmatriisi(Tormaysmatriisi::new());
+        kappaleet[1].aseta_maski(Tagit::from(Tagi::Pelaaja));
+        fysiikka.laske_uudet_sijainnit(&mut kappaleet, &PAIVITYSAIKA);
+        assert!(fysiikka.tormaykset.anna_tormaykset().is_empty());
+
+        kappaleet[1].aseta_maski(Tagit::kaikki());
+        fysiikka.laske_uudet_sijainnit(&mut kappaleet, &PAIVITYSAIKA);
+        assert_eq!(fysiikka.tormaykset.anna_tormaykset().len(), 2);
+    }
+
+    #[test]
+    fn matriisin_rivit_pysyvat_symmetrisina() {
+        let mut matriisi = Tormaysmatriisi::new();
+        matriisi.aseta_rivi(Tagi::Ammus, Tagit::from(Tagi::Seina));
+        // Myöhempi rivi kumoaa aiemman rivin vastaavan kohdan
+        matriisi.aseta_rivi(Tagi::Seina, Tagit::from(Tagi::Pelaaja));
+        assert!(!matriisi.rivi(Tagi::Ammus).sisaltaa(Tagi::Seina));
+        assert!(matriisi.rivi(Tagi::Pelaaja).sisaltaa(Tagi::Seina));
+
+        // Usean kerroksen kappale törmää, jos yksikin sen kerroksista törmää
+        let oma = Tagirekisteri::new().rekisteroi("lentava").unwrap();
+        let mut monikerroksinen = Tagit::from(Tagi::Ammus);
+        monikerroksinen.lisaa(oma);
+        let seina = Tagit::from(Tagi::Seina);
+        let mut matriisi = Tormaysmatriisi::new();
+        matriisi.aseta(Tagi::Ammus, Tagi::Seina, false);
+        assert!(matriisi.tormaavat(monikerroksinen, seina));
+        assert!(matriisi.tormaavat(seina, monikerroksinen));
+        matriisi.aseta(oma, Tagi::Seina, false);
+        assert!(!matriisi.tormaavat(monikerroksinen, seina));
+        assert!(!matriisi.tormaavat(seina, monikerroksinen));
+    }
+
+    #[test]
+    fn maski_ohittaa_myos_nopean_kappaleen_osumat() {
+        let mut ammus = kappale(Muoto::Ympyra(luku(5.0)), 0.0, 0.0, (260.0, 0.0));
+        ammus.aseta_nopea(true);
+        ammus.aseta_maski(Tagit::from(Tagi::Pelaaja));
+        let mut kappaleet = vec![ammus, seina(4.0, 200.0, 30.0, 0.0)];
+        let mut fysiikka = Fysiikka::new();
+        fysiikka.laske_uudet_sijainnit(&mut kappaleet, &Duration::from_millis(200));
+
+        assert!(lahes(keskipiste(&kappaleet[0]).0, 52.0));
+        assert!(fysiikka.tormaykset.anna_tormaykset().is_empty());
+    }
+
+    #[test]
+    fn staattiseen_kappaleeseen_ei_keraannu_voimaa() {
+        let mut kappaleet = vec![seina(20.0, 20.0, 0.0, 0.0)];
+        kappaleet[0].lisaa_voima(Vektori::new(luku(100.0), luku(0.0)));
+        kappaleet[0].aseta_tavoitenopeus(Vektori::new(luku(50.0), luku(0.0)), luku(100.0));
+        paivita_sekunti(&mut kappaleet, Integrointi::default());
+        assert!(kappaleet[0].anna_voima() == Vektori::default());
+        assert_eq!(keskipiste(&kappaleet[0]), (0.0, 0.0));
+
+        // Dynaamiseksi muutettu kappale ei saa vanhoja voimia tai tavoitteita
+        kappaleet[0].aseta_liikkuvuus(Liikkuvuus::Dynaaminen);
+        paivita_sekunti(&mut kappaleet, Integrointi::default());
+        assert_eq!(nopeus(&kappaleet[0]), (0.0, 0.0));
+        assert_eq!(keskipiste(&kappaleet[0]), (0.0, 0.0));
+    }
+
+    /// Neliö, jota on kierretty 45 astetta. Sen kärjet ovat akseleilla 14.14 päässä
+    /// keskipisteestä.
+    fn kierretty_nelio(x: f32, y: f32) -> Fysiikkakappale {
+        let mut kappale = nelio(x, y, (0.0, 0.0));
+        kappale.aseta_kulma(luku(FRAC_PI_4));
+        kappale
+    }
+
+    #[test]
+    fn kierretty_nelio_tormaa_kulmallaan() {
+        // Seinän vasen reuna on kohdassa x = 12 ja kierretyn neliön kärki kohdassa x = 14.14
+        let mut kappaleet = vec![seina(20.0, 20.0, 22.0, 0.0), kierretty_nelio(0.0, 0.0)];
+        let fysiikka = paivita(&mut kappaleet);
+        assert_eq!(fysiikka.tormaykset.anna_tormaykset().len(), 2);
+        // Kärki työnnetään seinän pintaan ja kierto säilyy
+        let (x, y) = keskipiste(&kappaleet[1]);
+        assert!(x < 12.0 - 14.14 + 0.05, "x = {}", x);
+        assert!(lahes(y, 0.0), "y = {}", y);
+        assert!(kappaleet[1].anna_kulma().lahes_sama(luku(FRAC_PI_4), 0.001));
+    }
+
+    #[test]
+    fn kierretty_nelio_ei_tormaa_rajauksensa_sisalla() {
+        // Seinä on kierretyn neliön rajauksen (-14.14..14.14) kulmassa, mutta neliön
+        // lähin reuna x + y = 14.14 jää kauas seinän kulmasta (12, 12)
+        let mut kappaleet = vec![seina(20.0, 20.0, 22.0, 22.0), kierretty_nelio(0.0, 0.0)];
+        let fysiikka = paivita(&mut kappaleet);
+        assert!(fysiikka.tormaykset.anna_tormaykset().is_empty());
+        assert_eq!(keskipiste(&kappaleet[1]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn pystyyn_kierretty_suorakaide_ja_ympyra() {
+        // Suorakaide 40 x 10 kierretään pystyyn, jolloin se on välillä x = -5..5, y = -20..20
+        let pystyyn = |ympyran_x: f32, ympyran_y: f32| {
+            let mut suorakaide = seina(40.0, 10.0, 0.0, 0.0);
+            suorakaide.aseta_kulma(luku(FRAC_PI_2));
+            let ympyra = kappale(Muoto::Ympyra(luku(5.0)), ympyran_x, ympyran_y, (0.0, 0.0));
+            let mut kappaleet = vec![suorakaide, ympyra];
+            let fysiikka = paivita(&mut kappaleet);
+            (
+                keskipiste(&kappaleet[1]),
+                fysiikka.tormaykset.anna_tormaykset().len(),
+            )
+        };
+        // Kiertämätön suorakaide osuisi ympyrään, kierretty ei
+        assert_eq!(pystyyn(12.0, 0.0), ((12.0, 0.0), 0));
+        // Kiertämätön suorakaide ei osuisi ympyrään, kierretty työntää sen päänsä yli
+        let ((x, y), tormayksia) = pystyyn(0.0, -22.0);
+        assert_eq!(tormayksia, 2);
+        assert!(lahes(x, 0.0), "x = {}", x);
+        assert!(y < -25.0 + 0.05, "y = {}", y);
+    }
+}
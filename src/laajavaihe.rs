@@ -0,0 +1,393 @@
+//! Törmäystarkistuksen laaja vaihe. Laaja vaihe etsii kappaleiden rajauksista parit, jotka
+//! voivat törmätä, jotta tarkka törmäystarkistus tehdään vain niille eikä kaikille pareille.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::maailma::kappale::{Kappale, Rajaus};
+use crate::maailma::luku::{luku, Luku, Skalaari};
+use crate::maailma::ruudukko::Ruudukko;
+use crate::maailma::vektori::Vektori;
+use crate::maailma::RcKappale;
+
+/// Ruudukon oletuksena käyttämä ruudun sivun pituus
+const OLETUS_RUUDUN_KOKO: Luku = luku(64.0);
+
+/// Ruudun koordinaatit ruudukossa
+type Ruutu = (i32, i32);
+
+/// Tapa etsiä mahdollisesti törmäävät kappaleparit
+pub trait Laajavaihe {
+    /// Antaa indeksiparit `(i, j)`, joissa `i < j` ja kappaleiden rajaukset leikkaavat.
+    /// Kahden staattisen kappaleen paria ei anneta, koska staattiset kappaleet eivät liiku.
+    /// Kukin pari annetaan vain kerran, mutta parien järjestys voi olla mikä tahansa.
+    /// # Arguments
+    /// * `rajaukset` - Kappaleiden rajaukset kappaleiden järjestyksessä
+    /// * `staattiset` - Onko kukin kappale staattinen samassa järjestyksessä
+    fn ehdokasparit(&mut self, rajaukset: &[Rajaus], staattiset: &[bool]) -> Vec<(usize, usize)>;
+}
+
+/// Laaja vaihe, joka vertaa jokaista paria keskenään. Hidas, mutta sopii vertailukohdaksi.
+#[derive(Default)]
+pub struct KaikkiParit;
+
+impl Laajavaihe for KaikkiParit {
+    fn ehdokasparit(&mut self, rajaukset: &[Rajaus], staattiset: &[bool]) -> Vec<(usize, usize)> {
+        let mut parit = Vec::new();
+        for i in 0..rajaukset.len() {
+            for j in i + 1..rajaukset.len() {
+                if !(staattiset[i] && staattiset[j]) && rajaukset[i].leikkaa(&rajaukset[j]) {
+                    parit.push((i, j));
+                }
+            }
+        }
+        parit
+    }
+}
+
+/// Laaja vaihe, joka jakaa maailman samankokoisiin ruutuihin. Vain samaan ruutuun osuvat
+/// kappaleet verrataan keskenään. Sopii, kun kappaleet ovat suunnilleen ruudun kokoisia.
+pub struct Ruudukkovaihe {
+    /// Ruudun sivun pituus
+    ruudun_koko: Luku,
+    /// Kuhunkin ruutuun osuvien kappaleiden indeksit
+    ruudut: HashMap<Ruutu, Vec<usize>>,
+}
+
+impl Default for Ruudukkovaihe {
+    fn default() -> Self {
+        Self::new(OLETUS_RUUDUN_KOKO)
+    }
+}
+
+impl Ruudukkovaihe {
+    /// Luo ruudukon, jonka ruuduilla on annettu koko
+    /// # Arguments
+    /// * `ruudun_koko` - Yhden ruudun sivun pituus
+    pub fn new(ruudun_koko: Luku) -> Self {
+        Ruudukkovaihe {
+            ruudun_koko,
+            ruudut: HashMap::new(),
+        }
+    }
+
+    /// Antaa ruudun, jossa annettu piste on
+    fn ruutu(&self, piste: Vektori) -> Ruutu {
+        (
+            (piste.x / self.ruudun_koko).floor().liukuluku() as i32,
+            (piste.y / self.ruudun_koko).floor().liukuluku() as i32,
+        )
+    }
+}
+
+impl Laajavaihe for Ruudukkovaihe {
+    fn ehdokasparit(&mut self, rajaukset: &[Rajaus], staattiset: &[bool]) -> Vec<(usize, usize)> {
+        self.ruudut.clear();
+        for (indeksi, rajaus) in rajaukset.iter().enumerate() {
+            let (x0, y0) = self.ruutu(rajaus.vasen_yla);
+            let (x1, y1) = self.ruutu(rajaus.oikea_ala);
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    self.ruudut.entry((x, y)).or_default().push(indeksi);
+                }
+            }
+        }
+
+        let mut parit = Vec::new();
+        for (ruutu, indeksit) in &self.ruudut {
+            for (k, &i) in indeksit.iter().enumerate() {
+                for &j in &indeksit[k + 1..] {
+                    let (a, b) = (&rajaukset[i], &rajaukset[j]);
+                    if (staattiset[i] && staattiset[j]) || !a.leikkaa(b) {
+                        continue;
+                    }
+                    // Pari annetaan vain siinä ruudussa, jossa rajausten leikkauksen
+                    // vasen yläkulma on, jotta useaan ruutuun osuvat parit eivät toistu
+                    let leikkauksen_kulma = Vektori::new(
+                        a.vasen_yla.x.max(b.vasen_yla.x),
+                        a.vasen_yla.y.max(b.vasen_yla.y),
+                    );
+                    if self.ruutu(leikkauksen_kulma) == *ruutu {
+                        parit.push((i.min(j), i.max(j)));
+                    }
+                }
+            }
+        }
+        parit
+    }
+}
+
+/// Laaja vaihe, joka järjestää kappaleet x-akselin mukaan ja vertaa vain kappaleita, joiden
+/// rajaukset ovat x-akselilla päällekkäin (sweep and prune). Staattisia kappaleita ei verrata
+/// keskenään lainkaan. Ei vaadi säätämistä.
+#[derive(Default)]
+pub struct Pyyhkaisyvaihe;
+
+impl Laajavaihe for Pyyhkaisyvaihe {
+    fn ehdokasparit(&mut self, rajaukset: &[Rajaus], staattiset: &[bool]) -> Vec<(usize, usize)> {
+        let mut jarjestys: Vec<usize> = (0..rajaukset.len()).collect();
+        jarjestys.sort_by(|a, b| {
+            rajaukset[*a]
+                .vasen_yla
+                .x
+                .partial_cmp(&rajaukset[*b].vasen_yla.x)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let mut parit = Vec::new();
+        // Kappaleet, joiden rajaus ulottuu vielä pyyhkäisykohtaan asti. Staattiset pidetään
+        // erikseen, jotta staattista kappaletta verrataan vain liikkuviin.
+        let mut aktiiviset: Vec<usize> = Vec::new();
+        let mut aktiiviset_staattiset: Vec<usize> = Vec::new();
+        for i in jarjestys {
+            let rajaus = &rajaukset[i];
+            aktiiviset.retain(|a| rajaukset[*a].oikea_ala.x >= rajaus.vasen_yla.x);
+            aktiiviset_staattiset.retain(|a| rajaukset[*a].oikea_ala.x >= rajaus.vasen_yla.x);
+            let staattiset_verrattavat = if staattiset[i] {
+                &[][..]
+            } else {
+                &aktiiviset_staattiset[..]
+            };
+            for &a in aktiiviset.iter().chain(staattiset_verrattavat) {
+                if rajaukset[a].leikkaa(rajaus) {
+                    parit.push((a.min(i), a.max(i)));
+                }
+            }
+            if staattiset[i] {
+                aktiiviset_staattiset.push(i);
+            } else {
+                aktiiviset.push(i);
+            }
+        }
+        parit
+    }
+}
+
+/// Laaja vaihe, joka hakee parit maailman alueindeksistä. Alueindeksi päivitetään ensin
+/// kappaleiden nykyisiin sijainteihin, joten sama indeksi on päivityksen jälkeen ajan tasalla
+/// myös muita sijaintiin perustuvia hakuja varten. Annettujen rajausten tulee olla
+/// fysiikkakappaleiden nykyiset rajaukset.
+pub struct Alueindeksivaihe<'a> {
+    /// Maailman alueindeksi
+    alueindeksi: &'a mut Ruudukko,
+    /// Maailman kaikki kappaleet siinä järjestyksessä, jossa ne päivitetään alueindeksiin
+    kappaleet: &'a [RcKappale],
+    /// Fysiikkakappaleiden indeksit kappaleiden mukaan
+    indeksit: HashMap<*const Kappale, usize>,
+}
+
+impl<'a> Alueindeksivaihe<'a> {
+    /// Luo laajan vaiheen maailman alueindeksistä
+    /// # Arguments
+    /// * `alueindeksi` - Maailman alueindeksi
+    /// * `kappaleet` - Maailman kaikki kappaleet
+    /// * `fysiikalliset` - Fysiikkakappaleiden kappaleet samassa järjestyksessä kuin rajaukset
+    pub fn new(
+        alueindeksi: &'a mut Ruudukko,
+        kappaleet: &'a [RcKappale],
+        fysiikalliset: &[RcKappale],
+    ) -> Self {
+        Alueindeksivaihe {
+            alueindeksi,
+            kappaleet,
+            indeksit: fysiikalliset
+                .iter()
+                .enumerate()
+                .map(|(i, x)| (x.as_ptr() as *const Kappale, i))
+                .collect(),
+        }
+    }
+}
+
+impl<'a> Laajavaihe for Alueindeksivaihe<'a> {
+    fn ehdokasparit(&mut self, rajaukset: &[Rajaus], staattiset: &[bool]) -> Vec<(usize, usize)> {
+        self.alueindeksi.paivita_kaikki(self.kappaleet);
+        let mut parit = Vec::new();
+        for (i, rajaus) in rajaukset.iter().enumerate() {
+            for kappale in self.alueindeksi.alueella(rajaus) {
+                match self.indeksit.get(&(kappale.as_ptr() as *const Kappale)) {
+                    Some(&j) if j > i && !(staattiset[i] && staattiset[j]) => parit.push((i, j)),
+                    _ => (),
+                }
+            }
+        }
+        parit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::fysiikka::{Fysiikka, Fysiikkakappale, Liikkuvuus};
+    use crate::maailma::kappale::{Kappale, Muoto, Tagi};
+    use crate::maailma::Lisaosa;
+
+    /// Yksinkertainen toistettava satunnaislukujen lähde väliltä 0..1
+    struct Satunnainen(u64);
+
+    impl Satunnainen {
+        fn seuraava(&mut self) -> f32 {
+            self.0 = self
+                .0
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1);
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32
+        }
+    }
+
+    /// Luo staattiset seinät alueen reunoille sekä pieniä ammuksia ja suurempia vihollisia
+    /// alueen sisälle
+    fn kappaleet(maara: usize) -> Vec<Fysiikkakappale> {
+        let mut satunnainen = Satunnainen(7);
+        let koko = 2000.0;
+        let mut kappaleet = vec![
+            (Muoto::Nelio(luku(koko), luku(20.0)), koko / 2.0, 0.0),
+            (Muoto::Nelio(luku(koko), luku(20.0)), koko / 2.0, koko),
+            (Muoto::Nelio(luku(20.0), luku(koko)), 0.0, koko / 2.0),
+            (Muoto::Nelio(luku(20.0), luku(koko)), koko, koko / 2.0),
+        ];
+        for i in 0..maara {
+            let muoto = match i % 3 {
+                0 => Muoto::Nelio(luku(30.0), luku(30.0)),
+                1 => Muoto::Kapseli(luku(20.0), luku(8.0)),
+                _ => Muoto::Ympyra(luku(5.0)),
+            };
+            let x = satunnainen.seuraava() * koko;
+            let y = satunnainen.seuraava() * koko;
+            kappaleet.push((muoto, x, y));
+        }
+        kappaleet
+            .into_iter()
+            .enumerate()
+            .map(|(i, (muoto, x, y))| {
+                let kappale =
+                    Kappale::new_keskipisteella(muoto, luku(x), luku(y), Tagi::Vihollinen);
+                let nopeus = Vektori::new(luku(100.0), luku(-50.0));
+                let mut fysiikka = Fysiikkakappale::new(nopeus, Rc::new(RefCell::new(kappale)));
+                if i < 4 {
+                    fysiikka.aseta_liikkuvuus(Liikkuvuus::Staattinen);
+                }
+                fysiikka
+            })
+            .collect()
+    }
+
+    fn rajaukset(kappaleet: &[Fysiikkakappale]) -> Vec<Rajaus> {
+        kappaleet
+            .iter()
+            .map(|x| x.anna_kappale().borrow().rajaus())
+            .collect()
+    }
+
+    fn jarjestetyt(
+        laajavaihe: &mut dyn Laajavaihe,
+        rajaukset: &[Rajaus],
+        staattiset: &[bool],
+    ) -> Vec<(usize, usize)> {
+        let mut parit = laajavaihe.ehdokasparit(rajaukset, staattiset);
+        parit.sort_unstable();
+        parit
+    }
+
+    #[test]
+    fn ruudukko_ja_pyyhkaisy_antavat_samat_parit_kuin_kaikki_parit() {
+        let kappaleet = kappaleet(300);
+        let rajaukset = rajaukset(&kappaleet);
+        let staattiset: Vec<bool> = kappaleet
+            .iter()
+            .map(|x| x.anna_liikkuvuus() == Liikkuvuus::Staattinen)
+            .collect();
+        let odotetut = jarjestetyt(&mut KaikkiParit, &rajaukset, &staattiset);
+        assert!(odotetut.iter().all(|(i, j)| i < j));
+        assert!(!odotetut.is_empty());
+        // Kulmissa toisiaan koskettavat staattiset seinät eivät ole pari
+        assert!(odotetut
+            .iter()
+            .all(|(i, j)| !(staattiset[*i] && staattiset[*j])));
+        assert!(rajaukset[0].leikkaa(&rajaukset[2]));
+        for laajavaihe in &mut [
+            Box::<Ruudukkovaihe>::default() as Box<dyn Laajavaihe>,
+            Box::new(Ruudukkovaihe::new(luku(7.0))),
+            Box::new(Pyyhkaisyvaihe),
+        ] {
+            assert_eq!(
+                jarjestetyt(laajavaihe.as_mut(), &rajaukset, &staattiset),
+                odotetut
+            );
+        }
+        let rc_kappaleet: Vec<_> = kappaleet.iter().map(|x| x.anna_kappale()).collect();
+        let mut alueindeksi = Ruudukko::default();
+        let mut laajavaihe = Alueindeksivaihe::new(&mut alueindeksi, &rc_kappaleet, &rc_kappaleet);
+        assert_eq!(
+            jarjestetyt(&mut laajavaihe, &rajaukset, &staattiset),
+            odotetut
+        );
+        // Alueindeksi on päivitetty kappaleiden sijainteihin
+        let seinan_leikkaavat = rajaukset.iter().filter(|x| x.leikkaa(&rajaukset[0]));
+        assert_eq!(
+            alueindeksi.alueella(&rajaukset[0]).len(),
+            seinan_leikkaavat.count()
+        );
+    }
+
+    #[test]
+    fn tormaykset_ovat_samat_laajasta_vaiheesta_riippumatta() {
+        let tormaykset = |laajavaihe: Box<dyn Laajavaihe>| {
+            let mut kappaleet = kappaleet(300);
+            let mut fysiikka = Fysiikka::new();
+            fysiikka.aseta_laajavaihe(laajavaihe);
+            fysiikka.laske_uudet_sijainnit(&mut kappaleet, &Duration::from_millis(16));
+            fysiikka
+                .tormaykset
+                .anna_tormaykset()
+                .iter()
+                .map(|x| (x.indeksi, x.anna_kohteet().to_vec()))
+                .collect::<Vec<_>>()
+        };
+        let odotetut = tormaykset(Box::new(KaikkiParit));
+        // Törmäys merkitään molemmille osapuolille
+        for (indeksi, kohteet) in &odotetut {
+            for kohde in kohteet {
+                let kohteen = odotetut.iter().find(|x| x.0 == *kohde).unwrap();
+                assert!(kohteen.1.contains(indeksi));
+            }
+        }
+        assert_eq!(tormaykset(Box::<Ruudukkovaihe>::default()), odotetut);
+        assert_eq!(tormaykset(Box::new(Pyyhkaisyvaihe)), odotetut);
+    }
+
+    /// Vertaa laajoja vaiheita kaikkien parien vertaamiseen. Ajetaan komennolla
+    /// `cargo test --release laajojen_vaiheiden_vertailu -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn laajojen_vaiheiden_vertailu() {
+        let laajavaihe = |nimi: &str| -> Box<dyn Laajavaihe> {
+            match nimi {
+                "ruudukko" => Box::<Ruudukkovaihe>::default(),
+                "pyyhkäisy" => Box::new(Pyyhkaisyvaihe),
+                _ => Box::new(KaikkiParit),
+            }
+        };
+        let paivityksia = 60;
+        for maara in &[100, 300, 1000, 3000] {
+            for nimi in &["kaikki parit", "ruudukko", "pyyhkäisy"] {
+                let mut kappaleet = kappaleet(*maara);
+                let alku = Instant::now();
+                for _ in 0..paivityksia {
+                    let mut fysiikka = Fysiikka::new();
+                    fysiikka.aseta_laajavaihe(laajavaihe(nimi));
+                    fysiikka.laske_uudet_sijainnit(&mut kappaleet, &Duration::from_millis(16));
+                }
+                println!(
+                    "{:>5} kappaletta, {:<12} {:>10.3} ms päivitystä kohden",
+                    maara,
+                    nimi,
+                    alku.elapsed().as_secs_f64() * 1000.0 / f64::from(paivityksia)
+                );
+            }
+        }
+    }
+}
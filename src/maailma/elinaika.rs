@@ -0,0 +1,201 @@
+//! Sisältää elinajan, jolla kappale voidaan poistaa maailmasta tietyn ajan kuluttua
+//! tai kun se on kulkenut tietyn matkan. Esim. ammus, joka karkaa seinien ulkopuolelle,
+//! ei jää maailmaan ikuisiksi ajoiksi.
+use std::rc::Rc;
+use std::time::Duration;
+
+use super::luku::Luku;
+use super::vektori::Vektori;
+use super::{Lisaosa, RcKappale};
+use crate::animointi::Kuolevainen;
+
+/// Lisäosa, joka poistaa kappaleen sen kuolinaikana tai kun se on kulkenut kantamansa verran
+pub struct Elinaika {
+    /// Kappale, joka kuolee kuolinaikana. Ilman aikarajaa kuolinaika on `Duration::MAX`.
+    kappale: Kuolevainen<RcKappale>,
+    /// Matka, jonka kappale saa vielä kulkea
+    kantama: Option<Luku>,
+    /// Kappaleen keskipiste edellisellä päivityksellä, josta kuljettu matka lasketaan
+    edellinen_sijainti: Vektori,
+}
+
+impl Elinaika {
+    /// Luo elinajan, jossa kappale kuolee annettuna pelin aikana
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka elinaikaa rajataan
+    /// * `kuolin_aika` - Pelin aika, jolloin kappale poistetaan
+    pub fn new(kappale: RcKappale, kuolin_aika: Duration) -> Self {
+        let edellinen_sijainti = kappale.borrow().keskipisteen_sijainti();
+        Elinaika {
+            kappale: Kuolevainen::new(kappale, kuolin_aika),
+            kantama: None,
+            edellinen_sijainti,
+        }
+    }
+
+    /// Luo elinajan, jossa kappale kuolee kuljettuaan annetun matkan
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka kulkemaa matkaa rajataan
+    /// * `kantama` - Matka, jonka kappale saa kulkea
+    pub fn new_kantamalla(kappale: RcKappale, kantama: Luku) -> Self {
+        let mut elinaika = Elinaika::new(kappale, Duration::MAX);
+        elinaika.kantama = Some(kantama);
+        elinaika
+    }
+
+    /// Rajaa myös kappaleen kulkeman matkan
+    /// # Arguments
+    /// * `kantama` - Matka, jonka kappale saa vielä kulkea
+    pub fn aseta_kantama(&mut self, kantama: Option<Luku>) {
+        self.kantama = kantama;
+    }
+
+    /// Antaa pelin ajan, jolloin kappale poistetaan, jos aikaa on rajattu
+    pub fn kuolin_aika(&self) -> Option<Duration> {
+        match self.kappale.kuolin_aika() {
+            Duration::MAX => None,
+            aika => Some(aika),
+        }
+    }
+
+    /// Antaa matkan, jonka kappale saa vielä kulkea, jos matkaa on rajattu
+    pub fn kantama(&self) -> Option<Luku> {
+        self.kantama
+    }
+
+    /// Siirtää sijaintia, josta kuljettu matka lasketaan. Käytetään, kun kappale siirretään
+    /// hyppäyksellä, jotta hyppäystä ei lasketa kuljetuksi matkaksi.
+    /// # Arguments
+    /// * `siirtyma` - Siirtymä, jonka verran kappale siirrettiin
+    pub fn siirra(&mut self, siirtyma: Vektori) {
+        self.edellinen_sijainti += siirtyma;
+    }
+
+    /// Päivittää kuljetun matkan kappaleen nykyisen sijainnin perusteella ja kertoo,
+    /// kuuluuko kappale poistaa
+    /// # Arguments
+    /// * `aika` - Pelin nykyinen aika
+    pub fn paivita(&mut self, aika: Duration) -> bool {
+        let sijainti = self.kappale.borrow().keskipisteen_sijainti();
+        let kuljettu = (sijainti - self.edellinen_sijainti).pituus();
+        self.edellinen_sijainti = sijainti;
+        if let Some(kantama) = &mut self.kantama {
+            *kantama -= kuljettu;
+            if *kantama <= 0.0 {
+                return true;
+            }
+        }
+        self.kappale.kuoleeko(&aika)
+    }
+}
+
+impl Lisaosa for Elinaika {
+    fn anna_kappale(&self) -> RcKappale {
+        Rc::clone(&self.kappale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::fysiikka::Fysiikkakappale;
+    use crate::maailma::kappale::{Kappale, Muoto, Rajaus, Tagi};
+    use crate::maailma::luku::luku;
+    use crate::maailma::rajat::{Maailmanrajat, Rajatoiminta};
+    use crate::maailma::Perusmaailma;
+
+    fn ammus(x: f32) -> Kappale {
+        Kappale::new_keskipisteella(Muoto::Ympyra(luku(1.0)), luku(x), luku(50.0), Tagi::Ammus)
+    }
+
+    fn siirra(kappale: &RcKappale, x: f32) {
+        kappale
+            .borrow_mut()
+            .aseta_keskipisteen_sijainti(Vektori::new(luku(x), luku(50.0)));
+    }
+
+    fn s(sekunnit: u64) -> Duration {
+        Duration::from_secs(sekunnit)
+    }
+
+    #[test]
+    fn kantama_kuluu_kuljetun_matkan_mukaan() {
+        let kappale = Rc::new(RefCell::new(ammus(0.0)));
+        let mut elinaika = Elinaika::new_kantamalla(Rc::clone(&kappale), luku(10.0));
+        assert_eq!(elinaika.kuolin_aika(), None);
+        assert!(!elinaika.paivita(s(0)));
+        siirra(&kappale, 6.0);
+        assert!(!elinaika.paivita(s(1)));
+        assert_eq!(elinaika.kantama(), Some(luku(4.0)));
+        // Matka lasketaan kumpaankin suuntaan
+        siirra(&kappale, 3.0);
+        assert!(!elinaika.paivita(s(2)));
+        assert_eq!(elinaika.kantama(), Some(luku(1.0)));
+        // Kantaman loppuun kulkenut kappale poistetaan
+        siirra(&kappale, 2.0);
+        assert!(elinaika.paivita(s(3)));
+    }
+
+    #[test]
+    fn aikaraja_ja_kantama_yhdessa() {
+        let kappale = Rc::new(RefCell::new(ammus(0.0)));
+        let mut elinaika = Elinaika::new(Rc::clone(&kappale), s(5));
+        assert_eq!(elinaika.kuolin_aika(), Some(s(5)));
+        assert!(!elinaika.paivita(s(4)));
+        assert!(elinaika.paivita(s(5)));
+
+        // Kumpi tahansa rajoista poistaa kappaleen
+        let mut elinaika = Elinaika::new(Rc::clone(&kappale), s(5));
+        elinaika.aseta_kantama(Some(luku(100.0)));
+        siirra(&kappale, 20.0);
+        assert!(!elinaika.paivita(s(1)));
+        assert!(elinaika.paivita(s(6)));
+        let mut elinaika = Elinaika::new(Rc::clone(&kappale), s(5));
+        elinaika.aseta_kantama(Some(luku(10.0)));
+        siirra(&kappale, 40.0);
+        assert!(elinaika.paivita(s(1)));
+    }
+
+    #[test]
+    fn maailma_poistaa_vanhentuneet() {
+        let mut maailma = Perusmaailma::new();
+        let lyhyt = maailma.lisaa_kappale(ammus(0.0));
+        let pitka = maailma.lisaa_kappale(ammus(10.0));
+        maailma.lisaa_lisaosa(Elinaika::new(Rc::clone(&lyhyt), s(1)));
+        maailma.lisaa_lisaosa(Elinaika::new(Rc::clone(&pitka), s(2)));
+        maailma.paivita_elinajat(s(1));
+        maailma.poista_poistettavat();
+        assert_eq!(maailma.kappaleet().len(), 1);
+        assert!(Rc::ptr_eq(&maailma.kappaleet()[0], &pitka));
+        assert_eq!(maailma.lisaosat::<Elinaika>().len(), 1);
+    }
+
+    #[test]
+    fn kierto_ei_kuluta_kantamaa() {
+        let mut maailma = Perusmaailma::new();
+        let mut rajat = Maailmanrajat::new(Rajaus::new(
+            Vektori::new(luku(0.0), luku(0.0)),
+            Vektori::new(luku(100.0), luku(100.0)),
+        ));
+        rajat.oletus = Some(Rajatoiminta::Kierto);
+        maailma.aseta_rajat(Some(rajat));
+        let kappale = maailma.lisaa_kappale(ammus(95.0));
+        let nopeus = Vektori::new(luku(6.0), luku(0.0));
+        maailma.lisaa_fysiikkakappale(Fysiikkakappale::new(nopeus, Rc::clone(&kappale)));
+        maailma.lisaa_lisaosa(Elinaika::new_kantamalla(Rc::clone(&kappale), luku(10.0)));
+
+        siirra(&kappale, 101.0);
+        maailma.sovella_rajoja();
+        assert_eq!(
+            kappale.borrow().keskipisteen_sijainti(),
+            Vektori::new(luku(1.0), luku(50.0))
+        );
+        maailma.paivita_elinajat(s(1));
+        let elinaika = maailma.anna_lisaosa::<Elinaika>(&kappale).unwrap();
+        assert_eq!(elinaika.kantama(), Some(luku(4.0)));
+        maailma.poista_poistettavat();
+        assert_eq!(maailma.kappaleet().len(), 1);
+    }
+}
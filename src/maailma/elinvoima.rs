@@ -0,0 +1,354 @@
+//! Sisältää kappaleiden elinvoiman ja vahingon. Kappale, jolla on elinvoima, voi ottaa
+//! vahinkoa vahingoittajilta ja kuolla, jolloin tapahtuu kappaleen kuolemalle asetetut seuraukset.
+use std::rc::Rc;
+use std::time::Duration;
+
+use super::kappale::Kappale;
+use super::luku::Luku;
+use super::tagit::Tagit;
+use super::{Lisaosa, RcKappale};
+use crate::piirtaja::Piirtotapa;
+
+/// Seuraus, joka tapahtuu kappaleen kuollessa
+#[derive(Clone)]
+pub enum Kuolema {
+    /// Kappale poistetaan maailmasta
+    Poisto,
+    /// Kappaleen paikalle jää katoamisanimaatio
+    Animaatio,
+    /// Kappaleen keskipisteeseen luodaan annettu kappale, esim. esine
+    Pudotus {
+        /// Luotava kappale, jonka keskipiste siirretään kuolleen kappaleen keskipisteeseen
+        kappale: Kappale,
+        /// Luotavan kappaleen piirtotapa
+        piirtotapa: Piirtotapa,
+    },
+    /// Peli päättyy
+    PelinLoppu,
+}
+
+/// Kappaleen elinvoiman ominaisuudet, jotka eivät muutu pelin aikana. Voidaan antaa
+/// esim. spawnerille, joka luo samanlaisia kappaleita.
+#[derive(Clone)]
+pub struct Kestavyys {
+    /// Elämien enimmäismäärä
+    pub maksimi: Luku,
+    /// Kuinka kauan kappale on suojassa uudelta vahingolta otettuaan vahinkoa
+    pub suojausaika: Duration,
+    /// Seuraukset, jotka tapahtuvat kappaleen kuollessa
+    pub kuolemat: Vec<Kuolema>,
+}
+
+impl Kestavyys {
+    /// Luo kestävyyden ilman suojausaikaa. Kuollessaan kappale poistetaan.
+    /// # Arguments
+    /// * `maksimi` - Elämien enimmäismäärä
+    pub fn new(maksimi: Luku) -> Self {
+        Kestavyys {
+            maksimi,
+            suojausaika: Duration::new(0, 0),
+            kuolemat: vec![Kuolema::Poisto],
+        }
+    }
+}
+
+/// Lisäosa, joka antaa kappaleelle elämät
+pub struct Elinvoima {
+    kappale: RcKappale,
+    /// Jäljellä olevat elämät
+    elamat: Luku,
+    /// Pelin aika, johon asti kappale on suojassa vahingolta
+    suojattu_asti: Duration,
+    /// Elinvoiman ominaisuudet
+    pub kestavyys: Kestavyys,
+}
+
+impl Elinvoima {
+    /// Luo elinvoiman, jossa elämät ovat täynnä
+    /// # Arguments
+    /// * `kappale` - Kappale, jolle elinvoima annetaan
+    /// * `kestavyys` - Elinvoiman ominaisuudet
+    pub fn new(kappale: RcKappale, kestavyys: Kestavyys) -> Self {
+        Elinvoima {
+            kappale,
+            elamat: kestavyys.maksimi,
+            suojattu_asti: Duration::new(0, 0),
+            kestavyys,
+        }
+    }
+
+    /// Antaa jäljellä olevat elämät
+    pub fn elamat(&self) -> Luku {
+        self.elamat
+    }
+
+    /// Asettaa jäljellä olevat elämät. Elämät rajataan nollan ja enimmäismäärän välille.
+    /// # Arguments
+    /// * `elamat` - Uudet elämät
+    pub fn aseta_elamat(&mut self, elamat: Luku) {
+        self.elamat = elamat.clamp(Luku::default(), self.kestavyys.maksimi);
+    }
+
+    /// Lisää elämiä enintään enimmäismäärään asti. Kuollutta ei voi parantaa.
+    /// # Arguments
+    /// * `maara` - Lisättävien elämien määrä
+    pub fn paranna(&mut self, maara: Luku) {
+        if !self.onko_kuollut() {
+            self.aseta_elamat(self.elamat + maara);
+        }
+    }
+
+    /// Onko kappaleen elämät loppuneet
+    pub fn onko_kuollut(&self) -> bool {
+        self.elamat <= Luku::default()
+    }
+
+    /// Antaa pelin ajan, johon asti kappale on suojassa vahingolta
+    pub fn suojattu_asti(&self) -> Duration {
+        self.suojattu_asti
+    }
+
+    /// Asettaa pelin ajan, johon asti kappale on suojassa vahingolta
+    /// # Arguments
+    /// * `aika` - Pelin aika, johon asti suojaus kestää
+    pub fn aseta_suojattu_asti(&mut self, aika: Duration) {
+        self.suojattu_asti = aika;
+    }
+
+    /// Onko kappale suojassa vahingolta annetulla hetkellä
+    /// # Arguments
+    /// * `aika` - Pelin aika
+    pub fn onko_suojattu(&self, aika: Duration) -> bool {
+        aika < self.suojattu_asti
+    }
+
+    /// Vähentää elämiä, jos kappale ei ole suojassa tai jo kuollut. Vahingon jälkeen
+    /// kappale on suojassa suojausajan verran. Palauttaa, otettiinko vahinkoa.
+    /// # Arguments
+    /// * `maara` - Vahingon määrä
+    /// * `aika` - Pelin aika, jolloin vahinko tapahtuu
+    pub fn ota_vahinkoa(&mut self, maara: Luku, aika: Duration) -> bool {
+        if self.onko_kuollut() || self.onko_suojattu(aika) {
+            return false;
+        }
+        self.aseta_elamat(self.elamat - maara);
+        self.suojattu_asti = aika + self.kestavyys.suojausaika;
+        true
+    }
+}
+
+impl Lisaosa for Elinvoima {
+    fn anna_kappale(&self) -> RcKappale {
+        Rc::clone(&self.kappale)
+    }
+}
+
+/// Vahinko, jonka vahingoittaja tekee törmätessään
+#[derive(Copy, Clone)]
+pub struct Vahinko {
+    /// Vahingon määrä
+    pub maara: Luku,
+    /// Tagit, joiden kappaleita vahingoitetaan. Tyhjä joukko vahingoittaa kaikkia.
+    pub kohteet: Tagit,
+}
+
+impl Vahinko {
+    /// Luo vahingon, joka vahingoittaa kaikkia kappaleita
+    /// # Arguments
+    /// * `maara` - Vahingon määrä
+    pub fn new(maara: Luku) -> Self {
+        Vahinko {
+            maara,
+            kohteet: Tagit::new(),
+        }
+    }
+
+    /// Vahingoittaako vahinko kappaletta, jolla on annetut tagit
+    /// # Arguments
+    /// * `tagit` - Vahingoitettavan kappaleen tagit
+    pub fn vahingoittaako(&self, tagit: Tagit) -> bool {
+        self.kohteet.is_empty() || self.kohteet.leikkaa(tagit)
+    }
+}
+
+/// Lisäosa, joka tekee kappaleesta vahingon lähteen. Kun elinvoimainen kappale
+/// törmää vahingoittajaan, se ottaa vahinkoa.
+pub struct Vahingoittaja {
+    kappale: RcKappale,
+    /// Vahinko, jonka kappale tekee
+    pub vahinko: Vahinko,
+}
+
+impl Vahingoittaja {
+    /// Luo uuden vahingoittajan
+    /// # Arguments
+    /// * `kappale` - Kappale, joka vahingoittaa
+    /// * `vahinko` - Vahinko, jonka kappale tekee
+    pub fn new(kappale: RcKappale, vahinko: Vahinko) -> Self {
+        Vahingoittaja { kappale, vahinko }
+    }
+}
+
+impl Lisaosa for Vahingoittaja {
+    fn anna_kappale(&self) -> RcKappale {
+        Rc::clone(&self.kappale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::maailma::kappale::{Muoto, Tagi};
+    use crate::maailma::luku::luku;
+    use crate::maailma::vektori::Vektori;
+    use crate::maailma::Perusmaailma;
+    use sdl2::pixels::Color;
+
+    fn kappale(tagi: Tagi) -> Kappale {
+        Kappale::new_keskipisteella(
+            Muoto::Nelio(luku(10.0), luku(10.0)),
+            luku(50.0),
+            luku(40.0),
+            tagi,
+        )
+    }
+
+    fn uusi_elinvoima(maksimi: f32, suojausaika: Duration) -> Elinvoima {
+        let mut kestavyys = Kestavyys::new(luku(maksimi));
+        kestavyys.suojausaika = suojausaika;
+        Elinvoima::new(Rc::new(RefCell::new(kappale(Tagi::Vihollinen))), kestavyys)
+    }
+
+    fn s(sekunnit: u64) -> Duration {
+        Duration::from_secs(sekunnit)
+    }
+
+    #[test]
+    fn suojausaika_estaa_uuden_vahingon() {
+        let mut elinvoima = uusi_elinvoima(3.0, s(2));
+        assert!(elinvoima.ota_vahinkoa(luku(1.0), s(10)));
+        assert_eq!(elinvoima.elamat(), luku(2.0));
+        assert_eq!(elinvoima.suojattu_asti(), s(12));
+        assert!(elinvoima.onko_suojattu(s(11)));
+        assert!(!elinvoima.ota_vahinkoa(luku(1.0), s(11)));
+        assert_eq!(elinvoima.elamat(), luku(2.0));
+        // Suojaus päättyy suojausajan kuluttua
+        assert!(!elinvoima.onko_suojattu(s(12)));
+        assert!(elinvoima.ota_vahinkoa(luku(1.0), s(12)));
+        assert_eq!(elinvoima.elamat(), luku(1.0));
+    }
+
+    #[test]
+    fn ilman_suojausaikaa_vahinkoa_voi_ottaa_heti() {
+        let mut elinvoima = uusi_elinvoima(3.0, Duration::new(0, 0));
+        assert!(elinvoima.ota_vahinkoa(luku(1.0), s(1)));
+        assert!(elinvoima.ota_vahinkoa(luku(1.0), s(1)));
+        assert_eq!(elinvoima.elamat(), luku(1.0));
+    }
+
+    #[test]
+    fn elamat_rajataan_nollan_ja_maksimin_valille() {
+        let mut elinvoima = uusi_elinvoima(3.0, Duration::new(0, 0));
+        elinvoima.aseta_elamat(luku(5.0));
+        assert_eq!(elinvoima.elamat(), luku(3.0));
+        elinvoima.aseta_elamat(luku(-2.0));
+        assert_eq!(elinvoima.elamat(), Luku::default());
+        assert!(elinvoima.onko_kuollut());
+
+        let mut elinvoima = uusi_elinvoima(3.0, Duration::new(0, 0));
+        elinvoima.ota_vahinkoa(luku(10.0), s(1));
+        assert_eq!(elinvoima.elamat(), Luku::default());
+        assert!(elinvoima.onko_kuollut());
+    }
+
+    #[test]
+    fn kuollutta_ei_paranneta_eika_vahingoiteta() {
+        let mut elinvoima = uusi_elinvoima(3.0, Duration::new(0, 0));
+        elinvoima.ota_vahinkoa(luku(2.0), s(1));
+        elinvoima.paranna(luku(5.0));
+        assert_eq!(elinvoima.elamat(), luku(3.0));
+
+        elinvoima.ota_vahinkoa(luku(3.0), s(2));
+        assert!(elinvoima.onko_kuollut());
+        assert!(!elinvoima.ota_vahinkoa(luku(1.0), s(3)));
+        elinvoima.paranna(luku(1.0));
+        assert!(elinvoima.onko_kuollut());
+    }
+
+    #[test]
+    fn vahinko_vahingoittaa_vain_kohteitaan() {
+        let kaikille = Vahinko::new(luku(1.0));
+        assert!(kaikille.vahingoittaako(Tagi::Pelaaja.into()));
+        assert!(kaikille.vahingoittaako(Tagit::new()));
+
+        let mut vihollisille = Vahinko::new(luku(1.0));
+        vihollisille.kohteet = Tagi::Vihollinen.into();
+        assert!(vihollisille.vahingoittaako(Tagi::Vihollinen.into()));
+        assert!(!vihollisille.vahingoittaako(Tagi::Pelaaja.into()));
+        assert!(!vihollisille.vahingoittaako(Tagit::new()));
+        let tagit: Tagit = [Tagi::Pelaaja, Tagi::Vihollinen].iter().copied().collect();
+        assert!(vihollisille.vahingoittaako(tagit));
+    }
+
+    #[test]
+    fn maailma_vahingoittaa_vain_kohteita_joilla_on_elinvoima() {
+        let mut maailma = Perusmaailma::new();
+        let pelaaja = maailma.lisaa_kappale(kappale(Tagi::Pelaaja));
+        let seina = maailma.lisaa_kappale(kappale(Tagi::Seina));
+        maailma.lisaa_lisaosa(Elinvoima::new(
+            Rc::clone(&pelaaja),
+            Kestavyys::new(luku(2.0)),
+        ));
+        let mut vahinko = Vahinko::new(luku(1.0));
+        vahinko.kohteet = Tagi::Vihollinen.into();
+        assert!(!maailma.vahingoita(&pelaaja, &vahinko, s(1)));
+        assert!(!maailma.vahingoita(&seina, &Vahinko::new(luku(1.0)), s(1)));
+        assert!(maailma.vahingoita(&pelaaja, &Vahinko::new(luku(1.0)), s(1)));
+        let elamat = maailma
+            .anna_lisaosa::<Elinvoima>(&pelaaja)
+            .unwrap()
+            .elamat();
+        assert_eq!(elamat, luku(1.0));
+    }
+
+    #[test]
+    fn kuoleman_seuraukset() {
+        let mut maailma = Perusmaailma::new();
+        let kuoleva = maailma.lisaa_kappale(kappale(Tagi::Vihollinen));
+        let mut kestavyys = Kestavyys::new(luku(1.0));
+        kestavyys.kuolemat = vec![
+            Kuolema::Poisto,
+            Kuolema::Animaatio,
+            Kuolema::Pudotus {
+                kappale: Kappale::new_kulmalla(
+                    Muoto::Ympyra(luku(2.0)),
+                    luku(0.0),
+                    luku(0.0),
+                    Tagi::Partikkeli,
+                ),
+                piirtotapa: Piirtotapa::Yksivarinen {
+                    vari: Color::RGB(0, 255, 0),
+                },
+            },
+            Kuolema::PelinLoppu,
+        ];
+        maailma.lisaa_lisaosa(Elinvoima::new(Rc::clone(&kuoleva), kestavyys));
+
+        assert!(maailma.vahingoita(&kuoleva, &Vahinko::new(luku(1.0)), s(1)));
+        maailma.poista_poistettavat();
+
+        // Kuollut kappale on poistettu ja pudotus on sen keskipisteessä
+        assert_eq!(maailma.kappaleet().len(), 1);
+        let pudotus = maailma.kappaleet()[0].borrow();
+        assert!(pudotus.tagit.sisaltaa(Tagi::Partikkeli));
+        assert_eq!(
+            pudotus.keskipisteen_sijainti(),
+            Vektori::new(luku(50.0), luku(40.0))
+        );
+        assert!(maailma.anna_lisaosa::<Elinvoima>(&kuoleva).is_none());
+        assert_eq!(maailma.animaatiot.kaikki().len(), 1);
+        assert!(maailma.onko_peli_paattynyt());
+    }
+}
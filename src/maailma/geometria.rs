@@ -0,0 +1,459 @@
+//! Sisältää muotojen yleisen esitystavan, jonka avulla minkä tahansa kahden muodon
+//! päällekkäisyys voidaan tarkistaa samalla tavalla. Jokainen muoto esitetään kuperana
+//! ytimenä (piste, jana tai monikulmio) ja säteenä, jonka verran muoto ulottuu ytimen
+//! ympärille. Esim. ympyrä on piste, jolla on säde, ja kapseli on jana, jolla on säde.
+use super::luku::{luku, Luku};
+use super::vektori::Vektori;
+
+/// Monikulmion kärkien suurin sallittu määrä
+pub const MAKSIMI_KARKIA: usize = 8;
+
+/// Kahden päällekkäisen rungon kosketus
+#[derive(Copy, Clone, Debug)]
+pub struct Kosketus {
+    /// Yksikkövektori, jonka suuntaan ensimmäistä runkoa siirtämällä rungot erkanevat
+    pub normaali: Vektori,
+    /// Kuinka paljon ensimmäistä runkoa pitää siirtää normaalin suuntaan, jotta rungot vain koskettavat
+    pub syvyys: Luku,
+}
+
+/// Muodon kupera ydin pelimaailman koordinaateissa ja säde ytimen ympärillä
+#[derive(Copy, Clone)]
+pub struct Runko {
+    /// Ytimen kärjet järjestyksessä
+    pisteet: [Vektori; MAKSIMI_KARKIA],
+    /// Käytössä olevien kärkien määrä
+    maara: usize,
+    /// Etäisyys, jonka verran muoto ulottuu ytimen ympärille
+    pub sade: Luku,
+}
+
+impl Runko {
+    /// Luo uuden rungon. Kärkiä voi olla korkeintaan `MAKSIMI_KARKIA`, ylimääräiset jätetään pois.
+    /// # Arguments
+    /// * `pisteet` - Ytimen kärjet järjestyksessä
+    /// * `sade` - Etäisyys, jonka verran muoto ulottuu ytimen ympärille
+    pub fn new(pisteet: &[Vektori], sade: Luku) -> Self {
+        let mut runko = Runko {
+            pisteet: [Vektori::default(); MAKSIMI_KARKIA],
+            maara: pisteet.len().min(MAKSIMI_KARKIA),
+            sade,
+        };
+        runko.pisteet[..runko.maara].copy_from_slice(&pisteet[..runko.maara]);
+        runko
+    }
+
+    /// Antaa ytimen kärjet
+    pub fn pisteet(&self) -> &[Vektori] {
+        &self.pisteet[..self.maara]
+    }
+
+    /// Ovatko rungot päällekkäin. Jos kummallakaan rungolla ei ole sädettä, niin
+    /// reunojen kosketus lasketaan päällekkäisyydeksi.
+    /// # Arguments
+    /// * `toinen` - Runko, johon verrataan
+    pub fn ovatko_paallekkain(&self, toinen: &Runko) -> bool {
+        let etaisyys = self.ytimien_etaisyys(toinen);
+        let sateet = self.sade + toinen.sade;
+        if sateet > 0.0 {
+            etaisyys < sateet
+        } else {
+            etaisyys <= 0.0
+        }
+    }
+
+    /// Antaa rungon ja toisen rungon kosketuksen eli lyhimmän siirron, jolla rungot saadaan
+    /// erilleen. Ei mitään, jos rungot eivät ole päällekkäin.
+    /// # Arguments
+    /// * `toinen` - Runko, josta tämä runko erotetaan
+    pub fn kosketus(&self, toinen: &Runko) -> Option<Kosketus> {
+        if !self.ovatko_paallekkain(toinen) {
+            return None;
+        }
+        let sateet = self.sade + toinen.sade;
+        if !self.leikkaa(toinen) {
+            // Erilliset ytimet erotetaan lähimpien kohtien kautta kulkevaa suoraa pitkin
+            let (oma, toisen) = self.lahimmat_pisteet(toinen);
+            let vali = oma - toisen;
+            let etaisyys = vali.pituus();
+            if etaisyys > 0.0 {
+                return Some(Kosketus {
+                    normaali: vali / etaisyys,
+                    syvyys: sateet - etaisyys,
+                });
+            }
+        }
+        // Leikkaavat ytimet erotetaan erottavien akseleiden avulla siihen suuntaan, jossa
+        // päällekkäisyys on pienin. Keskipisteiden suunta auttaa, jos ytimillä ei ole sivuja.
+        let mut akselit = Vec::with_capacity(2 * MAKSIMI_KARKIA + 1);
+        for runko in [self, toinen].iter() {
+            for (alku, loppu) in runko.sivut() {
+                akselit.extend((loppu - alku).kohtisuora().normalisoi());
+            }
+        }
+        akselit.extend((self.keskipiste() - toinen.keskipiste()).normalisoi());
+        if akselit.is_empty() {
+            akselit.push(Vektori::new(luku(1.0), luku(0.0)));
+        }
+        let mut paras: Option<Kosketus> = None;
+        for akseli in akselit {
+            let (oma_min, oma_max) = self.projektio(akseli);
+            let (toisen_min, toisen_max) = toinen.projektio(akseli);
+            let kosketus = if toisen_max - oma_min < oma_max - toisen_min {
+                Kosketus {
+                    normaali: akseli,
+                    syvyys: toisen_max - oma_min + sateet,
+                }
+            } else {
+                Kosketus {
+                    normaali: -akseli,
+                    syvyys: oma_max - toisen_min + sateet,
+                }
+            };
+            if !matches!(paras, Some(x) if x.syvyys <= kosketus.syvyys) {
+                paras = Some(kosketus);
+            }
+        }
+        paras
+    }
+
+    /// Antaa ytimien välisen lyhimmän etäisyyden. Etäisyys on nolla, jos ytimet leikkaavat.
+    /// # Arguments
+    /// * `toinen` - Runko, johon etäisyys lasketaan
+    pub fn ytimien_etaisyys(&self, toinen: &Runko) -> Luku {
+        if self.leikkaa(toinen) {
+            return Luku::default();
+        }
+        // Erillisten kuperien ytimien lähimmät kohdat ovat aina kärki ja toisen sivu
+        let mut lyhin = Luku::INFINITY;
+        for (a, b) in [(self, toinen), (toinen, self)].iter() {
+            for piste in a.pisteet() {
+                for (alku, loppu) in b.sivut() {
+                    lyhin = lyhin.min(pisteen_etaisyys_janasta(*piste, alku, loppu));
+                }
+            }
+        }
+        lyhin
+    }
+
+    /// Antaa erillisten ytimien lähimmät kohdat, ensin tämän ytimen ja sitten toisen
+    /// # Arguments
+    /// * `toinen` - Runko, jonka ytimeen verrataan
+    fn lahimmat_pisteet(&self, toinen: &Runko) -> (Vektori, Vektori) {
+        let mut lahimmat = (self.pisteet[0], toinen.pisteet[0]);
+        let mut lyhin = Luku::INFINITY;
+        for piste in self.pisteet() {
+            for (alku, loppu) in toinen.sivut() {
+                let lahin = lahin_piste_janalla(*piste, alku, loppu);
+                if (*piste - lahin).pituus() < lyhin {
+                    lyhin = (*piste - lahin).pituus();
+                    lahimmat = (*piste, lahin);
+                }
+            }
+        }
+        for piste in toinen.pisteet() {
+            for (alku, loppu) in self.sivut() {
+                let lahin = lahin_piste_janalla(*piste, alku, loppu);
+                if (*piste - lahin).pituus() < lyhin {
+                    lyhin = (*piste - lahin).pituus();
+                    lahimmat = (lahin, *piste);
+                }
+            }
+        }
+        lahimmat
+    }
+
+    /// Antaa ytimen kärkien keskiarvon
+    fn keskipiste(&self) -> Vektori {
+        let summa = self
+            .pisteet()
+            .iter()
+            .fold(Vektori::default(), |summa, x| summa + *x);
+        summa / luku(self.maara.max(1) as f32)
+    }
+
+    /// Antaa rungon projektion pienimmän ja suurimman arvon annetulla akselilla ilman sädettä
+    /// # Arguments
+    /// * `akseli` - Yksikkövektori, jolle ydin projisoidaan
+    fn projektio(&self, akseli: Vektori) -> (Luku, Luku) {
+        self.pisteet()
+            .iter()
+            .map(|x| x.pistetulo(akseli))
+            .fold((Luku::INFINITY, Luku::NEG_INFINITY), |(min, max), x| {
+                (min.min(x), max.max(x))
+            })
+    }
+
+    /// Leikkaavatko ytimet toisiaan eli ovatko ytimien sivut ristikkäin
+    /// tai onko ydin kokonaan toisen sisällä
+    fn leikkaa(&self, toinen: &Runko) -> bool {
+        if self.maara == 0 || toinen.maara == 0 {
+            return false;
+        }
+        for (a, b) in self.sivut() {
+            for (c, d) in toinen.sivut() {
+                if janat_leikkaavat(a, b, c, d) {
+                    return true;
+                }
+            }
+        }
+        self.sisaltaa(toinen.pisteet[0]) || toinen.sisaltaa(self.pisteet[0])
+    }
+
+    /// Onko piste ytimen sisällä. Piste ja jana eivät sisällä mitään.
+    fn sisaltaa(&self, piste: Vektori) -> bool {
+        if self.maara < 3 {
+            return false;
+        }
+        let mut positiivisia = false;
+        let mut negatiivisia = false;
+        for (alku, loppu) in self.sivut() {
+            let puoli = (loppu - alku).ristitulo(piste - alku);
+            positiivisia |= puoli > 0.0;
+            negatiivisia |= puoli < 0.0;
+        }
+        !(positiivisia && negatiivisia)
+    }
+
+    /// Antaa ytimen sivut. Pisteellä sivu on nollan mittainen ja janalla sivu on jana itse.
+    fn sivut<'a>(&'a self) -> impl Iterator<Item = (Vektori, Vektori)> + 'a {
+        let pisteet = self.pisteet();
+        let sivuja = match pisteet.len() {
+            0..=2 => pisteet.len().min(1),
+            n => n,
+        };
+        (0..sivuja).map(move |i| (pisteet[i], pisteet[(i + 1) % pisteet.len()]))
+    }
+}
+
+/// Antaa pisteen lyhimmän etäisyyden janasta
+/// # Arguments
+/// * `piste` - Piste, jonka etäisyys lasketaan
+/// * `alku` - Janan alkupiste
+/// * `loppu` - Janan loppupiste
+pub fn pisteen_etaisyys_janasta(piste: Vektori, alku: Vektori, loppu: Vektori) -> Luku {
+    (piste - lahin_piste_janalla(piste, alku, loppu)).pituus()
+}
+
+/// Antaa janan pisteen, joka on lähimpänä annettua pistettä
+/// # Arguments
+/// * `piste` - Piste, jota lähin janan piste etsitään
+/// * `alku` - Janan alkupiste
+/// * `loppu` - Janan loppupiste
+pub fn lahin_piste_janalla(piste: Vektori, alku: Vektori, loppu: Vektori) -> Vektori {
+    let jana = loppu - alku;
+    let pituus_toiseen = jana.pistetulo(jana);
+    let pistetulo = (piste - alku).pistetulo(jana);
+    if pituus_toiseen <= 0.0 || pistetulo <= 0.0 {
+        alku
+    } else if pistetulo >= pituus_toiseen {
+        loppu
+    } else {
+        // Kerrotaan ennen jakamista, jotta kiintoluvuillakaan pitkän janan piste ei heitä
+        alku + jana * pistetulo / pituus_toiseen
+    }
+}
+
+/// Leikkaavatko janat a-b ja c-d toisiaan. Kosketus lasketaan leikkaukseksi.
+fn janat_leikkaavat(a: Vektori, b: Vektori, c: Vektori, d: Vektori) -> bool {
+    let abc = (b - a).ristitulo(c - a);
+    let abd = (b - a).ristitulo(d - a);
+    let cda = (d - c).ristitulo(a - c);
+    let cdb = (d - c).ristitulo(b - c);
+    if ((abc > 0.0 && abd < 0.0) || (abc < 0.0 && abd > 0.0))
+        && ((cda > 0.0 && cdb < 0.0) || (cda < 0.0 && cdb > 0.0))
+    {
+        return true;
+    }
+    (abc == 0.0 && janalla(a, b, c))
+        || (abd == 0.0 && janalla(a, b, d))
+        || (cda == 0.0 && janalla(c, d, a))
+        || (cdb == 0.0 && janalla(c, d, b))
+}
+
+/// Onko samalla suoralla janan kanssa oleva piste janan päätepisteiden välissä
+fn janalla(alku: Vektori, loppu: Vektori, piste: Vektori) -> bool {
+    piste.x >= alku.x.min(loppu.x)
+        && piste.x <= alku.x.max(loppu.x)
+        && piste.y >= alku.y.min(loppu.y)
+        && piste.y <= alku.y.max(loppu.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maailma::kappale::{Kappale, Monikulmio, Muoto, Tagi};
+    use crate::maailma::luku::Skalaari;
+
+    fn v(x: f32, y: f32) -> Vektori {
+        Vektori::new(luku(x), luku(y))
+    }
+
+    /// Muodot ja niiden ulottuma keskipisteestä x-akselin suuntaan. Kaikki muodot ovat
+    /// symmetrisiä pystysuunnassa.
+    fn muodot() -> Vec<(&'static str, Muoto, f32)> {
+        // Monikulmion oikea reuna on suora ja vasemmalla on kärki
+        let talo = [
+            v(-10.0, 0.0),
+            v(0.0, -10.0),
+            v(10.0, -10.0),
+            v(10.0, 10.0),
+            v(0.0, 10.0),
+        ];
+        vec![
+            ("nelio", Muoto::Nelio(luku(20.0), luku(20.0)), 10.0),
+            ("ympyra", Muoto::Ympyra(luku(5.0)), 5.0),
+            (
+                "monikulmio",
+                Muoto::Monikulmio(Monikulmio::new(&talo).unwrap()),
+                10.0,
+            ),
+            ("kapseli", Muoto::Kapseli(luku(20.0), luku(5.0)), 15.0),
+            ("jana", Muoto::Jana(luku(20.0)), 10.0),
+        ]
+    }
+
+    fn runko(muoto: Muoto, x: f32, y: f32, kulma: f32) -> Runko {
+        let mut kappale = Kappale::new_keskipisteella(muoto, luku(x), luku(y), Tagi::Seina);
+        kappale.aseta_kulma(luku(kulma));
+        kappale.runko()
+    }
+
+    /// Tarkistaa kosketuksen normaalin ja syvyyden
+    fn tarkista(kosketus: Option<Kosketus>, normaali: Vektori, syvyys: f32, kuvaus: &str) {
+        let kosketus = kosketus.unwrap_or_else(|| panic!("{}: ei kosketusta", kuvaus));
+        assert!(
+            kosketus.normaali.lahes_sama(normaali, 1e-3)
+                && kosketus.syvyys.lahes_sama(luku(syvyys), 1e-3),
+            "{}: {:?}",
+            kuvaus,
+            kosketus
+        );
+    }
+
+    #[test]
+    fn muotoparit_paallekkain_vierekkain_ja_erillaan() {
+        let vasemmalle = v(-1.0, 0.0);
+        for (nimi_a, a, oikea) in muodot() {
+            for (nimi_b, b, vasen) in muodot() {
+                let kuvaus = format!("{} ja {}", nimi_a, nimi_b);
+                let rungot = |ero: f32| {
+                    let x = oikea + vasen + ero;
+                    (runko(a, 0.0, 0.0, 0.0), runko(b, x, 0.0, 0.0))
+                };
+
+                // Päällekkäin olevat rungot erotetaan vaakasuunnassa
+                let (runko_a, runko_b) = rungot(-2.0);
+                assert!(runko_a.ovatko_paallekkain(&runko_b), "{}", kuvaus);
+                let kosketus = runko_a.kosketus(&runko_b);
+                match (nimi_a, nimi_b) {
+                    // Janan pää on monikulmion kärjen sisällä, jolloin lyhin siirto
+                    // on kärjen viistoa sivua vastaan
+                    ("jana", "monikulmio") => {
+                        let normaali = v(-1.0, -1.0) / 2.0.sqrt();
+                        tarkista(kosketus, normaali, 2.0.sqrt(), &kuvaus)
+                    }
+                    // Päällekkäiset janat erkanevat millä tahansa sivuttaisella siirrolla
+                    ("jana", "jana") => tarkista(kosketus, v(0.0, -1.0), 0.0, &kuvaus),
+                    _ => {
+                        tarkista(kosketus, vasemmalle, 2.0, &kuvaus);
+                        let kaannetty = runko_b.kosketus(&runko_a);
+                        tarkista(kaannetty, -vasemmalle, 2.0, &kuvaus);
+                    }
+                }
+
+                // Koskettavat rungot ovat päällekkäin vain, jos kummallakaan ei ole sädettä
+                let (runko_a, runko_b) = rungot(0.0);
+                let sateettomat = runko_a.sade + runko_b.sade == 0.0;
+                assert_eq!(
+                    runko_a.ovatko_paallekkain(&runko_b),
+                    sateettomat,
+                    "{}",
+                    kuvaus
+                );
+                match runko_a.kosketus(&runko_b) {
+                    Some(kosketus) => assert!(kosketus.syvyys.abs() < 1e-3, "{}", kuvaus),
+                    None => assert!(!sateettomat, "{}", kuvaus),
+                }
+
+                let (runko_a, runko_b) = rungot(2.0);
+                assert!(!runko_a.ovatko_paallekkain(&runko_b), "{}", kuvaus);
+                assert!(runko_a.kosketus(&runko_b).is_none(), "{}", kuvaus);
+                let etaisyys = runko_a.ytimien_etaisyys(&runko_b) - runko_a.sade - runko_b.sade;
+                assert!(etaisyys.lahes_sama(luku(2.0), 1e-3), "{}", kuvaus);
+            }
+        }
+    }
+
+    #[test]
+    fn sisakkaiset_ytimet_erotetaan_lyhinta_reittia() {
+        let iso = runko(Muoto::Nelio(luku(20.0), luku(20.0)), 0.0, 0.0, 0.0);
+        // Pieni neliö on lähempänä ison oikeaa reunaa
+        let pieni = runko(Muoto::Nelio(luku(4.0), luku(4.0)), 2.0, 0.0, 0.0);
+        assert_eq!(pieni.ytimien_etaisyys(&iso), luku(0.0));
+        tarkista(pieni.kosketus(&iso), v(1.0, 0.0), 10.0, "pieni isossa");
+        tarkista(
+            iso.kosketus(&pieni),
+            v(-1.0, 0.0),
+            10.0,
+            "iso pienen ympärillä",
+        );
+
+        // Ympyrän ydin on neliön sisällä, joten myös säde pitää siirtää ulos
+        let ympyra = runko(Muoto::Ympyra(luku(5.0)), 0.0, -3.0, 0.0);
+        tarkista(ympyra.kosketus(&iso), v(0.0, -1.0), 12.0, "ympyrä neliössä");
+
+        // Jana on kokonaan monikulmion sisällä
+        let kuusikulmio = [
+            v(-10.0, -5.0),
+            v(0.0, -10.0),
+            v(10.0, -5.0),
+            v(10.0, 5.0),
+            v(0.0, 10.0),
+            v(-10.0, 5.0),
+        ];
+        let monikulmio = Muoto::Monikulmio(Monikulmio::new(&kuusikulmio).unwrap());
+        let monikulmio = runko(monikulmio, 0.0, 0.0, 0.0);
+        let jana = runko(Muoto::Jana(luku(4.0)), 5.0, 0.0, 0.0);
+        tarkista(
+            jana.kosketus(&monikulmio),
+            v(1.0, 0.0),
+            7.0,
+            "jana monikulmiossa",
+        );
+    }
+
+    #[test]
+    fn yhdensuuntaiset_janat_ja_kapselit() {
+        let jana = runko(Muoto::Jana(luku(20.0)), 0.0, 0.0, 0.0);
+        let toinen = runko(Muoto::Jana(luku(20.0)), 5.0, 3.0, 0.0);
+        assert!(toinen.ytimien_etaisyys(&jana).lahes_sama(luku(3.0), 1e-3));
+        assert!(jana.kosketus(&toinen).is_none());
+
+        // Säteellä yhdensuuntaiset ytimet erotetaan kohtisuoraan
+        let kapseli = runko(Muoto::Kapseli(luku(20.0), luku(5.0)), 5.0, 3.0, 0.0);
+        tarkista(
+            jana.kosketus(&kapseli),
+            v(0.0, -1.0),
+            2.0,
+            "jana ja kapseli",
+        );
+        let kapseli_alla = runko(Muoto::Kapseli(luku(20.0), luku(5.0)), 0.0, 0.0, 0.0);
+        tarkista(
+            kapseli_alla.kosketus(&kapseli),
+            v(0.0, -1.0),
+            7.0,
+            "kapselit",
+        );
+
+        // Ristikkäiset janat leikkaavat, vaikka kummankaan päät eivät ole toisen lähellä
+        let pysty = runko(
+            Muoto::Jana(luku(20.0)),
+            0.0,
+            0.0,
+            std::f32::consts::FRAC_PI_2,
+        );
+        assert!(jana.ovatko_paallekkain(&pysty));
+        assert_eq!(jana.ytimien_etaisyys(&pysty), luku(0.0));
+    }
+}
@@ -1,13 +1,24 @@
 //! Sisältää kappaleen ja siihen sisältyvät osat
+use std::f32::consts::PI;
+
+use super::geometria::{Runko, MAKSIMI_KARKIA};
+use super::luku::{luku, Luku};
+use super::tagit::{OmaTagi, Tagit};
 use super::Vektori;
 
 /// Ennalta määrätty muoto kuten neliä tai ympyrä
 #[derive(Copy, Clone)]
 pub enum Muoto {
     /// Tarkkaan ottaen suorakaide, jolla on leveys ja korkeus
-    Nelio(f32, f32),
+    Nelio(Luku, Luku),
     /// Ympyrä, jolla on säde
-    Ympyra(f32),
+    Ympyra(Luku),
+    /// Kupera monikulmio
+    Monikulmio(Monikulmio),
+    /// Vaakasuora jana, jonka ympärillä on säde. Sisältää janan pituuden ja säteen.
+    Kapseli(Luku, Luku),
+    /// Vaakasuora jana, jolla on pituus. Janan suuntaa voi muuttaa kappaleen asennolla.
+    Jana(Luku),
 }
 
 impl Muoto {
@@ -16,38 +27,202 @@ impl Muoto {
         match &self {
             Muoto::Nelio(leveys, korkeus) => Vektori::new(leveys / 2.0, korkeus / 2.0),
             Muoto::Ympyra(sade) => Vektori::new(*sade, *sade),
+            Muoto::Monikulmio(monikulmio) => {
+                Vektori::new(monikulmio.koko.0 / 2.0, monikulmio.koko.1 / 2.0)
+            }
+            Muoto::Kapseli(pituus, sade) => Vektori::new(pituus / 2.0 + sade, *sade),
+            Muoto::Jana(pituus) => Vektori::new(pituus / 2.0, Luku::default()),
         }
     }
 
     /// Antaa muodon maksimi leveyden ja korkeuden.
     /// Esim. ympyrällä halkaisijat
-    pub fn koko(&self) -> (f32, f32) {
+    pub fn koko(&self) -> (Luku, Luku) {
         match &self {
             Muoto::Nelio(leveys, korkeus) => (*leveys, *korkeus),
             Muoto::Ympyra(sade) => (sade * 2.0, sade * 2.0),
+            Muoto::Monikulmio(monikulmio) => monikulmio.koko,
+            Muoto::Kapseli(pituus, sade) => (pituus + sade * 2.0, sade * 2.0),
+            Muoto::Jana(pituus) => (*pituus, Luku::default()),
+        }
+    }
+
+    /// Antaa muodon ytimen kärjet suhteessa keskipisteeseen sekä säteen ytimen ympärillä
+    fn ydin(&self) -> ([Vektori; MAKSIMI_KARKIA], usize, Luku) {
+        let mut pisteet = [Vektori::default(); MAKSIMI_KARKIA];
+        let (maara, sade) = match &self {
+            Muoto::Nelio(leveys, korkeus) => {
+                let (x, y) = (leveys / 2.0, korkeus / 2.0);
+                pisteet[0] = Vektori::new(-x, -y);
+                pisteet[1] = Vektori::new(x, -y);
+                pisteet[2] = Vektori::new(x, y);
+                pisteet[3] = Vektori::new(-x, y);
+                (4, Luku::default())
+            }
+            Muoto::Ympyra(sade) => (1, *sade),
+            Muoto::Monikulmio(monikulmio) => {
+                for (piste, karki) in pisteet.iter_mut().zip(monikulmio.karjet()) {
+                    *piste = *karki - self.keskipiste();
+                }
+                (monikulmio.maara, Luku::default())
+            }
+            Muoto::Kapseli(pituus, sade) => {
+                pisteet[0] = Vektori::new(-pituus / 2.0, Luku::default());
+                pisteet[1] = Vektori::new(pituus / 2.0, Luku::default());
+                (2, *sade)
+            }
+            Muoto::Jana(pituus) => {
+                pisteet[0] = Vektori::new(-pituus / 2.0, Luku::default());
+                pisteet[1] = Vektori::new(pituus / 2.0, Luku::default());
+                (2, Luku::default())
+            }
+        };
+        (pisteet, maara, sade)
+    }
+}
+
+/// Kupera monikulmio, jolla on 3-8 kärkeä. Kärjet ovat suhteessa monikulmion
+/// rajaavan suorakaiteen vasempaan yläkulmaan.
+#[derive(Copy, Clone)]
+pub struct Monikulmio {
+    /// Monikulmion kärjet järjestyksessä
+    karjet: [Vektori; MAKSIMI_KARKIA],
+    /// Käytössä olevien kärkien määrä
+    maara: usize,
+    /// Monikulmion rajaavan suorakaiteen leveys ja korkeus
+    koko: (Luku, Luku),
+}
+
+impl Monikulmio {
+    /// Luo uuden monikulmion annetuista kärjistä. Kärjet siirretään niin, että rajaavan
+    /// suorakaiteen vasen yläkulma on origossa. Antaa virheen, jos monikulmio ei ole kupera.
+    /// # Arguments
+    /// * `karjet` - Monikulmion kärjet järjestyksessä kumpaan tahansa suuntaan
+    pub fn new(karjet: &[Vektori]) -> Result<Self, String> {
+        if karjet.len() < 3 || karjet.len() > MAKSIMI_KARKIA {
+            return Err(format!(
+                "Monikulmiolla pitää olla 3-{} kärkeä, mutta annettiin {}",
+                MAKSIMI_KARKIA,
+                karjet.len()
+            ));
+        }
+        let mut positiivisia = false;
+        let mut negatiivisia = false;
+        let mut kierros = Luku::default();
+        for i in 0..karjet.len() {
+            let a = karjet[i];
+            let b = karjet[(i + 1) % karjet.len()];
+            let c = karjet[(i + 2) % karjet.len()];
+            let kaanne = (b - a).ristitulo(c - b);
+            positiivisia |= kaanne > 0.0;
+            negatiivisia |= kaanne < 0.0;
+            kierros += (b - a).kulma_valilla(c - b);
         }
+        // Kuperan monikulmion reunat kiertävät yhden kierroksen. Esim. viisikanta kääntyy
+        // joka kärjessä samaan suuntaan, mutta sen reunat leikkaavat ja kiertävät kahdesti.
+        if positiivisia == negatiivisia || kierros.abs() > luku(3.0 * PI) {
+            return Err("Monikulmion pitää olla kupera eivätkä kaikki sen kärjet saa olla samalla suoralla".to_string());
+        }
+
+        let mut vasen_yla = karjet[0];
+        let mut oikea_ala = karjet[0];
+        for karki in karjet {
+            vasen_yla = Vektori::new(vasen_yla.x.min(karki.x), vasen_yla.y.min(karki.y));
+            oikea_ala = Vektori::new(oikea_ala.x.max(karki.x), oikea_ala.y.max(karki.y));
+        }
+        let mut monikulmio = Monikulmio {
+            karjet: [Vektori::default(); MAKSIMI_KARKIA],
+            maara: karjet.len(),
+            koko: (oikea_ala.x - vasen_yla.x, oikea_ala.y - vasen_yla.y),
+        };
+        for (uusi, karki) in monikulmio.karjet.iter_mut().zip(karjet) {
+            *uusi = *karki - vasen_yla;
+        }
+        Ok(monikulmio)
+    }
+
+    /// Antaa monikulmion kärjet suhteessa rajaavan suorakaiteen vasempaan yläkulmaan
+    pub fn karjet(&self) -> &[Vektori] {
+        &self.karjet[..self.maara]
+    }
+}
+
+/// Akselien suuntainen suorakulmainen alue pelimaailmassa
+#[derive(Copy, Clone)]
+pub struct Rajaus {
+    /// Alueen vasen yläkulma
+    pub vasen_yla: Vektori,
+    /// Alueen oikea alakulma
+    pub oikea_ala: Vektori,
+}
+
+impl Rajaus {
+    /// Luo uuden alueen kulmien avulla
+    /// # Arguments
+    /// * `vasen_yla` - Alueen vasen yläkulma
+    /// * `oikea_ala` - Alueen oikea alakulma
+    pub fn new(vasen_yla: Vektori, oikea_ala: Vektori) -> Self {
+        Rajaus {
+            vasen_yla,
+            oikea_ala,
+        }
+    }
+
+    /// Luo alueen, joka rajaa annetun ympyrän
+    /// # Arguments
+    /// * `keskipiste` - Ympyrän keskipiste
+    /// * `sade` - Ympyrän säde
+    pub fn ympyran_ymparilta(keskipiste: Vektori, sade: Luku) -> Self {
+        Rajaus {
+            vasen_yla: keskipiste - Vektori::new(sade, sade),
+            oikea_ala: keskipiste + Vektori::new(sade, sade),
+        }
+    }
+
+    /// Leikkaavatko alueet toisiaan
+    /// # Arguments
+    /// * `toinen` - Alue, johon verrataan
+    pub fn leikkaa(&self, toinen: &Rajaus) -> bool {
+        !(self.oikea_ala.x < toinen.vasen_yla.x
+            || toinen.oikea_ala.x < self.vasen_yla.x
+            || self.oikea_ala.y < toinen.vasen_yla.y
+            || toinen.oikea_ala.y < self.vasen_yla.y)
+    }
+
+    /// Antaa alueen pisteen, joka on lähimpänä annettua pistettä
+    /// # Arguments
+    /// * `piste` - Piste, jota lähin alueen piste etsitään
+    pub fn lahin_piste(&self, piste: Vektori) -> Vektori {
+        Vektori::new(
+            piste.x.max(self.vasen_yla.x).min(self.oikea_ala.x),
+            piste.y.max(self.vasen_yla.y).min(self.oikea_ala.y),
+        )
     }
 }
 
 /// Kertoo minkälainen kappale on kyseessä.
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Eq, Copy, Clone)]
 pub enum Tagi {
     Vihollinen,
     Seina,
     Ammus,
     Pelaaja,
     Partikkeli,
+    /// Pelin oma tagi, jonka nimi on rekisteröity tagirekisteriin
+    Oma(OmaTagi),
 }
 
-/// Kappale, jolla on muoto ja sijainti
+/// Kappale, jolla on muoto, sijainti ja asento
 #[derive(Copy, Clone)]
 pub struct Kappale {
     /// Kappaleen muoto
     pub muoto: Muoto,
-    /// Kappaleen kulman sijainti
-    sijainti: Vektori<f32>,
-    /// Minkälainen kappale on kyseessä
-    pub tagi: Tagi,
+    /// Kappaleen kulman sijainti. Kulman sijainti on kiertämättömän muodon vasen yläkulma.
+    sijainti: Vektori,
+    /// Kappaleen asento radiaaneina. Kappale on kierretty keskipisteensä ympäri.
+    kulma: Luku,
+    /// Minkälainen kappale on kyseessä. Kappaleella voi olla useita tageja.
+    pub tagit: Tagit,
 }
 
 impl Kappale {
@@ -56,11 +231,13 @@ impl Kappale {
     /// * `muoto` - Kappaleen muoto
     /// * `x` - Kappaleen keskipisteen sijainnin x-koordinaatti
     /// * `y` - Kappaleen keskipisteen sijainnin y-koordinaatti
-    pub fn new_keskipisteella(muoto: Muoto, x: f32, y: f32, tagi: Tagi) -> Self {
+    /// * `tagit` - Kappaleen tagi tai tagijoukko
+    pub fn new_keskipisteella<T: Into<Tagit>>(muoto: Muoto, x: Luku, y: Luku, tagit: T) -> Self {
         Kappale {
             muoto: muoto,
             sijainti: Vektori::new(x, y) - muoto.keskipiste(),
-            tagi: tagi,
+            kulma: Luku::default(),
+            tagit: tagit.into(),
         }
     }
 
@@ -69,14 +246,23 @@ impl Kappale {
     /// * `muoto` - Kappaleen muoto
     /// * `x` - Kappaleen kulman sijainnin x-koordinaatti
     /// * `y` - Kappaleen kulman sijainnin y-koordinaatti
-    pub fn new_kulmalla(muoto: Muoto, x: f32, y: f32, tagi: Tagi) -> Self {
+    /// * `tagit` - Kappaleen tagi tai tagijoukko
+    pub fn new_kulmalla<T: Into<Tagit>>(muoto: Muoto, x: Luku, y: Luku, tagit: T) -> Self {
         Kappale {
             muoto: muoto,
             sijainti: Vektori::new(x, y),
-            tagi: tagi,
+            kulma: Luku::default(),
+            tagit: tagit.into(),
         }
     }
 
+    /// Onko kappaleella annettu tagi
+    /// # Arguments
+    /// * `tagi` - Tagi, jota etsitään
+    pub fn onko_tagi(&self, tagi: Tagi) -> bool {
+        self.tagit.sisaltaa(tagi)
+    }
+
     /// Antaa kappaleen vasemman yläkulman(?) sijainnin
     pub fn kulman_sijainti(&self) -> Vektori {
         self.sijainti
@@ -87,6 +273,73 @@ impl Kappale {
         self.sijainti + self.muoto.keskipiste()
     }
 
+    /// Antaa kappaleen asennon radiaaneina
+    pub fn kulma(&self) -> Luku {
+        self.kulma
+    }
+
+    /// Antaa yksikkövektorin, joka osoittaa kappaleen katsomaan suuntaan
+    pub fn suunta(&self) -> Vektori {
+        Vektori::new(luku(1.0), luku(0.0)).kierra(self.kulma)
+    }
+
+    /// Antaa suorakaiteen kulmat pelimaailmassa kierrettyinä kappaleen asennon mukaan.
+    /// Muille muodoille annetaan niiden rajaavan suorakaiteen kulmat.
+    pub fn kulmat(&self) -> [Vektori; 4] {
+        let (leveys, korkeus) = self.muoto.koko();
+        let keskipiste = self.keskipisteen_sijainti();
+        let puolikas = Vektori::new(leveys / 2.0, korkeus / 2.0);
+        let mut kulmat = [
+            Vektori::new(-puolikas.x, -puolikas.y),
+            Vektori::new(puolikas.x, -puolikas.y),
+            Vektori::new(puolikas.x, puolikas.y),
+            Vektori::new(-puolikas.x, puolikas.y),
+        ];
+        for kulma in kulmat.iter_mut() {
+            *kulma = keskipiste + kulma.kierra(self.kulma);
+        }
+        kulmat
+    }
+
+    /// Antaa kappaleen rungon eli muodon ytimen pelimaailmassa kierrettynä kappaleen
+    /// asennon mukaan. Rungon avulla voidaan verrata mitä tahansa muotoja keskenään.
+    pub fn runko(&self) -> Runko {
+        let (mut pisteet, maara, sade) = self.muoto.ydin();
+        let keskipiste = self.keskipisteen_sijainti();
+        for piste in pisteet[..maara].iter_mut() {
+            *piste = keskipiste + piste.kierra(self.kulma);
+        }
+        Runko::new(&pisteet[..maara], sade)
+    }
+
+    /// Antaa lyhimmän etäisyyden annetusta pisteestä kappaleen reunaan.
+    /// Etäisyys on nolla, jos piste on kappaleen sisällä.
+    /// # Arguments
+    /// * `piste` - Piste, josta etäisyys lasketaan
+    pub fn etaisyys_pisteesta(&self, piste: Vektori) -> Luku {
+        let runko = self.runko();
+        (runko.ytimien_etaisyys(&Runko::new(&[piste], Luku::default())) - runko.sade).max(luku(0.0))
+    }
+
+    /// Antaa alueen, jonka sisälle kappale mahtuu kokonaan
+    pub fn rajaus(&self) -> Rajaus {
+        if self.kulma == 0.0 {
+            let (leveys, korkeus) = self.muoto.koko();
+            return Rajaus::new(self.sijainti, self.sijainti + Vektori::new(leveys, korkeus));
+        }
+        let runko = self.runko();
+        let pisteet = runko.pisteet();
+        let mut rajaus = Rajaus::new(pisteet[0], pisteet[0]);
+        for piste in &pisteet[1..] {
+            rajaus.vasen_yla.x = rajaus.vasen_yla.x.min(piste.x);
+            rajaus.vasen_yla.y = rajaus.vasen_yla.y.min(piste.y);
+            rajaus.oikea_ala.x = rajaus.oikea_ala.x.max(piste.x);
+            rajaus.oikea_ala.y = rajaus.oikea_ala.y.max(piste.y);
+        }
+        let sade = Vektori::new(runko.sade, runko.sade);
+        Rajaus::new(rajaus.vasen_yla - sade, rajaus.oikea_ala + sade)
+    }
+
     /// Asettaa kappaleen vasemman yläkulman sijainnin
     /// # Arguments
     /// * `uusi_sijainti` - Uusi kulman sijainti
@@ -100,4 +353,76 @@ impl Kappale {
     pub fn aseta_keskipisteen_sijainti(&mut self, uusi_sijainti: Vektori) {
         self.sijainti = uusi_sijainti - self.muoto.keskipiste();
     }
+
+    /// Asettaa kappaleen asennon
+    /// # Arguments
+    /// * `kulma` - Uusi asento radiaaneina
+    pub fn aseta_kulma(&mut self, kulma: Luku) {
+        self.kulma = kulma;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maailma::luku::Skalaari;
+
+    fn pisteet(pisteet: &[(f32, f32)]) -> Vec<Vektori> {
+        pisteet
+            .iter()
+            .map(|(x, y)| Vektori::new(luku(*x), luku(*y)))
+            .collect()
+    }
+
+    #[test]
+    fn kupera_monikulmio_kelpaa_kumpaankin_suuntaan() {
+        let mut karjet = pisteet(&[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (12.0, 8.0),
+            (5.0, 12.0),
+            (-2.0, 8.0),
+        ]);
+        let monikulmio = Monikulmio::new(&karjet).unwrap();
+        assert_eq!(monikulmio.koko, (luku(14.0), luku(12.0)));
+        assert_eq!(monikulmio.karjet()[0], Vektori::new(luku(2.0), luku(0.0)));
+        karjet.reverse();
+        assert!(Monikulmio::new(&karjet).is_ok());
+    }
+
+    #[test]
+    fn viisikanta_hylataan() {
+        // Viisikanta kääntyy joka kärjessä samaan suuntaan, mutta sen sivut leikkaavat
+        let karjet: Vec<Vektori> = (0..5)
+            .map(|i| {
+                let kulma = luku(i as f32 * 4.0 * PI / 5.0);
+                Vektori::new(luku(0.0), luku(-10.0)).kierra(kulma)
+            })
+            .collect();
+        let virhe = Monikulmio::new(&karjet).err().unwrap();
+        assert!(virhe.contains("kupera"), "{}", virhe);
+        // Samat kärjet oikeassa järjestyksessä muodostavat kuperan viisikulmion
+        let mut viisikulmio = karjet.clone();
+        viisikulmio.sort_by_key(|x| (x.kulma().liukuluku() * 1000.0) as i32);
+        assert!(Monikulmio::new(&viisikulmio).is_ok());
+    }
+
+    #[test]
+    fn koveraa_suoraa_tai_vaaran_kokoista_ei_hyvaksyta() {
+        let kovera = pisteet(&[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (5.0, 3.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+        ]);
+        assert!(Monikulmio::new(&kovera).is_err());
+        let suora = pisteet(&[(0.0, 0.0), (5.0, 5.0), (10.0, 10.0)]);
+        assert!(Monikulmio::new(&suora).is_err());
+        assert!(Monikulmio::new(&pisteet(&[(0.0, 0.0), (1.0, 0.0)])).is_err());
+        let liikaa: Vec<Vektori> = (0..MAKSIMI_KARKIA + 1)
+            .map(|i| Vektori::new(luku(10.0), luku(0.0)).kierra(luku(i as f32)))
+            .collect();
+        assert!(Monikulmio::new(&liikaa).is_err());
+    }
 }
@@ -1,40 +1,171 @@
+use std::collections::VecDeque;
+use std::f32::consts::PI;
 use std::rc::Rc;
+use std::time::Duration;
+
+use crate::animointi::lineaarinen_interpolaatio;
 
 use super::kappale::Kappale;
+use super::luku::{luku, Luku};
+use super::vektori::Vektori;
 use super::Lisaosa;
 use super::RcKappale;
 
-/// Muisti, joka muistaa kaksi kappaleen tilaa
+/// Muistin pienin syvyys. Interpolointiin tarvitaan vähintään kaksi tilaa.
+pub const MINIMI_SYVYYS: usize = 2;
+
+/// Kappaleen tila tiettynä pelin hetkenä
+#[derive(Copy, Clone)]
+pub struct Tilannekuva {
+    /// Pelin aika, jolloin tila tallennettiin
+    pub aika: Duration,
+    /// Kappaleen tila
+    pub kappale: Kappale,
+}
+
+/// Muisti, joka muistaa kappaleen viimeisimmät tilat aikaleimoineen
 pub struct Kappalemuisti {
-    vanhin_versio: Kappale,
-    uusin_versio: Kappale,
+    /// Tallennetut tilat vanhimmasta uusimpaan
+    historia: VecDeque<Tilannekuva>,
+    /// Kuinka monta tilaa muistetaan
+    syvyys: usize,
     oikea_versio: RcKappale,
 }
 
 impl Kappalemuisti {
     /// Luo uuden kappalemuistin tallentaen kopioiksi tämän hetkisen kappaleen tilan
-    pub fn new(kappale: RcKappale) -> Self {
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka tiloja muistetaan
+    /// * `aika` - Pelin aika, jota kappaleen nykyinen tila kuvaa
+    /// * `syvyys` - Kuinka monta tilaa muistetaan. Vähintään `MINIMI_SYVYYS`.
+    pub fn new(kappale: RcKappale, aika: Duration, syvyys: usize) -> Self {
+        let mut historia = VecDeque::new();
+        historia.push_back(Tilannekuva {
+            aika,
+            kappale: kopioi_sisalto(&kappale),
+        });
         Kappalemuisti {
-            vanhin_versio: kopioi_sisalto(&kappale),
-            uusin_versio: kopioi_sisalto(&kappale),
+            historia,
+            syvyys: syvyys.max(MINIMI_SYVYYS),
             oikea_versio: kappale,
         }
     }
 
-    /// Päivitää kappaleen muistia, jolloin uusin versio tulee vanhimmaksi versioksi
-    pub fn paivita_muistia(&mut self) {
-        self.vanhin_versio = self.uusin_versio;
-        self.uusin_versio = kopioi_sisalto(&self.oikea_versio);
+    /// Päivitää kappaleen muistia tallentamalla kappaleen nykyisen tilan uusimmaksi versioksi.
+    /// Vanhin versio unohdetaan, jos muisti on täynnä.
+    /// # Arguments
+    /// * `aika` - Pelin aika, jota kappaleen nykyinen tila kuvaa
+    pub fn paivita_muistia(&mut self, aika: Duration) {
+        let kappale = kopioi_sisalto(&self.oikea_versio);
+        self.aseta_tuleva_versio(kappale, aika);
+    }
+
+    /// Tallentaa annetun tilan uusimmaksi versioksi
+    /// # Arguments
+    /// * `tuleva_versio` - Tallennettava tila
+    /// * `aika` - Pelin aika, jota tila kuvaa
+    pub fn aseta_tuleva_versio(&mut self, tuleva_versio: Kappale, aika: Duration) {
+        self.historia.push_back(Tilannekuva {
+            aika,
+            kappale: tuleva_versio,
+        });
+        self.unohda_ylimaaraiset();
+    }
+
+    /// Asettaa, kuinka monta tilaa muistetaan. Jos muistissa on jo enemmän tiloja,
+    /// niin vanhimmat unohdetaan.
+    /// # Arguments
+    /// * `syvyys` - Muistettavien tilojen määrä. Vähintään `MINIMI_SYVYYS`.
+    pub fn aseta_syvyys(&mut self, syvyys: usize) {
+        self.syvyys = syvyys.max(MINIMI_SYVYYS);
+        self.unohda_ylimaaraiset();
     }
 
-    pub fn aseta_tuleva_versio(&mut self, tuleva_versio: Kappale) {
-        self.vanhin_versio = self.uusin_versio;
-        self.uusin_versio = tuleva_versio;
+    /// Siirtää kaikkia muistettuja tiloja annetun siirtymän verran. Käytetään, kun kappale
+    /// siirretään hyppäyksellä, jotta interpoloitaessa kappale ei näytä kulkevan siirtymän matkaa.
+    /// # Arguments
+    /// * `siirtyma` - Siirtymä, jonka verran tiloja siirretään
+    pub fn siirra(&mut self, siirtyma: Vektori) {
+        for tila in self.historia.iter_mut() {
+            let sijainti = tila.kappale.kulman_sijainti();
+            tila.kappale.aseta_kulman_sijainti(sijainti + siirtyma);
+        }
     }
 
-    /// Antaa kappaleen molemmat tallennetut versiot
+    /// Antaa kappaleen kaksi uusinta tallennettua versiota vanhemmasta alkaen.
+    /// Jos tiloja on tallennettu vasta yksi, niin molemmat versiot ovat samat.
     pub fn anna_versiot(&self) -> (&Kappale, &Kappale) {
-        (&self.vanhin_versio, &self.uusin_versio)
+        let uusin = self.historia.len() - 1;
+        (
+            &self.historia[uusin.saturating_sub(1)].kappale,
+            &self.historia[uusin].kappale,
+        )
+    }
+
+    /// Antaa kaikki muistetut tilat vanhimmasta uusimpaan
+    pub fn tilannekuvat(&self) -> impl Iterator<Item = &Tilannekuva> {
+        self.historia.iter()
+    }
+
+    /// Antaa vanhimman muistetun tilan
+    pub fn vanhin(&self) -> &Tilannekuva {
+        &self.historia[0]
+    }
+
+    /// Antaa suurimman etäisyyden, jonka päässä muistettujen tilojen ja niiden välille
+    /// interpoloitujen tilojen rajaukset voivat olla uusimman tilan rajauksesta. Kiertyvän
+    /// kappaleen rajaus muuttuu asennon mukana, joten etäisyyteen lisätään puolet sen lävistäjästä.
+    pub fn ulottuma(&self) -> Luku {
+        let uusin = &self.historia[self.historia.len() - 1].kappale;
+        let keskipiste = uusin.keskipisteen_sijainti();
+        let mut ulottuma = Luku::default();
+        let mut kiertyy = false;
+        for tila in &self.historia {
+            ulottuma = ulottuma.max((tila.kappale.keskipisteen_sijainti() - keskipiste).pituus());
+            kiertyy |= tila.kappale.kulma() != uusin.kulma();
+        }
+        if kiertyy {
+            let (leveys, korkeus) = uusin.muoto.koko();
+            ulottuma += Vektori::new(leveys, korkeus).pituus() / 2.0;
+        }
+        ulottuma
+    }
+
+    /// Antaa kappaleen, jonka sijainti ja asento on interpoloitu vanhimman ja uusimman
+    /// version välillä. Asento interpoloidaan lyhintä reittiä, jotta kappale ei pyörähdä
+    /// ympäri kulman ylittäessä täyden kierroksen.
+    /// # Arguments
+    /// * `interpolaatio_arvo` - 0.0 antaa vanhimman version ja 1.0 uusimman version
+    pub fn interpoloi(&self, interpolaatio_arvo: f32) -> Kappale {
+        let (vanha, uusi) = self.anna_versiot();
+        interpoloi_tilat(vanha, uusi, interpolaatio_arvo)
+    }
+
+    /// Antaa kappaleen tilan annetulla pelin hetkellä interpoloiden muistettujen tilojen
+    /// välillä. Uusinta tilaa myöhemmillä hetkillä annetaan uusin tila. Jos hetki on
+    /// vanhempi kuin vanhin muistettu tila, niin tilaa ei tiedetä.
+    /// # Arguments
+    /// * `aika` - Pelin aika, jolta tila halutaan
+    pub fn tila_hetkella(&self, aika: Duration) -> Option<Kappale> {
+        if aika < self.vanhin().aika {
+            return None;
+        }
+        // Ensimmäinen tila, joka on annetun hetken jälkeen
+        let jalkeen = match self.historia.iter().position(|x| x.aika > aika) {
+            Some(indeksi) => indeksi,
+            None => return Some(self.historia[self.historia.len() - 1].kappale),
+        };
+        let vanha = &self.historia[jalkeen - 1];
+        let uusi = &self.historia[jalkeen];
+        let arvo = (aika - vanha.aika).as_secs_f32() / (uusi.aika - vanha.aika).as_secs_f32();
+        Some(interpoloi_tilat(&vanha.kappale, &uusi.kappale, arvo))
+    }
+
+    /// Unohtaa vanhimmat tilat, joita ei mahdu muistiin
+    fn unohda_ylimaaraiset(&mut self) {
+        while self.historia.len() > self.syvyys {
+            self.historia.pop_front();
+        }
     }
 }
 
@@ -48,3 +179,210 @@ impl Lisaosa for Kappalemuisti {
 fn kopioi_sisalto(rckappale: &RcKappale) -> Kappale {
     (*rckappale.borrow_mut()) // Kopioi sisällön
 }
+
+/// Antaa kappaleen, jonka sijainti ja asento on interpoloitu kahden tilan välillä.
+/// Muut tiedot otetaan uudemmasta tilasta.
+/// # Arguments
+/// * `vanha` - Tila, joka annetaan arvolla 0.0
+/// * `uusi` - Tila, joka annetaan arvolla 1.0
+/// * `interpolaatio_arvo` - Arvo, jolla interpoloidaan
+fn interpoloi_tilat(vanha: &Kappale, uusi: &Kappale, interpolaatio_arvo: f32) -> Kappale {
+    let mut kappale = *uusi;
+    kappale.aseta_kulman_sijainti(lineaarinen_interpolaatio(
+        0.0,
+        vanha.kulman_sijainti(),
+        1.0,
+        uusi.kulman_sijainti(),
+        interpolaatio_arvo,
+    ));
+    kappale.aseta_kulma(
+        vanha.kulma() + kulmien_erotus(vanha.kulma(), uusi.kulma()) * interpolaatio_arvo,
+    );
+    kappale
+}
+
+/// Antaa lyhimmän kulman, jonka verran alkukulmaa pitää kiertää, jotta päästään loppukulmaan.
+/// Tulos on välillä -PI..PI.
+/// # Arguments
+/// * `alku` - Alkukulma radiaaneina
+/// * `loppu` - Loppukulma radiaaneina
+fn kulmien_erotus(alku: Luku, loppu: Luku) -> Luku {
+    let erotus = (loppu - alku) % luku(2.0 * PI);
+    if erotus > luku(PI) {
+        erotus - luku(2.0 * PI)
+    } else if erotus < luku(-PI) {
+        erotus + luku(2.0 * PI)
+    } else {
+        erotus
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::fysiikka::Fysiikkakappale;
+    use crate::maailma::kappale::{Muoto, Rajaus, Tagi};
+    use crate::maailma::luku::Skalaari;
+    use crate::maailma::rajat::{Maailmanrajat, Rajatoiminta};
+    use crate::maailma::Perusmaailma;
+
+    /// Luo neliön, jonka keskipiste on annetussa pisteessä
+    fn nelio(x: f32, y: f32) -> Kappale {
+        Kappale::new_keskipisteella(
+            Muoto::Nelio(luku(10.0), luku(10.0)),
+            luku(x),
+            luku(y),
+            Tagi::Vihollinen,
+        )
+    }
+
+    fn ms(millisekunnit: u64) -> Duration {
+        Duration::from_millis(millisekunnit)
+    }
+
+    fn keskipiste(x: f32, y: f32) -> Vektori {
+        Vektori::new(luku(x), luku(y))
+    }
+
+    /// Muisti, jossa kappale on ollut hetkillä 0, 100 ja 200 ms x-koordinaateissa 0, 10 ja 30
+    fn liikkunut_muisti(syvyys: usize) -> Kappalemuisti {
+        let kappale = Rc::new(RefCell::new(nelio(0.0, 0.0)));
+        let mut muisti = Kappalemuisti::new(Rc::clone(&kappale), ms(0), syvyys);
+        kappale
+            .borrow_mut()
+            .aseta_keskipisteen_sijainti(keskipiste(10.0, 0.0));
+        kappale.borrow_mut().aseta_kulma(luku(1.0));
+        muisti.paivita_muistia(ms(100));
+        kappale
+            .borrow_mut()
+            .aseta_keskipisteen_sijainti(keskipiste(30.0, 0.0));
+        muisti.paivita_muistia(ms(200));
+        muisti
+    }
+
+    #[test]
+    fn tila_hetkella_interpoloidaan() {
+        let muisti = liikkunut_muisti(3);
+        let tila = |aika| muisti.tila_hetkella(ms(aika)).unwrap();
+        assert_eq!(tila(0).keskipisteen_sijainti(), keskipiste(0.0, 0.0));
+        assert_eq!(tila(50).keskipisteen_sijainti(), keskipiste(5.0, 0.0));
+        assert_eq!(tila(50).kulma(), luku(0.5));
+        assert_eq!(tila(100).keskipisteen_sijainti(), keskipiste(10.0, 0.0));
+        assert_eq!(tila(150).keskipisteen_sijainti(), keskipiste(20.0, 0.0));
+        // Uusimman tilan jälkeen annetaan uusin tila
+        assert_eq!(tila(500).keskipisteen_sijainti(), keskipiste(30.0, 0.0));
+    }
+
+    #[test]
+    fn kulma_interpoloidaan_lyhinta_reittia() {
+        let kappale = Rc::new(RefCell::new(nelio(0.0, 0.0)));
+        kappale.borrow_mut().aseta_kulma(luku(3.0));
+        let mut muisti = Kappalemuisti::new(Rc::clone(&kappale), ms(0), 2);
+        kappale.borrow_mut().aseta_kulma(luku(-3.0));
+        muisti.paivita_muistia(ms(100));
+
+        // Kulmasta 3.0 kulmaan -3.0 kierretään PI:n kautta eikä nollan kautta
+        let kulma = |aika| muisti.tila_hetkella(ms(aika)).unwrap().kulma();
+        assert!(kulma(50).lahes_sama(luku(PI), 0.001), "{:?}", kulma(50));
+        assert!(kulma(25).lahes_sama(luku(3.0 + (PI - 3.0) / 2.0), 0.001));
+        assert!(kulma(25) > luku(3.0) && kulma(75) > luku(3.0));
+    }
+
+    #[test]
+    fn unohdettua_hetkea_ei_tiedeta() {
+        let muisti = liikkunut_muisti(2);
+        assert_eq!(muisti.vanhin().aika, ms(100));
+        assert!(muisti.tila_hetkella(ms(50)).is_none());
+        assert_eq!(
+            muisti
+                .tila_hetkella(ms(150))
+                .unwrap()
+                .keskipisteen_sijainti(),
+            keskipiste(20.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn syvyys_on_vahintaan_minimi() {
+        let muisti = liikkunut_muisti(0);
+        assert_eq!(muisti.tilannekuvat().count(), MINIMI_SYVYYS);
+
+        let mut muisti = liikkunut_muisti(3);
+        assert_eq!(muisti.tilannekuvat().count(), 3);
+        muisti.aseta_syvyys(1);
+        assert_eq!(muisti.tilannekuvat().count(), MINIMI_SYVYYS);
+        // Vanhimmat tilat unohdetaan
+        assert_eq!(muisti.vanhin().aika, ms(100));
+        let (vanha, uusi) = muisti.anna_versiot();
+        assert_eq!(vanha.keskipisteen_sijainti(), keskipiste(10.0, 0.0));
+        assert_eq!(uusi.keskipisteen_sijainti(), keskipiste(30.0, 0.0));
+    }
+
+    #[test]
+    fn ulottuma_kattaa_koko_historian() {
+        // Vanhin tila on 30 yksikön päässä uusimmasta, ja kappale on kiertynyt välillä
+        let muisti = liikkunut_muisti(3);
+        assert!(muisti
+            .ulottuma()
+            .lahes_sama(luku(30.0 + 50.0_f32.sqrt()), 0.001));
+
+        let kappale = Rc::new(RefCell::new(nelio(0.0, 0.0)));
+        let mut muisti = Kappalemuisti::new(Rc::clone(&kappale), ms(0), 2);
+        kappale
+            .borrow_mut()
+            .aseta_keskipisteen_sijainti(keskipiste(3.0, 4.0));
+        muisti.paivita_muistia(ms(100));
+        assert!(muisti.ulottuma().lahes_sama(luku(5.0), 0.001));
+    }
+
+    #[test]
+    fn kierto_siirtaa_muistetut_tilat() {
+        let mut maailma = Perusmaailma::new_interpoloiva();
+        let mut rajat =
+            Maailmanrajat::new(Rajaus::new(keskipiste(0.0, 0.0), keskipiste(100.0, 100.0)));
+        rajat.oletus = Some(Rajatoiminta::Kierto);
+        maailma.aseta_rajat(Some(rajat));
+        let kappale = maailma.lisaa_kappale(nelio(95.0, 50.0));
+        maailma.lisaa_fysiikkakappale(Fysiikkakappale::new(
+            Default::default(),
+            Rc::clone(&kappale),
+        ));
+        maailma.paivita_kappalemuistia(ms(100));
+
+        // Kappale ylittää oikean reunan 10 yksikön liikkeellä ja siirtyy vasempaan reunaan
+        kappale
+            .borrow_mut()
+            .aseta_keskipisteen_sijainti(keskipiste(105.0, 50.0));
+        maailma.sovella_rajoja();
+        maailma.paivita_kappalemuistia(ms(200));
+        assert_eq!(
+            kappale.borrow().keskipisteen_sijainti(),
+            keskipiste(5.0, 50.0)
+        );
+
+        // Välihetkellä kappale on vasemman reunan takana eikä keskellä maailmaa
+        let tila = maailma.kappale_hetkella(&kappale, ms(150)).unwrap();
+        assert_eq!(tila.keskipisteen_sijainti(), keskipiste(0.0, 50.0));
+        let tila = maailma.kappale_hetkella(&kappale, ms(100)).unwrap();
+        assert_eq!(tila.keskipisteen_sijainti(), keskipiste(-5.0, 50.0));
+    }
+
+    #[test]
+    fn vain_interpoloiva_maailma_muistaa() {
+        let mut maailma = Perusmaailma::new();
+        let kappale = maailma.lisaa_kappale(nelio(0.0, 0.0));
+        maailma.paivita_kappalemuistia(ms(100));
+        assert!(!maailma.muistaa_tilat());
+        assert!(maailma.kappale_hetkella(&kappale, ms(100)).is_err());
+
+        let mut maailma = Perusmaailma::new_interpoloiva();
+        maailma.aseta_pelin_aika(ms(100));
+        let kappale = maailma.lisaa_kappale(nelio(0.0, 0.0));
+        maailma.paivita_kappalemuistia(ms(200));
+        assert!(maailma.muistaa_tilat());
+        assert!(maailma.kappale_hetkella(&kappale, ms(150)).is_ok());
+        assert!(maailma.kappale_hetkella(&kappale, ms(50)).is_err());
+    }
+}
@@ -0,0 +1,614 @@
+//! Sisältää kiintoluvun, jolla simulaation laskut ovat bittitarkasti samat kaikilla
+//! kääntäjillä, optimointitasoilla ja alustoilla. Liukulukujen trigonometriset funktiot
+//! ja neliöjuuri lasketaan kiintoluvuille kokonaislukulaskuilla.
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign,
+};
+use std::str::FromStr;
+
+use super::luku::Skalaari;
+use super::vektori::Vektori;
+
+/// Murto-osan bittien määrä
+const MURTOBITIT: u32 = 16;
+/// Luvun 1 sisäinen esitys
+const YKSIKKO: i64 = 1 << MURTOBITIT;
+/// Murto-osan bittien määrä välilaskuissa, joissa pyöristysvirheet muuten kertyisivät
+const TARKAT_MURTOBITIT: u32 = 30;
+/// Luvun 1 esitys tarkkoina välilukuina
+const TARKKA_YKSIKKO: i64 = 1 << TARKAT_MURTOBITIT;
+/// Pii tarkkana välilukuna
+const TARKKA_PII: i64 = 3_373_259_426;
+/// Arkustangentin polynomin kertoimet tarkkoina välilukuina alimmasta asteesta alkaen.
+/// Polynomin virhe välillä -1..1 on noin 0.00001.
+const ARKUSTANGENTIN_KERTOIMET: [i64; 5] = [
+    1_073_597_943,
+    -354_656_388,
+    193_424_926,
+    -91_410_863,
+    22_371_518,
+];
+
+/// Kertoo kaksi tarkkaa välilukua
+/// # Arguments
+/// * `a` - Ensimmäinen tekijä
+/// * `b` - Toinen tekijä
+fn tarkka_tulo(a: i64, b: i64) -> i64 {
+    ((i128::from(a) * i128::from(b)) >> TARKAT_MURTOBITIT) as i64
+}
+
+/// Pyöristää tarkan väliluvun lähimmäksi kiintoluvuksi
+/// # Arguments
+/// * `tarkka` - Pyöristettävä väliluku
+fn pyorista(tarkka: i64) -> Kiintea {
+    let siirto = TARKAT_MURTOBITIT - MURTOBITIT;
+    Kiintea((tarkka + (1 << (siirto - 1))) >> siirto)
+}
+
+/// Etumerkillinen kiintoluku, jossa on 16 murto-osabittiä. Luvun tarkkuus on 1/65536.
+/// Laskut kyllästyvät suurimpaan ja pienimpään arvoon ylivuodon sijaan.
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Kiintea(i64);
+
+impl Kiintea {
+    /// Nolla
+    pub const NOLLA: Kiintea = Kiintea(0);
+    /// Yksi
+    pub const YKSI: Kiintea = Kiintea(YKSIKKO);
+    /// Pienin positiivinen luku
+    pub const EPSILON: Kiintea = Kiintea(1);
+    /// Suurin luku. Vastaa liukulukujen ääretöntä esim. minimin etsinnässä.
+    pub const INFINITY: Kiintea = Kiintea(i64::MAX);
+    /// Pienin luku. Vastaa liukulukujen negatiivista ääretöntä.
+    pub const NEG_INFINITY: Kiintea = Kiintea(i64::MIN + 1);
+    /// Pii
+    pub const PI: Kiintea = Kiintea(205_887);
+
+    /// Luo kiintoluvun sisäisestä esityksestä
+    /// # Arguments
+    /// * `bitit` - Luku kerrottuna luvulla 65536
+    pub const fn from_bits(bitit: i64) -> Self {
+        Kiintea(bitit)
+    }
+
+    /// Antaa luvun sisäisen esityksen eli luvun kerrottuna luvulla 65536
+    pub const fn to_bits(self) -> i64 {
+        self.0
+    }
+
+    /// Muuttaa liukuluvun lähimmäksi kiintoluvuksi. Kelpaa myös vakioille.
+    /// # Arguments
+    /// * `luku` - Muutettava liukuluku
+    pub const fn from_f32(luku: f32) -> Self {
+        // Kertominen kahden potenssilla on tarkka, joten vain pyöristys vaikuttaa tulokseen
+        let skaalattu = luku * YKSIKKO as f32;
+        if skaalattu >= 0.0 {
+            Kiintea((skaalattu + 0.5) as i64)
+        } else {
+            Kiintea((skaalattu - 0.5) as i64)
+        }
+    }
+
+    /// Muuttaa luvun lähimmäksi liukuluvuksi
+    pub fn to_f32(self) -> f32 {
+        (self.0 as f64 / YKSIKKO as f64) as f32
+    }
+
+    /// Muuttaa luvun liukuluvuksi. Muunnos on tarkka, joten sitä käytetään tekstiksi kirjoitettaessa.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / YKSIKKO as f64
+    }
+
+    /// Antaa luvun itseisarvon
+    pub fn abs(self) -> Self {
+        Kiintea(self.0.saturating_abs())
+    }
+
+    /// Antaa luvun etumerkin lukuna -1, 0 tai 1
+    pub fn signum(self) -> Self {
+        Kiintea(self.0.signum() * YKSIKKO)
+    }
+
+    /// Antaa pienemmän luvuista
+    /// # Arguments
+    /// * `toinen` - Luku, johon verrataan
+    pub fn min<T: Into<Kiintea>>(self, toinen: T) -> Self {
+        Ord::min(self, toinen.into())
+    }
+
+    /// Antaa suuremman luvuista
+    /// # Arguments
+    /// * `toinen` - Luku, johon verrataan
+    pub fn max<T: Into<Kiintea>>(self, toinen: T) -> Self {
+        Ord::max(self, toinen.into())
+    }
+
+    /// Rajaa luvun annetulle välille
+    /// # Arguments
+    /// * `pienin` - Välin alaraja
+    /// * `suurin` - Välin yläraja
+    pub fn clamp<T: Into<Kiintea>, U: Into<Kiintea>>(self, pienin: T, suurin: U) -> Self {
+        self.max(pienin).min(suurin)
+    }
+
+    /// Antaa suurimman kokonaisluvun, joka on enintään luku
+    pub fn floor(self) -> Self {
+        Kiintea(self.0 & !(YKSIKKO - 1))
+    }
+
+    /// Antaa jakojäännöksen, joka on aina ei-negatiivinen
+    /// # Arguments
+    /// * `jakaja` - Luku, jolla jaetaan
+    pub fn rem_euclid<T: Into<Kiintea>>(self, jakaja: T) -> Self {
+        Kiintea(self.0.rem_euclid(jakaja.into().0))
+    }
+
+    /// Kiintoluvut ovat aina äärellisiä
+    pub fn is_finite(self) -> bool {
+        true
+    }
+
+    /// Antaa luvun neliöjuuren. Negatiivisen luvun neliöjuuri on nolla.
+    pub fn sqrt(self) -> Self {
+        if self.0 <= 0 {
+            return Kiintea::NOLLA;
+        }
+        Kiintea((((self.0 as u128) << MURTOBITIT).isqrt()) as i64)
+    }
+
+    /// Antaa kulman sinin ja kosinin. Virhe on alle kiintoluvun tarkkuuden.
+    pub fn sin_cos(self) -> (Self, Self) {
+        // Siirretään kulma välille -PI..PI ja edelleen välille -PI/2..PI/2 tarkkoina
+        // välilukuina, jotta piin pyöristys ei kerry suurilla kulmilla
+        let pii = i128::from(TARKKA_PII);
+        let tarkka = i128::from(self.0) << (TARKAT_MURTOBITIT - MURTOBITIT);
+        let mut kulma = ((tarkka + pii).rem_euclid(2 * pii) - pii) as i64;
+        let mut kosinin_merkki = 1;
+        if kulma > TARKKA_PII / 2 {
+            kulma = TARKKA_PII - kulma;
+            kosinin_merkki = -1;
+        } else if kulma < -TARKKA_PII / 2 {
+            kulma = -TARKKA_PII - kulma;
+            kosinin_merkki = -1;
+        }
+        // Taylorin sarjat, joiden virhe välillä -PI/2..PI/2 on alle 0.000004. Sarjat
+        // lasketaan tarkkoina välilukuina ja pyöristetään vasta lopuksi.
+        let x2 = tarkka_tulo(kulma, kulma);
+        let mut sin = TARKKA_YKSIKKO;
+        for jakaja in &[72, 42, 20, 6] {
+            sin = TARKKA_YKSIKKO - tarkka_tulo(x2, sin) / jakaja;
+        }
+        let mut cos = TARKKA_YKSIKKO;
+        for jakaja in &[90, 56, 30, 12, 2] {
+            cos = TARKKA_YKSIKKO - tarkka_tulo(x2, cos) / jakaja;
+        }
+        let sin = pyorista(tarkka_tulo(kulma, sin));
+        let cos = pyorista(cos);
+        (sin, if kosinin_merkki < 0 { -cos } else { cos })
+    }
+
+    /// Antaa kulman sinin
+    pub fn sin(self) -> Self {
+        self.sin_cos().0
+    }
+
+    /// Antaa kulman kosinin
+    pub fn cos(self) -> Self {
+        self.sin_cos().1
+    }
+
+    /// Antaa pisteen (x, self) kulman x-akselista välillä -PI..PI. Virhe on alle kaksi
+    /// kiintoluvun tarkkuutta.
+    /// # Arguments
+    /// * `x` - Pisteen x-koordinaatti
+    pub fn atan2(self, x: Self) -> Self {
+        let y = self;
+        if x.0 == 0 && y.0 == 0 {
+            return Kiintea::NOLLA;
+        }
+        // Lasketaan arkustangentti tarkkana välilukuna suhteesta, jonka itseisarvo on enintään 1
+        let (pienempi, suurempi) = if x.abs() >= y.abs() { (y, x) } else { (x, y) };
+        let z = ((i128::from(pienempi.0) << TARKAT_MURTOBITIT) / i128::from(suurempi.0)) as i64;
+        let z2 = tarkka_tulo(z, z);
+        let mut kulma = 0;
+        for kerroin in ARKUSTANGENTIN_KERTOIMET.iter().rev() {
+            kulma = kerroin + tarkka_tulo(z2, kulma);
+        }
+        kulma = tarkka_tulo(z, kulma);
+        if x.abs() < y.abs() {
+            kulma = TARKKA_PII / 2 * y.0.signum() - kulma;
+        } else if x.0 < 0 {
+            kulma = if y.0 < 0 {
+                kulma - TARKKA_PII
+            } else {
+                kulma + TARKKA_PII
+            };
+        }
+        pyorista(kulma)
+    }
+}
+
+impl Skalaari for Kiintea {
+    fn sqrt(self) -> Self {
+        Kiintea::sqrt(self)
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        Kiintea::sin_cos(self)
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        Kiintea::atan2(self, x)
+    }
+
+    fn abs(self) -> Self {
+        Kiintea::abs(self)
+    }
+
+    fn is_finite(self) -> bool {
+        true
+    }
+
+    fn liukuluku(self) -> f32 {
+        self.to_f32()
+    }
+
+    /// Lähellä nollaa olevia lukuja verrataan tarkkuuteen sellaisenaan
+    fn lahes_sama(self, toinen: Self, tarkkuus: f32) -> bool {
+        let ero = (i128::from(self.0) - i128::from(toinen.0)).abs() as f64;
+        let suurin = i128::from(self.0)
+            .abs()
+            .max(i128::from(toinen.0).abs())
+            .max(i128::from(YKSIKKO)) as f64;
+        ero <= f64::from(tarkkuus) * suurin
+    }
+
+    /// Kiintoluvut ovat tarkkoja, joten vektorit ovat yhtä suuret vain täsmälleen samoina
+    fn yhtasuuri(self, toinen: Self) -> bool {
+        self == toinen
+    }
+}
+
+impl From<f32> for Kiintea {
+    fn from(luku: f32) -> Self {
+        Kiintea::from_f32(luku)
+    }
+}
+
+impl From<i32> for Kiintea {
+    fn from(luku: i32) -> Self {
+        Kiintea(i64::from(luku) << MURTOBITIT)
+    }
+}
+
+impl From<Kiintea> for f32 {
+    fn from(luku: Kiintea) -> Self {
+        luku.to_f32()
+    }
+}
+
+impl fmt::Display for Kiintea {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f64(), f)
+    }
+}
+
+impl fmt::Debug for Kiintea {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_f64(), f)
+    }
+}
+
+impl FromStr for Kiintea {
+    type Err = std::num::ParseFloatError;
+
+    /// Lukee luvun desimaalimuodosta. Tekstiksi kirjoitettu luku luetaan takaisin täsmälleen
+    /// samaksi, kun sen itseisarvo on alle 2^37 eli sisäinen esitys mahtuu liukuluvun mantissaan.
+    /// Liian suuret luvut kyllästyvät suurimpaan tai pienimpään lukuun.
+    fn from_str(teksti: &str) -> Result<Self, Self::Err> {
+        let skaalattu = teksti.parse::<f64>()? * YKSIKKO as f64;
+        Ok(Kiintea(skaalattu.round() as i64))
+    }
+}
+
+impl Add for Kiintea {
+    type Output = Kiintea;
+    fn add(self, other: Kiintea) -> Kiintea {
+        Kiintea(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Kiintea {
+    type Output = Kiintea;
+    fn sub(self, other: Kiintea) -> Kiintea {
+        Kiintea(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Mul for Kiintea {
+    type Output = Kiintea;
+    fn mul(self, other: Kiintea) -> Kiintea {
+        let tulo = (i128::from(self.0) * i128::from(other.0)) >> MURTOBITIT;
+        Kiintea(tulo.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64)
+    }
+}
+
+impl Div for Kiintea {
+    type Output = Kiintea;
+    /// Nollalla jakaminen antaa suurimman tai pienimmän luvun jaettavan etumerkin mukaan
+    fn div(self, other: Kiintea) -> Kiintea {
+        if other.0 == 0 {
+            return match self.0.cmp(&0) {
+                Ordering::Less => Kiintea::NEG_INFINITY,
+                Ordering::Equal => Kiintea::NOLLA,
+                Ordering::Greater => Kiintea::INFINITY,
+            };
+        }
+        let osamaara = (i128::from(self.0) << MURTOBITIT) / i128::from(other.0);
+        Kiintea(osamaara.clamp(i128::from(i64::MIN), i128::from(i64::MAX)) as i64)
+    }
+}
+
+impl Rem for Kiintea {
+    type Output = Kiintea;
+    /// Jakojäännöksellä on jaettavan etumerkki kuten liukuluvuilla. Nollalla jakaminen antaa nollan.
+    fn rem(self, other: Kiintea) -> Kiintea {
+        if other.0 == 0 {
+            return Kiintea::NOLLA;
+        }
+        Kiintea(self.0 % other.0)
+    }
+}
+
+impl Neg for Kiintea {
+    type Output = Kiintea;
+    fn neg(self) -> Kiintea {
+        Kiintea(self.0.saturating_neg())
+    }
+}
+
+impl Neg for &Kiintea {
+    type Output = Kiintea;
+    fn neg(self) -> Kiintea {
+        -*self
+    }
+}
+
+/// Toteuttaa laskutoimituksen ja sen sijoitusoperaattorin myös liukuluvuille ja viittauksille,
+/// jotta esim. `leveys / 2.0` toimii sekä liukuluvuilla että kiintoluvuilla
+macro_rules! sekalaskut {
+    ($tapa:ident, $funktio:ident, $sijoitustapa:ident, $sijoitusfunktio:ident) => {
+        impl $tapa<Kiintea> for &Kiintea {
+            type Output = Kiintea;
+            fn $funktio(self, other: Kiintea) -> Kiintea {
+                $tapa::$funktio(*self, other)
+            }
+        }
+
+        impl $tapa<&Kiintea> for Kiintea {
+            type Output = Kiintea;
+            fn $funktio(self, other: &Kiintea) -> Kiintea {
+                $tapa::$funktio(self, *other)
+            }
+        }
+
+        impl $tapa<f32> for &Kiintea {
+            type Output = Kiintea;
+            fn $funktio(self, other: f32) -> Kiintea {
+                $tapa::$funktio(*self, Kiintea::from_f32(other))
+            }
+        }
+
+        impl $tapa<f32> for Kiintea {
+            type Output = Kiintea;
+            fn $funktio(self, other: f32) -> Kiintea {
+                $tapa::$funktio(self, Kiintea::from_f32(other))
+            }
+        }
+
+        impl $tapa<Kiintea> for f32 {
+            type Output = Kiintea;
+            fn $funktio(self, other: Kiintea) -> Kiintea {
+                $tapa::$funktio(Kiintea::from_f32(self), other)
+            }
+        }
+
+        impl $sijoitustapa for Kiintea {
+            fn $sijoitusfunktio(&mut self, other: Kiintea) {
+                *self = $tapa::$funktio(*self, other);
+            }
+        }
+
+        impl $sijoitustapa<f32> for Kiintea {
+            fn $sijoitusfunktio(&mut self, other: f32) {
+                *self = $tapa::$funktio(*self, Kiintea::from_f32(other));
+            }
+        }
+    };
+}
+
+sekalaskut!(Add, add, AddAssign, add_assign);
+sekalaskut!(Sub, sub, SubAssign, sub_assign);
+sekalaskut!(Mul, mul, MulAssign, mul_assign);
+sekalaskut!(Div, div, DivAssign, div_assign);
+sekalaskut!(Rem, rem, RemAssign, rem_assign);
+
+impl PartialEq<f32> for Kiintea {
+    fn eq(&self, other: &f32) -> bool {
+        *self == Kiintea::from_f32(*other)
+    }
+}
+
+impl PartialEq<Kiintea> for f32 {
+    fn eq(&self, other: &Kiintea) -> bool {
+        Kiintea::from_f32(*self) == *other
+    }
+}
+
+impl PartialOrd<f32> for Kiintea {
+    fn partial_cmp(&self, other: &f32) -> Option<Ordering> {
+        Some(self.cmp(&Kiintea::from_f32(*other)))
+    }
+}
+
+impl PartialOrd<Kiintea> for f32 {
+    fn partial_cmp(&self, other: &Kiintea) -> Option<Ordering> {
+        Some(Kiintea::from_f32(*self).cmp(other))
+    }
+}
+
+impl std::iter::Sum for Kiintea {
+    fn sum<I: Iterator<Item = Kiintea>>(iter: I) -> Kiintea {
+        iter.fold(Kiintea::NOLLA, |a, b| a + b)
+    }
+}
+
+impl Mul<f32> for Vektori<Kiintea> {
+    type Output = Vektori<Kiintea>;
+    fn mul(self, other: f32) -> Self::Output {
+        self * Kiintea::from_f32(other)
+    }
+}
+
+impl Div<f32> for Vektori<Kiintea> {
+    type Output = Vektori<Kiintea>;
+    fn div(self, other: f32) -> Self::Output {
+        self / Kiintea::from_f32(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    /// Kiintoluvun tarkkuus eli pienin ero kahden luvun välillä
+    const ULP: f64 = 1.0 / YKSIKKO as f64;
+
+    /// Käy läpi kiintoluvut annetulta väliltä annetuin askelin
+    fn valilta(alku: f64, loppu: f64, askel: i64) -> impl Iterator<Item = Kiintea> {
+        let alku = (alku * YKSIKKO as f64) as i64;
+        let loppu = (loppu * YKSIKKO as f64) as i64;
+        (alku..=loppu).step_by(askel as usize).map(Kiintea)
+    }
+
+    #[test]
+    fn laskutoimitukset() {
+        let a = Kiintea::from_f32(1.5);
+        let b = Kiintea::from_f32(-0.25);
+        assert_eq!(a + b, Kiintea::from_f32(1.25));
+        assert_eq!(a - b, Kiintea::from_f32(1.75));
+        assert_eq!(a * b, Kiintea::from_f32(-0.375));
+        assert_eq!(a / b, Kiintea::from_f32(-6.0));
+        assert_eq!(a % 1.0, Kiintea::from_f32(0.5));
+        assert_eq!(-a, Kiintea::from_f32(-1.5));
+        assert_eq!(Kiintea::from_f32(-1.5).floor(), Kiintea::from(-2));
+        assert_eq!(
+            Kiintea::from_f32(-1.5).rem_euclid(1.0),
+            Kiintea::from_f32(0.5)
+        );
+        assert_eq!(2.0 * a, 3.0);
+        assert!(b < 0.0);
+    }
+
+    #[test]
+    fn tekstiksi_ja_takaisin() {
+        let suurin_tarkka: i64 = (1 << 53) - 1;
+        let bitit = [0, 1, -1, 3, 65535, 65536, -205_887];
+        for bitit in bitit.iter().chain(&[suurin_tarkka, -suurin_tarkka]) {
+            let luku = Kiintea::from_bits(*bitit);
+            assert_eq!(luku.to_string().parse::<Kiintea>(), Ok(luku));
+        }
+        assert_eq!("0.5".parse::<Kiintea>(), Ok(Kiintea::from_bits(32768)));
+        assert_eq!("-2".parse::<Kiintea>(), Ok(Kiintea::from(-2)));
+        assert_eq!("1e30".parse::<Kiintea>(), Ok(Kiintea::INFINITY));
+        assert!("puoli".parse::<Kiintea>().is_err());
+    }
+
+    #[test]
+    fn liukuluvusta_pyoristetaan_lahimpaan() {
+        assert_eq!(Kiintea::from_f32(0.6 / 65536.0), Kiintea::EPSILON);
+        assert_eq!(Kiintea::from_f32(-0.6 / 65536.0), -Kiintea::EPSILON);
+        assert_eq!(Kiintea::from_f32(0.4 / 65536.0), Kiintea::NOLLA);
+        assert_eq!(Kiintea::from_f32(0.1), Kiintea::from_bits(6554));
+    }
+
+    #[test]
+    fn ylivuoto_kyllastyy() {
+        let suuri = Kiintea::INFINITY;
+        assert_eq!(suuri + Kiintea::YKSI, suuri);
+        assert_eq!(suuri * 2.0, suuri);
+        assert_eq!(suuri / 0.5, suuri);
+        assert_eq!(-suuri, Kiintea::NEG_INFINITY);
+        assert_eq!(Kiintea::NEG_INFINITY * 2.0, Kiintea::from_bits(i64::MIN));
+        assert_eq!(
+            Kiintea::NEG_INFINITY - Kiintea::INFINITY,
+            Kiintea::from_bits(i64::MIN)
+        );
+        assert_eq!(-Kiintea::from_bits(i64::MIN), suuri);
+        assert_eq!(Kiintea::from_bits(i64::MIN).abs(), suuri);
+        assert_eq!(Kiintea::YKSI / 0.0, Kiintea::INFINITY);
+        assert_eq!(-Kiintea::YKSI / 0.0, Kiintea::NEG_INFINITY);
+        assert_eq!(Kiintea::NOLLA / 0.0, Kiintea::NOLLA);
+        assert_eq!(Kiintea::YKSI % 0.0, Kiintea::NOLLA);
+    }
+
+    #[test]
+    fn neliojuuri() {
+        assert_eq!(Kiintea::from(4).sqrt(), Kiintea::from(2));
+        assert_eq!(Kiintea::from(-4).sqrt(), Kiintea::NOLLA);
+        for luku in valilta(0.0, 1000.0, 997).chain(valilta(1e9, 1.1e9, 1 << 30)) {
+            let virhe = (luku.sqrt().to_f64() - luku.to_f64().sqrt()).abs();
+            assert!(virhe < ULP, "sqrt({}) virhe {}", luku, virhe);
+        }
+    }
+
+    #[test]
+    fn sini_ja_kosini() {
+        assert_eq!(Kiintea::NOLLA.sin_cos(), (Kiintea::NOLLA, Kiintea::YKSI));
+        assert_eq!(Kiintea::PI.sin_cos(), (Kiintea::NOLLA, -Kiintea::YKSI));
+        for kulma in valilta(-40.0, 40.0, 13).chain(valilta(10_000.0, 10_010.0, 7)) {
+            let (sin, cos) = kulma.sin_cos();
+            let virhe_sin = (sin.to_f64() - kulma.to_f64().sin()).abs();
+            let virhe_cos = (cos.to_f64() - kulma.to_f64().cos()).abs();
+            assert!(virhe_sin < ULP, "sin({}) virhe {}", kulma, virhe_sin);
+            assert!(virhe_cos < ULP, "cos({}) virhe {}", kulma, virhe_cos);
+        }
+    }
+
+    #[test]
+    fn arkustangentti() {
+        assert_eq!(Kiintea::NOLLA.atan2(Kiintea::NOLLA), Kiintea::NOLLA);
+        assert_eq!(Kiintea::NOLLA.atan2(-Kiintea::YKSI), Kiintea::PI);
+        assert_eq!(
+            Kiintea::YKSI.atan2(Kiintea::NOLLA),
+            Kiintea::from_f32(FRAC_PI_2)
+        );
+        let koordinaatit = valilta(-50.0, 50.0, 50_021)
+            .chain(valilta(-0.001, 0.001, 7))
+            .collect::<Vec<_>>();
+        for y in &koordinaatit {
+            for x in &koordinaatit {
+                if x.0 == 0 && y.0 == 0 {
+                    continue;
+                }
+                let virhe = (y.atan2(*x).to_f64() - y.to_f64().atan2(x.to_f64())).abs();
+                assert!(virhe < 2.0 * ULP, "atan2({}, {}) virhe {}", y, x, virhe);
+            }
+        }
+    }
+
+    #[test]
+    fn vertailu_tarkkuudella() {
+        let a = Kiintea::from(1000);
+        assert!(a.lahes_sama(a + 0.5, 0.001));
+        assert!(!a.lahes_sama(a + 2.0, 0.001));
+        assert!(Kiintea::NOLLA.lahes_sama(Kiintea::from_f32(0.0005), 0.001));
+        assert!(!Kiintea::INFINITY.lahes_sama(Kiintea::NEG_INFINITY, 0.5));
+        assert!(a.yhtasuuri(a));
+        assert!(!a.yhtasuuri(a + Kiintea::EPSILON));
+        assert_ne!(Vektori::new(a, a), Vektori::new(a, a + Kiintea::EPSILON));
+    }
+}
@@ -0,0 +1,346 @@
+//! Sisältää kyselyn, jolla maailman kappaleita voidaan hakea tagin, sijainnin ja
+//! lisäosien perusteella. Kysely rakennetaan ketjuttamalla ehtoja, esim.
+//! `maailma.kysely().tagilla(Tagi::Vihollinen).sateella(piste, 200.0).lahimmat_ensin(piste).hae()`
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use super::kappale::{Rajaus, Tagi};
+use super::tagit::Tagit;
+use super::lisaosarekisteri::Lisaosarekisteri;
+use super::luku::Luku;
+use super::ruudukko::Ruudukko;
+use super::vektori::Vektori;
+use super::{Lisaosa, RcKappale};
+
+/// Kyselyn ehto, jonka kappaleen tulee täyttää
+type Ehto<'a> = Box<dyn Fn(&RcKappale) -> bool + 'a>;
+
+/// Kysely, joka antaa kaikki annetut ehdot täyttävät kappaleet
+pub struct Kysely<'a> {
+    /// Kaikki maailman kappaleet
+    kappaleet: &'a [RcKappale],
+    /// Alueindeksi, jolla sijaintiin perustuvat haut rajataan
+    alueindeksi: &'a Ruudukko,
+    /// Maailman lisäosat
+    lisaosat: &'a Lisaosarekisteri,
+    /// Sallitut tagit. Tyhjä joukko sallii kaikki tagit.
+    tagit: Tagit,
+    /// Ympyrä, jonka sisällä kappaleen tulee olla
+    ympyra: Option<(Vektori, Luku)>,
+    /// Alue, jota kappaleen rajauksen tulee leikata
+    alue: Option<Rajaus>,
+    /// Muut ehdot, jotka kappaleen tulee täyttää
+    ehdot: Vec<Ehto<'a>>,
+    /// Kappaleet, joita ei anneta
+    ohitettavat: Vec<RcKappale>,
+    /// Piste, jota lähimmät kappaleet annetaan ensin
+    jarjestys: Option<Vektori>,
+    /// Annettavien kappaleiden enimmäismäärä
+    enintaan: Option<usize>,
+}
+
+impl<'a> Kysely<'a> {
+    /// Luo uuden kyselyn, joka antaa kaikki kappaleet lisäysjärjestyksessä
+    /// # Arguments
+    /// * `kappaleet` - Kaikki maailman kappaleet
+    /// * `alueindeksi` - Kappaleiden alueindeksi
+    /// * `lisaosat` - Kappaleisiin liitetyt lisäosat
+    pub fn new(
+        kappaleet: &'a [RcKappale],
+        alueindeksi: &'a Ruudukko,
+        lisaosat: &'a Lisaosarekisteri,
+    ) -> Self {
+        Kysely {
+            kappaleet,
+            alueindeksi,
+            lisaosat,
+            tagit: Tagit::new(),
+            ympyra: None,
+            alue: None,
+            ehdot: Vec::new(),
+            ohitettavat: Vec::new(),
+            jarjestys: None,
+            enintaan: None,
+        }
+    }
+
+    /// Rajaa haun kappaleisiin, joilla on annettu tagi. Usealla kutsulla sallitaan useampi tagi.
+    /// # Arguments
+    /// * `tagi` - Sallittu tagi
+    pub fn tagilla(mut self, tagi: Tagi) -> Self {
+        self.tagit.lisaa(tagi);
+        self
+    }
+
+    /// Rajaa haun kappaleisiin, joiden jokin osa on annetun etäisyyden päässä annetusta pisteestä
+    /// # Arguments
+    /// * `keskipiste` - Piste, jonka ympäriltä kappaleita haetaan
+    /// * `sade` - Etäisyys, jonka päästä kappaleita haetaan
+    pub fn sateella(mut self, keskipiste: Vektori, sade: Luku) -> Self {
+        self.ympyra = Some((keskipiste, sade));
+        self
+    }
+
+    /// Rajaa haun kappaleisiin, joiden rajaus leikkaa annettua aluetta
+    /// # Arguments
+    /// * `alue` - Alue, jolta kappaleita haetaan
+    pub fn alueella(mut self, alue: Rajaus) -> Self {
+        self.alue = Some(alue);
+        self
+    }
+
+    /// Rajaa haun kappaleisiin, joilla on annetun tyyppinen lisäosa
+    pub fn lisaosalla<T: Lisaosa + 'static>(self) -> Self {
+        let lisaosat = self.lisaosat;
+        self.ehdolla(move |kappale| lisaosat.onko::<T>(kappale))
+    }
+
+    /// Rajaa haun kappaleisiin, jotka täyttävät annetun ehdon. Ehdolla voidaan tarkistaa
+    /// esim. maailman omia lisäosia: `.ehdolla(|x| maailma.anna_fysiikka(x).is_some())`
+    /// # Arguments
+    /// * `ehto` - Ehto, joka kappaleen tulee täyttää
+    pub fn ehdolla<F: Fn(&RcKappale) -> bool + 'a>(mut self, ehto: F) -> Self {
+        self.ehdot.push(Box::new(ehto));
+        self
+    }
+
+    /// Jättää annetun kappaleen pois tuloksista. Hyödyllinen, kun kappale hakee muita
+    /// kappaleita ympäriltään.
+    /// # Arguments
+    /// * `kappale` - Pois jätettävä kappale
+    pub fn ilman(mut self, kappale: &RcKappale) -> Self {
+        self.ohitettavat.push(Rc::clone(kappale));
+        self
+    }
+
+    /// Järjestää tulokset etäisyyden mukaan annetusta pisteestä lähimmästä alkaen.
+    /// Etäisyys lasketaan kappaleen reunaan.
+    /// # Arguments
+    /// * `piste` - Piste, josta etäisyydet lasketaan
+    pub fn lahimmat_ensin(mut self, piste: Vektori) -> Self {
+        self.jarjestys = Some(piste);
+        self
+    }
+
+    /// Rajaa tulosten määrän
+    /// # Arguments
+    /// * `maara` - Annettavien kappaleiden enimmäismäärä
+    pub fn enintaan(mut self, maara: usize) -> Self {
+        self.enintaan = Some(maara);
+        self
+    }
+
+    /// Suorittaa kyselyn ja antaa ehdot täyttävät kappaleet. Ilman järjestystä kappaleet
+    /// annetaan lisäysjärjestyksessä tai sijainnilla rajattaessa alueindeksin järjestyksessä.
+    pub fn hae(&self) -> Vec<RcKappale> {
+        let mut tulokset: Vec<RcKappale> = self
+            .ehdokkaat()
+            .into_iter()
+            .filter(|x| self.tayttaa_ehdot(x))
+            .collect();
+        if let Some(piste) = self.jarjestys {
+            let mut etaisyydet: Vec<(Luku, RcKappale)> = tulokset
+                .into_iter()
+                .map(|x| {
+                    let etaisyys = x.borrow().etaisyys_pisteesta(piste);
+                    (etaisyys, x)
+                })
+                .collect();
+            etaisyydet.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+            tulokset = etaisyydet.into_iter().map(|x| x.1).collect();
+        }
+        if let Some(maara) = self.enintaan {
+            tulokset.truncate(maara);
+        }
+        tulokset
+    }
+
+    /// Suorittaa kyselyn ja antaa ensimmäisen ehdot täyttävän kappaleen, jos sellainen on
+    pub fn ensimmainen(&self) -> Option<RcKappale> {
+        self.hae().into_iter().next()
+    }
+
+    /// Antaa kappaleet, jotka voivat täyttää sijaintiin liittyvät ehdot. Jos sijaintia ei
+    /// ole rajattu, niin annetaan kaikki kappaleet.
+    fn ehdokkaat(&self) -> Vec<RcKappale> {
+        match (self.ympyra, &self.alue) {
+            (Some((keskipiste, sade)), _) => self
+                .alueindeksi
+                .alueella(&Rajaus::ympyran_ymparilta(keskipiste, sade)),
+            (None, Some(alue)) => self.alueindeksi.alueella(alue),
+            (None, None) => self.kappaleet.to_vec(),
+        }
+    }
+
+    /// Täyttääkö kappale kaikki kyselyn ehdot
+    fn tayttaa_ehdot(&self, kappale: &RcKappale) -> bool {
+        if self
+            .ohitettavat
+            .iter()
+            .any(|x| std::ptr::eq(x.as_ptr(), kappale.as_ptr()))
+        {
+            return false;
+        }
+        {
+            let k = kappale.borrow();
+            if !self.tagit.is_empty() && !self.tagit.leikkaa(k.tagit) {
+                return false;
+            }
+            if let Some((keskipiste, sade)) = self.ympyra {
+                if k.etaisyys_pisteesta(keskipiste) > sade {
+                    return false;
+                }
+            }
+            if let Some(alue) = &self.alue {
+                if !k.rajaus().leikkaa(alue) {
+                    return false;
+                }
+            }
+        }
+        self.ehdot.iter().all(|ehto| ehto(kappale))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maailma::elinvoima::{Elinvoima, Kestavyys};
+    use crate::maailma::kappale::{Kappale, Muoto};
+    use crate::maailma::luku::luku;
+    use crate::maailma::Perusmaailma;
+
+    /// Maailma, jossa on kaksi vihollista, seinä ja kaukainen ammus. Jälkimmäisellä
+    /// vihollisella on elinvoima.
+    fn maailma() -> (Perusmaailma, Vec<RcKappale>) {
+        let mut maailma = Perusmaailma::new();
+        let ympyra = Muoto::Ympyra(luku(5.0));
+        let nelio = Muoto::Nelio(luku(10.0), luku(10.0));
+        let kappaleet = vec![
+            (ympyra, 0.0, 0.0, Tagi::Vihollinen),
+            (nelio, 100.0, 0.0, Tagi::Seina),
+            (ympyra, 50.0, 0.0, Tagi::Vihollinen),
+            (ympyra, 300.0, 300.0, Tagi::Ammus),
+        ]
+        .into_iter()
+        .map(|(muoto, x, y, tagi)| {
+            maailma.lisaa_kappale(Kappale::new_keskipisteella(muoto, luku(x), luku(y), tagi))
+        })
+        .collect::<Vec<_>>();
+        let elinvoima = Elinvoima::new(Rc::clone(&kappaleet[2]), Kestavyys::new(luku(1.0)));
+        maailma.lisaa_lisaosa(elinvoima);
+        (maailma, kappaleet)
+    }
+
+    /// Antaa tulosten järjestysnumerot maailman kappaleissa
+    fn indeksit(tulokset: &[RcKappale], kappaleet: &[RcKappale]) -> Vec<usize> {
+        tulokset
+            .iter()
+            .map(|x| kappaleet.iter().position(|k| Rc::ptr_eq(k, x)).unwrap())
+            .collect()
+    }
+
+    /// Antaa tulosten järjestysnumerot suuruusjärjestyksessä
+    fn joukko(tulokset: &[RcKappale], kappaleet: &[RcKappale]) -> Vec<usize> {
+        let mut indeksit = indeksit(tulokset, kappaleet);
+        indeksit.sort_unstable();
+        indeksit
+    }
+
+    #[test]
+    fn ilman_ehtoja_annetaan_kaikki_lisaysjarjestyksessa() {
+        let (maailma, kappaleet) = maailma();
+        assert_eq!(
+            indeksit(&maailma.kysely().hae(), &kappaleet),
+            vec![0, 1, 2, 3]
+        );
+        let ensimmainen = maailma.kysely().ensimmainen().unwrap();
+        assert!(Rc::ptr_eq(&ensimmainen, &kappaleet[0]));
+    }
+
+    #[test]
+    fn tagit_sallivat_minka_tahansa_annetuista() {
+        let (maailma, kappaleet) = maailma();
+        let viholliset = maailma.kysely().tagilla(Tagi::Vihollinen).hae();
+        assert_eq!(indeksit(&viholliset, &kappaleet), vec![0, 2]);
+        let kysely = maailma
+            .kysely()
+            .tagilla(Tagi::Vihollinen)
+            .tagilla(Tagi::Seina);
+        assert_eq!(indeksit(&kysely.hae(), &kappaleet), vec![0, 1, 2]);
+        assert!(maailma
+            .kysely()
+            .tagilla(Tagi::Pelaaja)
+            .ensimmainen()
+            .is_none());
+    }
+
+    #[test]
+    fn sade_mitataan_kappaleen_reunaan() {
+        let (maailma, kappaleet) = maailma();
+        let origo = Vektori::new(luku(0.0), luku(0.0));
+        // Keskimmäisen vihollisen reuna on 45 päässä ja seinän reuna 95 päässä
+        let lahella = maailma.kysely().sateella(origo, luku(44.0)).hae();
+        assert_eq!(joukko(&lahella, &kappaleet), vec![0]);
+        let kauempana = maailma.kysely().sateella(origo, luku(46.0)).hae();
+        assert_eq!(joukko(&kauempana, &kappaleet), vec![0, 2]);
+        let kaikki = maailma.kysely().sateella(origo, luku(96.0)).hae();
+        assert_eq!(joukko(&kaikki, &kappaleet), vec![0, 1, 2]);
+        let aareton = maailma.kysely().sateella(origo, Luku::INFINITY).hae();
+        assert_eq!(joukko(&aareton, &kappaleet), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn alue_rajaa_leikkaavat_kappaleet() {
+        let (maailma, kappaleet) = maailma();
+        let alue = Rajaus::new(
+            Vektori::new(luku(90.0), luku(-10.0)),
+            Vektori::new(luku(400.0), luku(400.0)),
+        );
+        let alueella = maailma.kysely().alueella(alue).hae();
+        assert_eq!(joukko(&alueella, &kappaleet), vec![1, 3]);
+        let kysely = maailma.kysely().alueella(alue).tagilla(Tagi::Ammus);
+        assert_eq!(indeksit(&kysely.hae(), &kappaleet), vec![3]);
+    }
+
+    #[test]
+    fn lahimmat_ensin_ja_enimmaismaara() {
+        let (maailma, kappaleet) = maailma();
+        let piste = Vektori::new(luku(400.0), luku(0.0));
+        let jarjestetty = maailma.kysely().lahimmat_ensin(piste).hae();
+        assert_eq!(indeksit(&jarjestetty, &kappaleet), vec![1, 3, 2, 0]);
+        let kaksi = maailma.kysely().lahimmat_ensin(piste).enintaan(2).hae();
+        assert_eq!(indeksit(&kaksi, &kappaleet), vec![1, 3]);
+        let lahin = maailma
+            .kysely()
+            .tagilla(Tagi::Vihollinen)
+            .lahimmat_ensin(piste)
+            .ensimmainen()
+            .unwrap();
+        assert!(Rc::ptr_eq(&lahin, &kappaleet[2]));
+    }
+
+    #[test]
+    fn lisaosat_ehdot_ja_ohitettavat() {
+        let (mut maailma, kappaleet) = maailma();
+        let elavat = maailma.kysely().lisaosalla::<Elinvoima>().hae();
+        assert_eq!(indeksit(&elavat, &kappaleet), vec![2]);
+        let muut = maailma
+            .kysely()
+            .tagilla(Tagi::Vihollinen)
+            .ilman(&kappaleet[2])
+            .hae();
+        assert_eq!(indeksit(&muut, &kappaleet), vec![0]);
+        let oikealla = maailma
+            .kysely()
+            .ehdolla(|x| x.borrow().keskipisteen_sijainti().x > luku(75.0))
+            .hae();
+        assert_eq!(indeksit(&oikealla, &kappaleet), vec![1, 3]);
+
+        // Poistettu kappale ei enää löydy kyselyillä
+        maailma.lisaa_poistettava(Rc::clone(&kappaleet[2]));
+        maailma.poista_poistettavat();
+        assert!(maailma.kysely().lisaosalla::<Elinvoima>().hae().is_empty());
+        let viholliset = maailma.kysely().tagilla(Tagi::Vihollinen).hae();
+        assert_eq!(indeksit(&viholliset, &kappaleet), vec![0]);
+    }
+}
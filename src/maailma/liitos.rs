@@ -0,0 +1,192 @@
+//! Sisältää liitoksen, jolla kappale voidaan kiinnittää toiseen kappaleeseen.
+//! Liitetty kappale eli lapsi seuraa vanhempansa sijaintia ja asentoa.
+use std::rc::Rc;
+
+use super::kappale::Kappale;
+use super::luku::Luku;
+use super::vektori::Vektori;
+use super::{Lisaosa, RcKappale};
+
+/// Lisäosa, joka kiinnittää kappaleen toiseen kappaleeseen
+pub struct Liitos {
+    /// Liitetty kappale eli lapsi
+    kappale: RcKappale,
+    /// Kappale, johon lapsi on liitetty
+    vanhempi: RcKappale,
+    /// Lapsen keskipisteen sijainti vanhemman keskipisteestä vanhemman asennon suunnassa
+    pub siirtyma: Vektori,
+    /// Lapsen asento suhteessa vanhemman asentoon radiaaneina
+    pub kulma: Luku,
+}
+
+impl Liitos {
+    /// Luo uuden liitoksen
+    /// # Arguments
+    /// * `kappale` - Liitettävä kappale
+    /// * `vanhempi` - Kappale, johon liitetään
+    /// * `siirtyma` - Lapsen keskipisteen sijainti vanhemman keskipisteestä, kun vanhempaa ei ole kierretty
+    /// * `kulma` - Lapsen asento suhteessa vanhemman asentoon radiaaneina
+    pub fn new(kappale: RcKappale, vanhempi: RcKappale, siirtyma: Vektori, kulma: Luku) -> Self {
+        Liitos {
+            kappale,
+            vanhempi,
+            siirtyma,
+            kulma,
+        }
+    }
+
+    /// Antaa kappaleen, johon lapsi on liitetty
+    pub fn anna_vanhempi(&self) -> RcKappale {
+        Rc::clone(&self.vanhempi)
+    }
+
+    /// Siirtää ja kiertää lapsen vanhemman sijainnin ja asennon mukaiseksi
+    /// # Arguments
+    /// * `vanhempi` - Vanhemman tila, jonka mukaan lapsi asetetaan
+    /// * `lapsi` - Lapsen tila, joka asetetaan
+    pub fn sovita(&self, vanhempi: &Kappale, lapsi: &mut Kappale) {
+        lapsi.aseta_keskipisteen_sijainti(
+            vanhempi.keskipisteen_sijainti() + self.siirtyma.kierra(vanhempi.kulma()),
+        );
+        lapsi.aseta_kulma(vanhempi.kulma() + self.kulma);
+    }
+}
+
+impl Lisaosa for Liitos {
+    /// Antaa liitetyn kappaleen
+    fn anna_kappale(&self) -> RcKappale {
+        Rc::clone(&self.kappale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::maailma::kappale::{Muoto, Tagi};
+    use crate::maailma::luku::luku;
+    use crate::maailma::Perusmaailma;
+
+    fn v(x: f32, y: f32) -> Vektori {
+        Vektori::new(luku(x), luku(y))
+    }
+
+    fn lisaa(maailma: &mut Perusmaailma, x: f32, y: f32) -> RcKappale {
+        maailma.lisaa_kappale(Kappale::new_keskipisteella(
+            Muoto::Ympyra(luku(1.0)),
+            luku(x),
+            luku(y),
+            Tagi::Vihollinen,
+        ))
+    }
+
+    /// Siirtää ja kiertää kappaletta
+    fn siirra(kappale: &RcKappale, x: f32, y: f32, kulma: f32) {
+        let mut kappale = kappale.borrow_mut();
+        kappale.aseta_keskipisteen_sijainti(v(x, y));
+        kappale.aseta_kulma(luku(kulma));
+    }
+
+    #[test]
+    fn lapsi_seuraa_vanhemman_sijaintia_ja_asentoa() {
+        let mut maailma = Perusmaailma::new();
+        let vanhempi = lisaa(&mut maailma, 0.0, 0.0);
+        let lapsi = lisaa(&mut maailma, 100.0, 100.0);
+        maailma
+            .liita(&lapsi, &vanhempi, v(10.0, 0.0), luku(0.5))
+            .unwrap();
+        // Lapsi siirretään paikalleen heti liitettäessä
+        assert_eq!(lapsi.borrow().keskipisteen_sijainti(), v(10.0, 0.0));
+        assert_eq!(lapsi.borrow().kulma(), luku(0.5));
+
+        siirra(&vanhempi, 5.0, 5.0, FRAC_PI_2);
+        maailma.paivita_liitokset();
+        assert_eq!(lapsi.borrow().keskipisteen_sijainti(), v(5.0, 15.0));
+        assert_eq!(lapsi.borrow().kulma(), luku(FRAC_PI_2 + 0.5));
+        assert!(Rc::ptr_eq(
+            &maailma.anna_vanhempi(&lapsi).unwrap(),
+            &vanhempi
+        ));
+        assert!(maailma.anna_vanhempi(&vanhempi).is_none());
+    }
+
+    #[test]
+    fn vanhemmat_paivitetaan_ennen_lapsiaan() {
+        let mut maailma = Perusmaailma::new();
+        let isovanhempi = lisaa(&mut maailma, 0.0, 0.0);
+        let vanhempi = lisaa(&mut maailma, 0.0, 0.0);
+        let lapsi = lisaa(&mut maailma, 0.0, 0.0);
+        // Lapsen liitos lisätään ensin, joten lisäysjärjestyksessä se päivitettäisiin
+        // vanhemman vanhalla sijainnilla
+        maailma
+            .liita(&lapsi, &vanhempi, v(0.0, 10.0), Luku::default())
+            .unwrap();
+        maailma
+            .liita(&vanhempi, &isovanhempi, v(10.0, 0.0), Luku::default())
+            .unwrap();
+
+        siirra(&isovanhempi, 100.0, 0.0, FRAC_PI_2);
+        maailma.paivita_liitokset();
+        assert_eq!(vanhempi.borrow().keskipisteen_sijainti(), v(100.0, 10.0));
+        assert_eq!(lapsi.borrow().keskipisteen_sijainti(), v(90.0, 10.0));
+        assert_eq!(lapsi.borrow().kulma(), luku(FRAC_PI_2));
+    }
+
+    #[test]
+    fn poistetun_kappaleen_jalkelaiset_poistetaan() {
+        let mut maailma = Perusmaailma::new();
+        let isovanhempi = lisaa(&mut maailma, 0.0, 0.0);
+        let vanhempi = lisaa(&mut maailma, 0.0, 0.0);
+        let lapsi = lisaa(&mut maailma, 0.0, 0.0);
+        let sisarus = lisaa(&mut maailma, 0.0, 0.0);
+        let muu = lisaa(&mut maailma, 0.0, 0.0);
+        maailma
+            .liita(&lapsi, &vanhempi, v(1.0, 0.0), Luku::default())
+            .unwrap();
+        maailma
+            .liita(&vanhempi, &isovanhempi, v(1.0, 0.0), Luku::default())
+            .unwrap();
+        maailma
+            .liita(&sisarus, &isovanhempi, v(0.0, 1.0), Luku::default())
+            .unwrap();
+
+        // Lapsen poistaminen ei poista vanhempaa
+        maailma.lisaa_poistettava(Rc::clone(&lapsi));
+        maailma.poista_poistettavat();
+        assert_eq!(maailma.kappaleet().len(), 4);
+
+        let lapsi = lisaa(&mut maailma, 0.0, 0.0);
+        maailma
+            .liita(&lapsi, &vanhempi, v(1.0, 0.0), Luku::default())
+            .unwrap();
+        maailma.lisaa_poistettava(Rc::clone(&isovanhempi));
+        maailma.poista_poistettavat();
+        assert_eq!(maailma.kappaleet().len(), 1);
+        assert!(Rc::ptr_eq(&maailma.kappaleet()[0], &muu));
+        assert!(maailma.lisaosat::<Liitos>().is_empty());
+    }
+
+    #[test]
+    fn kehaa_ei_voi_liittaa() {
+        let mut maailma = Perusmaailma::new();
+        let a = lisaa(&mut maailma, 0.0, 0.0);
+        let b = lisaa(&mut maailma, 0.0, 0.0);
+        let c = lisaa(&mut maailma, 0.0, 0.0);
+        let liita = |maailma: &mut Perusmaailma, lapsi: &RcKappale, vanhempi: &RcKappale| {
+            maailma.liita(lapsi, vanhempi, v(1.0, 0.0), Luku::default())
+        };
+
+        assert!(liita(&mut maailma, &a, &a).is_err());
+        liita(&mut maailma, &b, &a).unwrap();
+        liita(&mut maailma, &c, &b).unwrap();
+        for (lapsi, vanhempi) in &[(&a, &b), (&a, &c), (&b, &c)] {
+            let virhe = liita(&mut maailma, lapsi, vanhempi).err().unwrap();
+            assert!(virhe.contains("omaan lapseensa"), "{}", virhe);
+        }
+        // Epäonnistunut liitos ei muuta vanhoja liitoksia
+        assert!(maailma.anna_vanhempi(&a).is_none());
+        assert!(Rc::ptr_eq(&maailma.anna_vanhempi(&c).unwrap(), &b));
+        assert_eq!(maailma.lisaosat::<Liitos>().len(), 2);
+    }
+}
@@ -0,0 +1,352 @@
+//! Sisältää yleisen rekisterin, johon kappaleille voidaan liittää minkä tahansa tyyppisiä lisäosia
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use super::kappale::Kappale;
+use super::Lisaosa;
+use super::RcKappale;
+
+/// Säilö yhden tyypin lisäosille. Mahdollistaa lisäosien poistamisen tietämättä niiden tyyppiä.
+trait Lisaosasailo {
+    /// Poistaa säilöstä annettuun kappaleeseen liitetyt lisäosat
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosat poistetaan
+    fn poista_kappaleen_lisaosat(&mut self, kappale: &RcKappale);
+
+    /// Antaa säilön, jotta se voidaan muuttaa takaisin oikeaksi tyypikseen
+    fn as_any(&self) -> &dyn Any;
+
+    /// Antaa säilön, jotta se voidaan muuttaa takaisin oikeaksi tyypikseen
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Yhden tyypin lisäosat lisäysjärjestyksessä. Lisäosan paikka haetaan kappaleen osoittimella,
+/// joten kappaleen lisäosan hakeminen ei vaadi koko säilön läpikäyntiä.
+struct Sailo<T> {
+    /// Lisäosat lisäysjärjestyksessä
+    lisaosat: Vec<T>,
+    /// Lisäosien indeksit niiden kappaleiden osoittimien mukaan
+    indeksit: HashMap<*const Kappale, usize>,
+}
+
+impl<T: Lisaosa> Sailo<T> {
+    /// Luo tyhjän säilön
+    fn new() -> Self {
+        Sailo {
+            lisaosat: Vec::new(),
+            indeksit: HashMap::new(),
+        }
+    }
+
+    /// Antaa annettuun kappaleeseen liitetyn lisäosan indeksin, jos sellainen on
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosaa etsitään
+    fn indeksi(&self, kappale: &RcKappale) -> Option<usize> {
+        self.indeksit.get(&osoitin(kappale)).copied()
+    }
+
+    /// Lisää lisäosan säilön loppuun. Kappaleella ei saa olla ennestään lisäosaa säilössä.
+    /// # Arguments
+    /// * `lisaosa` - Lisättävä lisäosa
+    fn lisaa(&mut self, lisaosa: T) {
+        self.indeksit
+            .insert(osoitin(&lisaosa.anna_kappale()), self.lisaosat.len());
+        self.lisaosat.push(lisaosa);
+    }
+
+    /// Poistaa annettuun kappaleeseen liitetyn lisäosan säilyttäen muiden järjestyksen
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosa poistetaan
+    fn poista(&mut self, kappale: &RcKappale) -> Option<T> {
+        let indeksi = self.indeksit.remove(&osoitin(kappale))?;
+        for muu in self.indeksit.values_mut() {
+            if *muu > indeksi {
+                *muu -= 1;
+            }
+        }
+        Some(self.lisaosat.remove(indeksi))
+    }
+}
+
+impl<T: Lisaosa + 'static> Lisaosasailo for Sailo<T> {
+    fn poista_kappaleen_lisaosat(&mut self, kappale: &RcKappale) {
+        self.poista(kappale);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Antaa kappaleen osoittimen, jolla kappaleen lisäosat tunnistetaan
+fn osoitin(kappale: &RcKappale) -> *const Kappale {
+    kappale.as_ptr()
+}
+
+/// Rekisteri, joka säilyttää kappaleisiin liitettyjä lisäosia tyypeittäin.
+/// Kullakin kappaleella voi olla korkeintaan yksi kutakin tyyppiä oleva lisäosa.
+#[derive(Default)]
+pub struct Lisaosarekisteri {
+    /// Lisäosien säilöt lisäosan tyypin mukaan
+    sailot: HashMap<TypeId, Box<dyn Lisaosasailo>>,
+}
+
+impl Lisaosarekisteri {
+    /// Luo uuden tyhjän rekisterin
+    pub fn new() -> Self {
+        Lisaosarekisteri {
+            sailot: HashMap::new(),
+        }
+    }
+
+    /// Lisää lisäosan sen osoittamalle kappaleelle. Jos kappaleella on jo saman tyyppinen
+    /// lisäosa, niin vanha korvataan ja palautetaan.
+    /// # Arguments
+    /// * `lisaosa` - Lisättävä lisäosa
+    pub fn lisaa<T: Lisaosa + 'static>(&mut self, lisaosa: T) -> Option<T> {
+        let vanha = self.poista::<T>(&lisaosa.anna_kappale());
+        self.sailo_mut::<T>().lisaa(lisaosa);
+        vanha
+    }
+
+    /// Antaa annettuun kappaleeseen liitetyn lisäosan, jos sellainen on
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosaa pyydetään
+    pub fn anna<T: Lisaosa + 'static>(&self, kappale: &RcKappale) -> Option<&T> {
+        let sailo = self.sailo::<T>()?;
+        Some(&sailo.lisaosat[sailo.indeksi(kappale)?])
+    }
+
+    /// Antaa annettuun kappaleeseen liitetyn lisäosan, jos sellainen on
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosaa pyydetään
+    pub fn anna_mut<T: Lisaosa + 'static>(&mut self, kappale: &RcKappale) -> Option<&mut T> {
+        let sailo = self.sailot.get_mut(&TypeId::of::<T>())?;
+        let sailo = sailo.as_any_mut().downcast_mut::<Sailo<T>>().unwrap();
+        let indeksi = sailo.indeksi(kappale)?;
+        Some(&mut sailo.lisaosat[indeksi])
+    }
+
+    /// Onko annetulla kappaleella annetun tyyppistä lisäosaa
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosaa etsitään
+    pub fn onko<T: Lisaosa + 'static>(&self, kappale: &RcKappale) -> bool {
+        self.anna::<T>(kappale).is_some()
+    }
+
+    /// Antaa kaikki annetun tyyppiset lisäosat
+    pub fn kaikki<T: Lisaosa + 'static>(&self) -> &[T] {
+        match self.sailo::<T>() {
+            Some(sailo) => &sailo.lisaosat,
+            None => &[],
+        }
+    }
+
+    /// Antaa kaikki annetun tyyppiset lisäosat
+    pub fn kaikki_mut<T: Lisaosa + 'static>(&mut self) -> &mut [T] {
+        match self.sailot.get_mut(&TypeId::of::<T>()) {
+            Some(sailo) => {
+                &mut sailo
+                    .as_any_mut()
+                    .downcast_mut::<Sailo<T>>()
+                    .unwrap()
+                    .lisaosat
+            }
+            None => &mut [],
+        }
+    }
+
+    /// Poistaa annetulta kappaleelta annetun tyyppisen lisäosan ja palauttaa sen
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosa poistetaan
+    pub fn poista<T: Lisaosa + 'static>(&mut self, kappale: &RcKappale) -> Option<T> {
+        self.sailot
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<Sailo<T>>()
+            .unwrap()
+            .poista(kappale)
+    }
+
+    /// Poistaa kaikki annettuun kappaleeseen liitetyt lisäosat tyypistä riippumatta
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosat poistetaan
+    pub fn poista_kappaleen_lisaosat(&mut self, kappale: &RcKappale) {
+        for sailo in self.sailot.values_mut() {
+            sailo.poista_kappaleen_lisaosat(kappale);
+        }
+    }
+
+    /// Antaa annetun tyypin säilön, jos sellainen on
+    fn sailo<T: Lisaosa + 'static>(&self) -> Option<&Sailo<T>> {
+        self.sailot
+            .get(&TypeId::of::<T>())
+            .map(|x| x.as_any().downcast_ref::<Sailo<T>>().unwrap())
+    }
+
+    /// Antaa annetun tyypin säilön ja luo sen tarvittaessa
+    fn sailo_mut<T: Lisaosa + 'static>(&mut self) -> &mut Sailo<T> {
+        self.sailot
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Sailo::<T>::new()))
+            .as_any_mut()
+            .downcast_mut::<Sailo<T>>()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::maailma::kappale::{Muoto, Tagi};
+    use crate::maailma::luku::luku;
+    use crate::maailma::Perusmaailma;
+
+    /// Lisäosa, joka yksilöidään nimellä
+    struct Nimi {
+        kappale: RcKappale,
+        nimi: &'static str,
+    }
+
+    impl Lisaosa for Nimi {
+        fn anna_kappale(&self) -> RcKappale {
+            Rc::clone(&self.kappale)
+        }
+    }
+
+    /// Toinen lisäosan tyyppi, jotta tyyppien erottelu voidaan tarkistaa
+    struct Laskuri {
+        kappale: RcKappale,
+        arvo: u32,
+    }
+
+    impl Lisaosa for Laskuri {
+        fn anna_kappale(&self) -> RcKappale {
+            Rc::clone(&self.kappale)
+        }
+    }
+
+    fn kappale() -> Kappale {
+        Kappale::new_keskipisteella(
+            Muoto::Nelio(luku(1.0), luku(1.0)),
+            luku(0.0),
+            luku(0.0),
+            Tagi::Vihollinen,
+        )
+    }
+
+    fn uusi_kappale() -> RcKappale {
+        Rc::new(RefCell::new(kappale()))
+    }
+
+    fn nimi(kappale: &RcKappale, nimi: &'static str) -> Nimi {
+        Nimi {
+            kappale: Rc::clone(kappale),
+            nimi,
+        }
+    }
+
+    fn nimet(rekisteri: &Lisaosarekisteri) -> Vec<&'static str> {
+        rekisteri.kaikki::<Nimi>().iter().map(|x| x.nimi).collect()
+    }
+
+    #[test]
+    fn lisaaminen_ja_hakeminen() {
+        let (a, b, c) = (uusi_kappale(), uusi_kappale(), uusi_kappale());
+        let mut rekisteri = Lisaosarekisteri::new();
+        assert!(rekisteri.anna::<Nimi>(&a).is_none());
+        assert!(rekisteri.kaikki::<Nimi>().is_empty());
+
+        assert!(rekisteri.lisaa(nimi(&a, "a")).is_none());
+        assert!(rekisteri.lisaa(nimi(&b, "b")).is_none());
+        rekisteri.lisaa(Laskuri {
+            kappale: Rc::clone(&a),
+            arvo: 1,
+        });
+
+        assert_eq!(rekisteri.anna::<Nimi>(&a).unwrap().nimi, "a");
+        assert_eq!(rekisteri.anna::<Nimi>(&b).unwrap().nimi, "b");
+        assert_eq!(rekisteri.anna::<Laskuri>(&a).unwrap().arvo, 1);
+        assert!(rekisteri.onko::<Laskuri>(&a));
+        assert!(!rekisteri.onko::<Laskuri>(&b));
+        assert!(!rekisteri.onko::<Nimi>(&c));
+        assert_eq!(nimet(&rekisteri), vec!["a", "b"]);
+
+        rekisteri.anna_mut::<Laskuri>(&a).unwrap().arvo += 1;
+        assert_eq!(rekisteri.anna::<Laskuri>(&a).unwrap().arvo, 2);
+    }
+
+    #[test]
+    fn saman_tyyppinen_lisaosa_korvataan() {
+        let (a, b) = (uusi_kappale(), uusi_kappale());
+        let mut rekisteri = Lisaosarekisteri::new();
+        rekisteri.lisaa(nimi(&a, "a"));
+        rekisteri.lisaa(nimi(&b, "b"));
+        let vanha = rekisteri.lisaa(nimi(&a, "uusi a")).unwrap();
+        assert_eq!(vanha.nimi, "a");
+        assert_eq!(rekisteri.anna::<Nimi>(&a).unwrap().nimi, "uusi a");
+        assert_eq!(nimet(&rekisteri), vec!["b", "uusi a"]);
+    }
+
+    #[test]
+    fn poistaminen_sailyttaa_muiden_jarjestyksen_ja_haut() {
+        let kappaleet: Vec<RcKappale> = (0..4).map(|_| uusi_kappale()).collect();
+        let mut rekisteri = Lisaosarekisteri::new();
+        for (kappale, merkki) in kappaleet.iter().zip(&["0", "1", "2", "3"]) {
+            rekisteri.lisaa(nimi(kappale, merkki));
+        }
+        assert_eq!(rekisteri.poista::<Nimi>(&kappaleet[1]).unwrap().nimi, "1");
+        assert!(rekisteri.poista::<Nimi>(&kappaleet[1]).is_none());
+        assert!(rekisteri.poista::<Laskuri>(&kappaleet[0]).is_none());
+        assert_eq!(nimet(&rekisteri), vec!["0", "2", "3"]);
+        // Poistetun jälkeiset lisäosat löytyvät edelleen oikeista kappaleista
+        assert_eq!(rekisteri.anna::<Nimi>(&kappaleet[2]).unwrap().nimi, "2");
+        assert_eq!(rekisteri.anna::<Nimi>(&kappaleet[3]).unwrap().nimi, "3");
+        rekisteri.anna_mut::<Nimi>(&kappaleet[3]).unwrap().nimi = "kolme";
+        assert_eq!(nimet(&rekisteri), vec!["0", "2", "kolme"]);
+    }
+
+    #[test]
+    fn kappaleen_kaikki_lisaosat_poistetaan() {
+        let (a, b) = (uusi_kappale(), uusi_kappale());
+        let mut rekisteri = Lisaosarekisteri::new();
+        rekisteri.lisaa(nimi(&a, "a"));
+        rekisteri.lisaa(nimi(&b, "b"));
+        rekisteri.lisaa(Laskuri {
+            kappale: Rc::clone(&a),
+            arvo: 1,
+        });
+        rekisteri.poista_kappaleen_lisaosat(&a);
+        assert!(!rekisteri.onko::<Nimi>(&a));
+        assert!(!rekisteri.onko::<Laskuri>(&a));
+        assert!(rekisteri.kaikki::<Laskuri>().is_empty());
+        assert_eq!(nimet(&rekisteri), vec!["b"]);
+    }
+
+    #[test]
+    fn poistettavan_kappaleen_lisaosat_poistetaan_maailmasta() {
+        let mut maailma = Perusmaailma::new();
+        let a = maailma.lisaa_kappale(kappale());
+        let b = maailma.lisaa_kappale(kappale());
+        maailma.lisaa_lisaosa(nimi(&a, "a"));
+        maailma.lisaa_lisaosa(nimi(&b, "b"));
+        maailma.lisaa_lisaosa(Laskuri {
+            kappale: Rc::clone(&a),
+            arvo: 1,
+        });
+
+        maailma.lisaa_poistettava(Rc::clone(&a));
+        maailma.poista_poistettavat();
+        assert!(maailma.anna_lisaosa::<Nimi>(&a).is_none());
+        assert!(maailma.lisaosat::<Laskuri>().is_empty());
+        assert_eq!(maailma.anna_lisaosa::<Nimi>(&b).unwrap().nimi, "b");
+        assert_eq!(maailma.lisaosat::<Nimi>().len(), 1);
+    }
+}
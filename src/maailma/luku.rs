@@ -0,0 +1,129 @@
+//! Sisältää simulaation lukutyypin. Oletuksena simulaatio laskee liukuluvuilla, mutta
+//! ominaisuudella `kiintea` sijainnit, nopeudet ja kulmat lasketaan kiintoluvuilla,
+//! jolloin esim. nauhoitteen toisto on bittitarkasti sama kaikilla kääntäjillä.
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::time::Duration;
+
+#[cfg(feature = "kiintea")]
+use super::kiintea::Kiintea;
+use super::vektori::VERTAILUTARKKUUS;
+
+/// Luku, jolla simulaation sijainnit, nopeudet ja kulmat lasketaan
+#[cfg(not(feature = "kiintea"))]
+pub type Luku = f32;
+
+/// Luku, jolla simulaation sijainnit, nopeudet ja kulmat lasketaan
+#[cfg(feature = "kiintea")]
+pub type Luku = Kiintea;
+
+/// Muuttaa liukuluvun simulaation luvuksi. Kelpaa myös vakioille.
+/// # Arguments
+/// * `luku` - Muutettava liukuluku
+#[cfg(not(feature = "kiintea"))]
+pub const fn luku(luku: f32) -> Luku {
+    luku
+}
+
+/// Muuttaa liukuluvun simulaation luvuksi. Kelpaa myös vakioille.
+/// # Arguments
+/// * `luku` - Muutettava liukuluku
+#[cfg(feature = "kiintea")]
+pub const fn luku(luku: f32) -> Luku {
+    Kiintea::from_f32(luku)
+}
+
+/// Muuttaa keston sekunneiksi simulaation lukuna
+/// # Arguments
+/// * `aika` - Muutettava kesto
+#[cfg(not(feature = "kiintea"))]
+pub fn sekunteina(aika: &Duration) -> Luku {
+    aika.as_micros() as f32 * 0.000_001
+}
+
+/// Muuttaa keston sekunneiksi simulaation lukuna. Muunnos lasketaan kokonaisluvuilla.
+/// # Arguments
+/// * `aika` - Muutettava kesto
+#[cfg(feature = "kiintea")]
+pub fn sekunteina(aika: &Duration) -> Luku {
+    let bitit = aika.as_micros() * Kiintea::YKSI.to_bits() as u128 / 1_000_000;
+    Kiintea::from_bits(bitit.min(i64::MAX as u128) as i64)
+}
+
+/// Lukutyyppi, jolla vektorin pituus, kierto ja kulmat voidaan laskea
+pub trait Skalaari:
+    Copy
+    + PartialOrd
+    + Default
+    + From<f32>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    /// Antaa luvun neliöjuuren
+    fn sqrt(self) -> Self;
+
+    /// Antaa kulman sinin ja kosinin
+    fn sin_cos(self) -> (Self, Self);
+
+    /// Antaa pisteen (x, self) kulman x-akselista välillä -PI..PI
+    /// # Arguments
+    /// * `x` - Pisteen x-koordinaatti
+    fn atan2(self, x: Self) -> Self;
+
+    /// Antaa luvun itseisarvon
+    fn abs(self) -> Self;
+
+    /// Onko luku äärellinen
+    fn is_finite(self) -> bool;
+
+    /// Muuttaa luvun liukuluvuksi esim. piirtämistä varten
+    fn liukuluku(self) -> f32;
+
+    /// Ovatko luvut yhtä suuret annetulla suhteellisella tarkkuudella
+    /// # Arguments
+    /// * `toinen` - Luku, johon verrataan
+    /// * `tarkkuus` - Suurin sallittu suhteellinen ero
+    fn lahes_sama(self, toinen: Self, tarkkuus: f32) -> bool;
+
+    /// Ovatko luvut yhtä suuret vektoreita verrattaessa. Oletuksena lukuja verrataan
+    /// tarkkuudella `VERTAILUTARKKUUS`, jotta laskujärjestyksestä johtuvat pyöristysvirheet
+    /// eivät tee vektoreista erisuuria.
+    /// # Arguments
+    /// * `toinen` - Luku, johon verrataan
+    fn yhtasuuri(self, toinen: Self) -> bool {
+        self.lahes_sama(toinen, VERTAILUTARKKUUS)
+    }
+}
+
+impl Skalaari for f32 {
+    fn sqrt(self) -> Self {
+        f32::sqrt(self)
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        f32::sin_cos(self)
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        f32::atan2(self, x)
+    }
+
+    fn abs(self) -> Self {
+        f32::abs(self)
+    }
+
+    fn is_finite(self) -> bool {
+        f32::is_finite(self)
+    }
+
+    fn liukuluku(self) -> f32 {
+        self
+    }
+
+    /// Lähellä nollaa olevia lukuja verrataan tarkkuuteen sellaisenaan
+    fn lahes_sama(self, toinen: Self, tarkkuus: f32) -> bool {
+        (self - toinen).abs() <= tarkkuus * self.abs().max(toinen.abs()).max(1.0)
+    }
+}
@@ -1,20 +1,67 @@
 use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::rc::Rc;
+use std::time::Duration;
 
-use crate::animointi::{lineaarinen_interpolaatio, Animaatiot};
-use crate::fysiikka::{Fysiikallinen, Fysiikkakappale};
-use crate::piirtaja::{PiirrettavaKappale, PiirrettavaMaailma, ValiaikainenPiirrettavaKappale};
+use sdl2::pixels::Color;
+
+use crate::animointi::{Animaatiot, KatoamisAnimaatio, Kuolevainen};
+use crate::fysiikka::{Fysiikallinen, Fysiikka, Fysiikkakappale, Integrointi, Tormaysmatriisi};
+use crate::laajavaihe::Alueindeksivaihe;
+use crate::paivitys::Paivitysaika;
+use crate::piirtaja::{
+    PiirrettavaKappale, PiirrettavaMaailma, Piirtokerros, Piirtotapa,
+    ValiaikainenPiirrettavaKappale,
+};
+use crate::spawneri::Spawneri;
 use crate::tekoaly::{AlyToiminta, Alyllinen, TekoalyMaailma};
-use kappale::Kappale;
-use kappalemuisti::Kappalemuisti;
+use kappale::{Kappale, Muoto, Rajaus, Tagi};
+use elinaika::Elinaika;
+use elinvoima::{Elinvoima, Kuolema, Vahinko};
+use kappalemuisti::{Kappalemuisti, MINIMI_SYVYYS};
+use kysely::Kysely;
+use liitos::Liitos;
+use lisaosarekisteri::Lisaosarekisteri;
+use luku::{luku, Luku, Skalaari};
 use pelihahmo::Pelihahmo;
+use rajat::{Maailmanrajat, Rajakayttaytyminen, Rajanylitys};
+use ruudukko::Ruudukko;
+use tagit::Tagirekisteri;
 use vektori::Vektori;
 
+pub mod elinaika;
+pub mod elinvoima;
+pub mod geometria;
 pub mod kappale;
 pub mod kappalemuisti;
+pub mod kiintea;
+pub mod kysely;
+pub mod liitos;
+pub mod lisaosarekisteri;
+pub mod luku;
 pub mod pelihahmo;
+pub mod rajat;
+pub mod ruudukko;
+pub mod tagit;
 pub mod vektori;
 
+/// Kappaleiden jälkiin piirrettävien pisteiden säde
+const JALJEN_SADE: Luku = luku(2.0);
+/// Kuolleen kappaleen katoamisanimaation kesto
+const KUOLEMAN_ANIMAATION_KESTO: Duration = Duration::from_secs(1);
+/// Kuolleen kappaleen katoamisanimaation väri
+const KUOLEMAN_ANIMAATION_VARI: Color = Color {
+    r: 200,
+    g: 0,
+    b: 100,
+    a: 255,
+};
+/// Nopeus, jolla tekoälyn ohjaamat kappaleet liikkuvat
+const TEKOALYN_NOPEUS: Luku = luku(40.0);
+/// Suurin kiihtyvyys, jolla tekoälyn ohjaamat kappaleet muuttavat nopeuttaan
+const TEKOALYN_KIIHTYVYYS: Luku = luku(400.0);
+
 pub type RcKappale = Rc<RefCell<Kappale>>;
 
 /// Sisältää tiedon pelimaailman tilasta eli kaikkien kappaleiden tiedot
@@ -22,21 +69,52 @@ pub type RcKappale = Rc<RefCell<Kappale>>;
 pub struct Perusmaailma {
     /// Pelimaailman sisältämät kappaleet
     kappaleet: Vec<RcKappale>,
+    /// Ruudukko, josta kappaleita voidaan hakea sijainnin perusteella
+    alueindeksi: Ruudukko,
     /// Muistit vanhoista tiloista
     kappalemuisti: Vec<Kappalemuisti>,
+    /// Kuinka monta tilaa kustakin kappaleesta muistetaan
+    muistin_syvyys: usize,
+    /// Pelin aika, jolloin kappalemuistia on viimeksi päivitetty
+    muistin_aika: Duration,
+    /// Suurin etäisyys, jonka päässä kappaleiden muistetut tilat ovat niiden nykyisestä
+    /// tilasta. Piirrettävät haetaan alueindeksistä vähintään tämän verran näkyvää aluetta
+    /// laajemmalta alueelta.
+    muistin_ulottuma: Luku,
+    /// Jos jotakin, niin kappaleet piirretään sellaisina kuin ne olivat annetulla pelin hetkellä
+    piirtohetki: Option<Duration>,
+    /// Piirretäänkö kappaleiden muistetuista sijainneista jäljet
+    jaljet: bool,
     /// Jos jotakin, niin interpolointi on käytössä
     /// Kuvastaa arvoa, jolla seuraava piirtäminen tehdään
     interpoloinnin_arvo: Option<f32>,
     /// Maailmassa olevat fysiikkakappaleet
     fysiikka_kappaleet: Vec<Fysiikkakappale>,
-    /// Piirrettävät kappaleet
+    /// Piirrettävät kappaleet piirtokerroksen mukaan järjestettynä. Saman kerroksen kappaleet
+    /// ovat lisäysjärjestyksessä.
     piirrettavat_kappaleet: Vec<PiirrettavaKappale>,
-    /// Mahdollinen pelattava hahmo
-    pelihahmo: Option<Pelihahmo>,
+    /// Pelaajien ohjaamat hahmot
+    pelihahmot: Vec<Pelihahmo>,
     /// Sisältää kaikki tekoälyä käyttävät otukset
     alylliset: Vec<Alyllinen>,
     /// Poistettavat kappaleet
     poistettavat: Vec<RcKappale>,
+    /// Maailmaan uusia kappaleita luovat spawnerit
+    spawnerit: Vec<Spawneri>,
+    /// Pelin kokonaisaika, josta maailman ajan kuuluu jatkua esim. tallennuksen latauksen jälkeen
+    pelin_aika: Duration,
+    /// Käyttäjän määrittelemät kappaleisiin liitetyt lisäosat
+    lisaosat: Lisaosarekisteri,
+    /// Tagien nimet, joihin on rekisteröity myös pelin omat tagit
+    tagit: Tagirekisteri,
+    /// Onko jonkin kappaleen kuolema päättänyt pelin
+    peli_paattynyt: bool,
+    /// Rajat, joiden sisällä kappaleiden tulee pysyä
+    rajat: Option<Maailmanrajat>,
+    /// Tapa, jolla fysiikka laskee kappaleiden uudet nopeudet ja sijainnit
+    integrointi: Integrointi,
+    /// Mitkä törmäyskerrokset törmäävät toisiinsa
+    tormaysmatriisi: Tormaysmatriisi,
     /// Lista kaikista animaatioista
     pub animaatiot: Animaatiot,
     pub animaatio_kuva: Vec<ValiaikainenPiirrettavaKappale>,
@@ -47,13 +125,27 @@ impl Perusmaailma {
     pub fn new() -> Self {
         Perusmaailma {
             kappaleet: Vec::new(),
+            alueindeksi: Default::default(),
             kappalemuisti: Default::default(),
+            muistin_syvyys: MINIMI_SYVYYS,
+            muistin_aika: Duration::new(0, 0),
+            muistin_ulottuma: Luku::default(),
+            piirtohetki: None,
+            jaljet: false,
             interpoloinnin_arvo: None,
             fysiikka_kappaleet: Vec::new(),
             piirrettavat_kappaleet: Vec::new(),
             alylliset: Default::default(),
-            pelihahmo: None,
+            pelihahmot: Vec::new(),
             poistettavat: Vec::new(),
+            spawnerit: Vec::new(),
+            pelin_aika: Duration::new(0, 0),
+            lisaosat: Default::default(),
+            tagit: Default::default(),
+            peli_paattynyt: false,
+            rajat: None,
+            integrointi: Integrointi::default(),
+            tormaysmatriisi: Tormaysmatriisi::default(),
             animaatiot: Default::default(),
             animaatio_kuva: Default::default(),
         }
@@ -63,47 +155,233 @@ impl Perusmaailma {
     pub fn new_interpoloiva() -> Self {
         Perusmaailma {
             kappaleet: Vec::new(),
+            alueindeksi: Default::default(),
             kappalemuisti: Default::default(),
+            muistin_syvyys: MINIMI_SYVYYS,
+            muistin_aika: Duration::new(0, 0),
+            muistin_ulottuma: Luku::default(),
+            piirtohetki: None,
+            jaljet: false,
             interpoloinnin_arvo: Some(0.0),
             fysiikka_kappaleet: Vec::new(),
             piirrettavat_kappaleet: Vec::new(),
             alylliset: Default::default(),
-            pelihahmo: None,
+            pelihahmot: Vec::new(),
             poistettavat: Vec::new(),
+            spawnerit: Vec::new(),
+            pelin_aika: Duration::new(0, 0),
+            lisaosat: Default::default(),
+            tagit: Default::default(),
+            peli_paattynyt: false,
+            rajat: None,
+            integrointi: Integrointi::default(),
+            tormaysmatriisi: Tormaysmatriisi::default(),
             animaatiot: Default::default(),
             animaatio_kuva: Default::default(),
         }
     }
 
+    /// Poistaa maailmasta kaikki kappaleet, lisäosat, spawnerit ja animaatiot.
+    /// Interpolointi ja kappalemuistin asetukset säilyvät. Rekisteröidyt tagit säilyvät,
+    /// jotta pelin aiemmin saamat omat tagit pysyvät samoina.
+    pub fn tyhjenna(&mut self) {
+        let tagit = std::mem::take(&mut self.tagit);
+        let (syvyys, aika, jaljet) = (self.muistin_syvyys, self.muistin_aika, self.jaljet);
+        *self = match self.interpoloinnin_arvo {
+            Some(_) => Perusmaailma::new_interpoloiva(),
+            None => Perusmaailma::new(),
+        };
+        self.tagit = tagit;
+        self.muistin_syvyys = syvyys;
+        self.muistin_aika = aika;
+        self.jaljet = jaljet;
+    }
+
+    /// Antaa rekisterin, josta tagien nimet löytyvät
+    pub fn tagirekisteri(&self) -> &Tagirekisteri {
+        &self.tagit
+    }
+
+    /// Rekisteröi pelin oman tagin ja antaa sen. Jos samanniminen tagi on jo
+    /// rekisteröity, niin annetaan se.
+    /// # Arguments
+    /// * `nimi` - Tagin nimi, jota käytetään esim. tasotiedostoissa
+    pub fn rekisteroi_tagi(&mut self, nimi: &str) -> Result<Tagi, String> {
+        self.tagit.rekisteroi(nimi)
+    }
+
+    /// Antaa pelin kokonaisajan, josta pelin kuuluu jatkua
+    pub fn anna_pelin_aika(&self) -> Duration {
+        self.pelin_aika
+    }
+
+    /// Asettaa pelin kokonaisajan, josta pelin kuuluu jatkua
+    /// # Arguments
+    /// * `pelin_aika` - Pelin alusta kulunut aika
+    pub fn aseta_pelin_aika(&mut self, pelin_aika: Duration) {
+        self.pelin_aika = pelin_aika;
+        self.muistin_aika = pelin_aika;
+    }
+
+    /// Antaa kaikki maailman kappaleet lisäysjärjestyksessä
+    pub fn kappaleet(&self) -> &[RcKappale] {
+        &self.kappaleet
+    }
+
     /// Lisää annetun kappaleen maailmaan ja antaa viiteen siihen
     /// # Arguments
     /// * `kappale` - Lisättävä kappale
     pub fn lisaa_kappale(&mut self, kappale: Kappale) -> RcKappale {
         let r_kappale = Rc::new(RefCell::new(kappale));
         self.kappaleet.push(Rc::clone(&r_kappale));
+        self.alueindeksi.lisaa(&r_kappale);
         // Luo kappaleelle muistin, jos interpolointi on käytössä
-        if self.interpoloinnin_arvo.is_some() {
-            self.kappalemuisti
-                .push(Kappalemuisti::new(Rc::clone(&r_kappale)));
+        if self.muistaa_tilat() {
+            self.kappalemuisti.push(Kappalemuisti::new(
+                Rc::clone(&r_kappale),
+                self.muistin_aika,
+                self.muistin_syvyys,
+            ));
         }
         r_kappale
     }
 
+    /// Päivittää alueindeksiin kappaleiden uudet sijainnit. Maailma pitää alueindeksin ajan
+    /// tasalla, kun kappaleita lisätään, poistetaan tai liikutetaan fysiikalla, rajoilla tai
+    /// liitoksilla. Tulee kutsua, jos kappaleita liikutetaan suoraan maailman ohi.
+    pub fn paivita_alueindeksi(&mut self) {
+        self.alueindeksi.paivita_kaikki(&self.kappaleet);
+    }
+
+    /// Antaa alueindeksin, josta kappaleita voidaan hakea sijainnin perusteella
+    pub fn alueindeksi(&self) -> &Ruudukko {
+        &self.alueindeksi
+    }
+
+    /// Antaa kappaleet, joiden rajaus leikkaa annettua aluetta
+    /// # Arguments
+    /// * `alue` - Alue, jolta kappaleita haetaan
+    pub fn kappaleet_alueella(&self, alue: &Rajaus) -> Vec<RcKappale> {
+        self.alueindeksi.alueella(alue)
+    }
+
+    /// Antaa kappaleet, joiden rajaus on annetun etäisyyden päässä annetusta pisteestä
+    /// # Arguments
+    /// * `keskipiste` - Piste, jonka ympäriltä kappaleita haetaan
+    /// * `sade` - Etäisyys, jonka päästä kappaleita haetaan
+    pub fn kappaleet_sateella(&self, keskipiste: Vektori, sade: Luku) -> Vec<RcKappale> {
+        self.alueindeksi.sateella(keskipiste, sade)
+    }
+
+    /// Aloittaa kyselyn, jolla maailman kappaleita voidaan hakea esim. tagin, sijainnin
+    /// tai lisäosien perusteella
+    pub fn kysely(&self) -> Kysely<'_> {
+        Kysely::new(&self.kappaleet, &self.alueindeksi, &self.lisaosat)
+    }
+
     pub fn aseta_interpolaatio_arvo(&mut self, arvo: f32) {
         self.interpoloinnin_arvo = Some(arvo);
     }
 
-    pub fn paivita_kappalemuistia(&mut self) {
+    /// Tallentaa kaikkien kappaleiden nykyisen tilan niiden muistiin
+    /// # Arguments
+    /// * `aika` - Pelin aika, jota kappaleiden nykyinen tila kuvaa
+    pub fn paivita_kappalemuistia(&mut self, aika: Duration) {
+        self.muistin_aika = aika;
         for kappale in &mut self.kappalemuisti {
-            kappale.paivita_muistia();
+            kappale.paivita_muistia(aika);
         }
+        self.muistin_ulottuma = self
+            .kappalemuisti
+            .iter()
+            .map(|x| x.ulottuma())
+            .fold(Luku::default(), |a, b| a.max(b));
+    }
+
+    /// Asettaa, kuinka monta tilaa kustakin kappaleesta muistetaan. Syvempi muisti
+    /// mahdollistaa esim. piirtoviiveen ja menneiden sijaintien tarkistamisen.
+    /// # Arguments
+    /// * `syvyys` - Muistettavien tilojen määrä. Vähintään kaksi.
+    pub fn aseta_muistin_syvyys(&mut self, syvyys: usize) {
+        self.muistin_syvyys = syvyys.max(MINIMI_SYVYYS);
+        for muisti in &mut self.kappalemuisti {
+            muisti.aseta_syvyys(syvyys);
+        }
+    }
+
+    /// Asettaa pelin hetken, jolta kappaleet piirretään. Jos hetkeä ei ole, niin kappaleet
+    /// interpoloidaan kahden uusimman tilan välillä interpolaatioarvon mukaan.
+    /// # Arguments
+    /// * `hetki` - Pelin aika, jolta kappaleet piirretään
+    pub fn aseta_piirtohetki(&mut self, hetki: Option<Duration>) {
+        self.piirtohetki = hetki;
+    }
+
+    /// Asettaa, piirretäänkö kappaleiden muistetuista sijainneista jäljet
+    /// # Arguments
+    /// * `jaljet` - Piirretäänkö jäljet
+    pub fn aseta_jalkien_piirto(&mut self, jaljet: bool) {
+        self.jaljet = jaljet;
+    }
+
+    /// Muistaako maailma kappaleiden aiempia tiloja. Vain `new_interpoloiva`-funktiolla luotu
+    /// maailma muistaa, ja tilat tallennetaan `paivita_kappalemuistia`-funktiolla.
+    pub fn muistaa_tilat(&self) -> bool {
+        self.interpoloinnin_arvo.is_some()
+    }
+
+    /// Antaa kappaleen tilan annetulla pelin hetkellä kappaleen muistin perusteella.
+    /// Voidaan käyttää esim. osumien tarkistamiseen viiveen verran menneisyydessä.
+    /// Antaa virheen, jos maailma ei muista tiloja (ks. `muistaa_tilat`), kappale ei ole
+    /// maailmassa tai muisti ei ulotu niin kauas.
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka tila halutaan
+    /// * `aika` - Pelin aika, jolta tila halutaan
+    pub fn kappale_hetkella(&self, kappale: &RcKappale, aika: Duration) -> Result<Kappale, String> {
+        if !self.muistaa_tilat() {
+            return Err("Maailma ei muista kappaleiden tiloja".to_string());
+        }
+        let muisti = self
+            .anna_kappalemuisti(kappale)
+            .ok_or_else(|| "Kappale ei ole maailmassa".to_string())?;
+        muisti.tila_hetkella(aika).ok_or_else(|| {
+            format!(
+                "Kappaleen muisti alkaa hetkestä {:?}, joten hetken {:?} tilaa ei tiedetä",
+                muisti.vanhin().aika,
+                aika
+            )
+        })
     }
 
     /// Lisää annetulle kappaleelle piirrettävyys ominaisuuden
     /// # Arguments
     /// * `kappale` - Lisättävä piirrettava kappale
     pub fn lisaa_piirrettava_kappale(&mut self, kappale: PiirrettavaKappale) {
-        self.piirrettavat_kappaleet.push(kappale);
+        // Lisätään kerroksensa viimeiseksi, jolloin lista pysyy järjestettynä
+        let indeksi = self
+            .piirrettavat_kappaleet
+            .partition_point(|x| x.anna_kerros() <= kappale.anna_kerros());
+        self.piirrettavat_kappaleet.insert(indeksi, kappale);
+    }
+
+    /// Siirtää kappaleen annetulle piirtokerrokselle. Kappale piirretään uuden kerroksensa
+    /// kappaleista viimeisenä.
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka kerros vaihdetaan
+    /// * `kerros` - Uusi piirtokerros
+    pub fn aseta_piirtokerros(&mut self, kappale: &RcKappale, kerros: Piirtokerros) {
+        if let Some(indeksi) = self
+            .piirrettavat_kappaleet
+            .iter()
+            .position(|x| std::ptr::eq(x.anna_kappale().as_ptr(), kappale.as_ptr()))
+        {
+            let vanha = self.piirrettavat_kappaleet.remove(indeksi);
+            self.lisaa_piirrettava_kappale(PiirrettavaKappale::new_kerroksella(
+                vanha.anna_kappale(),
+                vanha.anna_piirtotapa().clone(),
+                kerros,
+            ));
+        }
     }
 
     /// Lisää annettavalle kappaleelle fysiikan
@@ -113,11 +391,14 @@ impl Perusmaailma {
         self.fysiikka_kappaleet.push(kappale);
     }
 
-    /// Tekee annetusta kappaleesta pelihahmon
+    /// Tekee annetusta kappaleesta pelihahmon. Jos kappale on jo pelihahmo, niin vanha korvataan.
+    /// # Arguments
+    /// * `pelihahmo` - Lisättävä pelihahmo
     pub fn lisaa_pelihahmo(&mut self, pelihahmo: Pelihahmo) {
-        if self.pelihahmo.is_none() {
-            self.pelihahmo = Some(pelihahmo);
-        }
+        let kappale = pelihahmo.anna_kappale();
+        self.pelihahmot
+            .retain(|x| !std::ptr::eq(x.anna_kappale().as_ptr(), kappale.as_ptr()));
+        self.pelihahmot.push(pelihahmo);
     }
 
     /// Lisää tekoälyn maailmaan
@@ -127,9 +408,340 @@ impl Perusmaailma {
         self.alylliset.push(alyllinen);
     }
 
+    /// Liittää kappaleeseen minkä tahansa lisäosan. Jos kappaleella on jo saman tyyppinen
+    /// lisäosa, niin se korvataan ja vanha palautetaan.
+    /// # Arguments
+    /// * `lisaosa` - Lisättävä lisäosa
+    pub fn lisaa_lisaosa<T: Lisaosa + 'static>(&mut self, lisaosa: T) -> Option<T> {
+        self.lisaosat.lisaa(lisaosa)
+    }
+
+    /// Antaa annettuun kappaleeseen liitetyn lisäosan, jos sellainen on
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosaa pyydetään
+    pub fn anna_lisaosa<T: Lisaosa + 'static>(&self, kappale: &RcKappale) -> Option<&T> {
+        self.lisaosat.anna(kappale)
+    }
+
+    /// Antaa annettuun kappaleeseen liitetyn lisäosan, jos sellainen on
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosaa pyydetään
+    pub fn anna_lisaosa_mut<T: Lisaosa + 'static>(&mut self, kappale: &RcKappale) -> Option<&mut T> {
+        self.lisaosat.anna_mut(kappale)
+    }
+
+    /// Antaa kaikki maailmassa olevat annetun tyyppiset lisäosat
+    pub fn lisaosat<T: Lisaosa + 'static>(&self) -> &[T] {
+        self.lisaosat.kaikki()
+    }
+
+    /// Antaa kaikki maailmassa olevat annetun tyyppiset lisäosat
+    pub fn lisaosat_mut<T: Lisaosa + 'static>(&mut self) -> &mut [T] {
+        self.lisaosat.kaikki_mut()
+    }
+
+    /// Poistaa annetulta kappaleelta annetun tyyppisen lisäosan ja palauttaa sen
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka lisäosa poistetaan
+    pub fn poista_lisaosa<T: Lisaosa + 'static>(&mut self, kappale: &RcKappale) -> Option<T> {
+        self.lisaosat.poista(kappale)
+    }
+
+    /// Vahingoittaa kappaletta, jos sillä on elinvoima eikä se ole suojassa. Jos kappaleen
+    /// elämät loppuvat, niin tapahtuvat sen kuoleman seuraukset. Palauttaa, otettiinko vahinkoa.
+    /// # Arguments
+    /// * `kappale` - Vahingoitettava kappale
+    /// * `vahinko` - Vahinko, joka kappaleelle tehdään
+    /// * `aika` - Pelin aika, jolloin vahinko tapahtuu
+    pub fn vahingoita(&mut self, kappale: &RcKappale, vahinko: &Vahinko, aika: Duration) -> bool {
+        if !vahinko.vahingoittaako(kappale.borrow().tagit) {
+            return false;
+        }
+        let elinvoima = match self.lisaosat.anna_mut::<Elinvoima>(kappale) {
+            Some(elinvoima) => elinvoima,
+            None => return false,
+        };
+        if !elinvoima.ota_vahinkoa(vahinko.maara, aika) {
+            return false;
+        }
+        if elinvoima.onko_kuollut() {
+            let kuolemat = elinvoima.kestavyys.kuolemat.clone();
+            self.kuole(kappale, &kuolemat, aika);
+        }
+        true
+    }
+
+    /// Tekee kappaleen kuoleman seuraukset
+    /// # Arguments
+    /// * `kappale` - Kuollut kappale
+    /// * `kuolemat` - Kuoleman seuraukset
+    /// * `aika` - Pelin aika, jolloin kappale kuoli
+    pub fn kuole(&mut self, kappale: &RcKappale, kuolemat: &[Kuolema], aika: Duration) {
+        let keskipiste = kappale.borrow().keskipisteen_sijainti();
+        for kuolema in kuolemat {
+            match kuolema {
+                Kuolema::Poisto => self.lisaa_poistettava(Rc::clone(kappale)),
+                Kuolema::Animaatio => {
+                    self.animaatiot.lisaa_animaatio(Kuolevainen::new(
+                        Box::new(KatoamisAnimaatio::new(
+                            keskipiste,
+                            aika,
+                            kappale.borrow().muoto.koko().0.liukuluku(),
+                            1.0,
+                            KUOLEMAN_ANIMAATION_KESTO,
+                            KUOLEMAN_ANIMAATION_VARI,
+                        )),
+                        aika + KUOLEMAN_ANIMAATION_KESTO,
+                    ));
+                }
+                Kuolema::Pudotus {
+                    kappale: pudotus,
+                    piirtotapa,
+                } => {
+                    let mut pudotus = *pudotus;
+                    pudotus.aseta_keskipisteen_sijainti(keskipiste);
+                    let r_kappale = self.lisaa_kappale(pudotus);
+                    self.lisaa_piirrettava_kappale(PiirrettavaKappale::new(
+                        r_kappale,
+                        piirtotapa.clone(),
+                    ));
+                }
+                Kuolema::PelinLoppu => self.peli_paattynyt = true,
+            }
+        }
+    }
+
+    /// Merkitsee poistettaviksi kappaleet, joiden elinaika on loppunut tai jotka ovat
+    /// kulkeneet kantamansa verran. Tulee kutsua kappaleiden liikuttamisen jälkeen.
+    /// # Arguments
+    /// * `aika` - Pelin nykyinen aika
+    pub fn paivita_elinajat(&mut self, aika: Duration) {
+        let mut kuolleet = Vec::new();
+        for elinaika in self.lisaosat.kaikki_mut::<Elinaika>() {
+            if elinaika.paivita(aika) {
+                kuolleet.push(elinaika.anna_kappale());
+            }
+        }
+        for kappale in kuolleet {
+            self.lisaa_poistettava(kappale);
+        }
+    }
+
+    /// Asettaa maailman rajat. Jos ei mitään, niin kappaleet voivat liikkua vapaasti.
+    /// # Arguments
+    /// * `rajat` - Rajat, joiden sisällä kappaleiden tulee pysyä
+    pub fn aseta_rajat(&mut self, rajat: Option<Maailmanrajat>) {
+        self.rajat = rajat;
+    }
+
+    /// Antaa maailman rajat, jos ne on asetettu
+    pub fn rajat(&self) -> Option<&Maailmanrajat> {
+        self.rajat.as_ref()
+    }
+
+    /// Asettaa tavan, jolla fysiikka laskee kappaleiden uudet nopeudet ja sijainnit
+    /// # Arguments
+    /// * `integrointi` - Käytettävä integrointitapa
+    pub fn aseta_integrointi(&mut self, integrointi: Integrointi) {
+        self.integrointi = integrointi;
+    }
+
+    /// Antaa tavan, jolla fysiikka laskee kappaleiden uudet nopeudet ja sijainnit
+    pub fn integrointi(&self) -> Integrointi {
+        self.integrointi
+    }
+
+    /// Antaa törmäysmatriisin, joka kertoo, mitkä törmäyskerrokset törmäävät toisiinsa
+    pub fn tormaysmatriisi(&self) -> &Tormaysmatriisi {
+        &self.tormaysmatriisi
+    }
+
+    /// Antaa törmäysmatriisin muutettavaksi
+    pub fn tormaysmatriisi_mut(&mut self) -> &mut Tormaysmatriisi {
+        &mut self.tormaysmatriisi
+    }
+
+    /// Toteuttaa rajan ylittäneille fysiikkakappaleille niiden rajatoiminnan. Kappaleen oma
+    /// rajakäyttäytyminen ohittaa rajojen oletustoiminnan. Liitetyt kappaleet seuraavat
+    /// vanhempaansa, joten niihin rajoja ei sovelleta. Tulee kutsua kappaleiden liikuttamisen
+    /// jälkeen ja ennen liitosten päivittämistä.
+    pub fn sovella_rajoja(&mut self) {
+        let rajat = match self.rajat {
+            Some(rajat) => rajat,
+            None => return,
+        };
+        let mut kierretyt = Vec::new();
+        for fysiikka in &mut self.fysiikka_kappaleet {
+            let kappale = fysiikka.anna_kappale();
+            if self.lisaosat.anna::<Liitos>(&kappale).is_some() {
+                continue;
+            }
+            let toiminta = match self.lisaosat.anna::<Rajakayttaytyminen>(&kappale) {
+                Some(kayttaytyminen) => kayttaytyminen.toiminta,
+                None => match rajat.oletus {
+                    Some(toiminta) => toiminta,
+                    None => continue,
+                },
+            };
+            let mut nopeus = fysiikka.anna_nopeus();
+            let ylitys = rajat.sovella(toiminta, &mut kappale.borrow_mut(), &mut nopeus);
+            match ylitys {
+                Rajanylitys::Sisalla => (),
+                Rajanylitys::Palautettu => {
+                    fysiikka.aseta_nopeus(nopeus);
+                    self.alueindeksi.paivita(&kappale);
+                }
+                Rajanylitys::Kierretty(siirtyma) => {
+                    self.alueindeksi.paivita(&kappale);
+                    kierretyt.push((kappale, siirtyma));
+                }
+                Rajanylitys::Poistettava => self.poistettavat.push(kappale),
+            }
+        }
+        // Kierretyn kappaleen mukana siirtyvät myös siihen liitetyt kappaleet
+        let mut i = 0;
+        while i < kierretyt.len() {
+            let (vanhempi, siirtyma) = (Rc::clone(&kierretyt[i].0), kierretyt[i].1);
+            for liitos in self.lisaosat.kaikki::<Liitos>() {
+                if std::ptr::eq(liitos.anna_vanhempi().as_ptr(), vanhempi.as_ptr()) {
+                    kierretyt.push((liitos.anna_kappale(), siirtyma));
+                }
+            }
+            i += 1;
+        }
+        // Siirretään myös muistetut tilat, jotta kierretty kappale ei näytä kulkevan
+        // maailman poikki interpoloitaessa, eikä kierto kuluta kappaleen kantamaa
+        for (kappale, siirtyma) in kierretyt {
+            if let Some(elinaika) = self.lisaosat.anna_mut::<Elinaika>(&kappale) {
+                elinaika.siirra(siirtyma);
+            }
+            if let Some(muisti) = self
+                .kappalemuisti
+                .iter_mut()
+                .find(|x| std::ptr::eq(x.anna_kappale().as_ptr(), kappale.as_ptr()))
+            {
+                muisti.siirra(siirtyma);
+            }
+        }
+    }
+
+    /// Onko jonkin kappaleen kuolema päättänyt pelin
+    pub fn onko_peli_paattynyt(&self) -> bool {
+        self.peli_paattynyt
+    }
+
+    /// Liittää kappaleen toiseen kappaleeseen, jolloin liitetty kappale seuraa vanhempansa
+    /// sijaintia ja asentoa ja poistetaan vanhempansa mukana. Lapsi siirretään heti paikalleen.
+    /// Antaa virheen, jos liitos muodostaisi kehän.
+    /// # Arguments
+    /// * `lapsi` - Liitettävä kappale
+    /// * `vanhempi` - Kappale, johon liitetään
+    /// * `siirtyma` - Lapsen keskipisteen sijainti vanhemman keskipisteestä, kun vanhempaa ei ole kierretty
+    /// * `kulma` - Lapsen asento suhteessa vanhemman asentoon radiaaneina
+    pub fn liita(
+        &mut self,
+        lapsi: &RcKappale,
+        vanhempi: &RcKappale,
+        siirtyma: Vektori,
+        kulma: Luku,
+    ) -> Result<(), String> {
+        let mut esivanhempi = Some(Rc::clone(vanhempi));
+        while let Some(kappale) = esivanhempi {
+            if std::ptr::eq(kappale.as_ptr(), lapsi.as_ptr()) {
+                return Err("Kappaletta ei voi liittää itseensä tai omaan lapseensa".to_string());
+            }
+            esivanhempi = self.anna_vanhempi(&kappale);
+        }
+        let liitos = Liitos::new(Rc::clone(lapsi), Rc::clone(vanhempi), siirtyma, kulma);
+        liitos.sovita(&vanhempi.borrow(), &mut lapsi.borrow_mut());
+        self.alueindeksi.paivita(lapsi);
+        self.lisaosat.lisaa(liitos);
+        Ok(())
+    }
+
+    /// Antaa kappaleen, johon annettu kappale on liitetty, jos sellainen on
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka vanhempaa pyydetään
+    pub fn anna_vanhempi(&self, kappale: &RcKappale) -> Option<RcKappale> {
+        self.lisaosat
+            .anna::<Liitos>(kappale)
+            .map(|x| x.anna_vanhempi())
+    }
+
+    /// Siirtää kaikki liitetyt kappaleet vanhempiensa mukaisiin sijainteihin ja asentoihin.
+    /// Vanhemmat päivitetään ennen lapsiaan, joten myös usean tason liitokset toimivat.
+    pub fn paivita_liitokset(&mut self) {
+        let liitokset = self.lisaosat.kaikki::<Liitos>();
+        let mut jarjestys: Vec<(usize, &Liitos)> = liitokset
+            .iter()
+            .map(|x| (self.liitoksen_syvyys(&x.anna_kappale()), x))
+            .collect();
+        jarjestys.sort_by_key(|x| x.0);
+        for (_, liitos) in jarjestys {
+            let (vanhempi, lapsi) = (liitos.anna_vanhempi(), liitos.anna_kappale());
+            liitos.sovita(&vanhempi.borrow(), &mut lapsi.borrow_mut());
+            self.alueindeksi.paivita(&lapsi);
+        }
+    }
+
+    /// Antaa fysiikkakappaleiden liitosryhmät samassa järjestyksessä kuin fysiikkakappaleet.
+    /// Samaan kappaleeseen liitetyt kappaleet kuuluvat samaan ryhmään. Antaa tyhjän listan,
+    /// jos maailmassa ei ole liitoksia.
+    pub fn fysiikallisten_liitosryhmat(&self) -> Vec<usize> {
+        if self.lisaosat.kaikki::<Liitos>().is_empty() {
+            return Vec::new();
+        }
+        self.fysiikka_kappaleet
+            .iter()
+            .map(|x| {
+                let mut juuri = x.anna_kappale();
+                while let Some(vanhempi) = self.anna_vanhempi(&juuri) {
+                    juuri = vanhempi;
+                }
+                juuri.as_ptr() as usize
+            })
+            .collect()
+    }
+
+    /// Antaa kuinka monen vanhemman päässä kappale on liitoksettomasta kappaleesta
+    fn liitoksen_syvyys(&self, kappale: &RcKappale) -> usize {
+        let mut syvyys = 0;
+        let mut esivanhempi = self.anna_vanhempi(kappale);
+        while let Some(vanhempi) = esivanhempi {
+            syvyys += 1;
+            esivanhempi = self.anna_vanhempi(&vanhempi);
+        }
+        syvyys
+    }
+
+    /// Lisää maailmaan spawnerin
+    /// # Arguments
+    /// * `spawneri` - Lisättävä spawneri
+    pub fn lisaa_spawneri(&mut self, spawneri: Spawneri) {
+        self.spawnerit.push(spawneri);
+    }
+
+    /// Antaa maailman spawnerit
+    pub fn spawnerit(&self) -> &[Spawneri] {
+        &self.spawnerit
+    }
+
+    /// Päivittää kaikkia maailman spawnereita annetulla päivitysajalla
+    /// # Arguments
+    /// * `paivitysaika` - Päivityksessä käytettävä aika
+    pub fn paivita_spawnereita(&mut self, paivitysaika: &Paivitysaika) {
+        // Spawnerit otetaan hetkeksi pois, jotta ne voivat lisätä kappaleita maailmaan
+        let mut spawnerit = std::mem::take(&mut self.spawnerit);
+        for spawneri in &mut spawnerit {
+            spawneri.paivita_spawneria(self, paivitysaika);
+        }
+        spawnerit.append(&mut self.spawnerit);
+        self.spawnerit = spawnerit;
+    }
+
     /// Onko maailmassa pelihahmo olemassa
     pub fn onko_pelihahmo(&self) -> bool {
-        self.pelihahmo.is_some()
+        !self.pelihahmot.is_empty()
     }
 
     /// Antaa fysiikkalliset kappaleet
@@ -137,17 +749,50 @@ impl Perusmaailma {
         &mut self.fysiikka_kappaleet
     }
 
+    /// Laskee fysiikkakappaleille uudet sijainnit annetulla fysiikalla. Mahdollisesti
+    /// törmäävät parit haetaan maailman alueindeksistä, joka on päivityksen jälkeen ajan
+    /// tasalla kappaleiden uusista sijainneista.
+    /// # Arguments
+    /// * `fysiikka` - Fysiikka, jolla sijainnit lasketaan ja johon törmäykset kirjataan
+    /// * `paivitysaika` - Päivityksessä käytettävä aika
+    pub fn laske_fysiikka(&mut self, fysiikka: &mut Fysiikka, paivitysaika: &Duration) {
+        let fysiikalliset: Vec<RcKappale> = self
+            .fysiikka_kappaleet
+            .iter()
+            .map(|x| x.anna_kappale())
+            .collect();
+        let mut laajavaihe =
+            Alueindeksivaihe::new(&mut self.alueindeksi, &self.kappaleet, &fysiikalliset);
+        fysiikka.laske_uudet_sijainnit_laajavaiheella(
+            &mut self.fysiikka_kappaleet,
+            paivitysaika,
+            &mut laajavaihe,
+        );
+        // Törmänneiden erottaminen siirtää kappaleita laajan vaiheen jälkeen
+        self.alueindeksi.paivita_kaikki(&self.kappaleet);
+    }
+
     /// Antaa kaikki ai-hahmot
     pub fn alylliset(&mut self) -> &mut [Alyllinen] {
         &mut self.alylliset
     }
 
+    /// Antaa annettuun kappaleeseen liitetyn tekoälyn, jos sellainen on
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka tekoälyä pyydetään
+    pub fn anna_aly(&self, kappale: &RcKappale) -> Option<&Alyllinen> {
+        self.alylliset
+            .iter()
+            .find(|x| std::ptr::eq(x.anna_kappale().as_ptr(), kappale.as_ptr()))
+    }
+
     /// Toteuttaa kaikkien tekoälyjen toiminnot
     pub fn laske_tekoalyt(&mut self) {
         let tmaailma = TekoalyMaailma::new(
-            &mut self.kappaleet,
-            &mut self.pelihahmo,
-            &mut self.fysiikka_kappaleet,
+            &self.kappaleet,
+            &self.alueindeksi,
+            &self.lisaosat,
+            &mut self.pelihahmot,
         );
         let mut toiminnot = Vec::new();
         for aly in &self.alylliset {
@@ -161,7 +806,8 @@ impl Perusmaailma {
                     if let Some(f_kappale) =
                         self.anna_fysiikka_mut(&self.alylliset[indeksi].anna_kappale())
                     {
-                        f_kappale.aseta_nopeus(*suunta * 40.0);
+                        let tavoite = *suunta * TEKOALYN_NOPEUS;
+                        f_kappale.aseta_tavoitenopeus(tavoite, TEKOALYN_KIIHTYVYYS);
                     }
                 }
             }
@@ -175,9 +821,16 @@ impl Perusmaailma {
         self.poistettavat.push(poistettava);
     }
 
-    /// Poistaa poistettaviksi merkityt kappaleet kappaleisiin viittaavien ominaisuuksien kanssa
+    /// Poistaa poistettaviksi merkityt kappaleet kappaleisiin viittaavien ominaisuuksien kanssa.
+    /// Kappaleeseen liitetyt kappaleet poistetaan myös.
     pub fn poista_poistettavat(&mut self) {
         while let Some(poistettava) = self.poistettavat.pop() {
+            // Merkitään poistettavaksi myös kappaleeseen liitetyt kappaleet
+            for liitos in self.lisaosat.kaikki::<Liitos>() {
+                if std::ptr::eq(liitos.anna_vanhempi().as_ptr(), poistettava.as_ptr()) {
+                    self.poistettavat.push(liitos.anna_kappale());
+                }
+            }
             // Poistaa kappaleen fysiikkakappaleista
             self.fysiikka_kappaleet
                 .retain(|x| !std::ptr::eq(x.anna_kappale().as_ptr(), poistettava.as_ptr()));
@@ -190,74 +843,141 @@ impl Perusmaailma {
             // poistaa muistin
             self.kappalemuisti
                 .retain(|x| !std::ptr::eq(x.anna_kappale().as_ptr(), poistettava.as_ptr()));
+            // Poistaa käyttäjän lisäämät lisäosat
+            self.lisaosat.poista_kappaleen_lisaosat(&poistettava);
+            // Poistaa kappaleen alueindeksistä
+            self.alueindeksi.poista(&poistettava);
             // Poistaa kappaleen kappaleista
             self.kappaleet
                 .retain(|x| !std::ptr::eq(x.as_ptr(), poistettava.as_ptr()));
-            // Poistaa kappaleen pelihahmosta
-            if let Some(hahmo) = &mut self.pelihahmo {
-                if std::ptr::eq(hahmo.anna_kappale().as_ptr(), poistettava.as_ptr()) {
-                    self.pelihahmo = None
-                }
-            }
+            // Poistaa kappaleen pelihahmoista
+            self.pelihahmot
+                .retain(|x| !std::ptr::eq(x.anna_kappale().as_ptr(), poistettava.as_ptr()));
         }
     }
 
-    /// Antaa piirrettävät kappaleet
-    pub fn anna_piirrettavat(&self, lista: &mut Vec<ValiaikainenPiirrettavaKappale>) {
-        if let Some(interpolaatio_arvo) = self.interpoloinnin_arvo {
-            for kappale in &self.kappalemuisti {
-                let (vasen, oikea) = kappale.anna_versiot();
-
-                let interpoloitu_sijainti = lineaarinen_interpolaatio(
-                    0.0,
-                    vasen.kulman_sijainti(),
-                    1.0,
-                    oikea.kulman_sijainti(),
-                    interpolaatio_arvo,
-                );
-                let kappale = ValiaikainenPiirrettavaKappale::new(
-                    Kappale::new_kulmalla(
-                        oikea.muoto,
-                        interpoloitu_sijainti.x,
-                        interpoloitu_sijainti.y,
-                        vasen.tagi,
-                    ),
-                    (self
-                        .anna_piirrettavyys(&kappale.anna_kappale())
-                        .unwrap()
-                        .anna_piirtotapa())
-                    .clone(),
+    /// Antaa piirrettävät kappaleet ja animaatiot piirtokerroksen mukaan järjestettynä.
+    /// Kappaleet haetaan alueindeksistä näkyvän alueen ympäriltä niin laajalta, että mukana
+    /// ovat kaikki kappaleet, joiden interpoloitu tila tai jäljet voivat näkyä alueella.
+    /// # Arguments
+    /// * `lista` - Lista, johon piirrettävät lisätään
+    /// * `alue` - Pelimaailman alue, joka näkyy kamerassa
+    pub fn anna_piirrettavat(
+        &self,
+        lista: &mut Vec<ValiaikainenPiirrettavaKappale>,
+        alue: &Rajaus,
+    ) {
+        if let Some(arvo) = self.interpoloinnin_arvo {
+            // Ekstrapoloitaessa kappale voi olla uusinta tilaa kauempana kuin muistetut tilat
+            let kerroin = match self.piirtohetki {
+                Some(_) => 1.0,
+                None => arvo.max(1.0),
+            };
+            let marginaali = self.muistin_ulottuma * kerroin;
+            let marginaali = Vektori::new(marginaali, marginaali);
+            let haettava = Rajaus::new(alue.vasen_yla - marginaali, alue.oikea_ala + marginaali);
+            let nakyvat: HashSet<*const Kappale> = self
+                .alueindeksi
+                .alueella(&haettava)
+                .iter()
+                .map(|x| x.as_ptr() as *const Kappale)
+                .collect();
+            // Käydään läpi piirrettävät, jotta järjestys säilyy. Vain kappaleet, joilla on
+            // muisti, piirretään.
+            for piirrettavyys in &self.piirrettavat_kappaleet {
+                let rc_kappale = piirrettavyys.anna_kappale();
+                if !nakyvat.contains(&(rc_kappale.as_ptr() as *const Kappale)) {
+                    continue;
+                }
+                let kappale = match self.anna_kappalemuisti(&rc_kappale) {
+                    Some(kappale) => kappale,
+                    None => continue,
+                };
+                if self.jaljet {
+                    lisaa_jalki(lista, kappale, piirrettavyys);
+                }
+                let kappale = ValiaikainenPiirrettavaKappale::new_kerroksella(
+                    self.piirrettava_tila(kappale),
+                    piirrettavyys.anna_piirtotapa().clone(),
+                    piirrettavyys.anna_kerros(),
                 );
 
                 lista.push(kappale);
             }
         }
+        lista.extend(self.animaatio_kuva.iter().cloned());
+    }
+
+    /// Antaa kappaleen piirrettävän tilan. Tila interpoloidaan piirtohetkelle, jos se on
+    /// asetettu, ja muuten interpolaatioarvon mukaan kahden uusimman tilan välille.
+    /// Liitetyt kappaleet asetetaan interpoloidun vanhemman mukaan, jotta ne pysyvät
+    /// kiinni vanhemmassaan myös kiertyessään.
+    /// # Arguments
+    /// * `muisti` - Kappaleen muisti
+    fn piirrettava_tila(&self, muisti: &Kappalemuisti) -> Kappale {
+        let mut kappale = match self.piirtohetki {
+            Some(hetki) => muisti
+                .tila_hetkella(hetki)
+                .unwrap_or(muisti.vanhin().kappale),
+            None => muisti.interpoloi(self.interpoloinnin_arvo.unwrap_or(1.0)),
+        };
+        if let Some(liitos) = self.lisaosat.anna::<Liitos>(&muisti.anna_kappale()) {
+            if let Some(vanhemman_muisti) = self.anna_kappalemuisti(&liitos.anna_vanhempi()) {
+                let vanhempi = self.piirrettava_tila(vanhemman_muisti);
+                liitos.sovita(&vanhempi, &mut kappale);
+            }
+        }
+        kappale
     }
 }
 
 pub trait Pelihahmollinen {
-    /// Antaa pelihahmon, jos sellainen on luotu
-    fn anna_pelihahmo_mut(&mut self) -> Option<&mut Pelihahmo>;
+    /// Antaa kaikki pelihahmot lisäysjärjestyksessä
+    fn pelihahmot(&self) -> &[Pelihahmo];
 
-    /// Antaa pelihahmon, jos sellainen on luotu
-    fn anna_pelihahmo(&self) -> Option<&Pelihahmo>;
-}
+    /// Antaa kaikki pelihahmot lisäysjärjestyksessä
+    fn pelihahmot_mut(&mut self) -> &mut [Pelihahmo];
 
-impl Pelihahmollinen for Perusmaailma {
-    /// Antaa pelihahmon, jos sellainen on luotu
+    /// Antaa ensimmäisen pelihahmon, jos sellainen on luotu
     fn anna_pelihahmo_mut(&mut self) -> Option<&mut Pelihahmo> {
-        match &mut self.pelihahmo {
-            None => None,
-            Some(hahmo) => Some(hahmo),
-        }
+        self.pelihahmot_mut().first_mut()
     }
 
-    /// Antaa pelihahmon, jos sellainen on luotu
+    /// Antaa ensimmäisen pelihahmon, jos sellainen on luotu
     fn anna_pelihahmo(&self) -> Option<&Pelihahmo> {
-        match &self.pelihahmo {
-            None => None,
-            Some(hahmo) => Some(&hahmo),
-        }
+        self.pelihahmot().first()
+    }
+
+    /// Antaa pelihahmon, jonka kappale annettu kappale on
+    /// # Arguments
+    /// * `kappale` - Kappale, jonka pelihahmoa pyydetään
+    fn anna_kappaleen_pelihahmo(&self, kappale: &RcKappale) -> Option<&Pelihahmo> {
+        self.pelihahmot()
+            .iter()
+            .find(|x| std::ptr::eq(x.anna_kappale().as_ptr(), kappale.as_ptr()))
+    }
+
+    /// Antaa pelihahmon, jonka keskipiste on lähimpänä annettua pistettä
+    /// # Arguments
+    /// * `piste` - Piste, jota lähin pelihahmo etsitään
+    fn lahin_pelihahmo(&self, piste: Vektori) -> Option<&Pelihahmo> {
+        let etaisyys =
+            |x: &Pelihahmo| (x.anna_kappale().borrow().keskipisteen_sijainti() - piste).pituus();
+        self.pelihahmot()
+            .iter()
+            .min_by(|a, b| etaisyys(a).partial_cmp(&etaisyys(b)).unwrap_or(Ordering::Equal))
+    }
+}
+
+impl Pelihahmollinen for Perusmaailma {
+    /// Antaa kaikki pelihahmot lisäysjärjestyksessä
+    fn pelihahmot(&self) -> &[Pelihahmo] {
+        &self.pelihahmot
+    }
+
+    /// Antaa kaikki pelihahmot lisäysjärjestyksessä
+    fn pelihahmot_mut(&mut self) -> &mut [Pelihahmo] {
+        &mut self.pelihahmot
     }
 }
 
@@ -326,35 +1046,93 @@ impl LisaosienAntaja for Perusmaailma {
 impl PiirrettavaMaailma for Perusmaailma {
     /// Piirrettävät kappaleet maailmassa
     /// # Arguments
-    /// * `sijainti` - Ilmoittaa mistä päin maailmaa halutaan piirrettävät kappaleet
+    /// * `alue` - Ilmoittaa mistä päin maailmaa halutaan piirrettävät kappaleet
     fn piirrettavat<'a>(
         &'a self,
-        _sijainti: Vektori,
+        alue: &Rajaus,
     ) -> Box<Iterator<Item = &'a PiirrettavaKappale> + 'a> {
-        Box::new(self.piirrettavat_kappaleet.iter())
-    }
-
-    /// Antaa kameran sijainnin pelimaailmassa, jos maailma haluaa ehdottaa jotakin
-    fn anna_kameran_sijainti(&self) -> Option<Vektori> {
-        match self.anna_pelihahmo() {
-            None => None,
-            Some(hahmo) => match self.interpoloinnin_arvo {
-                Some(arvo) => match self.anna_kappalemuisti(&hahmo.anna_kappale()) {
-                    Some(muisti) => Some(lineaarinen_interpolaatio(
-                        0.0,
-                        muisti.anna_versiot().0.keskipisteen_sijainti(),
-                        1.0,
-                        muisti.anna_versiot().1.keskipisteen_sijainti(),
-                        arvo,
-                    )),
-                    None => Some(hahmo.anna_kappale().borrow().keskipisteen_sijainti()),
-                },
-                None => Some(hahmo.anna_kappale().borrow().keskipisteen_sijainti()),
-            },
+        let nakyvat: HashSet<*const Kappale> = self
+            .alueindeksi
+            .alueella(alue)
+            .iter()
+            .map(|x| x.as_ptr() as *const Kappale)
+            .collect();
+        // Säilytetään piirtojärjestys käymällä piirrettävät läpi järjestyksessä
+        Box::new(
+            self.piirrettavat_kappaleet
+                .iter()
+                .filter(move |x| nakyvat.contains(&(x.anna_kappale().as_ptr() as *const Kappale))),
+        )
+    }
+
+    /// Väliaikaiset kappaleet kuten animaatiot, jotka piirretään maailman kappaleiden lomaan
+    fn valiaikaiset(&self) -> &[ValiaikainenPiirrettavaKappale] {
+        &self.animaatio_kuva
+    }
+
+    /// Antaa alueen, jonka kameran tulisi näyttää, jos maailma haluaa ehdottaa jotakin.
+    /// Alue rajaa kaikkien pelihahmojen keskipisteet.
+    fn anna_kameran_alue(&self) -> Option<Rajaus> {
+        let mut sijainnit = self
+            .pelihahmot
+            .iter()
+            .map(|x| self.pelihahmon_keskipiste(x));
+        let ensimmainen = sijainnit.next()?;
+        Some(sijainnit.fold(Rajaus::new(ensimmainen, ensimmainen), |alue, x| {
+            Rajaus::new(
+                Vektori::new(alue.vasen_yla.x.min(x.x), alue.vasen_yla.y.min(x.y)),
+                Vektori::new(alue.oikea_ala.x.max(x.x), alue.oikea_ala.y.max(x.y)),
+            )
+        }))
+    }
+}
+
+impl Perusmaailma {
+    /// Antaa pelihahmon keskipisteen. Jos interpolointi on käytössä, niin keskipiste interpoloidaan.
+    /// # Arguments
+    /// * `hahmo` - Pelihahmo, jonka keskipiste annetaan
+    fn pelihahmon_keskipiste(&self, hahmo: &Pelihahmo) -> Vektori {
+        match self.anna_kappalemuisti(&hahmo.anna_kappale()) {
+            Some(muisti) => self.piirrettava_tila(muisti).keskipisteen_sijainti(),
+            None => hahmo.anna_kappale().borrow().keskipisteen_sijainti(),
         }
     }
 }
 
+/// Lisää piirrettäviin kappaleen jäljen eli pisteet kappaleen muistetuista sijainneista.
+/// Vanhemmat pisteet ovat läpinäkyvämpiä. Jälki piirretään kappaleen kerrokselle.
+/// # Arguments
+/// * `lista` - Piirrettävät, joihin jälki lisätään
+/// * `muisti` - Kappaleen muisti
+/// * `piirrettavyys` - Kappaleen piirto-ominaisuus, jonka värillä jälki piirretään
+fn lisaa_jalki(
+    lista: &mut Vec<ValiaikainenPiirrettavaKappale>,
+    muisti: &Kappalemuisti,
+    piirrettavyys: &PiirrettavaKappale,
+) {
+    let vari = match piirrettavyys.anna_piirtotapa() {
+        Piirtotapa::Yksivarinen { vari } => *vari,
+        Piirtotapa::Kuvallinen { .. } => Color::RGB(255, 255, 255),
+    };
+    let maara = muisti.tilannekuvat().count();
+    for (i, tila) in muisti.tilannekuvat().enumerate() {
+        let sijainti = tila.kappale.keskipisteen_sijainti();
+        let alpha = (255 * (i + 1) / (maara + 1)) as u8;
+        lista.push(ValiaikainenPiirrettavaKappale::new_kerroksella(
+            Kappale::new_keskipisteella(
+                Muoto::Ympyra(JALJEN_SADE),
+                sijainti.x,
+                sijainti.y,
+                Tagi::Partikkeli,
+            ),
+            Piirtotapa::Yksivarinen {
+                vari: Color::RGBA(vari.r, vari.g, vari.b, alpha),
+            },
+            piirrettavyys.anna_kerros(),
+        ));
+    }
+}
+
 /// Pystyy antamaan annetun kappaleen lisäosat pyydettäessä
 pub trait LisaosienAntaja {
     /// Antaa annettuun kappaleeseen liitetyt piirto-ominaisuudet, jos niitä on
@@ -1,37 +1,53 @@
 use std::rc::Rc;
 
+use super::luku::luku;
 use super::vektori::Vektori;
 use super::Lisaosa;
 use super::RcKappale;
 
 /// Pelissä oleva pelaajan ohjaama hahmo. Esimerkiksi kamera seuraa automaattisesti tätä.
+/// Pelihahmoja voi olla useita, jolloin kukin pelaaja ohjaa omaa hahmoaan.
 pub struct Pelihahmo {
     /// Pelihahmon käyttämä kappale
     kappale: RcKappale,
     /// Pelihahmon viimeisin suunta esim. minne katsoo, ampuu jne
-    suunta: Vektori<f32>,
+    suunta: Vektori,
+    /// Hahmoa ohjaavan pelaajan numero alkaen nollasta
+    pelaaja: usize,
 }
 
 impl Pelihahmo {
-    // Lisää annetun kappaleen pelihahmoksi.
+    // Lisää annetun kappaleen ensimmäisen pelaajan pelihahmoksi.
     pub fn new(kappale: RcKappale) -> Self {
         Pelihahmo {
             kappale: kappale,
-            suunta: Vektori::new(1.0, 0.0).yksikkovektori(),
+            suunta: Vektori::new(luku(1.0), luku(0.0)).yksikkovektori(),
+            pelaaja: 0,
         }
     }
 
+    /// Antaa hahmoa ohjaavan pelaajan numeron
+    pub fn anna_pelaaja(&self) -> usize {
+        self.pelaaja
+    }
+
+    /// Asettaa hahmoa ohjaavan pelaajan
+    /// # Arguments
+    /// * `pelaaja` - Pelaajan numero alkaen nollasta
+    pub fn aseta_pelaaja(&mut self, pelaaja: usize) {
+        self.pelaaja = pelaaja;
+    }
+
     /// Antaa pelihahmon suunnan
-    pub fn anna_suunta(&self) -> Vektori<f32> {
+    pub fn anna_suunta(&self) -> Vektori {
         self.suunta
     }
 
     /// Asettaa uuden suunnan pelihahmolle
     /// # Arguments
-    /// * `suunta` - Pelihahmon uusi suunta.
-    pub fn aseta_suunta(&mut self, suunta: Vektori<f32>) {
-        let yksikkovektori = suunta.yksikkovektori();
-        if yksikkovektori.x.is_finite() && yksikkovektori.y.is_finite() {
+    /// * `suunta` - Pelihahmon uusi suunta. Nollavektori ei muuta suuntaa.
+    pub fn aseta_suunta(&mut self, suunta: Vektori) {
+        if let Some(yksikkovektori) = suunta.normalisoi() {
             self.suunta = yksikkovektori;
         }
     }
@@ -42,3 +58,65 @@ impl Lisaosa for Pelihahmo {
         Rc::clone(&self.kappale)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maailma::kappale::{Kappale, Muoto, Tagi};
+    use crate::maailma::{Pelihahmollinen, Perusmaailma};
+    use crate::piirtaja::PiirrettavaMaailma;
+
+    fn v(x: f32, y: f32) -> Vektori {
+        Vektori::new(luku(x), luku(y))
+    }
+
+    /// Lisää maailmaan pelihahmot annettuihin keskipisteisiin
+    fn maailma_hahmoilla(keskipisteet: &[(f32, f32)]) -> Perusmaailma {
+        let mut maailma = Perusmaailma::new();
+        for (pelaaja, (x, y)) in keskipisteet.iter().enumerate() {
+            let kappale = maailma.lisaa_kappale(Kappale::new_keskipisteella(
+                Muoto::Nelio(luku(10.0), luku(10.0)),
+                luku(*x),
+                luku(*y),
+                Tagi::Pelaaja,
+            ));
+            let mut hahmo = Pelihahmo::new(kappale);
+            hahmo.aseta_pelaaja(pelaaja);
+            maailma.lisaa_pelihahmo(hahmo);
+        }
+        maailma
+    }
+
+    fn lahimman_pelaaja(maailma: &Perusmaailma, x: f32, y: f32) -> Option<usize> {
+        maailma.lahin_pelihahmo(v(x, y)).map(|x| x.anna_pelaaja())
+    }
+
+    #[test]
+    fn lahin_pelihahmo_valitaan_keskipisteen_mukaan() {
+        let maailma = maailma_hahmoilla(&[(0.0, 0.0), (100.0, 0.0), (0.0, 50.0)]);
+        assert_eq!(lahimman_pelaaja(&maailma, 60.0, 10.0), Some(1));
+        assert_eq!(lahimman_pelaaja(&maailma, -5.0, 30.0), Some(2));
+        assert_eq!(lahimman_pelaaja(&maailma, 10.0, 10.0), Some(0));
+        // Tasatilanteessa valitaan ensimmäinen
+        assert_eq!(lahimman_pelaaja(&maailma, 50.0, 0.0), Some(0));
+        assert_eq!(lahimman_pelaaja(&maailma_hahmoilla(&[]), 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn kameran_alue_rajaa_kaikki_pelihahmot() {
+        assert!(maailma_hahmoilla(&[]).anna_kameran_alue().is_none());
+
+        let alue = maailma_hahmoilla(&[(10.0, 20.0)])
+            .anna_kameran_alue()
+            .unwrap();
+        assert_eq!(
+            (alue.vasen_yla, alue.oikea_ala),
+            (v(10.0, 20.0), v(10.0, 20.0))
+        );
+
+        let maailma = maailma_hahmoilla(&[(0.0, 50.0), (100.0, -20.0), (-30.0, 10.0)]);
+        let alue = maailma.anna_kameran_alue().unwrap();
+        assert_eq!(alue.vasen_yla, v(-30.0, -20.0));
+        assert_eq!(alue.oikea_ala, v(100.0, 50.0));
+    }
+}
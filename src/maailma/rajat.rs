@@ -0,0 +1,313 @@
+//! Sisältää maailman rajat ja toiminnat, jotka tapahtuvat kappaleen ylittäessä rajan.
+//! Rajoilla voidaan estää esim. seinien läpi karanneita kappaleita katoamasta maailmasta.
+use std::rc::Rc;
+
+use super::kappale::{Kappale, Rajaus};
+use super::luku::{luku, Luku};
+use super::vektori::Vektori;
+use super::{Lisaosa, RcKappale};
+
+/// Toiminta, joka tapahtuu kappaleen ylittäessä maailman rajan
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Rajatoiminta {
+    /// Kappale pysäytetään rajalle
+    Pysaytys,
+    /// Kappale siirtyy vastakkaiselle reunalle
+    Kierto,
+    /// Kappale kimpoaa rajasta
+    Kimpoaminen,
+    /// Kappale poistetaan, kun se on kokonaan rajojen ulkopuolella
+    Tuhoutuminen,
+}
+
+/// Kertoo, mitä kappaleelle tapahtui rajoilla
+#[derive(Copy, Clone)]
+pub enum Rajanylitys {
+    /// Kappale on rajojen sisällä
+    Sisalla,
+    /// Kappale siirrettiin takaisin rajojen sisään
+    Palautettu,
+    /// Kappale siirtyi vastakkaiselle reunalle annetun siirtymän verran
+    Kierretty(Vektori),
+    /// Kappale on rajojen ulkopuolella ja se tulee poistaa
+    Poistettava,
+}
+
+/// Maailman rajat
+#[derive(Copy, Clone)]
+pub struct Maailmanrajat {
+    /// Alue, jonka sisällä kappaleiden tulee pysyä
+    pub alue: Rajaus,
+    /// Toiminta kappaleille, joille ei ole asetettu omaa toimintaa.
+    /// Jos ei mitään, niin rajat eivät vaikuta kappaleisiin ilman omaa toimintaa.
+    pub oletus: Option<Rajatoiminta>,
+}
+
+impl Maailmanrajat {
+    /// Luo rajat, jotka vaikuttavat vain kappaleisiin, joille on asetettu oma toiminta
+    /// # Arguments
+    /// * `alue` - Alue, jonka sisällä kappaleiden tulee pysyä
+    pub fn new(alue: Rajaus) -> Self {
+        Maailmanrajat { alue, oletus: None }
+    }
+
+    /// Toteuttaa annetun toiminnan kappaleelle, jos se on ylittänyt rajan. Pysäytettävän
+    /// ja kimpoavan kappaleen nopeudesta poistetaan tai käännetään rajan ylittävä osa.
+    /// # Arguments
+    /// * `toiminta` - Toiminta, joka rajan ylittäneelle kappaleelle tehdään
+    /// * `kappale` - Kappale, jonka sijaintia tarkastellaan
+    /// * `nopeus` - Kappaleen nopeus
+    pub fn sovella(
+        &self,
+        toiminta: Rajatoiminta,
+        kappale: &mut Kappale,
+        nopeus: &mut Vektori,
+    ) -> Rajanylitys {
+        let rajaus = kappale.rajaus();
+        match toiminta {
+            Rajatoiminta::Tuhoutuminen if !rajaus.leikkaa(&self.alue) => Rajanylitys::Poistettava,
+            Rajatoiminta::Tuhoutuminen => Rajanylitys::Sisalla,
+            Rajatoiminta::Kierto => {
+                let keskipiste = kappale.keskipisteen_sijainti();
+                let koko = self.alue.oikea_ala - self.alue.vasen_yla;
+                let kierretty = Vektori::new(
+                    kierra(keskipiste.x, self.alue.vasen_yla.x, koko.x),
+                    kierra(keskipiste.y, self.alue.vasen_yla.y, koko.y),
+                );
+                if kierretty.x == keskipiste.x && kierretty.y == keskipiste.y {
+                    return Rajanylitys::Sisalla;
+                }
+                kappale.aseta_keskipisteen_sijainti(kierretty);
+                Rajanylitys::Kierretty(kierretty - keskipiste)
+            }
+            Rajatoiminta::Pysaytys | Rajatoiminta::Kimpoaminen => {
+                let kimpoaa = toiminta == Rajatoiminta::Kimpoaminen;
+                let (dx, vx) = palauta(
+                    (rajaus.vasen_yla.x, rajaus.oikea_ala.x),
+                    (self.alue.vasen_yla.x, self.alue.oikea_ala.x),
+                    nopeus.x,
+                    kimpoaa,
+                );
+                let (dy, vy) = palauta(
+                    (rajaus.vasen_yla.y, rajaus.oikea_ala.y),
+                    (self.alue.vasen_yla.y, self.alue.oikea_ala.y),
+                    nopeus.y,
+                    kimpoaa,
+                );
+                if dx == 0.0 && dy == 0.0 {
+                    return Rajanylitys::Sisalla;
+                }
+                kappale.aseta_kulman_sijainti(kappale.kulman_sijainti() + Vektori::new(dx, dy));
+                *nopeus = Vektori::new(vx, vy);
+                Rajanylitys::Palautettu
+            }
+        }
+    }
+}
+
+/// Siirtää koordinaatin välille [alku, alku + pituus) jatkaen toiselta puolelta
+/// # Arguments
+/// * `arvo` - Siirrettävä koordinaatti
+/// * `alku` - Välin alku
+/// * `pituus` - Välin pituus
+fn kierra(arvo: Luku, alku: Luku, pituus: Luku) -> Luku {
+    if pituus <= 0.0 || (arvo >= alku && arvo < alku + pituus) {
+        return arvo;
+    }
+    alku + (arvo - alku).rem_euclid(pituus)
+}
+
+/// Laskee yhden akselin siirtymän, jolla kappale palautetaan rajojen sisään, ja uuden
+/// nopeuden. Jos kappale on aluetta suurempi, niin se asetetaan alueen alkuun.
+/// # Arguments
+/// * `kappale` - Kappaleen alku ja loppu akselilla
+/// * `alue` - Alueen alku ja loppu akselilla
+/// * `nopeus` - Kappaleen nopeus akselilla
+/// * `kimpoaa` - Käännetäänkö rajan ylittävä nopeus poistamisen sijaan
+fn palauta(kappale: (Luku, Luku), alue: (Luku, Luku), nopeus: Luku, kimpoaa: bool) -> (Luku, Luku) {
+    let ulospain = |suunta: Luku| {
+        if nopeus * suunta <= 0.0 {
+            nopeus
+        } else if kimpoaa {
+            -nopeus
+        } else {
+            Luku::default()
+        }
+    };
+    if kappale.0 < alue.0 {
+        (alue.0 - kappale.0, ulospain(luku(-1.0)))
+    } else if kappale.1 > alue.1 {
+        (
+            (alue.1 - kappale.1).max(alue.0 - kappale.0),
+            ulospain(luku(1.0)),
+        )
+    } else {
+        (Luku::default(), nopeus)
+    }
+}
+
+/// Lisäosa, joka asettaa kappaleelle oman toiminnan maailman rajalla
+pub struct Rajakayttaytyminen {
+    kappale: RcKappale,
+    /// Toiminta, joka tapahtuu kappaleen ylittäessä rajan
+    pub toiminta: Rajatoiminta,
+}
+
+impl Rajakayttaytyminen {
+    /// Luo uuden rajakäyttäytymisen
+    /// # Arguments
+    /// * `kappale` - Kappale, jolle toiminta asetetaan
+    /// * `toiminta` - Toiminta, joka tapahtuu kappaleen ylittäessä rajan
+    pub fn new(kappale: RcKappale, toiminta: Rajatoiminta) -> Self {
+        Rajakayttaytyminen { kappale, toiminta }
+    }
+}
+
+impl Lisaosa for Rajakayttaytyminen {
+    fn anna_kappale(&self) -> RcKappale {
+        Rc::clone(&self.kappale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fysiikka::{Fysiikallinen, Fysiikkakappale};
+    use crate::maailma::kappale::{Muoto, Tagi};
+    use crate::maailma::{LisaosienAntaja, Perusmaailma};
+
+    fn v(x: f32, y: f32) -> Vektori {
+        Vektori::new(luku(x), luku(y))
+    }
+
+    fn rajat() -> Maailmanrajat {
+        Maailmanrajat::new(Rajaus::new(v(0.0, 0.0), v(100.0, 100.0)))
+    }
+
+    /// Neliö, jonka sivu on 10
+    fn nelio(x: f32, y: f32) -> Kappale {
+        let muoto = Muoto::Nelio(luku(10.0), luku(10.0));
+        Kappale::new_keskipisteella(muoto, luku(x), luku(y), Tagi::Vihollinen)
+    }
+
+    /// Soveltaa toimintaa kappaleeseen ja antaa tuloksen, uuden keskipisteen ja nopeuden
+    fn sovella(
+        toiminta: Rajatoiminta,
+        keskipiste: Vektori,
+        nopeus: Vektori,
+    ) -> (Rajanylitys, Vektori, Vektori) {
+        let mut kappale = nelio(0.0, 0.0);
+        kappale.aseta_keskipisteen_sijainti(keskipiste);
+        let mut nopeus = nopeus;
+        let ylitys = rajat().sovella(toiminta, &mut kappale, &mut nopeus);
+        (ylitys, kappale.keskipisteen_sijainti(), nopeus)
+    }
+
+    #[test]
+    fn rajan_sisalla_ei_tapahdu_mitaan() {
+        // Rajaa koskettava kappale on vielä rajojen sisällä
+        let toiminnat = [
+            Rajatoiminta::Pysaytys,
+            Rajatoiminta::Kimpoaminen,
+            Rajatoiminta::Tuhoutuminen,
+        ];
+        for toiminta in &toiminnat {
+            let (ylitys, keskipiste, nopeus) = sovella(*toiminta, v(95.0, 5.0), v(3.0, -2.0));
+            assert!(matches!(ylitys, Rajanylitys::Sisalla), "{:?}", toiminta);
+            assert_eq!(keskipiste, v(95.0, 5.0));
+            assert_eq!(nopeus, v(3.0, -2.0));
+        }
+        let (ylitys, _, _) = sovella(Rajatoiminta::Kierto, v(99.0, 0.0), v(3.0, 0.0));
+        assert!(matches!(ylitys, Rajanylitys::Sisalla));
+    }
+
+    #[test]
+    fn pysaytys_palauttaa_rajalle_ja_poistaa_ulospain_menevan_nopeuden() {
+        let (ylitys, keskipiste, nopeus) =
+            sovella(Rajatoiminta::Pysaytys, v(103.0, 50.0), v(5.0, 2.0));
+        assert!(matches!(ylitys, Rajanylitys::Palautettu));
+        assert_eq!(keskipiste, v(95.0, 50.0));
+        assert_eq!(nopeus, v(0.0, 2.0));
+
+        // Jo takaisin päin liikkuvan kappaleen nopeus säilyy
+        let (_, keskipiste, nopeus) = sovella(Rajatoiminta::Pysaytys, v(50.0, -2.0), v(1.0, 4.0));
+        assert_eq!(keskipiste, v(50.0, 5.0));
+        assert_eq!(nopeus, v(1.0, 4.0));
+    }
+
+    #[test]
+    fn kimpoaminen_kaantaa_ulospain_menevan_nopeuden() {
+        let (ylitys, keskipiste, nopeus) =
+            sovella(Rajatoiminta::Kimpoaminen, v(-2.0, 103.0), v(-3.0, 4.0));
+        assert!(matches!(ylitys, Rajanylitys::Palautettu));
+        assert_eq!(keskipiste, v(5.0, 95.0));
+        assert_eq!(nopeus, v(3.0, -4.0));
+    }
+
+    #[test]
+    fn kierto_siirtaa_keskipisteen_vastakkaiselle_reunalle() {
+        let (ylitys, keskipiste, nopeus) =
+            sovella(Rajatoiminta::Kierto, v(101.0, -1.0), v(5.0, -1.0));
+        match ylitys {
+            Rajanylitys::Kierretty(siirtyma) => assert_eq!(siirtyma, v(-100.0, 100.0)),
+            _ => panic!("Kappaletta ei kierretty"),
+        }
+        assert_eq!(keskipiste, v(1.0, 99.0));
+        assert_eq!(nopeus, v(5.0, -1.0));
+
+        // Oikea reuna kuuluu jo vasempaan reunaan
+        let (_, keskipiste, _) = sovella(Rajatoiminta::Kierto, v(100.0, 0.0), v(1.0, 0.0));
+        assert_eq!(keskipiste, v(0.0, 0.0));
+    }
+
+    #[test]
+    fn tuhoutuminen_vasta_kokonaan_ulkopuolella() {
+        let (ylitys, _, _) = sovella(Rajatoiminta::Tuhoutuminen, v(104.0, 50.0), v(1.0, 0.0));
+        assert!(matches!(ylitys, Rajanylitys::Sisalla));
+        let (ylitys, _, _) = sovella(Rajatoiminta::Tuhoutuminen, v(106.0, 50.0), v(1.0, 0.0));
+        assert!(matches!(ylitys, Rajanylitys::Poistettava));
+    }
+
+    #[test]
+    fn maailma_soveltaa_omaa_tai_oletustoimintaa() {
+        let mut maailma = Perusmaailma::new();
+        let mut rajat = rajat();
+        rajat.oletus = Some(Rajatoiminta::Pysaytys);
+        maailma.aseta_rajat(Some(rajat));
+        let oletus = maailma.lisaa_kappale(nelio(103.0, 50.0));
+        let oma = maailma.lisaa_kappale(nelio(103.0, 20.0));
+        let lapsi = maailma.lisaa_kappale(nelio(0.0, 0.0));
+        let ilman_fysiikkaa = maailma.lisaa_kappale(nelio(-50.0, 50.0));
+        for kappale in &[&oletus, &oma] {
+            let fysiikka = Fysiikkakappale::new(v(5.0, 0.0), Rc::clone(kappale));
+            maailma.lisaa_fysiikkakappale(fysiikka);
+        }
+        maailma.lisaa_lisaosa(Rajakayttaytyminen::new(
+            Rc::clone(&oma),
+            Rajatoiminta::Kierto,
+        ));
+        maailma
+            .liita(&lapsi, &oma, v(0.0, 10.0), luku(0.0))
+            .unwrap();
+
+        maailma.sovella_rajoja();
+        maailma.paivita_liitokset();
+        assert_eq!(oletus.borrow().keskipisteen_sijainti(), v(95.0, 50.0));
+        assert_eq!(
+            maailma.anna_fysiikka(&oletus).unwrap().anna_nopeus(),
+            v(0.0, 0.0)
+        );
+        assert_eq!(oma.borrow().keskipisteen_sijainti(), v(3.0, 20.0));
+        // Liitetty kappale seuraa kierrettyä vanhempaansa
+        assert_eq!(lapsi.borrow().keskipisteen_sijainti(), v(3.0, 30.0));
+        // Rajoja sovelletaan vain fysiikkakappaleisiin
+        assert_eq!(
+            ilman_fysiikkaa.borrow().keskipisteen_sijainti(),
+            v(-50.0, 50.0)
+        );
+        // Palautetut ja kierretyt kappaleet löytyvät alueindeksistä uudesta sijainnistaan
+        let alue = Rajaus::new(v(0.0, 15.0), v(10.0, 25.0));
+        let loydetyt = maailma.kappaleet_alueella(&alue);
+        assert!(loydetyt.iter().any(|x| Rc::ptr_eq(x, &oma)));
+    }
+}
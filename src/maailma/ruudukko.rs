@@ -0,0 +1,338 @@
+//! Sisältää tasavälisen ruudukon, jonka avulla kappaleita voidaan hakea sijainnin perusteella
+//! käymättä läpi kaikkia maailman kappaleita.
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use super::kappale::Rajaus;
+use super::luku::{luku, Luku, Skalaari};
+use super::vektori::Vektori;
+use super::RcKappale;
+
+/// Ruudun koordinaatit ruudukossa
+type Ruutu = (i32, i32);
+
+/// Ruudukon oletuksena käyttämä ruudun sivun pituus
+const OLETUS_RUUDUN_KOKO: Luku = luku(64.0);
+
+/// Ruudukko, joka jakaa pelimaailman samankokoisiin ruutuihin. Jokaiseen ruutuun merkitään
+/// kappaleet, joiden rajaus osuu ruutuun. Kappaleiden siirtyessä ruudukko pitää päivittää.
+pub struct Ruudukko {
+    /// Ruudun sivun pituus
+    ruudun_koko: Luku,
+    /// Ruuduissa olevat kappaleet
+    ruudut: HashMap<Ruutu, Vec<RcKappale>>,
+    /// Kappaleet ja ruutualueet, joihin ne on viimeksi merkitty
+    kappaleiden_ruudut: HashMap<*const (), (RcKappale, (Ruutu, Ruutu))>,
+}
+
+impl Default for Ruudukko {
+    fn default() -> Self {
+        Self::new(OLETUS_RUUDUN_KOKO)
+    }
+}
+
+impl Ruudukko {
+    /// Luo uuden tyhjän ruudukon
+    /// # Arguments
+    /// * `ruudun_koko` - Yhden ruudun sivun pituus
+    pub fn new(ruudun_koko: Luku) -> Self {
+        Ruudukko {
+            ruudun_koko,
+            ruudut: HashMap::new(),
+            kappaleiden_ruudut: HashMap::new(),
+        }
+    }
+
+    /// Lisää kappaleen ruudukkoon sen nykyisen sijainnin perusteella
+    /// # Arguments
+    /// * `kappale` - Lisättävä kappale
+    pub fn lisaa(&mut self, kappale: &RcKappale) {
+        let alue = self.ruutualue(&kappale.borrow().rajaus());
+        self.merkitse(kappale, alue);
+        self.kappaleiden_ruudut
+            .insert(avain(kappale), (Rc::clone(kappale), alue));
+    }
+
+    /// Poistaa kappaleen ruudukosta
+    /// # Arguments
+    /// * `kappale` - Poistettava kappale
+    pub fn poista(&mut self, kappale: &RcKappale) {
+        if let Some((_, alue)) = self.kappaleiden_ruudut.remove(&avain(kappale)) {
+            self.poista_merkinnat(kappale, alue);
+        }
+    }
+
+    /// Päivittää kappaleen ruudut, jos kappale on siirtynyt toisiin ruutuihin
+    /// # Arguments
+    /// * `kappale` - Päivitettävä kappale
+    pub fn paivita(&mut self, kappale: &RcKappale) {
+        let uusi_alue = self.ruutualue(&kappale.borrow().rajaus());
+        let vanha_alue = match self.kappaleiden_ruudut.get_mut(&avain(kappale)) {
+            Some((_, alue)) if *alue == uusi_alue => return,
+            Some((_, alue)) => std::mem::replace(alue, uusi_alue),
+            None => return self.lisaa(kappale),
+        };
+        self.poista_merkinnat(kappale, vanha_alue);
+        self.merkitse(kappale, uusi_alue);
+    }
+
+    /// Päivittää kaikkien annettujen kappaleiden ruudut annetussa järjestyksessä.
+    /// Järjestys vaikuttaa siihen, missä järjestyksessä haut palauttavat kappaleet.
+    /// # Arguments
+    /// * `kappaleet` - Päivitettävät kappaleet
+    pub fn paivita_kaikki(&mut self, kappaleet: &[RcKappale]) {
+        for kappale in kappaleet {
+            self.paivita(kappale);
+        }
+    }
+
+    /// Antaa kappaleet, joiden rajaus leikkaa annettua aluetta
+    /// # Arguments
+    /// * `alue` - Alue, jolta kappaleita haetaan
+    pub fn alueella(&self, alue: &Rajaus) -> Vec<RcKappale> {
+        self.ehdokkaat(alue)
+            .into_iter()
+            .filter(|kappale| kappale.borrow().rajaus().leikkaa(alue))
+            .collect()
+    }
+
+    /// Antaa kappaleet, joiden rajaus on annetun etäisyyden päässä annetusta pisteestä
+    /// # Arguments
+    /// * `keskipiste` - Piste, jonka ympäriltä kappaleita haetaan
+    /// * `sade` - Etäisyys, jonka päästä kappaleita haetaan
+    pub fn sateella(&self, keskipiste: Vektori, sade: Luku) -> Vec<RcKappale> {
+        self.ehdokkaat(&Rajaus::ympyran_ymparilta(keskipiste, sade))
+            .into_iter()
+            .filter(|kappale| {
+                let lahin = kappale.borrow().rajaus().lahin_piste(keskipiste);
+                (lahin - keskipiste).pituus() <= sade
+            })
+            .collect()
+    }
+
+    /// Antaa kaikki kappaleet, jotka on merkitty annetun alueen ruutuihin. Kukin kappale
+    /// annetaan vain kerran ja ruutujen järjestyksessä.
+    /// # Arguments
+    /// * `alue` - Alue, jonka ruuduista kappaleet haetaan
+    fn ehdokkaat(&self, alue: &Rajaus) -> Vec<RcKappale> {
+        let mut loydetyt = HashSet::new();
+        let mut ehdokkaat = Vec::new();
+        for ruutu in self.alueen_ruudut(self.ruutualue(alue)) {
+            if let Some(ruutu) = self.ruudut.get(&ruutu) {
+                for kappale in ruutu {
+                    if loydetyt.insert(avain(kappale)) {
+                        ehdokkaat.push(Rc::clone(kappale));
+                    }
+                }
+            }
+        }
+        ehdokkaat
+    }
+
+    /// Antaa annetun ruutualueen ruudut riveittäin. Jos alueella on enemmän ruutuja kuin
+    /// ruudukossa on merkittyjä ruutuja, niin annetaan vain alueen merkityt ruudut, jotta
+    /// esim. äärettömän säteen haku ei käy läpi kaikkia mahdollisia ruutuja.
+    /// # Arguments
+    /// * `alue` - Ruutualue vasemman ylä- ja oikean alakulman ruutuina
+    fn alueen_ruudut(&self, ((x0, y0), (x1, y1)): (Ruutu, Ruutu)) -> Vec<Ruutu> {
+        let leveys = i64::from(x1) - i64::from(x0) + 1;
+        let korkeus = i64::from(y1) - i64::from(y0) + 1;
+        if leveys.saturating_mul(korkeus) > self.ruudut.len() as i64 {
+            let mut ruudut: Vec<Ruutu> = self
+                .ruudut
+                .keys()
+                .filter(|(x, y)| (x0..=x1).contains(x) && (y0..=y1).contains(y))
+                .cloned()
+                .collect();
+            ruudut.sort_by_key(|(x, y)| (*y, *x));
+            ruudut
+        } else {
+            (y0..=y1)
+                .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+                .collect()
+        }
+    }
+
+    /// Merkitsee kappaleen annetun alueen ruutuihin
+    fn merkitse(&mut self, kappale: &RcKappale, ((x0, y0), (x1, y1)): (Ruutu, Ruutu)) {
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                self.ruudut
+                    .entry((x, y))
+                    .or_default()
+                    .push(Rc::clone(kappale));
+            }
+        }
+    }
+
+    /// Poistaa kappaleen merkinnät annetun alueen ruuduista
+    fn poista_merkinnat(&mut self, kappale: &RcKappale, ((x0, y0), (x1, y1)): (Ruutu, Ruutu)) {
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                if let Some(ruutu) = self.ruudut.get_mut(&(x, y)) {
+                    ruutu.retain(|x| !std::ptr::eq(x.as_ptr(), kappale.as_ptr()));
+                    if ruutu.is_empty() {
+                        self.ruudut.remove(&(x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Antaa ruudut, joihin annettu alue osuu vasemman ylä- ja oikean alakulman ruutuina
+    fn ruutualue(&self, alue: &Rajaus) -> (Ruutu, Ruutu) {
+        (self.ruutu(alue.vasen_yla), self.ruutu(alue.oikea_ala))
+    }
+
+    /// Antaa ruudun, jossa annettu piste on
+    fn ruutu(&self, piste: Vektori) -> Ruutu {
+        (
+            (piste.x / self.ruudun_koko).floor().liukuluku() as i32,
+            (piste.y / self.ruudun_koko).floor().liukuluku() as i32,
+        )
+    }
+}
+
+/// Antaa kappaleen tunnisteen, jolla se löydetään ruudukosta
+fn avain(kappale: &RcKappale) -> *const () {
+    kappale.as_ptr() as *const ()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::maailma::kappale::{Kappale, Muoto, Tagi};
+
+    /// Luo neliön, jonka keskipiste on annetussa pisteessä
+    fn nelio(x: f32, y: f32, koko: f32) -> RcKappale {
+        Rc::new(RefCell::new(Kappale::new_keskipisteella(
+            Muoto::Nelio(luku(koko), luku(koko)),
+            luku(x),
+            luku(y),
+            Tagi::Vihollinen,
+        )))
+    }
+
+    /// Antaa, mitkä annetuista kappaleista ovat haun tuloksessa
+    fn loydetyt(tulos: &[RcKappale], kappaleet: &[&RcKappale]) -> Vec<bool> {
+        kappaleet
+            .iter()
+            .map(|k| tulos.iter().any(|x| Rc::ptr_eq(x, k)))
+            .collect()
+    }
+
+    /// Luo alueen kulmien koordinaateista
+    fn alue(x0: f32, y0: f32, x1: f32, y1: f32) -> Rajaus {
+        Rajaus::new(
+            Vektori::new(x0, y0).luvuiksi(),
+            Vektori::new(x1, y1).luvuiksi(),
+        )
+    }
+
+    #[test]
+    fn alueen_haku() {
+        let mut ruudukko = Ruudukko::new(luku(10.0));
+        let a = nelio(5.0, 5.0, 4.0);
+        let b = nelio(25.0, 5.0, 4.0);
+        let c = nelio(-15.0, -15.0, 4.0);
+        for kappale in &[&a, &b, &c] {
+            ruudukko.lisaa(kappale);
+        }
+        let tulos = ruudukko.alueella(&alue(0.0, 0.0, 10.0, 10.0));
+        assert_eq!(loydetyt(&tulos, &[&a, &b, &c]), [true, false, false]);
+        // Samassa ruudussa oleva, mutta alueen ulkopuolinen kappale ei kuulu tulokseen
+        let tulos = ruudukko.alueella(&alue(8.0, 0.0, 22.0, 10.0));
+        assert_eq!(loydetyt(&tulos, &[&a, &b, &c]), [false, false, false]);
+        let tulos = ruudukko.alueella(&alue(-20.0, -20.0, 30.0, 30.0));
+        assert_eq!(loydetyt(&tulos, &[&a, &b, &c]), [true, true, true]);
+        assert_eq!(tulos.len(), 3);
+    }
+
+    #[test]
+    fn sateen_haku() {
+        let mut ruudukko = Ruudukko::new(luku(10.0));
+        let a = nelio(0.0, 0.0, 2.0);
+        let b = nelio(10.0, 0.0, 2.0);
+        let c = nelio(8.0, 8.0, 2.0);
+        for kappale in &[&a, &b, &c] {
+            ruudukko.lisaa(kappale);
+        }
+        // Rajauksen lähin piste ratkaisee, joten b on säteellä 9 ja c vasta säteellä 7 * sqrt(2)
+        let tulos = ruudukko.sateella(Vektori::default(), luku(9.0));
+        assert_eq!(loydetyt(&tulos, &[&a, &b, &c]), [true, true, false]);
+        let tulos = ruudukko.sateella(Vektori::default(), luku(9.9));
+        assert_eq!(loydetyt(&tulos, &[&a, &b, &c]), [true, true, true]);
+        let tulos = ruudukko.sateella(Vektori::new(luku(20.0), luku(20.0)), luku(1.0));
+        assert!(tulos.is_empty());
+    }
+
+    #[test]
+    fn usean_ruudun_kappale_annetaan_kerran() {
+        let mut ruudukko = Ruudukko::new(luku(10.0));
+        let suuri = nelio(0.0, 0.0, 35.0);
+        ruudukko.lisaa(&suuri);
+        assert_eq!(ruudukko.ruudut.len(), 16);
+        let tulos = ruudukko.alueella(&alue(-15.0, -15.0, 15.0, 15.0));
+        assert_eq!(tulos.len(), 1);
+        // Kappale löytyy mistä tahansa sen peittämästä ruudusta
+        let tulos = ruudukko.sateella(Vektori::new(luku(16.0), luku(-16.0)), luku(1.0));
+        assert_eq!(loydetyt(&tulos, &[&suuri]), [true]);
+        ruudukko.poista(&suuri);
+        assert!(ruudukko.ruudut.is_empty());
+        assert!(ruudukko
+            .alueella(&alue(-15.0, -15.0, 15.0, 15.0))
+            .is_empty());
+    }
+
+    #[test]
+    fn siirtynyt_kappale_poistuu_vanhasta_ruudusta() {
+        let mut ruudukko = Ruudukko::new(luku(10.0));
+        let kappale = nelio(5.0, 5.0, 2.0);
+        ruudukko.lisaa(&kappale);
+        kappale
+            .borrow_mut()
+            .aseta_keskipisteen_sijainti(Vektori::new(luku(45.0), luku(5.0)));
+        // Ennen päivitystä kappale on vielä vanhassa ruudussa
+        assert!(ruudukko.alueella(&alue(40.0, 0.0, 50.0, 10.0)).is_empty());
+        ruudukko.paivita(&kappale);
+        assert!(ruudukko.alueella(&alue(0.0, 0.0, 10.0, 10.0)).is_empty());
+        let tulos = ruudukko.alueella(&alue(40.0, 0.0, 50.0, 10.0));
+        assert_eq!(loydetyt(&tulos, &[&kappale]), [true]);
+        assert_eq!(ruudukko.ruudut.len(), 1);
+        // Päivittämätön kappale lisätään
+        let uusi = nelio(-5.0, -5.0, 2.0);
+        ruudukko.paivita_kaikki(&[Rc::clone(&kappale), Rc::clone(&uusi)]);
+        assert_eq!(ruudukko.alueella(&alue(-50.0, -50.0, 50.0, 50.0)).len(), 2);
+    }
+
+    #[test]
+    fn aareton_haku_kay_lapi_vain_merkityt_ruudut() {
+        let mut ruudukko = Ruudukko::new(luku(10.0));
+        let a = nelio(-25.0, 15.0, 2.0);
+        let b = nelio(5.0, -5.0, 2.0);
+        let c = nelio(1.0e6, 1.0e6, 2.0);
+        for kappale in &[&a, &b, &c] {
+            ruudukko.lisaa(kappale);
+        }
+        let tulos = ruudukko.sateella(Vektori::default(), Luku::INFINITY);
+        assert_eq!(loydetyt(&tulos, &[&a, &b, &c]), [true, true, true]);
+        // Ruudut käydään läpi samassa järjestyksessä kuin pienellä alueella
+        assert!(Rc::ptr_eq(&tulos[0], &b) && Rc::ptr_eq(&tulos[1], &a));
+
+        let aareton = alue(
+            f32::NEG_INFINITY,
+            f32::NEG_INFINITY,
+            f32::INFINITY,
+            f32::INFINITY,
+        );
+        assert_eq!(ruudukko.alueella(&aareton).len(), 3);
+        // Suuri alue, jolla ei ole kappaleita
+        let tulos = ruudukko.alueella(&alue(-1.0e9, 100.0, 1.0e9, 1.0e9));
+        assert_eq!(loydetyt(&tulos, &[&a, &b, &c]), [false, false, true]);
+        assert!(ruudukko
+            .alueella(&alue(-1.0e9, 100.0, 1.0e5, 1.0e9))
+            .is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+//! Sisältää kappaleen tagijoukon ja rekisterin, johon pelit voivat lisätä omia tagejaan
+use std::iter::FromIterator;
+
+use super::kappale::Tagi;
+
+/// Omien tagien suurin mahdollinen määrä
+pub const MAKSIMI_OMAT_TAGIT: usize = 59;
+
+/// Sisäänrakennettujen tagien nimet siinä järjestyksessä kuin niiden bitit ovat tagijoukossa
+const SISAANRAKENNETUT: [(&str, Tagi); 5] = [
+    ("vihollinen", Tagi::Vihollinen),
+    ("seina", Tagi::Seina),
+    ("ammus", Tagi::Ammus),
+    ("pelaaja", Tagi::Pelaaja),
+    ("partikkeli", Tagi::Partikkeli),
+];
+
+/// Oman tagin järjestysnumero. Järjestysnumeron antaa vain tagirekisteri, joten se on aina
+/// pienempi kuin `MAKSIMI_OMAT_TAGIT` ja tagi mahtuu tagijoukkoon.
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub struct OmaTagi(u8);
+
+impl Tagi {
+    /// Antaa tagin järjestysnumeron tagijoukossa
+    pub(crate) fn bitti(self) -> u32 {
+        match self {
+            Tagi::Vihollinen => 0,
+            Tagi::Seina => 1,
+            Tagi::Ammus => 2,
+            Tagi::Pelaaja => 3,
+            Tagi::Partikkeli => 4,
+            Tagi::Oma(OmaTagi(n)) => SISAANRAKENNETUT.len() as u32 + n as u32,
+        }
+    }
+
+    /// Antaa tagin, jonka järjestysnumero tagijoukossa on annettu
+    fn bitista(bitti: u32) -> Self {
+        match SISAANRAKENNETUT.get(bitti as usize) {
+            Some((_, tagi)) => *tagi,
+            None => Tagi::Oma(OmaTagi((bitti as usize - SISAANRAKENNETUT.len()) as u8)),
+        }
+    }
+}
+
+/// Joukko tageja. Kappaleella voi olla useita tageja, esim. sekä vihollinen että lentävä.
+#[derive(Copy, Clone, PartialEq, Eq, Default)]
+pub struct Tagit(u64);
+
+impl Tagit {
+    /// Luo tyhjän tagijoukon
+    pub fn new() -> Self {
+        Tagit(0)
+    }
+
+    /// Luo joukon, jossa ovat kaikki mahdolliset tagit
+    pub fn kaikki() -> Self {
+        Tagit(u64::MAX)
+    }
+
+    /// Lisää tagin joukkoon
+    /// # Arguments
+    /// * `tagi` - Lisättävä tagi
+    pub fn lisaa(&mut self, tagi: Tagi) {
+        self.0 |= 1 << tagi.bitti();
+    }
+
+    /// Poistaa tagin joukosta
+    /// # Arguments
+    /// * `tagi` - Poistettava tagi
+    pub fn poista(&mut self, tagi: Tagi) {
+        self.0 &= !(1 << tagi.bitti());
+    }
+
+    /// Onko annettu tagi joukossa
+    /// # Arguments
+    /// * `tagi` - Tagi, jota etsitään
+    pub fn sisaltaa(&self, tagi: Tagi) -> bool {
+        self.0 & (1 << tagi.bitti()) != 0
+    }
+
+    /// Onko joukoilla yhteisiä tageja
+    /// # Arguments
+    /// * `toinen` - Joukko, johon verrataan
+    pub fn leikkaa(&self, toinen: Tagit) -> bool {
+        self.0 & toinen.0 != 0
+    }
+
+    /// Lisää toisen joukon tagit tähän joukkoon
+    /// # Arguments
+    /// * `toinen` - Joukko, jonka tagit lisätään
+    pub fn yhdista(&mut self, toinen: Tagit) {
+        self.0 |= toinen.0;
+    }
+
+    /// Onko joukko tyhjä
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Antaa joukon tagit järjestyksessä sisäänrakennetut ensin
+    pub fn iter(&self) -> impl Iterator<Item = Tagi> {
+        let bitit = self.0;
+        (0..64)
+            .filter(move |x| bitit & (1 << x) != 0)
+            .map(Tagi::bitista)
+    }
+}
+
+impl From<Tagi> for Tagit {
+    fn from(tagi: Tagi) -> Self {
+        let mut tagit = Tagit::new();
+        tagit.lisaa(tagi);
+        tagit
+    }
+}
+
+impl FromIterator<Tagi> for Tagit {
+    fn from_iter<I: IntoIterator<Item = Tagi>>(tagit: I) -> Self {
+        let mut joukko = Tagit::new();
+        for tagi in tagit {
+            joukko.lisaa(tagi);
+        }
+        joukko
+    }
+}
+
+/// Rekisteri tagien nimistä. Sisältää aina sisäänrakennetut tagit, ja siihen voidaan
+/// rekisteröidä omia tageja, joita voidaan käyttää esim. tasotiedostoissa ja törmäyksissä.
+#[derive(Clone, Default)]
+pub struct Tagirekisteri {
+    /// Omien tagien nimet. Nimen indeksi on tagin `Tagi::Oma` järjestysnumero.
+    omat: Vec<String>,
+}
+
+impl Tagirekisteri {
+    /// Luo rekisterin, jossa on vain sisäänrakennetut tagit
+    pub fn new() -> Self {
+        Tagirekisteri { omat: Vec::new() }
+    }
+
+    /// Rekisteröi uuden tagin ja antaa sen. Jos samanniminen tagi on jo olemassa,
+    /// niin annetaan se.
+    /// # Arguments
+    /// * `nimi` - Tagin nimi
+    pub fn rekisteroi(&mut self, nimi: &str) -> Result<Tagi, String> {
+        if let Some(tagi) = self.anna(nimi) {
+            return Ok(tagi);
+        }
+        if nimi.is_empty() || nimi.contains(char::is_whitespace) {
+            return Err(format!("Tagin nimi '{}' ei saa olla tyhjä tai sisältää välilyöntejä", nimi));
+        }
+        if self.omat.len() >= MAKSIMI_OMAT_TAGIT {
+            return Err(format!(
+                "Tagia '{}' ei voi rekisteröidä, koska omia tageja voi olla korkeintaan {}",
+                nimi, MAKSIMI_OMAT_TAGIT
+            ));
+        }
+        self.omat.push(nimi.to_string());
+        Ok(Tagi::Oma(OmaTagi((self.omat.len() - 1) as u8)))
+    }
+
+    /// Antaa annetun nimisen tagin, jos sellainen on
+    /// # Arguments
+    /// * `nimi` - Tagin nimi
+    pub fn anna(&self, nimi: &str) -> Option<Tagi> {
+        SISAANRAKENNETUT
+            .iter()
+            .find(|(x, _)| *x == nimi)
+            .map(|(_, tagi)| *tagi)
+            .or_else(|| {
+                self.omat
+                    .iter()
+                    .position(|x| x == nimi)
+                    .map(|x| Tagi::Oma(OmaTagi(x as u8)))
+            })
+    }
+
+    /// Antaa tagin nimen, jos tagi on rekisteröity
+    /// # Arguments
+    /// * `tagi` - Tagi, jonka nimi annetaan
+    pub fn nimi(&self, tagi: Tagi) -> Option<&str> {
+        match tagi {
+            Tagi::Oma(OmaTagi(n)) => self.omat.get(n as usize).map(|x| x.as_str()),
+            _ => SISAANRAKENNETUT
+                .iter()
+                .find(|(_, x)| *x == tagi)
+                .map(|(nimi, _)| *nimi),
+        }
+    }
+
+    /// Antaa omien tagien nimet rekisteröintijärjestyksessä
+    pub fn omat(&self) -> &[String] {
+        &self.omat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Antaa oman tagin ohi rekisterin
+    fn oma(n: u8) -> Tagi {
+        Tagi::Oma(OmaTagi(n))
+    }
+
+    #[test]
+    fn omia_tageja_voi_olla_enimmaismaara() {
+        let mut rekisteri = Tagirekisteri::new();
+        for n in 0..MAKSIMI_OMAT_TAGIT {
+            let tagi = rekisteri.rekisteroi(&format!("tagi{}", n)).unwrap();
+            assert!(tagi == oma(n as u8));
+        }
+        let virhe = rekisteri.rekisteroi("liikaa").err().unwrap();
+        assert!(virhe.contains("korkeintaan 59"), "{}", virhe);
+        assert!(rekisteri.anna("liikaa").is_none());
+        assert_eq!(rekisteri.omat().len(), MAKSIMI_OMAT_TAGIT);
+        // Jo rekisteröidyn tagin saa yhä, vaikka rekisteri on täynnä
+        assert!(rekisteri.rekisteroi("tagi3").unwrap() == oma(3));
+    }
+
+    #[test]
+    fn viimeinen_oma_tagi_on_joukon_viimeinen_bitti() {
+        let viimeinen = oma(MAKSIMI_OMAT_TAGIT as u8 - 1);
+        assert_eq!(viimeinen.bitti(), 63);
+        let tagit = Tagit::from(viimeinen);
+        assert!(tagit.sisaltaa(viimeinen));
+        assert!(!tagit.sisaltaa(oma(0)));
+        assert!(tagit.leikkaa(Tagit::kaikki()));
+        assert!(tagit.iter().eq(vec![viimeinen]));
+        assert_eq!(Tagit::kaikki().iter().count(), 64);
+    }
+
+    #[test]
+    fn joukon_tagit_jarjestyksessa() {
+        let mut tagit: Tagit = vec![oma(2), Tagi::Pelaaja, Tagi::Vihollinen]
+            .into_iter()
+            .collect();
+        let odotetut = vec![Tagi::Vihollinen, Tagi::Pelaaja, oma(2)];
+        assert!(tagit.iter().eq(odotetut));
+        tagit.poista(Tagi::Pelaaja);
+        assert!(!tagit.sisaltaa(Tagi::Pelaaja));
+        assert!(!tagit.leikkaa(Tagit::from(Tagi::Pelaaja)));
+        tagit.yhdista(Tagit::from(Tagi::Seina));
+        assert!(tagit.sisaltaa(Tagi::Seina));
+        assert!(Tagit::new().is_empty());
+    }
+
+    #[test]
+    fn nimet() {
+        let mut rekisteri = Tagirekisteri::new();
+        assert!(rekisteri.anna("seina").unwrap() == Tagi::Seina);
+        // Sisäänrakennettua tagia ei rekisteröidä uudelleen omaksi
+        assert!(rekisteri.rekisteroi("ammus").unwrap() == Tagi::Ammus);
+        assert!(rekisteri.omat().is_empty());
+
+        let lentava = rekisteri.rekisteroi("lentava").unwrap();
+        assert_eq!(rekisteri.nimi(lentava), Some("lentava"));
+        assert_eq!(rekisteri.nimi(Tagi::Partikkeli), Some("partikkeli"));
+        assert_eq!(rekisteri.nimi(oma(1)), None);
+        assert!(rekisteri.rekisteroi("").is_err());
+        assert!(rekisteri.rekisteroi("kaksi sanaa").is_err());
+    }
+}
@@ -1,9 +1,15 @@
 //! Sisältää 2d-vektoriin liittyvät toiminnot (ja vektorin itsensä)
-use std::ops::{Add, Div, Mul, Sub};
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use super::luku::{luku, Luku, Skalaari};
+
+/// Suhteellinen tarkkuus, jolla liukulukuvektorien yhtäsuuruutta verrataan
+pub const VERTAILUTARKKUUS: f32 = 1e-5;
 
 /// Sijainti 2d maailmassa. Muodoilla vasemman yläkulman sijainti. Origo on vasemmassa yläkulmassa.
-#[derive(Copy, Clone)]
-pub struct Vektori<T = f32> {
+#[derive(Copy, Clone, Debug)]
+pub struct Vektori<T = Luku> {
     /// x-koordinaatti
     pub x: T,
     /// y-koordinaatti
@@ -32,7 +38,7 @@ where
     }
 }
 
-impl<T: std::ops::AddAssign> Vektori<T> {
+impl<T: AddAssign> Vektori<T> {
     /// Siirtää sijaintia annetun verran
     /// # Arguments
     /// * `x` - x-koordinaatin muutos
@@ -95,15 +101,314 @@ impl<T: Sub<Output = T>> Sub for Vektori<T> {
     }
 }
 
-impl Vektori<f32> {
+impl<T: Neg<Output = T>> Neg for Vektori<T> {
+    type Output = Vektori<T>;
+
+    fn neg(self) -> Self::Output {
+        Vektori {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
+impl<T: AddAssign> AddAssign for Vektori<T> {
+    fn add_assign(&mut self, other: Self) {
+        self.x += other.x;
+        self.y += other.y;
+    }
+}
+
+impl<T: SubAssign> SubAssign for Vektori<T> {
+    fn sub_assign(&mut self, other: Self) {
+        self.x -= other.x;
+        self.y -= other.y;
+    }
+}
+
+impl<T: MulAssign + Copy> MulAssign<T> for Vektori<T> {
+    fn mul_assign(&mut self, other: T) {
+        self.x *= other;
+        self.y *= other;
+    }
+}
+
+impl<T: DivAssign + Copy> DivAssign<T> for Vektori<T> {
+    fn div_assign(&mut self, other: T) {
+        self.x /= other;
+        self.y /= other;
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Vektori<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+impl<T> Vektori<T>
+where
+    T: Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Copy,
+{
+    /// Antaa vektorien pistetulon
+    /// # Arguments
+    /// * `toinen` - Vektori, jonka kanssa pistetulo lasketaan
+    pub fn pistetulo(self, toinen: Self) -> T {
+        self.x * toinen.x + self.y * toinen.y
+    }
+
+    /// Antaa vektorien ristitulon z-komponentin. Kertoo kummalla puolella toinen vektori on.
+    /// # Arguments
+    /// * `toinen` - Vektori, jonka kanssa ristitulo lasketaan
+    pub fn ristitulo(self, toinen: Self) -> T {
+        self.x * toinen.y - self.y * toinen.x
+    }
+
+    /// Antaa vektorin pituuden toisen potenssin. Kelpaa pituuksien vertailuun ilman neliöjuurta.
+    pub fn pituuden_nelio(self) -> T {
+        self.pistetulo(self)
+    }
+
+    /// Antaa lineaarisesti interpoloidun vektorin tämän ja toisen vektorin väliltä
+    /// # Arguments
+    /// * `toinen` - Vektori, joka annetaan osuudella 1
+    /// * `osuus` - Kuinka suuri osa matkasta toiseen vektoriin kuljetaan
+    pub fn interpoloi(self, toinen: Self, osuus: T) -> Self {
+        Vektori {
+            x: self.x + (toinen.x - self.x) * osuus,
+            y: self.y + (toinen.y - self.y) * osuus,
+        }
+    }
+}
+
+impl<T: Neg<Output = T>> Vektori<T> {
+    /// Antaa samanpituisen kohtisuoran vektorin. Koska y-akseli osoittaa alas, niin
+    /// vektori on kierretty näytöllä 90 astetta myötäpäivään.
+    pub fn kohtisuora(self) -> Self {
+        Vektori {
+            x: -self.y,
+            y: self.x,
+        }
+    }
+}
+
+impl<T: Skalaari> Vektori<T> {
     /// Antaa annetun vektorin pituuden
-    pub fn pituus(self) -> f32 {
-        // Näyttää kaamealta...
-        (f32::powf(self.x, 2.0) + f32::powf(self.y, 2.0)).sqrt()
+    pub fn pituus(self) -> T {
+        self.pituuden_nelio().sqrt()
     }
 
-    /// Antaa annetun vektorin yksikkövektorin
+    /// Antaa annetun vektorin yksikkövektorin. Nollavektorille tulos ei ole äärellinen,
+    /// joten suunnaltaan tuntemattomalle vektorille kannattaa käyttää `normalisoi`-funktiota.
     pub fn yksikkovektori(self) -> Self {
         self / self.pituus()
     }
+
+    /// Antaa vektorin yksikkövektorin, jos vektorilla on suunta. Nollavektorille ja
+    /// ei-äärelliselle vektorille ei anneta mitään.
+    pub fn normalisoi(self) -> Option<Self> {
+        let pituus = self.pituus();
+        if pituus > T::from(f32::EPSILON) && pituus.is_finite() {
+            Some(self / pituus)
+        } else {
+            None
+        }
+    }
+
+    /// Antaa vektorien kärkien välisen etäisyyden
+    /// # Arguments
+    /// * `toinen` - Vektori, johon etäisyys lasketaan
+    pub fn etaisyys(self, toinen: Self) -> T {
+        (toinen - self).pituus()
+    }
+
+    /// Antaa vektorin, jonka pituus on enintään annettu maksimi. Suunta säilyy.
+    /// # Arguments
+    /// * `maksimi` - Suurin sallittu pituus
+    pub fn rajaa_pituus(self, maksimi: T) -> Self {
+        let nolla = T::default();
+        let pituus = self.pituus();
+        if pituus > maksimi && pituus > nolla {
+            let maksimi = if maksimi > nolla { maksimi } else { nolla };
+            self * (maksimi / pituus)
+        } else {
+            self
+        }
+    }
+
+    /// Antaa vektorin kierrettynä annetun kulman verran. Koska y-akseli osoittaa alas,
+    /// niin positiivinen kulma kiertää näytöllä myötäpäivään.
+    /// # Arguments
+    /// * `kulma` - Kiertokulma radiaaneina
+    pub fn kierra(self, kulma: T) -> Self {
+        let (sin, cos) = kulma.sin_cos();
+        Vektori::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    /// Antaa vektorin suunnan kulmana radiaaneina x-akselista mitattuna
+    pub fn kulma(self) -> T {
+        self.y.atan2(self.x)
+    }
+
+    /// Antaa kulman, jonka verran vektoria pitää kiertää, jotta se osoittaa toisen vektorin
+    /// suuntaan. Tulos on välillä -PI..PI ja sen etumerkki on sama kuin `kierra`-funktiossa.
+    /// # Arguments
+    /// * `toinen` - Vektori, jonka suuntaan kulma lasketaan
+    pub fn kulma_valilla(self, toinen: Self) -> T {
+        self.ristitulo(toinen).atan2(self.pistetulo(toinen))
+    }
+
+    /// Ovatko vektorit yhtä suuret annetulla suhteellisella tarkkuudella. Lähellä nollaa
+    /// olevia liukulukukoordinaatteja verrataan tarkkuuteen sellaisenaan.
+    /// # Arguments
+    /// * `toinen` - Vektori, johon verrataan
+    /// * `tarkkuus` - Suurin sallittu suhteellinen ero koordinaateissa
+    pub fn lahes_sama(self, toinen: Self, tarkkuus: f32) -> bool {
+        self.x.lahes_sama(toinen.x, tarkkuus) && self.y.lahes_sama(toinen.y, tarkkuus)
+    }
+
+    /// Muuttaa vektorin liukulukuvektoriksi esim. piirtämistä varten
+    pub fn liukuluvuiksi(self) -> Vektori<f32> {
+        Vektori::new(self.x.liukuluku(), self.y.liukuluku())
+    }
+}
+
+impl Vektori<f32> {
+    /// Muuttaa liukulukuvektorin simulaation luvuiksi esim. näytön koordinaateista
+    pub fn luvuiksi(self) -> Vektori {
+        Vektori::new(luku(self.x), luku(self.y))
+    }
+}
+
+/// Liukulukuvektoreita verrataan tarkkuudella `VERTAILUTARKKUUS`, jotta laskujärjestyksestä
+/// johtuvat pyöristysvirheet eivät tee vektoreista erisuuria. Kiintolukuvektorit ovat
+/// yhtä suuret vain täsmälleen samoina.
+impl<T: Skalaari> PartialEq for Vektori<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.x.yhtasuuri(other.x) && self.y.yhtasuuri(other.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    #[test]
+    fn laskutoimitukset() {
+        let a = Vektori::new(1.0, 2.0);
+        let b = Vektori::new(3.0, -4.0);
+        assert_eq!(a + b, Vektori::new(4.0, -2.0));
+        assert_eq!(a - b, Vektori::new(-2.0, 6.0));
+        assert_eq!(a * 2.0, Vektori::new(2.0, 4.0));
+        assert_eq!(b / 2.0, Vektori::new(1.5, -2.0));
+        assert_eq!(-a, Vektori::new(-1.0, -2.0));
+    }
+
+    #[test]
+    fn sijoitusoperaattorit() {
+        let mut v = Vektori::new(1.0, 2.0);
+        v += Vektori::new(1.0, 1.0);
+        assert_eq!(v, Vektori::new(2.0, 3.0));
+        v -= Vektori::new(4.0, 1.0);
+        assert_eq!(v, Vektori::new(-2.0, 2.0));
+        v *= 3.0;
+        assert_eq!(v, Vektori::new(-6.0, 6.0));
+        v /= 2.0;
+        assert_eq!(v, Vektori::new(-3.0, 3.0));
+    }
+
+    #[test]
+    fn kokonaislukuvektori() {
+        let mut v = Vektori::new(2, -3) + Vektori::new(1, 1);
+        v *= 2;
+        assert_eq!((v.x, v.y), (6, -4));
+        assert_eq!(v.pistetulo(Vektori::new(1, 1)), 2);
+        assert_eq!(v.ristitulo(Vektori::new(1, 0)), 4);
+        let kohtisuora = v.kohtisuora();
+        assert_eq!((kohtisuora.x, kohtisuora.y), (4, 6));
+    }
+
+    #[test]
+    fn piste_ja_ristitulo() {
+        let a = Vektori::new(2.0, 0.0);
+        let b = Vektori::new(3.0, 4.0);
+        assert_eq!(a.pistetulo(b), 6.0);
+        assert_eq!(a.ristitulo(b), 8.0);
+        assert_eq!(b.ristitulo(a), -8.0);
+        assert_eq!(b.pituuden_nelio(), 25.0);
+        assert_eq!(b.pituus(), 5.0);
+    }
+
+    #[test]
+    fn kierto_ja_kulmat() {
+        let v = Vektori::new(1.0, 0.0);
+        assert_eq!(v.kierra(FRAC_PI_2), Vektori::new(0.0, 1.0));
+        assert_eq!(v.kierra(FRAC_PI_2), v.kohtisuora());
+        assert_eq!(v.kierra(PI), -v);
+        assert!((Vektori::new(0.0, 2.0).kulma() - FRAC_PI_2).abs() < 1e-6);
+        assert!((v.kulma_valilla(Vektori::new(0.0, 3.0)) - FRAC_PI_2).abs() < 1e-6);
+        assert!((v.kulma_valilla(Vektori::new(0.0, -3.0)) + FRAC_PI_2).abs() < 1e-6);
+        let b = Vektori::new(-2.0, 5.0);
+        assert_eq!(v.kierra(v.kulma_valilla(b)), b.yksikkovektori());
+        assert_eq!(
+            Vektori::new(3.0, 4.0)
+                .kohtisuora()
+                .pistetulo(Vektori::new(3.0, 4.0)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn normalisointi() {
+        assert_eq!(
+            Vektori::new(3.0, 4.0).normalisoi(),
+            Some(Vektori::new(0.6, 0.8))
+        );
+        assert_eq!(Vektori::new(0.0, 0.0).normalisoi(), None);
+        assert_eq!(Vektori::new(f32::NAN, 1.0).normalisoi(), None);
+        assert_eq!(Vektori::new(f32::INFINITY, 1.0).normalisoi(), None);
+        assert!(!Vektori::new(0.0, 0.0).yksikkovektori().x.is_finite());
+    }
+
+    #[test]
+    fn etaisyys_ja_interpolointi() {
+        let a = Vektori::new(1.0, 1.0);
+        let b = Vektori::new(4.0, 5.0);
+        assert_eq!(a.etaisyys(b), 5.0);
+        assert_eq!(b.etaisyys(a), 5.0);
+        assert_eq!(a.interpoloi(b, 0.0), a);
+        assert_eq!(a.interpoloi(b, 1.0), b);
+        assert_eq!(a.interpoloi(b, 0.5), Vektori::new(2.5, 3.0));
+        assert_eq!(a.interpoloi(b, 2.0), Vektori::new(7.0, 9.0));
+    }
+
+    #[test]
+    fn pituuden_rajaus() {
+        let v = Vektori::new(6.0, 8.0);
+        assert_eq!(v.rajaa_pituus(5.0), Vektori::new(3.0, 4.0));
+        assert_eq!(v.rajaa_pituus(20.0), v);
+        assert_eq!(v.rajaa_pituus(-1.0), Vektori::new(0.0, 0.0));
+        assert_eq!(
+            Vektori::new(0.0, 0.0).rajaa_pituus(1.0),
+            Vektori::new(0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn yhtasuuruus_tarkkuudella() {
+        assert_eq!(Vektori::new(0.1 + 0.2, 1.0), Vektori::new(0.3, 1.0));
+        assert_eq!(Vektori::new(1e6, 0.0), Vektori::new(1e6 + 1.0, 0.0));
+        assert_ne!(Vektori::new(1.0, 0.0), Vektori::new(1.001, 0.0));
+        assert_ne!(Vektori::new(f32::NAN, 0.0), Vektori::new(f32::NAN, 0.0));
+        assert!(Vektori::new(1.0, 0.0).lahes_sama(Vektori::new(1.001, 0.0), 0.01));
+    }
+
+    #[test]
+    fn tulostus() {
+        let v = Vektori::new(1.5, -2.0);
+        assert_eq!(v.to_string(), "(1.5, -2)");
+        assert_eq!(format!("{:?}", v), "Vektori { x: 1.5, y: -2.0 }");
+        assert_eq!(Vektori::<i32>::default().to_string(), "(0, 0)");
+    }
 }
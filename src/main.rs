@@ -1,6 +1,7 @@
 extern crate sdl2;
 
 use std::env;
+use std::time::Duration;
 
 use sdl2::image::{InitFlag, LoadTexture};
 use sdl2::pixels::Color;
@@ -8,31 +9,91 @@ use sdl2::render::BlendMode;
 
 pub mod animointi;
 pub mod fysiikka;
+pub mod laajavaihe;
 pub mod maailma;
 pub mod paivitys;
 pub mod piirtaja;
 pub mod silmukka;
 pub mod spawneri;
 pub mod syotteet;
+pub mod tallennus;
 pub mod tekoaly;
+pub mod tiedosto;
+pub mod tiiviste;
 pub mod tormays;
 
 mod peli;
 
 use crate::paivitys::{Paivitys, YhdistettyPaivitys};
+use crate::peli::nauhoituksenpaivitys::NauhoituksenPaivitys;
 use crate::peli::pelihahmonpaivitys::PelihahmonPaivitys;
 use crate::peli::pelinpaivitys::{
     AnimaatioidenPaivitys, FysiikanPaivitys, SpawnerinPaivitys, TekoalynPaivitys,
 };
+use crate::peli::tallennuksenpaivitys::TallennuksenPaivitys;
+use crate::peli::tarkistus::tarkista_nauhoite;
+use crate::peli::tasonpaivitys::{TasonPaivitys, OLETUSTASO};
+use crate::peli::tiivisteenpaivitys::TiivisteenPaivitys;
 use crate::piirtaja::{Peruspiirtaja, Piirtovalmius};
 use crate::silmukka::erillisetpaivityksetsilmukka::ErillisetPaivityksetSilmukka;
 use crate::silmukka::interpoloivasilmukka::InterpoloivaSilmukka;
 use crate::silmukka::perussilmukka::Perussilmukka;
 use crate::silmukka::saannollinensilmukka::SaannollinenSilmukka;
 use crate::silmukka::Paasilmukka;
+use crate::syotteet::Nauhoite;
+use crate::tiiviste::{ensimmainen_poikkeama, Poikkeama, Tiivistehistoria};
 
 fn main() -> Result<(), String> {
     let args: Vec<String> = env::args().collect();
+    // Tallennus, josta peli jatketaan, voidaan antaa argumentilla --lataa <tiedosto>
+    let ladattava = anna_valitsimen_arvo(&args, "--lataa");
+    // Pelattava taso voidaan antaa argumentilla --taso <tiedosto>
+    let taso = anna_valitsimen_arvo(&args, "--taso").unwrap_or_else(|| OLETUSTASO.to_string());
+    // Interpoloivien silmukoiden piirtoviive voidaan antaa argumentilla --piirtoviive <millisekunnit>
+    let piirtoviive = match anna_valitsimen_arvo(&args, "--piirtoviive") {
+        Some(arvo) => Some(Duration::from_millis(
+            arvo.parse::<u64>()
+                .map_err(|_| format!("Piirtoviive '{}' ei ole millisekunteja", arvo))?,
+        )),
+        None => None,
+    };
+    // Interpoloivat silmukat piirtävät kappaleiden jäljet argumentilla --jaljet
+    let jaljet = args.iter().any(|x| x == "--jaljet");
+
+    // Kahden ajon tiivistetiedostot voidaan verrata argumentilla --vertaa <tiedosto> <tiedosto>
+    if let Some(i) = args.iter().position(|x| x == "--vertaa") {
+        let ensimmainen = lue_tiivisteet(args.get(i + 1))?;
+        let toinen = lue_tiivisteet(args.get(i + 2))?;
+        return tulosta_poikkeama(ensimmainen_poikkeama(&ensimmainen, &toinen));
+    }
+    // Nauhoite voidaan ajaa säännöllisellä ja interpoloivalla silmukalla ilman ikkunaa
+    // argumentilla --tarkista <tiedosto>
+    if let Some(polku) = anna_valitsimen_arvo(&args, "--tarkista") {
+        let nauhoite = Nauhoite::lue(&lue_tiedosto(&polku)?)
+            .map_err(|e| format!("Virheellinen nauhoite {}: {}", polku, e))?;
+        return match tarkista_nauhoite(&taso, &nauhoite, 60)? {
+            Some((silmukka, poikkeama)) => Err(format!(
+                "{} (säännöllinen ja {} ajo)",
+                poikkeama, silmukka
+            )),
+            None => tulosta_poikkeama(None),
+        };
+    }
+
+    let tason_paivitys: &mut Paivitys = &mut TasonPaivitys::new(&taso)?;
+    // Syötteet voidaan nauhoittaa argumentilla --nauhoita <tiedosto> ja toistaa argumentilla --toista <tiedosto>
+    let nauhoituksen_paivitys: &mut dyn Paivitys = &mut match (
+        anna_valitsimen_arvo(&args, "--nauhoita"),
+        anna_valitsimen_arvo(&args, "--toista"),
+    ) {
+        (Some(_), Some(_)) => return Err("Ei voi nauhoittaa ja toistaa samaan aikaan".to_string()),
+        (Some(polku), None) => NauhoituksenPaivitys::new_nauhoittava(&polku)?,
+        (None, Some(polku)) => NauhoituksenPaivitys::new_toistava(&polku)?,
+        (None, None) => NauhoituksenPaivitys::new(),
+    };
+    // Maailman tilan tiivisteet kirjoitetaan päivityksittäin argumentilla --tiivisteet <tiedosto>
+    let tiivisteen_paivitys: &mut dyn Paivitys =
+        &mut TiivisteenPaivitys::new(anna_valitsimen_arvo(&args, "--tiivisteet"))?;
 
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
@@ -77,7 +138,8 @@ fn main() -> Result<(), String> {
     let fysiikan_paivitys: &mut Paivitys = &mut FysiikanPaivitys;
     let spawnerin_paivitys: &mut Paivitys = &mut SpawnerinPaivitys::new();
     let tekoalyn_paivitys: &mut Paivitys = &mut TekoalynPaivitys;
-    let pelihahmon_paivitys: &mut Paivitys = &mut PelihahmonPaivitys;
+    let pelihahmon_paivitys: &mut Paivitys = &mut PelihahmonPaivitys::new();
+    let tallennuksen_paivitys: &mut Paivitys = &mut TallennuksenPaivitys::new(ladattava);
 
     let mut epasaannollinen_paivitys: YhdistettyPaivitys;
     let mut saannollinen_paivitys: YhdistettyPaivitys;
@@ -85,11 +147,15 @@ fn main() -> Result<(), String> {
         match args.get(1).get_or_insert(&"kala".to_string()).parse::<u8>() {
             Ok(1) => {
                 epasaannollinen_paivitys = YhdistettyPaivitys::new(vec![
+                    nauhoituksen_paivitys,
+                    tason_paivitys,
                     spawnerin_paivitys,
                     tekoalyn_paivitys,
                     pelihahmon_paivitys,
                     fysiikan_paivitys,
                     animaatioiden_paivitys,
+                    tallennuksen_paivitys,
+                    tiivisteen_paivitys,
                 ]);
                 Box::new(Perussilmukka::new(
                     events,
@@ -100,11 +166,15 @@ fn main() -> Result<(), String> {
             }
             Ok(2) => {
                 saannollinen_paivitys = YhdistettyPaivitys::new(vec![
+                    nauhoituksen_paivitys,
+                    tason_paivitys,
                     spawnerin_paivitys,
                     tekoalyn_paivitys,
                     pelihahmon_paivitys,
                     fysiikan_paivitys,
                     animaatioiden_paivitys,
+                    tallennuksen_paivitys,
+                    tiivisteen_paivitys,
                 ]);
                 Box::new(SaannollinenSilmukka::new(
                     events,
@@ -116,12 +186,16 @@ fn main() -> Result<(), String> {
             }
             Ok(3) => {
                 saannollinen_paivitys = YhdistettyPaivitys::new(vec![
+                    nauhoituksen_paivitys,
+                    tason_paivitys,
                     spawnerin_paivitys,
                     tekoalyn_paivitys,
+                    pelihahmon_paivitys,
                     fysiikan_paivitys,
+                    tiivisteen_paivitys,
                 ]);
                 epasaannollinen_paivitys =
-                    YhdistettyPaivitys::new(vec![pelihahmon_paivitys, animaatioiden_paivitys]);
+                    YhdistettyPaivitys::new(vec![animaatioiden_paivitys, tallennuksen_paivitys]);
                 Box::new(ErillisetPaivityksetSilmukka::new(
                     events,
                     sdl_context,
@@ -133,48 +207,70 @@ fn main() -> Result<(), String> {
             }
             Ok(4) => {
                 saannollinen_paivitys = YhdistettyPaivitys::new(vec![
+                    nauhoituksen_paivitys,
+                    tason_paivitys,
                     spawnerin_paivitys,
                     tekoalyn_paivitys,
+                    pelihahmon_paivitys,
                     fysiikan_paivitys,
+                    tiivisteen_paivitys,
                 ]);
                 epasaannollinen_paivitys =
-                    YhdistettyPaivitys::new(vec![pelihahmon_paivitys, animaatioiden_paivitys]);
-                Box::new(InterpoloivaSilmukka::new(
+                    YhdistettyPaivitys::new(vec![animaatioiden_paivitys, tallennuksen_paivitys]);
+                let mut interpoloiva = InterpoloivaSilmukka::new(
                     events,
                     sdl_context,
                     &mut piirtaja,
                     &mut saannollinen_paivitys,
                     &mut epasaannollinen_paivitys,
                     5, // Kuinka monta kertaa sekunnissa päivitetään. Ilmeisesti itselläni on vielä 10_000 toimiva...
-                ))
+                );
+                if let Some(viive) = piirtoviive {
+                    interpoloiva.aseta_piirtoviive(viive);
+                }
+                interpoloiva.aseta_jalkien_piirto(jaljet);
+                Box::new(interpoloiva)
             }
             Ok(5) => {
                 saannollinen_paivitys = YhdistettyPaivitys::new(vec![
+                    nauhoituksen_paivitys,
+                    tason_paivitys,
                     spawnerin_paivitys,
                     tekoalyn_paivitys,
+                    pelihahmon_paivitys,
                     fysiikan_paivitys,
+                    tiivisteen_paivitys,
                 ]);
                 epasaannollinen_paivitys =
-                    YhdistettyPaivitys::new(vec![pelihahmon_paivitys, animaatioiden_paivitys]);
-                Box::new(InterpoloivaSilmukka::new_ekstrapoloiva(
+                    YhdistettyPaivitys::new(vec![animaatioiden_paivitys, tallennuksen_paivitys]);
+                let mut interpoloiva = InterpoloivaSilmukka::new_ekstrapoloiva(
                     events,
                     sdl_context,
                     &mut piirtaja,
                     &mut saannollinen_paivitys,
                     &mut epasaannollinen_paivitys,
                     5, // Kuinka monta kertaa sekunnissa päivitetään. Ilmeisesti itselläni on vielä 10_000 toimiva...
-                ))
+                );
+                if let Some(viive) = piirtoviive {
+                    interpoloiva.aseta_piirtoviive(viive);
+                }
+                interpoloiva.aseta_jalkien_piirto(jaljet);
+                Box::new(interpoloiva)
             }
             _ => {
                 println!(
                     "Ei tunnisttettu argumenttina silmukkaa (1-n). Käytetään oletusta (1)."
                 );
                 epasaannollinen_paivitys = YhdistettyPaivitys::new(vec![
+                    nauhoituksen_paivitys,
+                    tason_paivitys,
                     spawnerin_paivitys,
                     tekoalyn_paivitys,
                     pelihahmon_paivitys,
                     fysiikan_paivitys,
                     animaatioiden_paivitys,
+                    tallennuksen_paivitys,
+                    tiivisteen_paivitys,
                 ]);
                 Box::new(Perussilmukka::new(
                     events,
@@ -187,3 +283,44 @@ fn main() -> Result<(), String> {
     println!("{}", silmukka);
     silmukka.kaynnista_silmukka()
 }
+
+/// Antaa komentoriviltä annetun valitsimen arvon eli valitsinta seuraavan argumentin
+/// # Arguments
+/// * `args` - Komentorivin argumentit
+/// * `valitsin` - Valitsin, jonka arvo halutaan, esim. `--taso`
+fn anna_valitsimen_arvo(args: &[String], valitsin: &str) -> Option<String> {
+    args.iter()
+        .position(|x| x == valitsin)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+/// Lukee tiedoston tekstinä
+/// # Arguments
+/// * `polku` - Luettavan tiedoston polku
+fn lue_tiedosto(polku: &str) -> Result<String, String> {
+    std::fs::read_to_string(polku)
+        .map_err(|e| format!("Tiedoston {} lukeminen epäonnistui: {}", polku, e))
+}
+
+/// Lukee tiivistetiedoston, joka on kirjoitettu argumentilla --tiivisteet
+/// # Arguments
+/// * `polku` - Tiivistetiedoston polku
+fn lue_tiivisteet(polku: Option<&String>) -> Result<Tiivistehistoria, String> {
+    let polku = polku.ok_or("Valitsin --vertaa tarvitsee kaksi tiivistetiedostoa")?;
+    Tiivistehistoria::lue(&lue_tiedosto(polku)?)
+        .map_err(|e| format!("Virheellinen tiivistetiedosto {}: {}", polku, e))
+}
+
+/// Tulostaa ajojen ensimmäisen poikkeaman. Poikkeama palautetaan virheenä.
+/// # Arguments
+/// * `poikkeama` - Ensimmäinen kohta, jossa ajot eroavat
+fn tulosta_poikkeama(poikkeama: Option<Poikkeama>) -> Result<(), String> {
+    match poikkeama {
+        Some(poikkeama) => Err(poikkeama.to_string()),
+        None => {
+            println!("Ajot ovat samat");
+            Ok(())
+        }
+    }
+}
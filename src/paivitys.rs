@@ -17,7 +17,8 @@ pub trait Paivitys {
         events: &sdl2::EventPump,
     );
 
-    /// Päivittää annetun pelimaailman tilan annetuilla syötteillä ja päivitysajalla
+    /// Päivittää annetun pelimaailman tilan annetuilla syötteillä ja päivitysajalla. Palauttaa
+    /// virheen, jos päivitystä ei voida jatkaa, jolloin silmukka lopetetaan.
     /// # Arguments
     /// * `maailma` - Pelimaailma, jonka tila päivitetään
     /// * `syotteet` - Päivityksessä käytettävät syötteet
@@ -27,7 +28,7 @@ pub trait Paivitys {
         maailma: &mut Perusmaailma,
         syotteet: &mut Syotteet,
         paivitys_aika: &Paivitysaika,
-    );
+    ) -> Result<(), String>;
 }
 
 /// Sisältää tiedon kuinka paljon peliä päivitetään ja kuinka paljon aikaa on kulunut pelin alusta
@@ -77,7 +78,8 @@ impl<'a> Paivitys for YhdistettyPaivitys<'a> {
         }
     }
 
-    /// Suorittaa päivityksen kaikille yhdistetyille päivityksille järjestyksessä, jossa vasen on ensin
+    /// Suorittaa päivityksen kaikille yhdistetyille päivityksille järjestyksessä, jossa vasen on ensin.
+    /// Ensimmäinen virhe lopettaa päivityksen.
     /// # Arguments
     /// * `maailma` - Pelimaailma, jonka tila päivitetään
     /// * `syotteet` - Päivityksessä käytettävät syötteet
@@ -87,9 +89,10 @@ impl<'a> Paivitys for YhdistettyPaivitys<'a> {
         maailma: &mut Perusmaailma,
         syotteet: &mut Syotteet,
         paivitys_aika: &Paivitysaika,
-    ) {
+    ) -> Result<(), String> {
         for paivitys in &mut self.paivitykset {
-            paivitys.paivita(maailma, syotteet, paivitys_aika);
+            paivitys.paivita(maailma, syotteet, paivitys_aika)?;
         }
+        Ok(())
     }
 }
@@ -0,0 +1,127 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::maailma::Perusmaailma;
+use crate::paivitys::{Paivitys, Paivitysaika};
+use crate::syotteet::*;
+
+/// Mitä nauhoituksen päivitys tekee syötteille
+enum Nauhoitustila {
+    /// Syötteitä ei nauhoiteta eikä toisteta
+    Ei,
+    /// Pohjassa olevat näppäimet kirjoitetaan tiedostoon päivityksittäin
+    Nauhoitus(BufWriter<File>),
+    /// Syötteet korvataan nauhoitteen syötteillä
+    Toisto {
+        /// Toistettava nauhoite
+        nauhoite: Nauhoite,
+        /// Nauhoitteen mukaiset syötteet, joiden tila säilyy päivitysten välillä
+        syotteet: Syotteet,
+    },
+}
+
+/// Nauhoittaa tai toistaa pelaajan syötteet. Tulee olla säännöllisten päivitysten
+/// ensimmäisenä, jotta muut päivitykset käyttävät toistettuja syötteitä. Toisto antaa
+/// saman pelin kulun vain silmukoissa, joissa pelihahmot päivitetään säännöllisesti.
+pub struct NauhoituksenPaivitys {
+    tila: Nauhoitustila,
+    /// Seuraavan päivityksen järjestysnumero
+    askel: usize,
+}
+
+impl NauhoituksenPaivitys {
+    /// Luo päivityksen, joka ei tee mitään
+    pub fn new() -> Self {
+        NauhoituksenPaivitys {
+            tila: Nauhoitustila::Ei,
+            askel: 0,
+        }
+    }
+
+    /// Luo päivityksen, joka nauhoittaa syötteet tiedostoon
+    /// # Arguments
+    /// * `polku` - Nauhoitetiedoston polku
+    pub fn new_nauhoittava(polku: &str) -> Result<Self, String> {
+        let tiedosto = File::create(polku)
+            .map_err(|e| format!("Nauhoitteen {} luominen epäonnistui: {}", polku, e))?;
+        Ok(NauhoituksenPaivitys {
+            tila: Nauhoitustila::Nauhoitus(BufWriter::new(tiedosto)),
+            askel: 0,
+        })
+    }
+
+    /// Luo päivityksen, joka toistaa tiedostoon nauhoitetut syötteet
+    /// # Arguments
+    /// * `polku` - Nauhoitetiedoston polku
+    pub fn new_toistava(polku: &str) -> Result<Self, String> {
+        let teksti = fs::read_to_string(polku)
+            .map_err(|e| format!("Nauhoitteen {} lukeminen epäonnistui: {}", polku, e))?;
+        let nauhoite = Nauhoite::lue(&teksti)
+            .map_err(|e| format!("Virheellinen nauhoite {}: {}", polku, e))?;
+        Ok(NauhoituksenPaivitys {
+            tila: Nauhoitustila::Toisto {
+                nauhoite,
+                syotteet: Syotteet::new(),
+            },
+            askel: 0,
+        })
+    }
+}
+
+impl Default for NauhoituksenPaivitys {
+    fn default() -> Self {
+        NauhoituksenPaivitys::new()
+    }
+}
+
+impl Paivitys for NauhoituksenPaivitys {
+    /// Alustaa pelin
+    /// # Arguments
+    /// * `_maailma` - Pelimaailma, joka alustetaan
+    /// * `_syotteet` - Alustettavat syotteet
+    /// * `_events` - Sdl:n osa, jolta voidaan kysyä tapahtumia kuten näppäinten painalluksia
+    fn alusta(
+        &mut self,
+        _maailma: &mut Perusmaailma,
+        _syotteet: &mut Syotteet,
+        _events: &sdl2::EventPump,
+    ) {
+    }
+
+    /// Nauhoittaa pohjassa olevat näppäimet tai korvaa syötteet nauhoitetuilla
+    /// # Arguments
+    /// * `_maailma` - Pelimaailma, jonka tila päivitetään
+    /// * `syotteet` - Päivityksessä käytettävät syötteet
+    /// * `_paivitysaika` - Aika, jonka verran pelimaailmaa paivitetaan
+    fn paivita(
+        &mut self,
+        _maailma: &mut Perusmaailma,
+        syotteet: &mut Syotteet,
+        _paivitysaika: &Paivitysaika,
+    ) -> Result<(), String> {
+        match &mut self.tila {
+            Nauhoitustila::Ei => (),
+            Nauhoitustila::Nauhoitus(tiedosto) => {
+                let rivi = Nauhoite::kirjoita_rivi(&syotteet.pohjassa_olevat());
+                // Tyhjennetään puskuri heti, jotta kirjoitusvirhe huomataan pelin aikana
+                writeln!(tiedosto, "{}", rivi)
+                    .and_then(|_| tiedosto.flush())
+                    .map_err(|virhe| {
+                        format!("Nauhoitteen kirjoittaminen epäonnistui: {}", virhe)
+                    })?;
+            }
+            Nauhoitustila::Toisto {
+                nauhoite,
+                syotteet: toistetut,
+            } => {
+                if self.askel == nauhoite.pituus() {
+                    println!("Nauhoite päättyi päivitykseen {}", self.askel);
+                }
+                toistetut.aseta_pohjassa_olevat(nauhoite.pohjassa(self.askel));
+                *syotteet = toistetut.clone();
+            }
+        }
+        self.askel += 1;
+        Ok(())
+    }
+}
@@ -1,25 +1,52 @@
-use sdl2::keyboard::Scancode;
+use std::rc::Rc;
+use std::time::Duration;
 
 use super::lisaa_kuvallinen_kappale;
 use super::Nopeus;
-use crate::fysiikka::{Fysiikallinen, Fysiikkakappale};
+use crate::fysiikka::Fysiikkakappale;
+use crate::maailma::elinaika::Elinaika;
+use crate::maailma::elinvoima::{Vahingoittaja, Vahinko};
 use crate::maailma::kappale::{Kappale, Muoto, Tagi::*};
-use crate::maailma::{Lisaosa, LisaosienAntaja, Pelihahmollinen, Perusmaailma};
+use crate::maailma::luku::{luku, Luku};
+use crate::maailma::{Lisaosa, LisaosienAntaja, Pelihahmollinen, Perusmaailma, RcKappale};
 use crate::paivitys::{Paivitys, Paivitysaika};
 use crate::syotteet::*;
 
 // Vakioita eri asioille
-const OIKEALLE_LIIKKUMINEN: Scancode = Scancode::Right;
-const VASEMMALLE_LIIKKUMINEN: Scancode = Scancode::Left;
-const ALAS_LIIKKUMINEN: Scancode = Scancode::Down;
-const YLOS_LIIKKUMINEN: Scancode = Scancode::Up;
-const AMPUMINEN: Scancode = Scancode::Space;
-const PELIHAHMON_NOPEUS: f32 = 120.0;
-const AMMUKSEN_NOPEUS: f32 = 260.0;
-const AMMUKSEN_LEVEYS: f32 = 5.0;
-
-/// Huolehtii pelihahmon päivityksestä
-pub struct PelihahmonPaivitys;
+const PELIHAHMON_NOPEUS: Luku = luku(120.0);
+const PELIHAHMON_KIIHTYVYYS: Luku = luku(1200.0);
+const AMMUKSEN_NOPEUS: Luku = luku(260.0);
+const AMMUKSEN_LEVEYS: Luku = luku(5.0);
+const AMMUKSEN_VAHINKO: Luku = luku(1.0);
+const AMMUKSEN_ELINAIKA: Duration = Duration::from_secs(3);
+const AMMUKSEN_KANTAMA: Luku = luku(600.0);
+
+/// Huolehtii pelihahmojen päivityksestä. Kukin pelaaja ohjaa hahmoaan omalla ohjauksellaan.
+pub struct PelihahmonPaivitys {
+    /// Pelaajien ohjaukset pelaajan numeron mukaan
+    ohjaukset: Vec<Ohjaus>,
+}
+
+impl PelihahmonPaivitys {
+    /// Luo päivityksen, jossa ensimmäinen pelaaja ohjaa nuolinäppäimillä ja toinen WASD-näppäimillä
+    pub fn new() -> Self {
+        PelihahmonPaivitys::new_ohjauksilla(vec![Ohjaus::nuolinappaimet(), Ohjaus::wasd()])
+    }
+
+    /// Luo päivityksen annetuilla ohjauksilla. Pelihahmoa, jonka pelaajalle ei ole
+    /// ohjausta, ei ohjata.
+    /// # Arguments
+    /// * `ohjaukset` - Pelaajien ohjaukset pelaajan numeron mukaan
+    pub fn new_ohjauksilla(ohjaukset: Vec<Ohjaus>) -> Self {
+        PelihahmonPaivitys { ohjaukset }
+    }
+}
+
+impl Default for PelihahmonPaivitys {
+    fn default() -> Self {
+        PelihahmonPaivitys::new()
+    }
+}
 
 impl Paivitys for PelihahmonPaivitys {
     /// Alustaa pelin
@@ -33,14 +60,12 @@ impl Paivitys for PelihahmonPaivitys {
         syotteet: &mut Syotteet,
         events: &sdl2::EventPump,
     ) {
-        syotteet.lisaa_nappain(events, OIKEALLE_LIIKKUMINEN);
-        syotteet.lisaa_nappain(events, VASEMMALLE_LIIKKUMINEN);
-        syotteet.lisaa_nappain(events, YLOS_LIIKKUMINEN);
-        syotteet.lisaa_nappain(events, ALAS_LIIKKUMINEN);
-        syotteet.lisaa_nappain(events, AMPUMINEN);
+        for ohjaus in &self.ohjaukset {
+            ohjaus.lisaa_nappaimet(syotteet, events);
+        }
     }
 
-    /// Päivittää pelihahmon tilan
+    /// Päivittää pelihahmojen tilan
     /// # Arguments
     /// * `maailma` - Pelimaailma, jonka tila päivitetään
     /// * `syotteet` - Päivityksessä käytettävät syötteet
@@ -49,67 +74,206 @@ impl Paivitys for PelihahmonPaivitys {
         &mut self,
         maailma: &mut Perusmaailma,
         syotteet: &mut Syotteet,
-        _paivitysaika: &Paivitysaika,
-    ) {
-        if let Some(pelihahmo) = maailma.anna_pelihahmo_mut() {
-            let mut x = 0.0;
-            let mut y = 0.0;
-
-            // Liikutetaan pelihahmoa
-            if syotteet.nappain_pohjassa(OIKEALLE_LIIKKUMINEN) {
-                x += PELIHAHMON_NOPEUS;
-            }
-            if syotteet.nappain_pohjassa(VASEMMALLE_LIIKKUMINEN) {
-                x -= PELIHAHMON_NOPEUS;
-            }
-            if syotteet.nappain_pohjassa(YLOS_LIIKKUMINEN) {
-                y -= PELIHAHMON_NOPEUS;
-            }
-            if syotteet.nappain_pohjassa(ALAS_LIIKKUMINEN) {
-                y += PELIHAHMON_NOPEUS;
+        paivitysaika: &Paivitysaika,
+    ) -> Result<(), String> {
+        for i in 0..maailma.pelihahmot().len() {
+            let pelihahmo = &maailma.pelihahmot()[i];
+            let hahmon_kappale = pelihahmo.anna_kappale();
+            if let Some(ohjaus) = self.ohjaukset.get(pelihahmo.anna_pelaaja()) {
+                ohjaa_pelihahmoa(maailma, i, hahmon_kappale, ohjaus, syotteet, paivitysaika);
             }
+        }
+        Ok(())
+    }
+}
 
-            let hahmon_kappale = pelihahmo.anna_kappale();
-            let pelaajan_nopeus = Nopeus::new(x, y);
+/// Liikuttaa pelihahmoa ja ampuu ohjauksen näppäinten mukaan
+/// # Arguments
+/// * `maailma` - Pelimaailma, jossa pelihahmo on
+/// * `indeksi` - Pelihahmon indeksi maailman pelihahmoissa
+/// * `hahmon_kappale` - Pelihahmon kappale
+/// * `ohjaus` - Pelihahmoa ohjaavan pelaajan näppäimet
+/// * `syotteet` - Päivityksessä käytettävät syötteet
+/// * `paivitysaika` - Päivitysaika, josta ammuksen elinaika lasketaan
+fn ohjaa_pelihahmoa(
+    maailma: &mut Perusmaailma,
+    indeksi: usize,
+    hahmon_kappale: RcKappale,
+    ohjaus: &Ohjaus,
+    syotteet: &Syotteet,
+    paivitysaika: &Paivitysaika,
+) {
+    let mut x = Luku::default();
+    let mut y = Luku::default();
 
-            if let Some(hahmon_fysiikka) = maailma.anna_fysiikka_mut(&hahmon_kappale) {
-                hahmon_fysiikka.aseta_nopeus(pelaajan_nopeus);
-            }
+    // Liikutetaan pelihahmoa
+    if syotteet.nappain_pohjassa(ohjaus.oikealle) {
+        x += PELIHAHMON_NOPEUS;
+    }
+    if syotteet.nappain_pohjassa(ohjaus.vasemmalle) {
+        x -= PELIHAHMON_NOPEUS;
+    }
+    if syotteet.nappain_pohjassa(ohjaus.ylos) {
+        y -= PELIHAHMON_NOPEUS;
+    }
+    if syotteet.nappain_pohjassa(ohjaus.alas) {
+        y += PELIHAHMON_NOPEUS;
+    }
 
-            let pelihahmo = maailma.anna_pelihahmo_mut().unwrap();
-            // Päivitetään suunta
-            pelihahmo.aseta_suunta(pelaajan_nopeus);
-
-            // Pelihahmon ampuminen
-            if syotteet.nappain_painettu(AMPUMINEN) {
-                // Lasketaan lisättävän ammuksen sijainti
-                let pelaajan_keskipiste = hahmon_kappale.borrow().keskipisteen_sijainti();
-                let pelaajan_koko = hahmon_kappale.borrow().muoto.koko();
-                let ammuksen_suunta = pelihahmo.anna_suunta();
-
-                let ammuksen_muoto = Muoto::Ympyra(AMMUKSEN_LEVEYS);
-                let muutos_kerroin = pelaajan_koko.0 / 2.0 + ammuksen_muoto.koko().0 / 2.0 + 10.0;
-
-                let ammuksen_sijainti = pelaajan_keskipiste + ammuksen_suunta * muutos_kerroin;
-
-                // Lisätään ammus pelaajan katsomissuuntaan vähän matkan päähän
-                let r_kappale = lisaa_kuvallinen_kappale(
-                    maailma,
-                    Kappale::new_keskipisteella(
-                        ammuksen_muoto,
-                        ammuksen_sijainti.x,
-                        ammuksen_sijainti.y,
-                        Ammus,
-                    ),
-                    "ammus".to_string(),
-                );
-
-                // Lisätään ammukselle fysiikka ja ammuksen alkunopeus
-                maailma.lisaa_fysiikkakappale(Fysiikkakappale::new(
-                    ammuksen_suunta * AMMUKSEN_NOPEUS,
-                    r_kappale,
-                ));
-            }
+    let pelaajan_nopeus = Nopeus::new(x, y);
+
+    // Pelihahmo kiihtyy näppäinten suuntaan, joten törmäysten antama vauhti ei katoa heti
+    if let Some(hahmon_fysiikka) = maailma.anna_fysiikka_mut(&hahmon_kappale) {
+        hahmon_fysiikka.aseta_tavoitenopeus(pelaajan_nopeus, PELIHAHMON_KIIHTYVYYS);
+    }
+
+    let pelihahmo = &mut maailma.pelihahmot_mut()[indeksi];
+    // Päivitetään suunta
+    pelihahmo.aseta_suunta(pelaajan_nopeus);
+
+    // Pelihahmon ampuminen
+    if syotteet.nappain_painettu(ohjaus.ampuminen) {
+        // Lasketaan lisättävän ammuksen sijainti
+        let pelaajan_keskipiste = hahmon_kappale.borrow().keskipisteen_sijainti();
+        let pelaajan_koko = hahmon_kappale.borrow().muoto.koko();
+        let ammuksen_suunta = pelihahmo.anna_suunta();
+
+        let ammuksen_muoto = Muoto::Ympyra(AMMUKSEN_LEVEYS);
+        let muutos_kerroin = pelaajan_koko.0 / 2.0 + ammuksen_muoto.koko().0 / 2.0 + 10.0;
+
+        let ammuksen_sijainti = pelaajan_keskipiste + ammuksen_suunta * muutos_kerroin;
+
+        // Lisätään ammus pelaajan katsomissuuntaan vähän matkan päähän
+        let r_kappale = lisaa_kuvallinen_kappale(
+            maailma,
+            Kappale::new_keskipisteella(
+                ammuksen_muoto,
+                ammuksen_sijainti.x,
+                ammuksen_sijainti.y,
+                Ammus,
+            ),
+            "ammus".to_string(),
+        );
+
+        // Ammus katoaa, jos se ei osu mihinkään riittävän ajan tai matkan kuluessa
+        let mut elinaika = Elinaika::new(
+            Rc::clone(&r_kappale),
+            *paivitysaika.kokonais_pelin_aika + AMMUKSEN_ELINAIKA,
+        );
+        elinaika.aseta_kantama(Some(AMMUKSEN_KANTAMA));
+        maailma.lisaa_lisaosa(elinaika);
+
+        // Ammus vahingoittaa kappaleita, joihin se osuu
+        maailma.lisaa_lisaosa(Vahingoittaja::new(
+            Rc::clone(&r_kappale),
+            Vahinko::new(AMMUKSEN_VAHINKO),
+        ));
+
+        // Lisätään ammukselle fysiikka ja ammuksen alkunopeus. Ammus on nopea, jotta se
+        // ei mene ohuiden kappaleiden läpi pitkissäkään päivityksissä.
+        let mut fysiikka = Fysiikkakappale::new(ammuksen_suunta * AMMUKSEN_NOPEUS, r_kappale);
+        fysiikka.aseta_nopea(true);
+        maailma.lisaa_fysiikkakappale(fysiikka);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::keyboard::Scancode;
+
+    use super::*;
+    use crate::maailma::pelihahmo::Pelihahmo;
+
+    /// Maailma, jossa on pelaajien 0, 1 ja 2 hahmot rivissä 100 yksikön välein
+    fn kolmen_pelaajan_maailma() -> Perusmaailma {
+        let mut maailma = Perusmaailma::new();
+        for pelaaja in 0..3 {
+            let kappale = maailma.lisaa_kappale(Kappale::new_keskipisteella(
+                Muoto::Nelio(luku(10.0), luku(10.0)),
+                luku(100.0 * pelaaja as f32),
+                luku(0.0),
+                Pelaaja,
+            ));
+            maailma.lisaa_fysiikkakappale(Fysiikkakappale::new(
+                Nopeus::default(),
+                Rc::clone(&kappale),
+            ));
+            let mut hahmo = Pelihahmo::new(kappale);
+            hahmo.aseta_pelaaja(pelaaja);
+            maailma.lisaa_pelihahmo(hahmo);
         }
+        maailma
+    }
+
+    /// Päivittää pelihahmot, kun annetut näppäimet ovat pohjassa
+    fn paivita(maailma: &mut Perusmaailma, pohjassa: &[Scancode]) {
+        let mut syotteet = Syotteet::new();
+        syotteet.aseta_pohjassa_olevat(pohjassa);
+        let aika = Duration::from_millis(16);
+        PelihahmonPaivitys::new()
+            .paivita(maailma, &mut syotteet, &Paivitysaika::new(&aika, &aika))
+            .unwrap();
+    }
+
+    /// Antaa pelaajien hahmojen tavoitenopeudet
+    fn tavoitteet(maailma: &Perusmaailma) -> Vec<Option<Nopeus>> {
+        maailma
+            .pelihahmot()
+            .iter()
+            .map(|x| {
+                let fysiikka = maailma.anna_fysiikka(&x.anna_kappale()).unwrap();
+                fysiikka.anna_tavoitenopeus().map(|x| x.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn kukin_ohjaus_liikuttaa_vain_omaa_hahmoaan() {
+        let mut maailma = kolmen_pelaajan_maailma();
+        paivita(&mut maailma, &[Scancode::Right, Scancode::W]);
+        // Kolmannella pelaajalla ei ole ohjausta, joten sen hahmoa ei ohjata
+        assert_eq!(
+            tavoitteet(&maailma),
+            vec![
+                Some(Nopeus::new(PELIHAHMON_NOPEUS, luku(0.0))),
+                Some(Nopeus::new(luku(0.0), -PELIHAHMON_NOPEUS)),
+                None
+            ]
+        );
+        let suunnat: Vec<Nopeus> = maailma
+            .pelihahmot()
+            .iter()
+            .map(|x| x.anna_suunta())
+            .collect();
+        assert_eq!(suunnat[1], Nopeus::new(luku(0.0), luku(-1.0)));
+
+        paivita(&mut maailma, &[Scancode::A]);
+        assert_eq!(
+            tavoitteet(&maailma)[..2],
+            [
+                Some(Nopeus::default()),
+                Some(Nopeus::new(-PELIHAHMON_NOPEUS, luku(0.0)))
+            ]
+        );
+    }
+
+    #[test]
+    fn kukin_pelaaja_ampuu_omasta_hahmostaan() {
+        let mut maailma = kolmen_pelaajan_maailma();
+        // Toinen pelaaja katsoo ylös ja ampuu, ensimmäinen ei ammu
+        paivita(&mut maailma, &[Scancode::W]);
+        paivita(&mut maailma, &[Scancode::LShift]);
+        assert_eq!(maailma.kappaleet().len(), 4);
+        let ammus = maailma.kappaleet()[3].borrow();
+        assert!(ammus.tagit.sisaltaa(Ammus));
+        assert_eq!(ammus.keskipisteen_sijainti().x, luku(100.0));
+        assert!(ammus.keskipisteen_sijainti().y < luku(0.0));
+        drop(ammus);
+
+        paivita(&mut maailma, &[Scancode::Space]);
+        assert_eq!(maailma.kappaleet().len(), 5);
+        let ammus = maailma.kappaleet()[4].borrow();
+        assert!(ammus.keskipisteen_sijainti().x > luku(0.0));
+        assert!(ammus.keskipisteen_sijainti().x < luku(100.0));
     }
 }
@@ -0,0 +1,77 @@
+use sdl2::keyboard::Scancode;
+
+use crate::maailma::Perusmaailma;
+use crate::paivitys::{Paivitys, Paivitysaika};
+use crate::syotteet::*;
+use crate::tallennus::{lataa_tiedostosta, tallenna_tiedostoon, Lajit};
+
+// Vakioita eri asioille
+const PIKATALLENNUS: Scancode = Scancode::F5;
+const PIKATALLENNUKSEN_TIEDOSTO: &str = "pikatallennus.txt";
+
+/// Huolehtii pelimaailman pikatallennuksesta ja tallennuksen lataamisesta pelin alussa.
+/// Tulee olla viimeisenä alustettava päivitys, koska lataus korvaa koko maailman sisällön.
+pub struct TallennuksenPaivitys {
+    /// Tiedosto, josta maailma ladataan alustuksessa, jos jotakin
+    ladattava: Option<String>,
+    /// Rekisterit, joiden avulla tekoälyt ja animaatiot ladataan
+    lajit: Lajit,
+}
+
+impl TallennuksenPaivitys {
+    /// Luo uuden tallennuksen päivityksen
+    /// # Arguments
+    /// * `ladattava` - Tiedosto, josta maailma ladataan alustuksessa, jos jotakin
+    pub fn new(ladattava: Option<String>) -> Self {
+        TallennuksenPaivitys {
+            ladattava,
+            lajit: Default::default(),
+        }
+    }
+}
+
+impl Paivitys for TallennuksenPaivitys {
+    /// Alustaa pelin ja lataa maailman tallennuksesta, jos sellainen on annettu
+    /// # Arguments
+    /// * `maailma` - Pelimaailma, joka alustetaan
+    /// * `syotteet` - Alustettavat syotteet
+    /// * `events` - Sdl:n osa, jolta voidaan kysyä tapahtumia kuten näppäinten painalluksia
+    fn alusta(
+        &mut self,
+        maailma: &mut Perusmaailma,
+        syotteet: &mut Syotteet,
+        events: &sdl2::EventPump,
+    ) {
+        syotteet.lisaa_nappain(events, PIKATALLENNUS);
+        if let Some(polku) = &self.ladattava {
+            match lataa_tiedostosta(maailma, polku, &self.lajit) {
+                Ok(()) => println!("Ladattiin tallennus {}", polku),
+                Err(virhe) => println!("{}", virhe),
+            }
+        }
+    }
+
+    /// Tallentaa maailman, jos pikatallennusnäppäintä on painettu
+    /// # Arguments
+    /// * `maailma` - Pelimaailma, joka tallennetaan
+    /// * `syotteet` - Päivityksessä käytettävät syötteet
+    /// * `paivitysaika` - Aika, jonka verran pelimaailmaa paivitetaan
+    fn paivita(
+        &mut self,
+        maailma: &mut Perusmaailma,
+        syotteet: &mut Syotteet,
+        paivitysaika: &Paivitysaika,
+    ) -> Result<(), String> {
+        if syotteet.nappain_painettu(PIKATALLENNUS) {
+            match tallenna_tiedostoon(
+                maailma,
+                *paivitysaika.kokonais_pelin_aika,
+                PIKATALLENNUKSEN_TIEDOSTO,
+            ) {
+                Ok(()) => println!("Tallennettiin {}", PIKATALLENNUKSEN_TIEDOSTO),
+                Err(virhe) => println!("{}", virhe),
+            }
+        }
+        Ok(())
+    }
+}
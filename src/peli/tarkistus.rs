@@ -0,0 +1,203 @@
+//! Pelin ajaminen ilman ikkunaa nauhoitetuilla syötteillä. Ajamalla saman nauhoitteen
+//! useasti eri silmukoiden päivitysjärjestyksillä voidaan tarkistaa, että simulaatio on
+//! deterministinen.
+use std::fmt;
+use std::time::Duration;
+
+use super::pelihahmonpaivitys::PelihahmonPaivitys;
+use super::pelinpaivitys::{
+    AnimaatioidenPaivitys, FysiikanPaivitys, SpawnerinPaivitys, TekoalynPaivitys,
+};
+use super::tasonpaivitys::TasonPaivitys;
+use crate::maailma::Perusmaailma;
+use crate::paivitys::{Paivitys, Paivitysaika, YhdistettyPaivitys};
+use crate::syotteet::{Nauhoite, Syotteet};
+use crate::tiiviste::{ensimmainen_poikkeama, Poikkeama, Tiivistehistoria, Tilatiiviste};
+
+/// Silmukka, jonka päivitysjärjestystä nauhoitteen ajo jäljittelee
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Silmukkatyyppi {
+    /// Kaikki päivitykset tehdään kerran jokaisella askeleella kuten silmukassa 2
+    Saannollinen,
+    /// Säännölliset päivitykset tehdään kiinteällä askeleella ja epäsäännölliset vaihtelevan
+    /// pituisilla kuvilla kuten silmukoissa 3-5. Maailma muistaa kappaleiden aiemmat tilat.
+    Interpoloiva,
+}
+
+impl fmt::Display for Silmukkatyyppi {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Silmukkatyyppi::Saannollinen => write!(f, "säännöllinen"),
+            Silmukkatyyppi::Interpoloiva => write!(f, "interpoloiva"),
+        }
+    }
+}
+
+/// Interpoloivan ajon kuvien pituudet prosentteina päivitysvälistä. Kuvan aikana tehdään
+/// siten välillä nolla, välillä yksi ja välillä kaksi säännöllistä päivitystä.
+const KUVIEN_PITUUDET: [u32; 4] = [40, 170, 90, 100];
+
+/// Ajaa tasoa nauhoitteen verran kiinteällä päivitysvälillä samoilla päivityksillä kuin
+/// annettu silmukka ja antaa maailman tilan tiivisteet päivityksittäin
+/// # Arguments
+/// * `taso` - Tasotiedoston polku
+/// * `nauhoite` - Toistettavat syötteet
+/// * `paivitys_tiheys` - Kuinka monta kertaa sekunnissa päivitetään
+/// * `silmukka` - Silmukka, jonka päivitysjärjestystä käytetään
+pub fn aja_nauhoite(
+    taso: &str,
+    nauhoite: &Nauhoite,
+    paivitys_tiheys: u32,
+    silmukka: Silmukkatyyppi,
+) -> Result<Tiivistehistoria, String> {
+    let paivitysvali = Duration::new(0, 1_000_000_000 / paivitys_tiheys);
+    let mut tason_paivitys = TasonPaivitys::new(taso)?;
+    let mut maailma = match silmukka {
+        Silmukkatyyppi::Saannollinen => Perusmaailma::new(),
+        Silmukkatyyppi::Interpoloiva => Perusmaailma::new_interpoloiva(),
+    };
+    tason_paivitys.lataa_taso(&mut maailma)?;
+
+    let mut spawnerin_paivitys = SpawnerinPaivitys::new();
+    let mut tekoalyn_paivitys = TekoalynPaivitys;
+    let mut pelihahmon_paivitys = PelihahmonPaivitys::new();
+    let mut fysiikan_paivitys = FysiikanPaivitys;
+    let mut animaatioiden_paivitys = AnimaatioidenPaivitys;
+
+    let mut syotteet = Syotteet::new();
+    let mut historia = Tiivistehistoria::new();
+    let mut kokonaisaika_pelin_alusta = maailma.anna_pelin_aika();
+    match silmukka {
+        Silmukkatyyppi::Saannollinen => {
+            let mut paivitys = YhdistettyPaivitys::new(vec![
+                &mut tason_paivitys,
+                &mut spawnerin_paivitys,
+                &mut tekoalyn_paivitys,
+                &mut pelihahmon_paivitys,
+                &mut fysiikan_paivitys,
+                &mut animaatioiden_paivitys,
+            ]);
+            for askel in 0..nauhoite.pituus() {
+                kokonaisaika_pelin_alusta += paivitysvali;
+                syotteet.aseta_pohjassa_olevat(nauhoite.pohjassa(askel));
+                paivitys.paivita(
+                    &mut maailma,
+                    &mut syotteet,
+                    &Paivitysaika::new(&paivitysvali, &kokonaisaika_pelin_alusta),
+                )?;
+                historia.lisaa(Tilatiiviste::new(
+                    askel as u64,
+                    &maailma,
+                    kokonaisaika_pelin_alusta,
+                ));
+                maailma.poista_poistettavat();
+            }
+        }
+        Silmukkatyyppi::Interpoloiva => {
+            let mut saannollinen_paivitys = YhdistettyPaivitys::new(vec![
+                &mut tason_paivitys,
+                &mut spawnerin_paivitys,
+                &mut tekoalyn_paivitys,
+                &mut pelihahmon_paivitys,
+                &mut fysiikan_paivitys,
+            ]);
+            let mut epasaannollinen_paivitys =
+                YhdistettyPaivitys::new(vec![&mut animaatioiden_paivitys]);
+            // Kuvien aika kulkee erikseen kuten interpoloivassa silmukassa
+            let mut kuvien_aika = kokonaisaika_pelin_alusta;
+            let mut aikaa_seuraavaan_saannolliseen_paivitykseen = paivitysvali;
+            let mut askel = 0;
+            for kuva in 0.. {
+                while aikaa_seuraavaan_saannolliseen_paivitykseen >= paivitysvali {
+                    if askel == nauhoite.pituus() {
+                        return Ok(historia);
+                    }
+                    kokonaisaika_pelin_alusta += paivitysvali;
+                    syotteet.aseta_pohjassa_olevat(nauhoite.pohjassa(askel));
+                    saannollinen_paivitys.paivita(
+                        &mut maailma,
+                        &mut syotteet,
+                        &Paivitysaika::new(&paivitysvali, &kokonaisaika_pelin_alusta),
+                    )?;
+                    historia.lisaa(Tilatiiviste::new(
+                        askel as u64,
+                        &maailma,
+                        kokonaisaika_pelin_alusta,
+                    ));
+                    maailma.paivita_kappalemuistia(kokonaisaika_pelin_alusta);
+                    maailma.poista_poistettavat();
+                    aikaa_seuraavaan_saannolliseen_paivitykseen -= paivitysvali;
+                    askel += 1;
+                }
+
+                let kuvan_pituus =
+                    paivitysvali * KUVIEN_PITUUDET[kuva % KUVIEN_PITUUDET.len()] / 100;
+                kuvien_aika += kuvan_pituus;
+                aikaa_seuraavaan_saannolliseen_paivitykseen += kuvan_pituus;
+                epasaannollinen_paivitys.paivita(
+                    &mut maailma,
+                    &mut syotteet,
+                    &Paivitysaika::new(&kuvan_pituus, &kuvien_aika),
+                )?;
+                maailma.poista_poistettavat();
+            }
+        }
+    }
+    Ok(historia)
+}
+
+/// Ajaa nauhoitteen kahdesti säännöllisellä silmukalla ja kerran interpoloivalla silmukalla
+/// samalla päivitysvälillä. Antaa ensimmäisen ajon, joka eroaa ensimmäisestä säännöllisestä
+/// ajosta, ja kohdan, jossa ajot eroavat.
+/// # Arguments
+/// * `taso` - Tasotiedoston polku
+/// * `nauhoite` - Toistettavat syötteet
+/// * `paivitys_tiheys` - Kuinka monta kertaa sekunnissa päivitetään
+pub fn tarkista_nauhoite(
+    taso: &str,
+    nauhoite: &Nauhoite,
+    paivitys_tiheys: u32,
+) -> Result<Option<(Silmukkatyyppi, Poikkeama)>, String> {
+    let ensimmainen = aja_nauhoite(
+        taso,
+        nauhoite,
+        paivitys_tiheys,
+        Silmukkatyyppi::Saannollinen,
+    )?;
+    for silmukka in &[Silmukkatyyppi::Saannollinen, Silmukkatyyppi::Interpoloiva] {
+        let toinen = aja_nauhoite(taso, nauhoite, paivitys_tiheys, *silmukka)?;
+        if let Some(poikkeama) = ensimmainen_poikkeama(&ensimmainen, &toinen) {
+            return Ok(Some((*silmukka, poikkeama)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::keyboard::Scancode;
+
+    #[test]
+    fn silmukat_tuottavat_saman_pelin_kulun() {
+        // Pelaaja liikkuu ja ampuu, jotta törmäyksiä, poistoja ja uusia kappaleita syntyy
+        let mut nauhoite = Nauhoite::new();
+        for askel in 0..240 {
+            let mut pohjassa = vec![if askel % 80 < 40 {
+                Scancode::Right
+            } else {
+                Scancode::Up
+            }];
+            if askel % 15 < 2 {
+                pohjassa.push(Scancode::Space);
+            }
+            nauhoite.lisaa(pohjassa);
+        }
+        for taso in &["tasot/perustaso.txt", "tasot/kaksinpeli.txt"] {
+            let poikkeama = tarkista_nauhoite(taso, &nauhoite, 60).unwrap();
+            if let Some((silmukka, poikkeama)) = poikkeama {
+                panic!("{}: {} ({})", taso, poikkeama, silmukka);
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs;
+
+use crate::maailma::Perusmaailma;
+use crate::paivitys::{Paivitys, Paivitysaika};
+use crate::syotteet::*;
+use crate::tallennus::{lataa, Lajit};
+
+/// Taso, jota käytetään, jos muuta ei ole annettu
+pub const OLETUSTASO: &str = "tasot/perustaso.txt";
+
+/// Lataa pelin tason tasotiedostosta pelin alussa. Tulee olla ensimmäisenä alustettava
+/// päivitys, koska lataus korvaa koko maailman sisällön.
+pub struct TasonPaivitys {
+    /// Tasotiedoston polku
+    polku: String,
+    /// Tasotiedoston sisältö
+    teksti: String,
+    /// Rekisterit, joiden avulla tekoälyt ja animaatiot ladataan
+    lajit: Lajit,
+}
+
+impl TasonPaivitys {
+    /// Lukee annetun tasotiedoston ja tarkistaa sen. Virheellinen tiedosto huomataan siis
+    /// jo ennen pelin käynnistämistä.
+    /// # Arguments
+    /// * `polku` - Tasotiedoston polku
+    pub fn new(polku: &str) -> Result<Self, String> {
+        let teksti = fs::read_to_string(polku)
+            .map_err(|e| format!("Tasotiedoston {} lukeminen epäonnistui: {}", polku, e))?;
+        let lajit = Default::default();
+        lataa(&mut Perusmaailma::new(), &teksti, &lajit)
+            .map_err(|e| format!("Virheellinen tasotiedosto {}: {}", polku, e))?;
+        Ok(TasonPaivitys {
+            polku: polku.to_string(),
+            teksti,
+            lajit,
+        })
+    }
+
+    /// Lataa tason annettuun maailmaan. Maailman aiempi sisältö poistetaan.
+    /// # Arguments
+    /// * `maailma` - Maailma, johon taso ladataan
+    pub fn lataa_taso(&self, maailma: &mut Perusmaailma) -> Result<(), String> {
+        lataa(maailma, &self.teksti, &self.lajit)
+            .map_err(|e| format!("Virheellinen tasotiedosto {}: {}", self.polku, e))
+    }
+}
+
+impl Paivitys for TasonPaivitys {
+    /// Alustaa pelin lataamalla tason maailmaan
+    /// # Arguments
+    /// * `maailma` - Pelimaailma, joka alustetaan
+    /// * `_syotteet` - Alustettavat syotteet
+    /// * `_events` - Sdl:n osa, jolta voidaan kysyä tapahtumia kuten näppäinten painalluksia
+    fn alusta(
+        &mut self,
+        maailma: &mut Perusmaailma,
+        _syotteet: &mut Syotteet,
+        _events: &sdl2::EventPump,
+    ) {
+        match self.lataa_taso(maailma) {
+            Ok(()) => println!("Ladattiin taso {}", self.polku),
+            Err(virhe) => println!("{}", virhe),
+        }
+    }
+
+    /// Aloittaa tason alusta, jos peli on päättynyt
+    /// # Arguments
+    /// * `maailma` - Pelimaailma, jonka tila päivitetään
+    /// * `_syotteet` - Päivityksessä käytettävät syötteet
+    /// * `paivitysaika` - Aika, jonka verran pelimaailmaa paivitetaan
+    fn paivita(
+        &mut self,
+        maailma: &mut Perusmaailma,
+        _syotteet: &mut Syotteet,
+        paivitysaika: &Paivitysaika,
+    ) -> Result<(), String> {
+        if maailma.onko_peli_paattynyt() {
+            println!("Peli päättyi. Aloitetaan taso {} alusta.", self.polku);
+            // Tason kappaleiden muisti alkaa nykyisestä pelin ajasta, koska tasotiedostossa ei ole aikaa
+            maailma.aseta_pelin_aika(*paivitysaika.kokonais_pelin_aika);
+            if let Err(virhe) = self.lataa_taso(maailma) {
+                println!("{}", virhe);
+            }
+        }
+        Ok(())
+    }
+}
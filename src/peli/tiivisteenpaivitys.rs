@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::maailma::Perusmaailma;
+use crate::paivitys::{Paivitys, Paivitysaika};
+use crate::syotteet::*;
+use crate::tiiviste::Tilatiiviste;
+
+/// Kirjoittaa maailman tilan tiivisteen tiedostoon jokaisen päivityksen jälkeen. Tulee
+/// olla säännöllisten päivitysten viimeisenä, jotta tiiviste kuvaa koko päivityksen.
+pub struct TiivisteenPaivitys {
+    /// Tiedosto, johon tiivisteet kirjoitetaan. Jos ei mitään, niin tiivisteitä ei lasketa.
+    tiedosto: Option<BufWriter<File>>,
+    /// Seuraavan päivityksen järjestysnumero
+    askel: u64,
+}
+
+impl TiivisteenPaivitys {
+    /// Luo uuden tiivisteen päivityksen
+    /// # Arguments
+    /// * `polku` - Tiedosto, johon tiivisteet kirjoitetaan. Jos ei mitään, niin päivitys ei tee mitään.
+    pub fn new(polku: Option<String>) -> Result<Self, String> {
+        let tiedosto = match polku {
+            Some(polku) => Some(BufWriter::new(File::create(&polku).map_err(|e| {
+                format!("Tiivistetiedoston {} luominen epäonnistui: {}", polku, e)
+            })?)),
+            None => None,
+        };
+        Ok(TiivisteenPaivitys { tiedosto, askel: 0 })
+    }
+}
+
+impl Paivitys for TiivisteenPaivitys {
+    /// Alustaa pelin
+    /// # Arguments
+    /// * `_maailma` - Pelimaailma, joka alustetaan
+    /// * `_syotteet` - Alustettavat syotteet
+    /// * `_events` - Sdl:n osa, jolta voidaan kysyä tapahtumia kuten näppäinten painalluksia
+    fn alusta(
+        &mut self,
+        _maailma: &mut Perusmaailma,
+        _syotteet: &mut Syotteet,
+        _events: &sdl2::EventPump,
+    ) {
+    }
+
+    /// Kirjoittaa maailman tilan tiivisteen tiedostoon
+    /// # Arguments
+    /// * `maailma` - Pelimaailma, jonka tila tiivistetään
+    /// * `_syotteet` - Päivityksessä käytettävät syötteet
+    /// * `paivitysaika` - Aika, jonka verran pelimaailmaa paivitetaan
+    fn paivita(
+        &mut self,
+        maailma: &mut Perusmaailma,
+        _syotteet: &mut Syotteet,
+        paivitysaika: &Paivitysaika,
+    ) -> Result<(), String> {
+        if let Some(tiedosto) = &mut self.tiedosto {
+            let tiiviste =
+                Tilatiiviste::new(self.askel, maailma, *paivitysaika.kokonais_pelin_aika);
+            // Tyhjennetään puskuri heti, jotta kirjoitusvirhe huomataan pelin aikana
+            writeln!(tiedosto, "{}", tiiviste.kirjoita())
+                .and_then(|_| tiedosto.flush())
+                .map_err(|virhe| format!("Tiivisteen kirjoittaminen epäonnistui: {}", virhe))?;
+        }
+        self.askel += 1;
+        Ok(())
+    }
+}
@@ -2,14 +2,17 @@
 //! Peli voidaan esittää esimerkiksi piirtämällä näytölle kuva tai
 //! lähettämällä pelimaailman tila verkon yli asiakkaalle.
 use sdl2::pixels::Color;
-use sdl2::rect::Rect;
+use sdl2::rect::{Point, Rect};
 use sdl2::render::Canvas;
 use sdl2::render::Texture;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::iter::Peekable;
 use std::rc::Rc;
 
-use crate::maailma::kappale::{Kappale, Muoto};
+use crate::maailma::kappale::{Kappale, Muoto, Rajaus};
+use crate::maailma::luku::{luku, Skalaari};
 use crate::maailma::vektori::Vektori;
 use crate::maailma::Lisaosa;
 
@@ -23,6 +26,11 @@ pub trait Piirtovalmius {
     /// # Arguments
     /// * `kameran_sijainti` - Piirtavan kameran sijainti
     fn aseta_kameran_sijainti(&mut self, kameran_sijainti: Vektori) -> Result<(), String>;
+    /// Kohdistaa kameran annetun alueen keskelle ja loitontaa tarvittaessa niin, että koko
+    /// alue mahtuu kuvaan. Kamera ei koskaan zoomaa asetettua zoomia lähemmäs.
+    /// # Arguments
+    /// * `alue` - Pelimaailman alue, jonka tulee näkyä kuvassa
+    fn sovita_kamera(&mut self, alue: &Rajaus) -> Result<(), String>;
     /// Asettaa kameran zoomin
     /// # Arguments
     /// * `kameran_zoomi` - Kuinka paljon kamera zoomaa kuvaa. Suhteellinen luku, jolloin 1.0 on ei-zoomia. Suurempi luku zoomaa.
@@ -31,10 +39,13 @@ pub trait Piirtovalmius {
     /// # Arguments
     /// * `etaisyys` - Kuinka paljon kamera voi jäädä jälkeen seurattavasta. Suhteellinen arvo väliltä 0-1. Sisältää x ja y koordinaatin erikseen.
     fn aseta_kameran_seurauksen_etaisyys(&mut self, etaisyys: (f32, f32)) -> Result<(), String>;
+    /// Antaa pelimaailman alueen, joka näkyy kamerassa
+    fn anna_nakyva_alue(&self) -> Result<Rajaus, String>;
 }
 
 pub trait ValiaikaistenPiirtaja: Piirtovalmius {
-    /// Esittää pelitilan käyttäjälle jollain tavalla.
+    /// Esittää pelitilan käyttäjälle jollain tavalla. Kappaleet piirretään kerroksittain
+    /// ja saman kerroksen kappaleet listan järjestyksessä.
     /// # Arguments
     /// * `piirrettavat` - Lista piirrettävistä kappaleista
     fn piirra_kappaleista(
@@ -44,41 +55,69 @@ pub trait ValiaikaistenPiirtaja: Piirtovalmius {
 }
 
 pub trait MaailmanPiirtaja: Piirtovalmius {
-    /// Esittää pelitilan käyttäjälle jollain tavalla.
+    /// Esittää pelitilan käyttäjälle jollain tavalla. Maailman kappaleet ja väliaikaiset
+    /// kappaleet piirretään kerroksittain. Samalla kerroksella väliaikaiset piirretään päälle.
     /// # Arguments
     /// * `maailma` - Esitettävä pelimaailma
     fn piirra_maailma(&mut self, maailma: &PiirrettavaMaailma) -> Result<(), String>;
 }
 
 pub trait PiirrettavaMaailma {
-    /// Piirrettävät kappaleet maailmassa
+    /// Piirrettävät kappaleet maailmassa piirtokerroksen mukaan järjestettynä
     /// # Arguments
-    /// * `sijainti` - Ilmoittaa mistä päin maailmaa halutaan piirrettävät kappaleet
+    /// * `alue` - Ilmoittaa mistä päin maailmaa halutaan piirrettävät kappaleet
     fn piirrettavat<'a>(
         &'a self,
-        sijainti: Vektori,
+        alue: &Rajaus,
     ) -> Box<Iterator<Item = &'a PiirrettavaKappale> + 'a>;
 
-    /// Antaa kameran sijainnin pelimaailmassa, jos maailma haluaa ehdottaa jotakin
-    fn anna_kameran_sijainti(&self) -> Option<Vektori>;
+    /// Väliaikaiset kappaleet kuten animaatiot, jotka piirretään maailman kappaleiden lomaan
+    fn valiaikaiset(&self) -> &[ValiaikainenPiirrettavaKappale];
+
+    /// Antaa alueen, jonka kameran tulisi näyttää, jos maailma haluaa ehdottaa jotakin
+    fn anna_kameran_alue(&self) -> Option<Rajaus>;
 }
 
 type RcKappale = Rc<RefCell<Kappale>>;
 
+/// Kuinka moneen osaan kaaret jaetaan piirrettäessä
+const KAAREN_PALOJA: usize = 8;
+/// Kuinka paljon tilaa kameraan sovitetun alueen ympärille jätetään pelimaailman yksiköissä
+const KAMERAN_REUNUS: f32 = 120.0;
+
+/// Kerros, jolle kappale piirretään. Myöhemmät kerrokset piirretään aiempien päälle.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Piirtokerros {
+    /// Tausta, joka piirretään kaiken alle
+    Tausta,
+    /// Pelimaailman tavalliset kappaleet
+    #[default]
+    Maailma,
+    /// Efektit kuten animaatiot
+    Efektit,
+    /// Käyttöliittymä, joka piirretään kaiken päälle
+    Kayttoliittyma,
+}
+
 /// Kappale, joka voidaan piirtää
 pub struct PiirrettavaKappale {
     /// Piirrettävä kappale
     kappale: RcKappale,
     /// Millä tavalla piirtäminen tehdään
     piirtotapa: Piirtotapa,
+    /// Kerros, jolle kappale piirretään
+    kerros: Piirtokerros,
 }
 
 /// Kappale, joka piirretään interpoloimalla kahden muistin välillä
+#[derive(Clone)]
 pub struct ValiaikainenPiirrettavaKappale {
     /// Piirrettävä kappale
     kappale: Kappale,
     /// Millä tavalla piirtäminen tehdään
     piirtotapa: Piirtotapa,
+    /// Kerros, jolle kappale piirretään
+    kerros: Piirtokerros,
 }
 
 impl PiirrettavaKappale {
@@ -87,15 +126,35 @@ impl PiirrettavaKappale {
     /// * `kappale` - Piirrettävä kappale
     /// * `piirtotapa` - Tapa, jolla kappale piirretään
     pub fn new(kappale: RcKappale, piirtotapa: Piirtotapa) -> Self {
+        PiirrettavaKappale::new_kerroksella(kappale, piirtotapa, Default::default())
+    }
+
+    /// Luo uuden piirrettävän kappaleen annetulle kerrokselle
+    /// # Arguments
+    /// * `kappale` - Piirrettävä kappale
+    /// * `piirtotapa` - Tapa, jolla kappale piirretään
+    /// * `kerros` - Kerros, jolle kappale piirretään
+    pub fn new_kerroksella(
+        kappale: RcKappale,
+        piirtotapa: Piirtotapa,
+        kerros: Piirtokerros,
+    ) -> Self {
         PiirrettavaKappale {
-            kappale: kappale,
-            piirtotapa: piirtotapa,
+            kappale,
+            piirtotapa,
+            kerros,
         }
     }
 
     pub fn anna_piirtotapa(&self) -> &Piirtotapa {
         &self.piirtotapa
     }
+
+    /// Antaa kerroksen, jolle kappale piirretään. Kerrosta vaihdetaan maailman kautta,
+    /// jotta maailma pitää piirtojärjestyksen ajan tasalla.
+    pub fn anna_kerros(&self) -> Piirtokerros {
+        self.kerros
+    }
 }
 
 impl ValiaikainenPiirrettavaKappale {
@@ -104,15 +163,35 @@ impl ValiaikainenPiirrettavaKappale {
     /// * `kappale` - Piirrettävä kappale
     /// * `piirtotapa` - Tapa, jolla kappale piirretään
     pub fn new(kappale: Kappale, piirtotapa: Piirtotapa) -> Self {
+        ValiaikainenPiirrettavaKappale::new_kerroksella(kappale, piirtotapa, Default::default())
+    }
+
+    /// Luo uuden piirrettävän kappaleen annetulle kerrokselle
+    /// # Arguments
+    /// * `kappale` - Piirrettävä kappale
+    /// * `piirtotapa` - Tapa, jolla kappale piirretään
+    /// * `kerros` - Kerros, jolle kappale piirretään
+    pub fn new_kerroksella(kappale: Kappale, piirtotapa: Piirtotapa, kerros: Piirtokerros) -> Self {
         ValiaikainenPiirrettavaKappale {
-            kappale: kappale,
-            piirtotapa: piirtotapa,
+            kappale,
+            piirtotapa,
+            kerros,
         }
     }
 
     pub fn anna_piirtotapa(&self) -> &Piirtotapa {
         &self.piirtotapa
     }
+
+    /// Antaa kerroksen, jolle kappale piirretään
+    pub fn anna_kerros(&self) -> Piirtokerros {
+        self.kerros
+    }
+
+    /// Antaa piirrettävän kappaleen
+    pub fn anna_kappale(&self) -> &Kappale {
+        &self.kappale
+    }
 }
 
 /// Piirtämisessä käytettävä tapa
@@ -141,10 +220,13 @@ pub trait Piirrettava {
     fn piirra(
         &self,
         canvas: &mut Canvas<sdl2::video::Window>,
-        kameran_aiheuttama_muutos: Vektori,
+        kameran_aiheuttama_muutos: Vektori<f32>,
         kameran_zoomaus: f32,
         tekstuurit: &HashMap<String, Texture>,
     ) -> Result<(), String>;
+
+    /// Antaa kerroksen, jolle kohde piirretään
+    fn kerros(&self) -> Piirtokerros;
 }
 
 impl Kappale {
@@ -156,33 +238,73 @@ impl Kappale {
     fn piirra(
         &self,
         canvas: &mut Canvas<sdl2::video::Window>,
-        kameran_aiheuttama_muutos: Vektori,
+        kameran_aiheuttama_muutos: Vektori<f32>,
         kameran_zoomaus: f32,
     ) -> Result<(), String> {
-        let sijainti = self.kulman_sijainti() * kameran_zoomaus + kameran_aiheuttama_muutos;
+        let naytolle = |x: Vektori| x.liukuluvuiksi() * kameran_zoomaus + kameran_aiheuttama_muutos;
+        let sijainti = naytolle(self.kulman_sijainti());
         match self.muoto {
+            Muoto::Nelio(_, _) if self.kulma() != 0.0 => {
+                let kulmat: Vec<Vektori<f32>> =
+                    self.kulmat().iter().map(|x| naytolle(*x)).collect();
+                tayta_monikulmio(canvas, &kulmat)?;
+            }
             Muoto::Nelio(leveys, korkeus) => {
                 canvas.fill_rect(Some(Rect::new(
                     sijainti.x as i32,
                     sijainti.y as i32,
-                    (leveys * kameran_zoomaus) as u32,
-                    (korkeus * kameran_zoomaus) as u32,
+                    (leveys.liukuluku() * kameran_zoomaus) as u32,
+                    (korkeus.liukuluku() * kameran_zoomaus) as u32,
                 )))?;
             }
             Muoto::Ympyra(sade) => {
                 canvas.fill_rect(Some(Rect::new(
                     sijainti.x as i32,
                     sijainti.y as i32,
-                    (sade * 2.0 * kameran_zoomaus) as u32,
-                    (sade * 2.0 * kameran_zoomaus) as u32,
+                    (sade.liukuluku() * 2.0 * kameran_zoomaus) as u32,
+                    (sade.liukuluku() * 2.0 * kameran_zoomaus) as u32,
                 )))?;
             }
+            Muoto::Monikulmio(_) | Muoto::Kapseli(_, _) => {
+                let reuna: Vec<Vektori<f32>> =
+                    self.reunapisteet().iter().map(|x| naytolle(*x)).collect();
+                tayta_monikulmio(canvas, &reuna)?;
+            }
+            Muoto::Jana(_) => {
+                let runko = self.runko();
+                let pisteet = runko.pisteet();
+                let alku = naytolle(pisteet[0]);
+                let loppu = naytolle(pisteet[1]);
+                canvas.draw_line(
+                    Point::new(alku.x as i32, alku.y as i32),
+                    Point::new(loppu.x as i32, loppu.y as i32),
+                )?;
+            }
         }
 
         Ok(())
     }
 
-    /// Piirtää käyttämällä annettua tekstuuria
+    /// Antaa kappaleen reunan monikulmiona pelimaailman koordinaateissa.
+    /// Kapselin päädyt arvioidaan puoliympyrän muotoisiksi murtoviivoiksi.
+    fn reunapisteet(&self) -> Vec<Vektori> {
+        let runko = self.runko();
+        let pisteet = runko.pisteet();
+        if runko.sade <= 0.0 || pisteet.len() != 2 {
+            return pisteet.to_vec();
+        }
+        let suunta = self.suunta();
+        let mut reuna = Vec::with_capacity(2 * (KAAREN_PALOJA + 1));
+        for (paaty, aloituskulma) in [(pisteet[1], -FRAC_PI_2), (pisteet[0], FRAC_PI_2)].iter() {
+            for i in 0..=KAAREN_PALOJA {
+                let kulma = aloituskulma + PI * i as f32 / KAAREN_PALOJA as f32;
+                reuna.push(*paaty + suunta.kierra(luku(kulma)) * runko.sade);
+            }
+        }
+        reuna
+    }
+
+    /// Piirtää käyttämällä annettua tekstuuria. Tekstuuri kierretään kappaleen asennon mukaan.
     /// # Arguments
     /// * `canvas` - Canvas, jolle piirretään
     /// * `kameran_aiheuttama_muunnos` - Kameran sijainnista johtuva muunnos
@@ -191,40 +313,67 @@ impl Kappale {
     fn piirra_kuvalla(
         &self,
         canvas: &mut Canvas<sdl2::video::Window>,
-        kameran_aiheuttama_muutos: Vektori,
+        kameran_aiheuttama_muutos: Vektori<f32>,
         kameran_zoomaus: f32,
         tekstuuri: &Texture,
     ) -> Result<(), String> {
-        let sijainti = self.kulman_sijainti() * kameran_zoomaus + kameran_aiheuttama_muutos;
-        match self.muoto {
-            Muoto::Nelio(leveys, korkeus) => {
-                canvas.copy(
-                    tekstuuri,
-                    None,
-                    Some(Rect::new(
-                        sijainti.x as i32,
-                        sijainti.y as i32,
-                        (leveys * kameran_zoomaus) as u32,
-                        (korkeus * kameran_zoomaus) as u32,
-                    )),
-                )?;
+        let sijainti =
+            self.kulman_sijainti().liukuluvuiksi() * kameran_zoomaus + kameran_aiheuttama_muutos;
+        let (leveys, korkeus) = self.muoto.koko();
+        canvas.copy_ex(
+            tekstuuri,
+            None,
+            Some(Rect::new(
+                sijainti.x as i32,
+                sijainti.y as i32,
+                (leveys.liukuluku() * kameran_zoomaus) as u32,
+                (korkeus.liukuluku() * kameran_zoomaus) as u32,
+            )),
+            f64::from(self.kulma().liukuluku().to_degrees()),
+            None,
+            false,
+            false,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Täyttää annetun monikulmion canvakselle nykyisellä piirtovärillä vaakaviiva kerrallaan
+/// # Arguments
+/// * `canvas` - Canvas, jolle piirretään
+/// * `pisteet` - Monikulmion kärjet näytön koordinaateissa järjestyksessä
+fn tayta_monikulmio(
+    canvas: &mut Canvas<sdl2::video::Window>,
+    pisteet: &[Vektori<f32>],
+) -> Result<(), String> {
+    let yla = pisteet.iter().map(|x| x.y).fold(f32::INFINITY, f32::min);
+    let ala = pisteet.iter().map(|x| x.y).fold(f32::NEG_INFINITY, f32::max);
+    let mut leikkaukset = Vec::new();
+    let mut y = yla.floor() as i32;
+    while y as f32 <= ala {
+        // Rivin keskikohta, jotta kärkipisteet eivät osu täsmälleen riville
+        let rivi = y as f32 + 0.5;
+        leikkaukset.clear();
+        for i in 0..pisteet.len() {
+            let a = pisteet[i];
+            let b = pisteet[(i + 1) % pisteet.len()];
+            if (a.y <= rivi) != (b.y <= rivi) {
+                leikkaukset.push(a.x + (rivi - a.y) / (b.y - a.y) * (b.x - a.x));
             }
-            Muoto::Ympyra(sade) => {
-                canvas.copy(
-                    tekstuuri,
-                    None,
-                    Some(Rect::new(
-                        sijainti.x as i32,
-                        sijainti.y as i32,
-                        (sade * 2.0 * kameran_zoomaus) as u32,
-                        (sade * 2.0 * kameran_zoomaus) as u32,
-                    )),
-                )?;
+        }
+        leikkaukset.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        for pari in leikkaukset.chunks(2) {
+            if let [alku, loppu] = pari {
+                let (alku, loppu) = (alku.round() as i32, loppu.round() as i32);
+                if alku < loppu {
+                    canvas.draw_line(Point::new(alku, y), Point::new(loppu - 1, y))?;
+                }
             }
         }
-
-        Ok(())
+        y += 1;
     }
+    Ok(())
 }
 
 impl Piirrettava for PiirrettavaKappale {
@@ -237,7 +386,7 @@ impl Piirrettava for PiirrettavaKappale {
     fn piirra(
         &self,
         canvas: &mut Canvas<sdl2::video::Window>,
-        kameran_aiheuttama_muutos: Vektori,
+        kameran_aiheuttama_muutos: Vektori<f32>,
         kameran_zoomaus: f32,
         tekstuurit: &HashMap<String, Texture>,
     ) -> Result<(), String> {
@@ -269,6 +418,10 @@ impl Piirrettava for PiirrettavaKappale {
         }
         Ok(())
     }
+
+    fn kerros(&self) -> Piirtokerros {
+        self.kerros
+    }
 }
 
 impl Piirrettava for ValiaikainenPiirrettavaKappale {
@@ -281,7 +434,7 @@ impl Piirrettava for ValiaikainenPiirrettavaKappale {
     fn piirra(
         &self,
         canvas: &mut Canvas<sdl2::video::Window>,
-        kameran_aiheuttama_muutos: Vektori,
+        kameran_aiheuttama_muutos: Vektori<f32>,
         kameran_zoomaus: f32,
         tekstuurit: &HashMap<String, Texture>,
     ) -> Result<(), String> {
@@ -309,6 +462,24 @@ impl Piirrettava for ValiaikainenPiirrettavaKappale {
         }
         Ok(())
     }
+
+    fn kerros(&self) -> Piirtokerros {
+        self.kerros
+    }
+}
+
+/// Antaa väliaikaiset kappaleet kerroksittain järjestettynä. Saman kerroksen kappaleet
+/// säilyttävät keskinäisen järjestyksensä. Valmiiksi järjestettyä listaa ei järjestetä uudelleen.
+/// # Arguments
+/// * `piirrettavat` - Järjestettävät kappaleet
+fn kerroksittain(
+    piirrettavat: &[ValiaikainenPiirrettavaKappale],
+) -> Vec<&ValiaikainenPiirrettavaKappale> {
+    let mut jarjestetyt: Vec<&ValiaikainenPiirrettavaKappale> = piirrettavat.iter().collect();
+    if jarjestetyt.windows(2).any(|x| x[0].kerros > x[1].kerros) {
+        jarjestetyt.sort_by_key(|x| x.kerros);
+    }
+    jarjestetyt
 }
 
 /// Peruspiirtäjä, joka piirtää pelin tilan näytölle
@@ -324,9 +495,11 @@ pub struct Peruspiirtaja<'a> {
 /// Kamera, joka rajaa mikä alue esitetään pelimaailmasta.
 struct Kamera {
     /// Kameran sijainti pelimaailmassa
-    sijainti: Vektori,
+    sijainti: Vektori<f32>,
     /// Kerroin, jolla zoomataan piirrettäviä kohteita.
     zoomin_kerroin: f32,
+    /// Asetettu zoomi, jota lähemmäs kamera ei zoomaa sovittaessaan aluetta kuvaan.
+    suurin_zoomi: f32,
     /// Suhteellinen etäisyys kuinka paljon kamera voi jäädä jälkeen seurattavasta kohteesta.
     etaisyys_seurattavasta: (f32, f32),
 }
@@ -336,13 +509,30 @@ impl Kamera {
     /// # Arguments
     /// * `sijainti` - Kameran sijainti pelimaailmassa
     /// * `zoomin_kerroin` - Kuinka paljon kamera zoomaa kuvaa. Suhteellinen luku, jolloin 1.0 on ei-zoomia. Suurempi luku zoomaa.
-    pub fn new(sijainti: Vektori, zoomin_kerroin: f32) -> Self {
+    pub fn new(sijainti: Vektori<f32>, zoomin_kerroin: f32) -> Self {
         Kamera {
             sijainti: sijainti,
             zoomin_kerroin: zoomin_kerroin,
+            suurin_zoomi: zoomin_kerroin,
             etaisyys_seurattavasta: (0.0, 0.0),
         }
     }
+
+    /// Valitsee zoomin, jolla annettu alue reunuksineen mahtuu kuvaan. Zoomi pysyy välillä
+    /// 0.1 ja asetettu zoomi. Kameran sijainti skaalataan uudelle zoomille.
+    /// # Arguments
+    /// * `kuvan_koko` - Kuvan leveys ja korkeus pikseleinä
+    /// * `alue` - Pelimaailman alue, jonka tulee näkyä kuvassa
+    fn sovita_zoomi(&mut self, kuvan_koko: (u32, u32), alue: &Rajaus) {
+        let alueen_koko = (alue.oikea_ala - alue.vasen_yla).liukuluvuiksi()
+            + Vektori::new(KAMERAN_REUNUS * 2.0, KAMERAN_REUNUS * 2.0);
+        let mahtuva_zoomi =
+            (kuvan_koko.0 as f32 / alueen_koko.x).min(kuvan_koko.1 as f32 / alueen_koko.y);
+        let uusi_zoomi = mahtuva_zoomi.clamp(0.1, self.suurin_zoomi.max(0.1));
+        // Kameran sijainti on zoomatuissa koordinaateissa, joten se skaalataan uudelle zoomille
+        self.sijainti *= uusi_zoomi / self.zoomin_kerroin;
+        self.zoomin_kerroin = uusi_zoomi;
+    }
 }
 
 impl<'a> Peruspiirtaja<'a> {
@@ -358,7 +548,7 @@ impl<'a> Peruspiirtaja<'a> {
     }
 
     /// Laskee kameran aiheuttaman sijainnin muutoksen ja palauttaa sen
-    fn kameran_aiheuttama_muutos(&self) -> Result<(Vektori), String> {
+    fn kameran_aiheuttama_muutos(&self) -> Result<(Vektori<f32>), String> {
         let keskipiste = self.keskipiste()?;
         let muutos = keskipiste - self.kamera.sijainti;
         Ok(muutos)
@@ -367,13 +557,25 @@ impl<'a> Peruspiirtaja<'a> {
     /// Antaa piirtoalueen keskipisteen
     /// # Arguments
     /// * `canvas` - Piirtoalue, jonka keskipiste lasketaan
-    fn canvaksen_keskipiste(canvas: &Canvas<sdl2::video::Window>) -> Result<Vektori, String> {
+    fn canvaksen_keskipiste(canvas: &Canvas<sdl2::video::Window>) -> Result<Vektori<f32>, String> {
         let koko = canvas.output_size()?;
         Ok(Vektori::new(koko.0 as f32 / 2.0, koko.1 as f32 / 2.0))
     }
 
+    /// Antaa pelimaailman alueen, joka näkyy kamerassa
+    /// # Arguments
+    /// * `muutos` - Kameran aiheuttama muutos
+    fn nakyva_alue(&self, muutos: Vektori<f32>) -> Result<Rajaus, String> {
+        let koko = self.canvas.output_size()?;
+        let oikea_ala = Vektori::new(koko.0 as f32, koko.1 as f32);
+        Ok(Rajaus::new(
+            ((Vektori::new(0.0, 0.0) - muutos) / self.kamera.zoomin_kerroin).luvuiksi(),
+            ((oikea_ala - muutos) / self.kamera.zoomin_kerroin).luvuiksi(),
+        ))
+    }
+
     /// Antaa piirtoalueen keskipisteen
-    fn keskipiste(&self) -> Result<Vektori, String> {
+    fn keskipiste(&self) -> Result<Vektori<f32>, String> {
         Peruspiirtaja::canvaksen_keskipiste(&self.canvas)
     }
 
@@ -384,6 +586,50 @@ impl<'a> Peruspiirtaja<'a> {
     pub fn lisaa_tekstuuri(&mut self, tekstuuri: Texture<'a>, nimi: String) {
         self.tekstuurit.insert(nimi, tekstuuri);
     }
+
+    /// Piirtää kaksi kerroksittain järjestettyä joukkoa lomittain niin, että myös lopputulos
+    /// on kerroksittain järjestetty. Samalla kerroksella alemmat piirretään ensin.
+    /// # Arguments
+    /// * `muutos` - Kameran aiheuttama muutos
+    /// * `alemmat` - Kerroksittain järjestetyt kohteet, jotka piirretään ensin
+    /// * `ylemmat` - Kerroksittain järjestetyt kohteet, jotka piirretään samalla kerroksella päälle
+    fn piirra_lomittain<'b>(
+        &mut self,
+        muutos: Vektori<f32>,
+        alemmat: impl Iterator<Item = &'b dyn Piirrettava>,
+        ylemmat: impl Iterator<Item = &'b dyn Piirrettava>,
+    ) -> Result<(), String> {
+        let mut alemmat = alemmat.peekable();
+        let mut ylemmat = ylemmat.peekable();
+        while let Some(piirrettava) = seuraava_kerroksittain(&mut alemmat, &mut ylemmat) {
+            piirrettava.piirra(
+                &mut self.canvas,
+                muutos,
+                self.kamera.zoomin_kerroin,
+                &self.tekstuurit,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Antaa seuraavan piirrettävän kahdesta kerroksittain järjestetystä joukosta
+/// # Arguments
+/// * `alemmat` - Joukko, josta otetaan ensin, jos kerrokset ovat samat
+/// * `ylemmat` - Toinen joukko
+fn seuraava_kerroksittain<'b, A, B>(
+    alemmat: &mut Peekable<A>,
+    ylemmat: &mut Peekable<B>,
+) -> Option<&'b dyn Piirrettava>
+where
+    A: Iterator<Item = &'b dyn Piirrettava>,
+    B: Iterator<Item = &'b dyn Piirrettava>,
+{
+    match (alemmat.peek(), ylemmat.peek()) {
+        (Some(a), Some(b)) if a.kerros() > b.kerros() => ylemmat.next(),
+        (Some(_), _) => alemmat.next(),
+        (None, _) => ylemmat.next(),
+    }
 }
 
 impl<'a> Piirtovalmius for Peruspiirtaja<'a> {
@@ -400,7 +646,7 @@ impl<'a> Piirtovalmius for Peruspiirtaja<'a> {
     /// # Arguments
     /// * `sijainti` - Kameran sijainti
     fn aseta_kameran_sijainti(&mut self, sijainti: Vektori) -> Result<(), String> {
-        let zoomattu_sijainti = sijainti * self.kamera.zoomin_kerroin;
+        let zoomattu_sijainti = sijainti.liukuluvuiksi() * self.kamera.zoomin_kerroin;
 
         self.kamera.sijainti.x = match self.kamera.sijainti.x - zoomattu_sijainti.x {
             x if x < -self.kamera.etaisyys_seurattavasta.0 * self.keskipiste()?.x => {
@@ -430,7 +676,18 @@ impl<'a> Piirtovalmius for Peruspiirtaja<'a> {
         self.kamera.zoomin_kerroin = match kameran_zoomi {
             x if x <= 0.1 => 0.1,
             x => x,
-        }
+        };
+        self.kamera.suurin_zoomi = self.kamera.zoomin_kerroin;
+    }
+
+    /// Kohdistaa kameran annetun alueen keskelle ja loitontaa tarvittaessa niin, että koko
+    /// alue reunuksineen mahtuu kuvaan. Kamera ei koskaan zoomaa asetettua zoomia lähemmäs
+    /// eikä loitonna alle arvon 0.1.
+    /// # Arguments
+    /// * `alue` - Pelimaailman alue, jonka tulee näkyä kuvassa
+    fn sovita_kamera(&mut self, alue: &Rajaus) -> Result<(), String> {
+        self.kamera.sovita_zoomi(self.canvas.output_size()?, alue);
+        self.aseta_kameran_sijainti((alue.vasen_yla + alue.oikea_ala) / 2.0)
     }
 
     /// Asettaa suhteellisen etaisyyn, jonka verran kamera voi jäädä jälkeen seurattavasta kohteesta.
@@ -450,6 +707,11 @@ impl<'a> Piirtovalmius for Peruspiirtaja<'a> {
         };
         Ok(())
     }
+
+    /// Antaa pelimaailman alueen, joka näkyy kamerassa
+    fn anna_nakyva_alue(&self) -> Result<Rajaus, String> {
+        self.nakyva_alue(self.kameran_aiheuttama_muutos()?)
+    }
 }
 
 impl<'a> MaailmanPiirtaja for Peruspiirtaja<'a> {
@@ -457,24 +719,27 @@ impl<'a> MaailmanPiirtaja for Peruspiirtaja<'a> {
     /// # Arguments
     /// * `maailma` - Pelimaailma, jonka pohjalta kuva piirretään
     fn piirra_maailma(&mut self, maailma: &PiirrettavaMaailma) -> Result<(), String> {
-        if let Some(sijainti) = maailma.anna_kameran_sijainti() {
-            self.aseta_kameran_sijainti(sijainti)?;
+        if let Some(alue) = maailma.anna_kameran_alue() {
+            self.sovita_kamera(&alue)?;
         }
         // Lasketaan kameran aiheuttama muutos
         let muutos = self.kameran_aiheuttama_muutos()?;
 
         self.canvas.set_draw_color(Color::RGB(200, 100, 10));
 
-        for piirrettava in maailma.piirrettavat(muutos) {
-            piirrettava.piirra(
-                &mut self.canvas,
-                muutos,
-                self.kamera.zoomin_kerroin,
-                &self.tekstuurit,
-            )?;
-        }
-
-        Ok(())
+        let nakyva_alue = self.nakyva_alue(muutos)?;
+
+        let valiaikaiset = kerroksittain(maailma.valiaikaiset());
+        self.piirra_lomittain(
+            muutos,
+            maailma
+                .piirrettavat(&nakyva_alue)
+                .map(|x| x as &dyn Piirrettava),
+            valiaikaiset
+                .into_iter()
+                .filter(|x| x.anna_kappale().rajaus().leikkaa(&nakyva_alue))
+                .map(|x| x as &dyn Piirrettava),
+        )
     }
 }
 
@@ -491,7 +756,13 @@ impl<'a> ValiaikaistenPiirtaja for Peruspiirtaja<'a> {
 
         self.canvas.set_draw_color(Color::RGB(200, 100, 10));
 
-        for piirrettava in piirrettavat {
+        let nakyva_alue = self.nakyva_alue(muutos)?;
+
+        // Jätetään piirtämättä kappaleet, jotka eivät näy kamerassa
+        for piirrettava in kerroksittain(piirrettavat)
+            .into_iter()
+            .filter(|x| x.anna_kappale().rajaus().leikkaa(&nakyva_alue))
+        {
             piirrettava.piirra(
                 &mut self.canvas,
                 muutos,
@@ -503,3 +774,47 @@ impl<'a> ValiaikaistenPiirtaja for Peruspiirtaja<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Alue, jonka vasen yläkulma on origossa
+    fn alue(leveys: f32, korkeus: f32) -> Rajaus {
+        Rajaus::new(
+            Vektori::new(luku(0.0), luku(0.0)),
+            Vektori::new(luku(leveys), luku(korkeus)),
+        )
+    }
+
+    /// Antaa zoomin ja sijainnin, jotka kamera valitsee 800 x 600 kuvaan
+    fn sovitettu(suurin_zoomi: f32, alue: Rajaus) -> (f32, Vektori<f32>) {
+        let mut kamera = Kamera::new(Vektori::new(100.0, 50.0), suurin_zoomi);
+        kamera.sovita_zoomi((800, 600), &alue);
+        (kamera.zoomin_kerroin, kamera.sijainti)
+    }
+
+    #[test]
+    fn pieni_alue_ei_zoomaa_asetettua_lahemmas() {
+        let (zoomi, sijainti) = sovitettu(2.0, alue(10.0, 10.0));
+        assert_eq!(zoomi, 2.0);
+        assert_eq!(sijainti, Vektori::new(100.0, 50.0));
+    }
+
+    #[test]
+    fn suuri_alue_loitonnetaan_mahtumaan() {
+        // Alue reunuksineen on 1240 x 1240, joten korkeus rajaa zoomia
+        let (zoomi, sijainti) = sovitettu(2.0, alue(1000.0, 1000.0));
+        assert!((zoomi - 600.0 / 1240.0).abs() < 1e-6, "{}", zoomi);
+        // Sijainti on zoomatuissa koordinaateissa
+        assert_eq!(sijainti, Vektori::new(100.0, 50.0) * (zoomi / 2.0));
+    }
+
+    #[test]
+    fn zoomi_ei_loitonna_alle_minimin() {
+        assert_eq!(sovitettu(2.0, alue(1.0e6, 10.0)).0, 0.1);
+        assert_eq!(sovitettu(1.0, alue(f32::INFINITY, 10.0)).0, 0.1);
+        // Myös minimiä pienempi asetettu zoomi nostetaan minimiin
+        assert_eq!(sovitettu(0.05, alue(10.0, 10.0)).0, 0.1);
+    }
+}
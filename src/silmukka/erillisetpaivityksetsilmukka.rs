@@ -66,8 +66,6 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka
     fn kaynnista_silmukka(&mut self) -> Result<(), String> {
         let mut _timer = self.context.timer()?;
         let mut peliaika = Instant::now();
-        let mut kokonaisaika_pelin_alusta = Duration::new(0, 0);
-        let mut kokonaisaika_pelin_alusta_saannollinen = Duration::new(0, 0);
         let mut vanha_peliaika = peliaika;
         // Kuinka kauan aikaa ennen kuin seuraava säännöllinen päivitys tehdään
         let mut aikaa_seuraavaan_saannolliseen_paivitykseen = self.paivitysvali;
@@ -79,6 +77,10 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka
         self.epasaannollinen_paivitys
             .alusta(&mut maailma, &mut self.syotteet, &self.events);
 
+        // Jatketaan pelin aikaa siitä, mihin maailma on alustettu
+        let mut kokonaisaika_pelin_alusta = maailma.anna_pelin_aika();
+        let mut kokonaisaika_pelin_alusta_saannollinen = maailma.anna_pelin_aika();
+
         'paasilmukka: loop {
             for event in self.events.poll_iter() {
                 match event {
@@ -110,7 +112,11 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka
                     &mut maailma,
                     &mut self.syotteet,
                     &Paivitysaika::new(&self.paivitysvali, &kokonaisaika_pelin_alusta_saannollinen),
-                );
+                )?;
+
+                // Poistetaan heti, jotta seuraava säännöllinen päivitys näkee saman maailman
+                // kuin säännöllisessä silmukassa
+                maailma.poista_poistettavat();
 
                 aikaa_seuraavaan_saannolliseen_paivitykseen -= self.paivitysvali;
             }
@@ -120,14 +126,13 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka
                 &mut maailma,
                 &mut self.syotteet,
                 &Paivitysaika::new(&paivitysaika, &kokonaisaika_pelin_alusta),
-            );
+            )?;
 
             maailma.poista_poistettavat();
 
             // Piirretään maailma ja animaatiot
             self.piirtaja.puhdista_kuva();
             self.piirtaja.piirra_maailma(&maailma)?;
-            self.piirtaja.piirra_kappaleista(&maailma.animaatio_kuva)?;
             self.piirtaja.esita_kuva();
         }
 
@@ -5,11 +5,15 @@ use sdl2::keyboard::Keycode;
 use std::time::{Duration, Instant};
 
 use super::Paasilmukka;
+use crate::maailma::kappalemuisti::MINIMI_SYVYYS;
 use crate::maailma::*;
 use crate::paivitys::*;
 use crate::piirtaja::*;
 use crate::syotteet::*;
 
+/// Kuinka monta tilaa kappaleista muistetaan, kun niiden jäljet piirretään
+const JALJEN_PITUUS: usize = 20;
+
 /// Pääsilmukka, joka päivittää pelin tilaa säännöllisin väliajoin
 /// Säännöllinen päivitys tehdään niin monta kertaa kuin niitä mahtuu päivitysaikaan.
 /// Esim. jos aikaa on kulunut viimeisestä säännöllisestä päivityksestä 3,6 kertaa päivitysväli,
@@ -31,6 +35,10 @@ pub struct InterpoloivaSilmukka<'a> {
     paivitysvali: Duration,
     /// Käytännössä arvo 0 tai 1. 0, jos interpoloidaan ja 1, jos ekstrapoloidaan.
     ekstrapolointi_lisa: f32,
+    /// Jos jotakin, niin kappaleet piirretään sellaisina kuin ne olivat viiveen verran sitten
+    piirtoviive: Option<Duration>,
+    /// Piirretäänkö kappaleiden muistetuista sijainneista jäljet
+    jaljet: bool,
 }
 
 impl<'a> InterpoloivaSilmukka<'a> {
@@ -58,6 +66,8 @@ impl<'a> InterpoloivaSilmukka<'a> {
             epasaannollinen_paivitys: epasaannollinen_paivitys,
             paivitysvali: Duration::new(0, 1_000_000_000 / paivitys_tiheys),
             ekstrapolointi_lisa: 0.0,
+            piirtoviive: None,
+            jaljet: false,
         }
     }
 
@@ -85,6 +95,37 @@ impl<'a> InterpoloivaSilmukka<'a> {
             epasaannollinen_paivitys: epasaannollinen_paivitys,
             paivitysvali: Duration::new(0, 1_000_000_000 / paivitys_tiheys),
             ekstrapolointi_lisa: 1.0,
+            piirtoviive: None,
+            jaljet: false,
+        }
+    }
+
+    /// Asettaa viiveen, jonka verran jäljessä kappaleet piirretään. Ilman viivettä kappaleet
+    /// piirretään kahden uusimman tilan välille, mikä vastaa yhden päivitysvälin viivettä.
+    /// Viive ohittaa ekstrapoloinnin.
+    /// # Arguments
+    /// * `viive` - Kuinka paljon jäljessä pelin ajasta kappaleet piirretään
+    pub fn aseta_piirtoviive(&mut self, viive: Duration) {
+        self.piirtoviive = Some(viive);
+    }
+
+    /// Asettaa, piirretäänkö kappaleiden viimeisimmistä sijainneista jäljet
+    /// # Arguments
+    /// * `jaljet` - Piirretäänkö jäljet
+    pub fn aseta_jalkien_piirto(&mut self, jaljet: bool) {
+        self.jaljet = jaljet;
+    }
+
+    /// Antaa, kuinka monta tilaa kappaleista pitää muistaa piirtoviiveen ja jälkien piirtämiseksi
+    fn muistin_syvyys(&self) -> usize {
+        let syvyys = match self.piirtoviive {
+            Some(viive) => (viive.as_micros() / self.paivitysvali.as_micros()) as usize + MINIMI_SYVYYS,
+            None => MINIMI_SYVYYS,
+        };
+        if self.jaljet {
+            syvyys.max(JALJEN_PITUUS)
+        } else {
+            syvyys
         }
     }
 }
@@ -94,19 +135,23 @@ impl<'a> Paasilmukka for InterpoloivaSilmukka<'a> {
     fn kaynnista_silmukka(&mut self) -> Result<(), String> {
         let mut _timer = self.context.timer()?;
         let mut peliaika = Instant::now();
-        let mut kokonaisaika_pelin_alusta = Duration::new(0, 0);
-        let mut kokonaisaika_pelin_alusta_saannollinen = Duration::new(0, 0);
         let mut vanha_peliaika = peliaika;
         // Kuinka kauan aikaa ennen kuin seuraava säännöllinen päivitys tehdään
         let mut aikaa_seuraavaan_saannolliseen_paivitykseen = self.paivitysvali;
         let mut paivitysaika;
 
         let mut maailma = Perusmaailma::new_interpoloiva();
+        maailma.aseta_muistin_syvyys(self.muistin_syvyys());
+        maailma.aseta_jalkien_piirto(self.jaljet);
         self.saannollinen_paivitys
             .alusta(&mut maailma, &mut self.syotteet, &self.events);
         self.epasaannollinen_paivitys
             .alusta(&mut maailma, &mut self.syotteet, &self.events);
 
+        // Jatketaan pelin aikaa siitä, mihin maailma on alustettu
+        let mut kokonaisaika_pelin_alusta = maailma.anna_pelin_aika();
+        let mut kokonaisaika_pelin_alusta_saannollinen = maailma.anna_pelin_aika();
+
         'paasilmukka: loop {
             for event in self.events.poll_iter() {
                 match event {
@@ -138,9 +183,13 @@ impl<'a> Paasilmukka for InterpoloivaSilmukka<'a> {
                     &mut maailma,
                     &mut self.syotteet,
                     &Paivitysaika::new(&self.paivitysvali, &kokonaisaika_pelin_alusta_saannollinen),
-                );
+                )?;
 
-                maailma.paivita_kappalemuistia();
+                maailma.paivita_kappalemuistia(kokonaisaika_pelin_alusta_saannollinen);
+
+                // Poistetaan heti, jotta seuraava säännöllinen päivitys näkee saman maailman
+                // kuin säännöllisessä silmukassa
+                maailma.poista_poistettavat();
 
                 aikaa_seuraavaan_saannolliseen_paivitykseen -= self.paivitysvali;
             }
@@ -150,7 +199,7 @@ impl<'a> Paasilmukka for InterpoloivaSilmukka<'a> {
                 &mut maailma,
                 &mut self.syotteet,
                 &Paivitysaika::new(&paivitysaika, &kokonaisaika_pelin_alusta),
-            );
+            )?;
 
             maailma.poista_poistettavat();
 
@@ -161,17 +210,22 @@ impl<'a> Paasilmukka for InterpoloivaSilmukka<'a> {
                     + aikaa_seuraavaan_saannolliseen_paivitykseen.as_micros() as f32
                         / self.paivitysvali.as_micros() as f32,
             );
+            if let Some(viive) = self.piirtoviive {
+                maailma.aseta_piirtohetki(Some(
+                    kokonaisaika_pelin_alusta.checked_sub(viive).unwrap_or_default(),
+                ));
+            }
 
-            if let Some(kamera) = maailma.anna_kameran_sijainti() {
-                self.piirtaja.aseta_kameran_sijainti(kamera)?;
+            if let Some(alue) = maailma.anna_kameran_alue() {
+                self.piirtaja.sovita_kamera(&alue)?;
             }
 
-            maailma.anna_piirrettavat(&mut piirrettavat_kappaleet);
+            let nakyva_alue = self.piirtaja.anna_nakyva_alue()?;
+            maailma.anna_piirrettavat(&mut piirrettavat_kappaleet, &nakyva_alue);
 
             // Piirretään maailma ja animaatiot
             self.piirtaja.puhdista_kuva();
             self.piirtaja.piirra_kappaleista(&piirrettavat_kappaleet)?;
-            self.piirtaja.piirra_kappaleista(&maailma.animaatio_kuva)?;
             self.piirtaja.esita_kuva();
         }
 
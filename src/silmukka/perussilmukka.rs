@@ -2,7 +2,7 @@ extern crate sdl2;
 
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
 use super::Paasilmukka;
 use crate::maailma::*;
@@ -53,7 +53,6 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka for Perussilmu
         // Alustetaan aikaan liittyvät muuttujat
         let mut _timer = self.context.timer()?;
         let mut peliaika = Instant::now();
-        let mut kokonaisaika_pelin_alusta = Duration::new(0, 0);
         let mut vanha_peliaika = peliaika;
         let mut paivitysaika;
 
@@ -62,6 +61,9 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka for Perussilmu
         self.paivitys
             .alusta(&mut maailma, &mut self.syotteet, &self.events);
 
+        // Jatketaan pelin aikaa siitä, mihin maailma on alustettu
+        let mut kokonaisaika_pelin_alusta = maailma.anna_pelin_aika();
+
         // Varsinainen pääsilmukka
         'paasilmukka: loop {
             // Kerätään tapahtumat
@@ -93,7 +95,7 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka for Perussilmu
                 &mut maailma,
                 &mut self.syotteet,
                 &Paivitysaika::new(&paivitysaika, &kokonaisaika_pelin_alusta),
-            );
+            )?;
 
             // Poistetaan maailmasta poistettaviksi merkityt kappaleet
             maailma.poista_poistettavat();
@@ -101,7 +103,6 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka for Perussilmu
             // Piirretään maailma ja animaatiot
             self.piirtaja.puhdista_kuva();
             self.piirtaja.piirra_maailma(&maailma)?;
-            self.piirtaja.piirra_kappaleista(&maailma.animaatio_kuva)?;
             self.piirtaja.esita_kuva();
         }
 
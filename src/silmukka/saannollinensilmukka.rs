@@ -57,7 +57,6 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka for Saannollin
     fn kaynnista_silmukka(&mut self) -> Result<(), String> {
         let mut _timer = self.context.timer()?;
         let mut peliaika = Instant::now();
-        let mut kokonaisaika_pelin_alusta = Duration::new(0, 0);
         let mut vanha_peliaika = peliaika;
         let mut paivitysaika;
 
@@ -67,6 +66,9 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka for Saannollin
         self.paivitys
             .alusta(&mut maailma, &mut self.syotteet, &self.events);
 
+        // Jatketaan pelin aikaa siitä, mihin maailma on alustettu
+        let mut kokonaisaika_pelin_alusta = maailma.anna_pelin_aika();
+
         'paasilmukka: loop {
             for event in self.events.poll_iter() {
                 match event {
@@ -102,14 +104,13 @@ impl<'a, T: MaailmanPiirtaja + ValiaikaistenPiirtaja> Paasilmukka for Saannollin
                 &mut maailma,
                 &mut self.syotteet,
                 &Paivitysaika::new(&paivitysaika, &kokonaisaika_pelin_alusta),
-            );
+            )?;
 
             maailma.poista_poistettavat();
 
             // Piirretään maailma ja animaatiot
             self.piirtaja.puhdista_kuva();
             self.piirtaja.piirra_maailma(&maailma)?;
-            self.piirtaja.piirra_kappaleista(&maailma.animaatio_kuva)?;
             self.piirtaja.esita_kuva();
         }
 
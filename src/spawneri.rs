@@ -2,12 +2,22 @@ use std::rc::Rc;
 use std::time::Duration;
 
 use crate::fysiikka::Fysiikkakappale;
+use crate::maailma::elinaika::Elinaika;
+use crate::maailma::elinvoima::{Elinvoima, Kestavyys, Vahingoittaja, Vahinko};
 use crate::maailma::kappale::Kappale;
+use crate::maailma::luku::Luku;
+use crate::maailma::tagit::Tagirekisteri;
 use crate::maailma::vektori::Vektori;
 use crate::maailma::Perusmaailma;
 use crate::paivitys::Paivitysaika;
 use crate::piirtaja::{PiirrettavaKappale, Piirtotapa};
+use crate::tallennus::{
+    kirjoita_aly, kirjoita_elinaika, kirjoita_kappale, kirjoita_kestavyys, kirjoita_piirtotapa,
+    kirjoita_vahinko, lue_aly, lue_elinaika, lue_kappale, lue_kestavyys, lue_piirtotapa,
+    lue_vahinko, Lajit,
+};
 use crate::tekoaly::{Aly, Alyllinen};
+use crate::tiedosto::Tietue;
 
 /// Spawneri, joka pystyy luomaan asioita annetuin väliajoin
 pub struct Spawneri {
@@ -23,6 +33,14 @@ pub struct Spawneri {
     nopeus: Option<Vektori>,
     /// Spawnattavan kappaleen tekoäly
     aly: Option<Box<Aly>>,
+    /// Spawnattavan kappaleen kestävyys, jos sillä on elinvoima
+    kestavyys: Option<Kestavyys>,
+    /// Spawnattavan kappaleen tekemä vahinko, jos se on vahingoittaja
+    vahinko: Option<Vahinko>,
+    /// Kuinka kauan spawnattu kappale on olemassa
+    elinaika: Option<Duration>,
+    /// Kuinka pitkän matkan spawnattu kappale voi kulkea
+    kantama: Option<Luku>,
 }
 
 impl Spawneri {
@@ -47,9 +65,36 @@ impl Spawneri {
             piirtotapa: piirtotapa,
             nopeus: nopeus,
             aly: aly,
+            kestavyys: None,
+            vahinko: None,
+            elinaika: None,
+            kantama: None,
         }
     }
 
+    /// Antaa spawnattaville kappaleille elinvoiman
+    /// # Arguments
+    /// * `kestavyys` - Spawnattavien kappaleiden kestävyys
+    pub fn aseta_kestavyys(&mut self, kestavyys: Option<Kestavyys>) {
+        self.kestavyys = kestavyys;
+    }
+
+    /// Tekee spawnattavista kappaleista vahingoittajia
+    /// # Arguments
+    /// * `vahinko` - Spawnattavien kappaleiden tekemä vahinko
+    pub fn aseta_vahinko(&mut self, vahinko: Option<Vahinko>) {
+        self.vahinko = vahinko;
+    }
+
+    /// Rajaa spawnattavien kappaleiden elinaikaa tai kulkemaa matkaa
+    /// # Arguments
+    /// * `elinaika` - Kuinka kauan spawnattu kappale on olemassa
+    /// * `kantama` - Kuinka pitkän matkan spawnattu kappale voi kulkea
+    pub fn aseta_elinaika(&mut self, elinaika: Option<Duration>, kantama: Option<Luku>) {
+        self.elinaika = elinaika;
+        self.kantama = kantama;
+    }
+
     /// Päivittää spawnerin tilaa ja tarvittaessa luo uuden spawnin
     /// # Arguments
     /// * `maailma` - Maailma, johon spawni luodaan
@@ -57,7 +102,7 @@ impl Spawneri {
     pub fn paivita_spawneria(&mut self, maailma: &mut Perusmaailma, paivitysaika: &Paivitysaika) {
         if self.aikaa_seuraavaan_spawniin <= *paivitysaika.paivitysaika {
             // Spawnataan kappale
-            self.spawnaa(maailma);
+            self.spawnaa(maailma, *paivitysaika.kokonais_pelin_aika);
             let ylijaama = &(*paivitysaika.paivitysaika - self.aikaa_seuraavaan_spawniin);
             let ylijaava_aika = &Paivitysaika::new(ylijaama, paivitysaika.kokonais_pelin_aika);
             self.aikaa_seuraavaan_spawniin = self.spawnin_vali;
@@ -72,7 +117,8 @@ impl Spawneri {
     /// Spawnaa uuden otuksen
     /// # Arguments
     /// * `maailma` - Maailma johon otus spawnataan
-    pub fn spawnaa(&mut self, maailma: &mut Perusmaailma) {
+    /// * `aika` - Pelin aika, josta spawnatun elinaika lasketaan
+    pub fn spawnaa(&mut self, maailma: &mut Perusmaailma, aika: Duration) {
         let _rk = maailma.lisaa_kappale(self.kappale);
         if let Some(nopeus) = self.nopeus {
             maailma.lisaa_fysiikkakappale(Fysiikkakappale::new(nopeus, Rc::clone(&_rk)));
@@ -82,11 +128,83 @@ impl Spawneri {
             self.piirtotapa.clone(),
         ));
 
+        if let Some(kestavyys) = &self.kestavyys {
+            maailma.lisaa_lisaosa(Elinvoima::new(Rc::clone(&_rk), kestavyys.clone()));
+        }
+        if let Some(vahinko) = self.vahinko {
+            maailma.lisaa_lisaosa(Vahingoittaja::new(Rc::clone(&_rk), vahinko));
+        }
+        if self.elinaika.is_some() || self.kantama.is_some() {
+            let kuolin_aika = match self.elinaika {
+                Some(elinaika) => aika + elinaika,
+                None => Duration::MAX,
+            };
+            let mut elinaika = Elinaika::new(Rc::clone(&_rk), kuolin_aika);
+            elinaika.aseta_kantama(self.kantama);
+            maailma.lisaa_lisaosa(elinaika);
+        }
+
         // Se on clone, ei copy!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
         if let Some(alylaatikko) = &self.aly {
             maailma.lisaa_aly(Alyllinen::new(_rk, alylaatikko.clone()));
         }
     }
+
+    /// Tallentaa spawnerin tilan annettuun tietueeseen
+    /// # Arguments
+    /// * `tietue` - Tietue, johon spawneri tallennetaan
+    /// * `tagit` - Rekisteri, josta spawnattavan tagien nimet haetaan
+    pub fn tallenna(&self, tietue: &mut Tietue, tagit: &Tagirekisteri) {
+        tietue.lisaa_kesto("vali", self.spawnin_vali);
+        tietue.lisaa_kesto("aikaa_seuraavaan", self.aikaa_seuraavaan_spawniin);
+        kirjoita_kappale(tietue, &self.kappale, tagit);
+        kirjoita_piirtotapa(tietue, &self.piirtotapa);
+        if let Some(nopeus) = self.nopeus {
+            tietue.lisaa_vektori("nopeus", nopeus);
+        }
+        if let Some(aly) = &self.aly {
+            kirjoita_aly(tietue, &**aly);
+        }
+        if let Some(kestavyys) = &self.kestavyys {
+            kirjoita_kestavyys(tietue, kestavyys, tagit);
+        }
+        if let Some(vahinko) = &self.vahinko {
+            kirjoita_vahinko(tietue, vahinko, tagit);
+        }
+        kirjoita_elinaika(tietue, self.elinaika, self.kantama);
+    }
+
+    /// Lataa spawnerin tietueesta. Jos aikaa seuraavaan spawniin ei ole annettu,
+    /// niin ensimmäinen spawni tehdään spawnivälin kuluttua.
+    /// # Arguments
+    /// * `tietue` - Tietue, josta spawneri ladataan
+    /// * `lajit` - Rekisterit, joiden avulla spawnattavan tekoäly ladataan
+    /// * `tagit` - Rekisteri, josta spawnattavan tagit haetaan nimen perusteella
+    pub fn lataa(tietue: &Tietue, lajit: &Lajit, tagit: &Tagirekisteri) -> Result<Spawneri, String> {
+        let piirtotapa = match lue_piirtotapa(tietue)? {
+            Some(piirtotapa) => piirtotapa,
+            None => return Err(tietue.virhe("puuttuu kenttä 'vari' tai 'kuva'")),
+        };
+        let nopeus = match tietue.sisaltaa("nopeus") {
+            true => Some(tietue.vektori("nopeus")?),
+            false => None,
+        };
+        let mut spawneri = Spawneri::new(
+            tietue.kesto("vali")?,
+            lue_kappale(tietue, tagit)?,
+            piirtotapa,
+            nopeus,
+            lue_aly(tietue, lajit)?,
+        );
+        if tietue.sisaltaa("aikaa_seuraavaan") {
+            spawneri.aikaa_seuraavaan_spawniin = tietue.kesto("aikaa_seuraavaan")?;
+        }
+        spawneri.aseta_kestavyys(lue_kestavyys(tietue, tagit)?);
+        spawneri.aseta_vahinko(lue_vahinko(tietue, tagit)?);
+        let (elinaika, kantama) = lue_elinaika(tietue)?;
+        spawneri.aseta_elinaika(elinaika, kantama);
+        Ok(spawneri)
+    }
 }
 
 impl Clone for Box<Aly> {
@@ -2,7 +2,7 @@ use sdl2::keyboard::Scancode;
 use sdl2::EventPump;
 
 /// Sisältää joukon näppäimiä, joden tilaa komponentti tarkkailee käskettäessä
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Syotteet {
     /// Lista tarkkailtavista näppäimistä
     tarkkailtavat_nappaimet: Vec<Nappain>,
@@ -83,9 +83,168 @@ impl Syotteet {
             nappain.paivita_tila(&events);
         }
     }
+
+    /// Antaa tarkkailtavista näppäimistä ne, jotka ovat pohjassa, lisäysjärjestyksessä
+    pub fn pohjassa_olevat(&self) -> Vec<Scancode> {
+        self.tarkkailtavat_nappaimet
+            .iter()
+            .filter(|x| x.tila.pohjassa())
+            .map(|x| x.nappain)
+            .collect()
+    }
+
+    /// Päivittää kaikkien näppäinten tilan ikään kuin vain annetut näppäimet olisivat
+    /// pohjassa. Tarkkailemattomat näppäimet lisätään tarkkailtaviksi. Käytetään esim.
+    /// nauhoitettujen syötteiden toistamiseen ilman näppäimistöä.
+    /// # Arguments
+    /// * `pohjassa` - Näppäimet, jotka ovat pohjassa
+    pub fn aseta_pohjassa_olevat(&mut self, pohjassa: &[Scancode]) {
+        for nappain_koodi in pohjassa {
+            if !self
+                .tarkkailtavat_nappaimet
+                .iter()
+                .any(|x| x.nappain == *nappain_koodi)
+            {
+                self.tarkkailtavat_nappaimet.push(Nappain {
+                    nappain: *nappain_koodi,
+                    tila: Tila::EiPohjassa(Muutos::Pysynyt),
+                });
+            }
+        }
+        for nappain in self.tarkkailtavat_nappaimet.iter_mut() {
+            nappain.aseta_pohjassa(pohjassa.contains(&nappain.nappain));
+        }
+    }
+}
+
+/// Nauhoite pohjassa olleista näppäimistä päivityksittäin. Nauhoitteen toistaminen
+/// kiinteällä päivitysvälillä antaa saman pelin kulun joka kerta.
+#[derive(Default, Clone)]
+pub struct Nauhoite {
+    /// Kunkin päivityksen aikana pohjassa olleet näppäimet
+    paivitykset: Vec<Vec<Scancode>>,
+}
+
+impl Nauhoite {
+    /// Luo tyhjän nauhoitteen
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Lisää päivityksen pohjassa olleet näppäimet nauhoitteen loppuun
+    /// # Arguments
+    /// * `pohjassa` - Päivityksen aikana pohjassa olleet näppäimet
+    pub fn lisaa(&mut self, pohjassa: Vec<Scancode>) {
+        self.paivitykset.push(pohjassa);
+    }
+
+    /// Antaa annetun päivityksen aikana pohjassa olleet näppäimet. Nauhoitteen
+    /// jälkeen mitään näppäintä ei ole pohjassa.
+    /// # Arguments
+    /// * `askel` - Päivityksen järjestysnumero
+    pub fn pohjassa(&self, askel: usize) -> &[Scancode] {
+        self.paivitykset.get(askel).map_or(&[], |x| &x[..])
+    }
+
+    /// Nauhoitettujen päivitysten määrä
+    pub fn pituus(&self) -> usize {
+        self.paivitykset.len()
+    }
+
+    /// Kirjoittaa päivityksen näppäimet yhdeksi riviksi näppäinten niminä pilkuilla erotettuna,
+    /// esim. `Space, Left Shift`
+    /// # Arguments
+    /// * `pohjassa` - Päivityksen aikana pohjassa olleet näppäimet
+    pub fn kirjoita_rivi(pohjassa: &[Scancode]) -> String {
+        pohjassa
+            .iter()
+            .map(|x| x.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Kirjoittaa nauhoitteen tekstiksi, jossa on yksi päivitys riviä kohden
+    pub fn kirjoita(&self) -> String {
+        let mut teksti = String::new();
+        for pohjassa in &self.paivitykset {
+            teksti.push_str(&Nauhoite::kirjoita_rivi(pohjassa));
+            teksti.push('\n');
+        }
+        teksti
+    }
+
+    /// Lukee nauhoitteen tekstistä, joka on kirjoitettu `kirjoita`-funktiolla
+    /// # Arguments
+    /// * `teksti` - Luettava teksti
+    pub fn lue(teksti: &str) -> Result<Self, String> {
+        let mut nauhoite = Nauhoite::new();
+        for (numero, rivi) in teksti.lines().enumerate() {
+            let pohjassa = rivi
+                .split(',')
+                .map(|x| x.trim())
+                .filter(|x| !x.is_empty())
+                .map(|x| {
+                    Scancode::from_name(x)
+                        .ok_or_else(|| format!("Rivi {}: tuntematon näppäin '{}'", numero + 1, x))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            nauhoite.lisaa(pohjassa);
+        }
+        Ok(nauhoite)
+    }
+}
+
+/// Näppäimet, joilla pelaaja ohjaa pelihahmoaan
+#[derive(Copy, Clone)]
+pub struct Ohjaus {
+    /// Näppäin, jolla liikutaan oikealle
+    pub oikealle: Scancode,
+    /// Näppäin, jolla liikutaan vasemmalle
+    pub vasemmalle: Scancode,
+    /// Näppäin, jolla liikutaan ylös
+    pub ylos: Scancode,
+    /// Näppäin, jolla liikutaan alas
+    pub alas: Scancode,
+    /// Näppäin, jolla ammutaan
+    pub ampuminen: Scancode,
+}
+
+impl Ohjaus {
+    /// Ohjaus nuolinäppäimillä ja välilyönnillä
+    pub fn nuolinappaimet() -> Self {
+        Ohjaus {
+            oikealle: Scancode::Right,
+            vasemmalle: Scancode::Left,
+            ylos: Scancode::Up,
+            alas: Scancode::Down,
+            ampuminen: Scancode::Space,
+        }
+    }
+
+    /// Ohjaus WASD-näppäimillä ja vasemmalla vaihtonäppäimellä
+    pub fn wasd() -> Self {
+        Ohjaus {
+            oikealle: Scancode::D,
+            vasemmalle: Scancode::A,
+            ylos: Scancode::W,
+            alas: Scancode::S,
+            ampuminen: Scancode::LShift,
+        }
+    }
+
+    /// Lisää ohjauksen näppäimet tarkkailtaviin näppäimiin
+    /// # Arguments
+    /// * `syotteet` - Syötteet, joihin näppäimet lisätään
+    /// * `events` - Tapahtumalista, josta tarkastetaan, onko näppäin luotaessa jo pohjassa
+    pub fn lisaa_nappaimet(&self, syotteet: &mut Syotteet, events: &EventPump) {
+        for nappain in &[self.oikealle, self.vasemmalle, self.ylos, self.alas, self.ampuminen] {
+            syotteet.lisaa_nappain(events, *nappain);
+        }
+    }
 }
 
 /// Näppäin, jolla on tieto omasta tilastaan
+#[derive(Clone)]
 struct Nappain {
     /// Tarkkailtavan näppäimen koodi
     pub nappain: Scancode,
@@ -116,10 +275,14 @@ impl Nappain {
     /// # Arguments
     /// * `events` - Tapahtumalista, josta tarkastetaan, onko näppäin luotaessa jo pohjassa
     fn paivita_tila(&mut self, events: &EventPump) {
-        match (
-            events.keyboard_state().is_scancode_pressed(self.nappain),
-            self.tila.pohjassa(),
-        ) {
+        self.aseta_pohjassa(events.keyboard_state().is_scancode_pressed(self.nappain));
+    }
+
+    /// Päivittää näppäimen tilan sen mukaan, onko näppäin nyt pohjassa
+    /// # Arguments
+    /// * `pohjassa` - Onko näppäin pohjassa
+    fn aseta_pohjassa(&mut self, pohjassa: bool) {
+        match (pohjassa, self.tila.pohjassa()) {
             (false, true) => self.tila = Tila::EiPohjassa(Muutos::Muuttunut),
             (false, false) => self.tila = Tila::EiPohjassa(Muutos::Pysynyt),
             (true, true) => self.tila = Tila::Pohjassa(Muutos::Pysynyt),
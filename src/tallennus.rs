@@ -0,0 +1,1278 @@
+//! Pelimaailman tallentaminen ihmisluettavaan tiedostoon ja lataaminen takaisin.
+//! Tiedosto sisältää maailman kappaleet lisäosineen, spawnerit, animaatiot ja pelin kokonaisajan.
+//! Tekoälyt ja animaatiot tunnistetaan lajin nimellä, jolle pitää olla rekisteröity lataaja.
+use std::collections::HashMap;
+use std::fs;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::animointi::{AmmusAnimaatio, Animaatio, KatoamisAnimaatio, Kuolevainen};
+use crate::fysiikka::{Fysiikallinen, Fysiikkakappale, Integrointi, Liikkuvuus, Tormaysmatriisi};
+use crate::maailma::elinaika::Elinaika;
+use crate::maailma::elinvoima::{Elinvoima, Kestavyys, Kuolema, Vahingoittaja, Vahinko};
+use crate::maailma::kappale::{Kappale, Monikulmio, Muoto, Rajaus};
+use crate::maailma::liitos::Liitos;
+use crate::maailma::luku::Luku;
+use crate::maailma::tagit::{Tagirekisteri, Tagit};
+use crate::maailma::vektori::Vektori;
+use crate::maailma::pelihahmo::Pelihahmo;
+use crate::maailma::rajat::{Maailmanrajat, Rajakayttaytyminen, Rajatoiminta};
+use crate::maailma::*;
+use crate::piirtaja::{PiirrettavaKappale, Piirtokerros, Piirtotapa};
+use crate::spawneri::Spawneri;
+use crate::tekoaly::{Aly, Alyllinen, SeurausAly};
+use crate::tiedosto::{kirjoita_tietueet, lue_tietueet, Tietue};
+
+/// Funktio, joka luo tietueesta annetun tyyppisen otuksen
+pub type Lataaja<T> = fn(&Tietue) -> Result<T, String>;
+
+/// Rekisteri, joka yhdistää lajien nimet niiden lataajiin
+pub struct Lajirekisteri<T> {
+    /// Lataajat lajin nimen mukaan
+    lataajat: HashMap<String, Lataaja<T>>,
+}
+
+impl<T> Default for Lajirekisteri<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Lajirekisteri<T> {
+    /// Luo uuden tyhjän rekisterin
+    pub fn new() -> Self {
+        Lajirekisteri {
+            lataajat: HashMap::new(),
+        }
+    }
+
+    /// Rekisteröi lajille lataajan. Korvaa mahdollisen aiemman saman nimisen lataajan.
+    /// # Arguments
+    /// * `nimi` - Lajin nimi
+    /// * `lataaja` - Funktio, joka luo lajin otuksen tietueesta
+    pub fn rekisteroi(&mut self, nimi: &str, lataaja: Lataaja<T>) {
+        self.lataajat.insert(nimi.to_string(), lataaja);
+    }
+
+    /// Lataa annetun lajin otuksen tietueesta
+    /// # Arguments
+    /// * `nimi` - Ladattavan lajin nimi
+    /// * `tietue` - Tietue, josta otus ladataan
+    pub fn lataa(&self, nimi: &str, tietue: &Tietue) -> Result<T, String> {
+        match self.lataajat.get(nimi) {
+            Some(lataaja) => lataaja(tietue),
+            None => Err(tietue.virhe(&format!("tuntematon laji '{}'", nimi))),
+        }
+    }
+}
+
+/// Kaikki latauksessa tarvittavat lajirekisterit
+pub struct Lajit {
+    /// Tekoälyjen lajit
+    pub alyt: Lajirekisteri<Box<dyn Aly>>,
+    /// Animaatioiden lajit
+    pub animaatiot: Lajirekisteri<Box<dyn Animaatio>>,
+}
+
+impl Default for Lajit {
+    /// Luo rekisterit, joihin on rekisteröity pelin omat tekoälyt ja animaatiot
+    fn default() -> Self {
+        let mut alyt = Lajirekisteri::new();
+        alyt.rekisteroi(SeurausAly.nimi(), SeurausAly::lataa);
+        let mut animaatiot = Lajirekisteri::new();
+        animaatiot.rekisteroi("katoaminen", KatoamisAnimaatio::lataa);
+        animaatiot.rekisteroi("ammus", AmmusAnimaatio::lataa);
+        Lajit { alyt, animaatiot }
+    }
+}
+
+/// Tallentaa maailman tilan tekstiksi
+/// # Arguments
+/// * `maailma` - Tallennettava maailma
+/// * `pelin_aika` - Pelin alusta kulunut aika
+pub fn tallenna(maailma: &Perusmaailma, pelin_aika: Duration) -> String {
+    kirjoita_tietueet(&tallenna_tietueiksi(maailma, pelin_aika))
+}
+
+/// Tallentaa maailman tilan tietueiksi. Tietueet ovat aina samassa järjestyksessä:
+/// maailma, tagit, törmäykset, kappaleet, spawnerit ja animaatiot.
+/// # Arguments
+/// * `maailma` - Tallennettava maailma
+/// * `pelin_aika` - Pelin alusta kulunut aika
+pub fn tallenna_tietueiksi(maailma: &Perusmaailma, pelin_aika: Duration) -> Vec<Tietue> {
+    let mut tietueet = Vec::new();
+
+    let mut tietue = Tietue::new("maailma");
+    tietue.lisaa_kesto("pelin_aika", pelin_aika);
+    if let Some(rajat) = maailma.rajat() {
+        kirjoita_rajat(&mut tietue, rajat);
+    }
+    if maailma.integrointi() != Integrointi::default() {
+        tietue.lisaa("integrointi", integroinnin_nimi(maailma.integrointi()));
+    }
+    tietueet.push(tietue);
+
+    let tagit = maailma.tagirekisteri();
+    if !tagit.omat().is_empty() {
+        let mut tietue = Tietue::new("tagit");
+        tietue.lisaa("nimet", tagit.omat().join(" "));
+        tietueet.push(tietue);
+    }
+
+    if let Some(tietue) = kirjoita_tormaysmatriisi(maailma.tormaysmatriisi(), tagit) {
+        tietueet.push(tietue);
+    }
+
+    // Vanhemmiksi liitetyt kappaleet nimetään niiden järjestysnumerolla
+    let mut vanhemmat = HashMap::new();
+    for liitos in maailma.lisaosat::<Liitos>() {
+        let vanhempi = liitos.anna_vanhempi();
+        if let Some(indeksi) = maailma
+            .kappaleet()
+            .iter()
+            .position(|x| std::ptr::eq(x.as_ptr(), vanhempi.as_ptr()))
+        {
+            vanhemmat.insert(vanhempi.as_ptr() as *const Kappale, indeksi);
+        }
+    }
+
+    for (indeksi, kappale) in maailma.kappaleet().iter().enumerate() {
+        let mut tietue = Tietue::new("kappale");
+        if vanhemmat.contains_key(&(kappale.as_ptr() as *const Kappale)) {
+            tietue.lisaa("nimi", indeksi);
+        }
+        kirjoita_kappale(&mut tietue, &kappale.borrow(), tagit);
+        if let Some(piirrettava) = maailma.anna_piirrettavyys(kappale) {
+            kirjoita_piirtotapa(&mut tietue, piirrettava.anna_piirtotapa());
+            kirjoita_piirtokerros(&mut tietue, piirrettava.anna_kerros());
+        }
+        if let Some(fysiikka) = maailma.anna_fysiikka(kappale) {
+            tietue.lisaa_vektori("nopeus", fysiikka.anna_nopeus());
+            if fysiikka.anna_kulmanopeus() != 0.0 {
+                tietue.lisaa("kulmanopeus", fysiikka.anna_kulmanopeus());
+            }
+            if fysiikka.onko_nopea() {
+                tietue.lisaa("nopea", true);
+            }
+            if fysiikka.anna_liikkuvuus() != Liikkuvuus::default() {
+                tietue.lisaa("liikkuvuus", liikkuvuuden_nimi(fysiikka.anna_liikkuvuus()));
+            }
+            if fysiikka.anna_massa() != 1.0 {
+                tietue.lisaa("massa", fysiikka.anna_massa());
+            }
+            if fysiikka.anna_kimmoisuus() != 0.0 {
+                tietue.lisaa("kimmoisuus", fysiikka.anna_kimmoisuus());
+            }
+            if fysiikka.anna_kitka() != 0.0 {
+                tietue.lisaa("kitka", fysiikka.anna_kitka());
+            }
+            if fysiikka.anna_vaimennus() != 0.0 {
+                tietue.lisaa("vaimennus", fysiikka.anna_vaimennus());
+            }
+            if let Some(maksiminopeus) = fysiikka.anna_maksiminopeus() {
+                tietue.lisaa("maksiminopeus", maksiminopeus);
+            }
+            if let Some((nopeus, kiihtyvyys)) = fysiikka.anna_tavoitenopeus() {
+                tietue.lisaa_vektori("tavoitenopeus", nopeus);
+                tietue.lisaa("tavoitekiihtyvyys", kiihtyvyys);
+            }
+            if fysiikka.anna_kerrokset() != fysiikka.anna_tagit() {
+                tietue.lisaa(
+                    "tormayskerrokset",
+                    tagien_nimet(fysiikka.anna_kerrokset(), tagit),
+                );
+            }
+            if fysiikka.anna_maski() != Tagit::kaikki() {
+                tietue.lisaa("tormaysmaski", tagien_nimet(fysiikka.anna_maski(), tagit));
+            }
+        }
+        if let Some(alyllinen) = maailma.anna_aly(kappale) {
+            kirjoita_aly(&mut tietue, alyllinen.anna_aly());
+        }
+        if let Some(hahmo) = maailma.anna_kappaleen_pelihahmo(kappale) {
+            tietue.lisaa_vektori("pelihahmo", hahmo.anna_suunta());
+            tietue.lisaa("pelaaja", hahmo.anna_pelaaja());
+        }
+        if let Some(elinvoima) = maailma.anna_lisaosa::<Elinvoima>(kappale) {
+            kirjoita_kestavyys(&mut tietue, &elinvoima.kestavyys, tagit);
+            if elinvoima.elamat() < elinvoima.kestavyys.maksimi {
+                tietue.lisaa("elamia", elinvoima.elamat());
+            }
+            if elinvoima.suojattu_asti() > Duration::new(0, 0) {
+                tietue.lisaa_kesto("suojattu_asti", elinvoima.suojattu_asti());
+            }
+        }
+        if let Some(vahingoittaja) = maailma.anna_lisaosa::<Vahingoittaja>(kappale) {
+            kirjoita_vahinko(&mut tietue, &vahingoittaja.vahinko, tagit);
+        }
+        if let Some(elinaika) = maailma.anna_lisaosa::<Elinaika>(kappale) {
+            // Elinaika tallennetaan jäljellä olevana aikana, jotta se toimii myös tasotiedostoissa
+            let jaljella = elinaika
+                .kuolin_aika()
+                .map(|x| x.checked_sub(pelin_aika).unwrap_or_default());
+            kirjoita_elinaika(&mut tietue, jaljella, elinaika.kantama());
+        }
+        if let Some(kayttaytyminen) = maailma.anna_lisaosa::<Rajakayttaytyminen>(kappale) {
+            tietue.lisaa("raja", rajatoiminnan_nimi(kayttaytyminen.toiminta));
+        }
+        if let Some(liitos) = maailma.anna_lisaosa::<Liitos>(kappale) {
+            let vanhempi = liitos.anna_vanhempi().as_ptr() as *const Kappale;
+            if let Some(vanhemman_indeksi) = vanhemmat.get(&vanhempi) {
+                tietue.lisaa("vanhempi", vanhemman_indeksi);
+                tietue.lisaa_vektori("siirtyma", liitos.siirtyma);
+                if liitos.kulma != 0.0 {
+                    tietue.lisaa("liitoskulma", liitos.kulma);
+                }
+            }
+        }
+        tietueet.push(tietue);
+    }
+
+    for spawneri in maailma.spawnerit() {
+        let mut tietue = Tietue::new("spawneri");
+        spawneri.tallenna(&mut tietue, tagit);
+        tietueet.push(tietue);
+    }
+
+    for animaatio in maailma.animaatiot.kaikki() {
+        let mut tietue = Tietue::new("animaatio");
+        tietue.lisaa("laji", animaatio.nimi());
+        tietue.lisaa_kesto("kuolin_aika", animaatio.kuolin_aika());
+        animaatio.tallenna(&mut tietue);
+        tietueet.push(tietue);
+    }
+
+    tietueet
+}
+
+/// Lataa tekstistä maailman tilan annettuun maailmaan. Maailman aiempi sisältö poistetaan.
+/// Samaa muotoa käytetään myös tasotiedostoissa, joista voidaan jättää pois esim. pelin aika.
+/// # Arguments
+/// * `maailma` - Maailma, johon tila ladataan
+/// * `teksti` - Tallennettu maailman tila
+/// * `lajit` - Rekisterit, joiden avulla tekoälyt ja animaatiot ladataan
+pub fn lataa(maailma: &mut Perusmaailma, teksti: &str, lajit: &Lajit) -> Result<(), String> {
+    let tietueet = lue_tietueet(teksti)?;
+    maailma.tyhjenna();
+    // Omat tagit rekisteröidään ennen kappaleita, jotta niitä voidaan käyttää missä tahansa
+    for tietue in tietueet.iter().filter(|x| x.laji() == "tagit") {
+        for nimi in tietue.vaadi("nimet")?.split_whitespace() {
+            maailma
+                .rekisteroi_tagi(nimi)
+                .map_err(|e| tietue.kentan_virhe("nimet", &e))?;
+        }
+    }
+    let tagit = maailma.tagirekisteri().clone();
+    // Nimetyt kappaleet, joihin voidaan liittää muita kappaleita
+    let mut nimetyt: HashMap<&str, RcKappale> = HashMap::new();
+    // Liitettävät kappaleet, jotka liitetään, kun kaikki kappaleet on ladattu
+    let mut liitettavat = Vec::new();
+
+    for (indeksi, tietue) in tietueet.iter().enumerate() {
+        match tietue.laji() {
+            "maailma" => {
+                if tietue.sisaltaa("pelin_aika") {
+                    maailma.aseta_pelin_aika(tietue.kesto("pelin_aika")?);
+                }
+                maailma.aseta_rajat(lue_rajat(tietue)?);
+                if let Some(integrointi) = lue_integrointi(tietue)? {
+                    maailma.aseta_integrointi(integrointi);
+                }
+            }
+            "tagit" => (),
+            "tormaykset" => lue_tormaysmatriisi(tietue, &tagit, maailma.tormaysmatriisi_mut())?,
+            "kappale" => {
+                let kappale = lataa_kappale(maailma, tietue, lajit, &tagit)?;
+                if let Some(nimi) = tietue.anna("nimi") {
+                    if nimetyt.insert(nimi, Rc::clone(&kappale)).is_some() {
+                        return Err(tietue
+                            .kentan_virhe("nimi", &format!("nimi '{}' on jo käytössä", nimi)));
+                    }
+                }
+                if tietue.anna("vanhempi").is_some() {
+                    let siirtyma = match tietue.sisaltaa("siirtyma") {
+                        true => tietue.vektori("siirtyma")?,
+                        false => Default::default(),
+                    };
+                    let kulma = match tietue.sisaltaa("liitoskulma") {
+                        true => tietue.arvo("liitoskulma")?,
+                        false => Luku::default(),
+                    };
+                    liitettavat.push((kappale, indeksi, siirtyma, kulma));
+                }
+            }
+            "spawneri" => maailma.lisaa_spawneri(Spawneri::lataa(tietue, lajit, &tagit)?),
+            "animaatio" => {
+                let animaatio = lajit.animaatiot.lataa(tietue.vaadi("laji")?, tietue)?;
+                maailma
+                    .animaatiot
+                    .lisaa_animaatio(Kuolevainen::new(animaatio, tietue.kesto("kuolin_aika")?));
+            }
+            laji => return Err(tietue.virhe(&format!("tuntematon tietueen laji '{}'", laji))),
+        }
+        tietue.tarkista_tuntemattomat()?;
+    }
+
+    for (lapsi, indeksi, siirtyma, kulma) in liitettavat {
+        let tietue = &tietueet[indeksi];
+        let nimi = tietue.vaadi("vanhempi")?;
+        let vanhempi = nimetyt.get(nimi).ok_or_else(|| {
+            tietue.kentan_virhe("vanhempi", &format!("kappaletta nimeltä '{}' ei ole", nimi))
+        })?;
+        maailma
+            .liita(&lapsi, vanhempi, siirtyma, kulma)
+            .map_err(|e| tietue.kentan_virhe("vanhempi", &e))?;
+    }
+    Ok(())
+}
+
+/// Tallentaa maailman tilan annettuun tiedostoon
+/// # Arguments
+/// * `maailma` - Tallennettava maailma
+/// * `pelin_aika` - Pelin alusta kulunut aika
+/// * `polku` - Tiedosto, johon tallennetaan
+pub fn tallenna_tiedostoon(
+    maailma: &Perusmaailma,
+    pelin_aika: Duration,
+    polku: &str,
+) -> Result<(), String> {
+    fs::write(polku, tallenna(maailma, pelin_aika))
+        .map_err(|e| format!("Tiedostoon {} tallentaminen epäonnistui: {}", polku, e))
+}
+
+/// Lataa maailman tilan annetusta tiedostosta
+/// # Arguments
+/// * `maailma` - Maailma, johon tila ladataan
+/// * `polku` - Tiedosto, josta ladataan
+/// * `lajit` - Rekisterit, joiden avulla tekoälyt ja animaatiot ladataan
+pub fn lataa_tiedostosta(maailma: &mut Perusmaailma, polku: &str, lajit: &Lajit) -> Result<(), String> {
+    let teksti = fs::read_to_string(polku)
+        .map_err(|e| format!("Tiedoston {} lukeminen epäonnistui: {}", polku, e))?;
+    lataa(maailma, &teksti, lajit).map_err(|e| format!("{}: {}", polku, e))
+}
+
+/// Lataa yksittäisen kappaleen lisäosineen maailmaan ja antaa viitteen siihen
+/// # Arguments
+/// * `maailma` - Maailma, johon kappale lisätään
+/// * `tietue` - Kappaleen tietue
+/// * `lajit` - Rekisterit, joiden avulla tekoäly ladataan
+/// * `tagit` - Rekisteri, josta tagit haetaan nimen perusteella
+fn lataa_kappale(
+    maailma: &mut Perusmaailma,
+    tietue: &Tietue,
+    lajit: &Lajit,
+    tagit: &Tagirekisteri,
+) -> Result<RcKappale, String> {
+    let kappale = lue_kappale(tietue, tagit)?;
+    let piirtotapa = lue_piirtotapa(tietue)?;
+    let kerros = lue_piirtokerros(tietue)?;
+    let aly = lue_aly(tietue, lajit)?;
+    let kestavyys = lue_kestavyys(tietue, tagit)?;
+    let vahinko = lue_vahinko(tietue, tagit)?;
+    let (elinaika, kantama) = lue_elinaika(tietue)?;
+    let rajatoiminta = lue_rajatoiminta(tietue, "raja")?;
+
+    let r_kappale = maailma.lisaa_kappale(kappale);
+    if let Some(piirtotapa) = piirtotapa {
+        maailma.lisaa_piirrettava_kappale(PiirrettavaKappale::new_kerroksella(
+            Rc::clone(&r_kappale),
+            piirtotapa,
+            kerros,
+        ));
+    }
+    if tietue.sisaltaa("nopeus") {
+        let mut fysiikka = Fysiikkakappale::new(tietue.vektori("nopeus")?, Rc::clone(&r_kappale));
+        if tietue.sisaltaa("kulmanopeus") {
+            fysiikka.aseta_kulmanopeus(tietue.arvo("kulmanopeus")?);
+        }
+        if tietue.sisaltaa("nopea") {
+            fysiikka.aseta_nopea(tietue.arvo("nopea")?);
+        }
+        if let Some(liikkuvuus) = lue_liikkuvuus(tietue)? {
+            fysiikka.aseta_liikkuvuus(liikkuvuus);
+        }
+        if tietue.sisaltaa("massa") {
+            let massa: Luku = tietue.arvo("massa")?;
+            if massa <= 0.0 {
+                return Err(tietue.kentan_virhe("massa", "massan pitää olla positiivinen"));
+            }
+            fysiikka.aseta_massa(massa);
+        }
+        if tietue.sisaltaa("kimmoisuus") {
+            fysiikka.aseta_kimmoisuus(tietue.arvo("kimmoisuus")?);
+        }
+        if tietue.sisaltaa("kitka") {
+            fysiikka.aseta_kitka(tietue.arvo("kitka")?);
+        }
+        if tietue.sisaltaa("vaimennus") {
+            fysiikka.aseta_vaimennus(tietue.arvo("vaimennus")?);
+        }
+        if tietue.sisaltaa("maksiminopeus") {
+            fysiikka.aseta_maksiminopeus(Some(tietue.arvo("maksiminopeus")?));
+        }
+        if tietue.sisaltaa("tavoitenopeus") {
+            let kiihtyvyys: Luku = tietue.arvo("tavoitekiihtyvyys")?;
+            if kiihtyvyys < 0.0 {
+                return Err(tietue.kentan_virhe("tavoitekiihtyvyys", "ei voi olla negatiivinen"));
+            }
+            fysiikka.aseta_tavoitenopeus(tietue.vektori("tavoitenopeus")?, kiihtyvyys);
+        }
+        if tietue.sisaltaa("tormayskerrokset") {
+            fysiikka.aseta_kerrokset(lue_tagit(tietue, "tormayskerrokset", tagit)?);
+        }
+        if tietue.sisaltaa("tormaysmaski") {
+            fysiikka.aseta_maski(lue_tagit(tietue, "tormaysmaski", tagit)?);
+        }
+        maailma.lisaa_fysiikkakappale(fysiikka);
+    }
+    if let Some(aly) = aly {
+        maailma.lisaa_aly(Alyllinen::new(Rc::clone(&r_kappale), aly));
+    }
+    if tietue.sisaltaa("pelihahmo") {
+        let mut hahmo = Pelihahmo::new(Rc::clone(&r_kappale));
+        hahmo.aseta_suunta(tietue.vektori("pelihahmo")?);
+        // Ilman pelaajan numeroa hahmot annetaan pelaajille tiedoston järjestyksessä
+        hahmo.aseta_pelaaja(match tietue.sisaltaa("pelaaja") {
+            true => tietue.arvo("pelaaja")?,
+            false => maailma.pelihahmot().len(),
+        });
+        maailma.lisaa_pelihahmo(hahmo);
+    }
+    if let Some(kestavyys) = kestavyys {
+        let mut elinvoima = Elinvoima::new(Rc::clone(&r_kappale), kestavyys);
+        if tietue.sisaltaa("elamia") {
+            elinvoima.aseta_elamat(tietue.arvo("elamia")?);
+        }
+        if tietue.sisaltaa("suojattu_asti") {
+            elinvoima.aseta_suojattu_asti(tietue.kesto("suojattu_asti")?);
+        }
+        maailma.lisaa_lisaosa(elinvoima);
+    }
+    if let Some(vahinko) = vahinko {
+        maailma.lisaa_lisaosa(Vahingoittaja::new(Rc::clone(&r_kappale), vahinko));
+    }
+    if elinaika.is_some() || kantama.is_some() {
+        let kuolin_aika = match elinaika {
+            Some(elinaika) => maailma.anna_pelin_aika() + elinaika,
+            None => Duration::MAX,
+        };
+        let mut elinaika = Elinaika::new(Rc::clone(&r_kappale), kuolin_aika);
+        elinaika.aseta_kantama(kantama);
+        maailma.lisaa_lisaosa(elinaika);
+    }
+    if let Some(toiminta) = rajatoiminta {
+        maailma.lisaa_lisaosa(Rajakayttaytyminen::new(Rc::clone(&r_kappale), toiminta));
+    }
+    Ok(r_kappale)
+}
+
+/// Tallentaa kappaleen muodon, sijainnin, asennon ja tagit tietueeseen
+/// # Arguments
+/// * `tietue` - Tietue, johon kappale tallennetaan
+/// * `kappale` - Tallennettava kappale
+/// * `tagit` - Rekisteri, josta tagien nimet haetaan
+pub fn kirjoita_kappale(tietue: &mut Tietue, kappale: &Kappale, tagit: &Tagirekisteri) {
+    match kappale.muoto {
+        Muoto::Nelio(leveys, korkeus) => tietue.lisaa("muoto", format!("nelio {} {}", leveys, korkeus)),
+        Muoto::Ympyra(sade) => tietue.lisaa("muoto", format!("ympyra {}", sade)),
+        Muoto::Monikulmio(monikulmio) => {
+            let karjet: Vec<String> = monikulmio
+                .karjet()
+                .iter()
+                .map(|x| format!("{} {}", x.x, x.y))
+                .collect();
+            tietue.lisaa("muoto", format!("monikulmio {}", karjet.join(" ")))
+        }
+        Muoto::Kapseli(pituus, sade) => tietue.lisaa("muoto", format!("kapseli {} {}", pituus, sade)),
+        Muoto::Jana(pituus) => tietue.lisaa("muoto", format!("jana {}", pituus)),
+    }
+    tietue.lisaa_vektori("sijainti", kappale.kulman_sijainti());
+    if kappale.kulma() != 0.0 {
+        tietue.lisaa("kulma", kappale.kulma());
+    }
+    if !kappale.tagit.is_empty() {
+        tietue.lisaa("tagi", tagien_nimet(kappale.tagit, tagit));
+    }
+}
+
+/// Lukee kappaleen tietueesta. Sijainti voidaan antaa joko kulman sijaintina
+/// kentällä `sijainti` tai keskipisteen sijaintina kentällä `keskipiste`.
+/// Asento annetaan radiaaneina kentällä `kulma`, joka voidaan jättää pois.
+/// # Arguments
+/// * `tietue` - Tietue, josta kappale luetaan
+/// * `tagit` - Rekisteri, josta tagit haetaan nimen perusteella
+pub fn lue_kappale(tietue: &Tietue, tagit: &Tagirekisteri) -> Result<Kappale, String> {
+    let muoto = lue_muoto(tietue)?;
+    let kappaleen_tagit = lue_tagit(tietue, "tagi", tagit)?;
+    let mut kappale = match (tietue.sisaltaa("sijainti"), tietue.sisaltaa("keskipiste")) {
+        (true, false) => {
+            let sijainti = tietue.vektori("sijainti")?;
+            Kappale::new_kulmalla(muoto, sijainti.x, sijainti.y, kappaleen_tagit)
+        }
+        (false, true) => {
+            let sijainti = tietue.vektori("keskipiste")?;
+            Kappale::new_keskipisteella(muoto, sijainti.x, sijainti.y, kappaleen_tagit)
+        }
+        (true, true) => {
+            return Err(tietue.virhe("anna joko 'sijainti' tai 'keskipiste', ei molempia"))
+        }
+        (false, false) => return Err(tietue.virhe("puuttuu kenttä 'sijainti' tai 'keskipiste'")),
+    };
+    if tietue.sisaltaa("kulma") {
+        kappale.aseta_kulma(tietue.arvo("kulma")?);
+    }
+    Ok(kappale)
+}
+
+/// Tallentaa piirtotavan tietueeseen
+/// # Arguments
+/// * `tietue` - Tietue, johon piirtotapa tallennetaan
+/// * `piirtotapa` - Tallennettava piirtotapa
+pub fn kirjoita_piirtotapa(tietue: &mut Tietue, piirtotapa: &Piirtotapa) {
+    match piirtotapa {
+        Piirtotapa::Yksivarinen { vari } => tietue.lisaa_vari("vari", *vari),
+        Piirtotapa::Kuvallinen { kuvan_nimi } => tietue.lisaa("kuva", kuvan_nimi),
+    }
+}
+
+/// Lukee piirtotavan tietueesta, jos tietueessa on kenttä `vari` tai `kuva`
+/// # Arguments
+/// * `tietue` - Tietue, josta piirtotapa luetaan
+pub fn lue_piirtotapa(tietue: &Tietue) -> Result<Option<Piirtotapa>, String> {
+    match (tietue.sisaltaa("vari"), tietue.anna("kuva")) {
+        (true, None) => Ok(Some(Piirtotapa::Yksivarinen {
+            vari: tietue.vari("vari")?,
+        })),
+        (false, Some(kuva)) => Ok(Some(Piirtotapa::Kuvallinen {
+            kuvan_nimi: kuva.to_string(),
+        })),
+        (true, Some(_)) => Err(tietue.virhe("anna joko 'vari' tai 'kuva', ei molempia")),
+        (false, None) => Ok(None),
+    }
+}
+
+/// Tallentaa piirtokerroksen tietueeseen, jos se ei ole oletuskerros
+/// # Arguments
+/// * `tietue` - Tietue, johon piirtokerros tallennetaan
+/// * `kerros` - Tallennettava piirtokerros
+pub fn kirjoita_piirtokerros(tietue: &mut Tietue, kerros: Piirtokerros) {
+    let nimi = match kerros {
+        Piirtokerros::Tausta => "tausta",
+        Piirtokerros::Maailma => return,
+        Piirtokerros::Efektit => "efektit",
+        Piirtokerros::Kayttoliittyma => "kayttoliittyma",
+    };
+    tietue.lisaa("kerros", nimi);
+}
+
+/// Lukee piirtokerroksen tietueesta. Jos kenttää `kerros` ei ole, annetaan oletuskerros.
+/// # Arguments
+/// * `tietue` - Tietue, josta piirtokerros luetaan
+pub fn lue_piirtokerros(tietue: &Tietue) -> Result<Piirtokerros, String> {
+    match tietue.anna("kerros") {
+        None => Ok(Default::default()),
+        Some("tausta") => Ok(Piirtokerros::Tausta),
+        Some("maailma") => Ok(Piirtokerros::Maailma),
+        Some("efektit") => Ok(Piirtokerros::Efektit),
+        Some("kayttoliittyma") => Ok(Piirtokerros::Kayttoliittyma),
+        Some(x) => Err(tietue.kentan_virhe(
+            "kerros",
+            &format!(
+                "tuntematon kerros '{}'. Odotettiin 'tausta', 'maailma', 'efektit' tai 'kayttoliittyma'",
+                x
+            ),
+        )),
+    }
+}
+
+/// Tallentaa maailman rajat tietueeseen
+/// # Arguments
+/// * `tietue` - Tietue, johon rajat tallennetaan
+/// * `rajat` - Tallennettavat rajat
+pub fn kirjoita_rajat(tietue: &mut Tietue, rajat: &Maailmanrajat) {
+    let (vasen_yla, oikea_ala) = (rajat.alue.vasen_yla, rajat.alue.oikea_ala);
+    tietue.lisaa(
+        "rajat",
+        format!("{} {} {} {}", vasen_yla.x, vasen_yla.y, oikea_ala.x, oikea_ala.y),
+    );
+    if let Some(oletus) = rajat.oletus {
+        tietue.lisaa("raja", rajatoiminnan_nimi(oletus));
+    }
+}
+
+/// Lukee maailman rajat tietueesta, jos tietueessa on kenttä `rajat`
+/// # Arguments
+/// * `tietue` - Tietue, josta rajat luetaan
+pub fn lue_rajat(tietue: &Tietue) -> Result<Option<Maailmanrajat>, String> {
+    if !tietue.sisaltaa("rajat") {
+        return Ok(None);
+    }
+    let luvut = tietue.luvut::<Luku>("rajat", 4)?;
+    if luvut[2] <= luvut[0] || luvut[3] <= luvut[1] {
+        return Err(tietue.kentan_virhe(
+            "rajat",
+            "oikean alakulman pitää olla vasemman yläkulman oikealla puolella ja alapuolella",
+        ));
+    }
+    let mut rajat = Maailmanrajat::new(Rajaus::new(
+        Vektori::new(luvut[0], luvut[1]),
+        Vektori::new(luvut[2], luvut[3]),
+    ));
+    rajat.oletus = lue_rajatoiminta(tietue, "raja")?;
+    Ok(Some(rajat))
+}
+
+/// Antaa rajatoiminnan nimen tallennusta varten
+/// # Arguments
+/// * `toiminta` - Rajatoiminta, jonka nimi annetaan
+fn rajatoiminnan_nimi(toiminta: Rajatoiminta) -> &'static str {
+    match toiminta {
+        Rajatoiminta::Pysaytys => "pysaytys",
+        Rajatoiminta::Kierto => "kierto",
+        Rajatoiminta::Kimpoaminen => "kimpoaminen",
+        Rajatoiminta::Tuhoutuminen => "tuhoutuminen",
+    }
+}
+
+/// Lukee rajatoiminnan tietueen annetusta kentästä, jos kenttä on olemassa
+/// # Arguments
+/// * `tietue` - Tietue, josta rajatoiminta luetaan
+/// * `avain` - Kentän nimi
+fn lue_rajatoiminta(tietue: &Tietue, avain: &str) -> Result<Option<Rajatoiminta>, String> {
+    match tietue.anna(avain) {
+        None => Ok(None),
+        Some("pysaytys") => Ok(Some(Rajatoiminta::Pysaytys)),
+        Some("kierto") => Ok(Some(Rajatoiminta::Kierto)),
+        Some("kimpoaminen") => Ok(Some(Rajatoiminta::Kimpoaminen)),
+        Some("tuhoutuminen") => Ok(Some(Rajatoiminta::Tuhoutuminen)),
+        Some(x) => Err(tietue.kentan_virhe(
+            avain,
+            &format!(
+                "tuntematon rajatoiminta '{}'. Odotettiin 'pysaytys', 'kierto', 'kimpoaminen' tai 'tuhoutuminen'",
+                x
+            ),
+        )),
+    }
+}
+
+/// Antaa integrointitavan nimen tallennusta varten
+/// # Arguments
+/// * `integrointi` - Integrointitapa, jonka nimi annetaan
+fn integroinnin_nimi(integrointi: Integrointi) -> &'static str {
+    match integrointi {
+        Integrointi::SemiimplisiittinenEuler => "euler",
+        Integrointi::Verlet => "verlet",
+    }
+}
+
+/// Lukee maailman integrointitavan tietueesta, jos se on annettu
+/// # Arguments
+/// * `tietue` - Tietue, josta integrointitapa luetaan
+fn lue_integrointi(tietue: &Tietue) -> Result<Option<Integrointi>, String> {
+    match tietue.anna("integrointi") {
+        None => Ok(None),
+        Some("euler") => Ok(Some(Integrointi::SemiimplisiittinenEuler)),
+        Some("verlet") => Ok(Some(Integrointi::Verlet)),
+        Some(x) => Err(tietue.kentan_virhe(
+            "integrointi",
+            &format!(
+                "tuntematon integrointitapa '{}'. Odotettiin 'euler' tai 'verlet'",
+                x
+            ),
+        )),
+    }
+}
+
+/// Antaa liikkuvuuden nimen tallennusta varten
+/// # Arguments
+/// * `liikkuvuus` - Liikkuvuus, jonka nimi annetaan
+fn liikkuvuuden_nimi(liikkuvuus: Liikkuvuus) -> &'static str {
+    match liikkuvuus {
+        Liikkuvuus::Staattinen => "staattinen",
+        Liikkuvuus::Kinemaattinen => "kinemaattinen",
+        Liikkuvuus::Dynaaminen => "dynaaminen",
+    }
+}
+
+/// Lukee fysiikkakappaleen liikkuvuuden tietueesta, jos se on annettu
+/// # Arguments
+/// * `tietue` - Tietue, josta liikkuvuus luetaan
+fn lue_liikkuvuus(tietue: &Tietue) -> Result<Option<Liikkuvuus>, String> {
+    match tietue.anna("liikkuvuus") {
+        None => Ok(None),
+        Some("staattinen") => Ok(Some(Liikkuvuus::Staattinen)),
+        Some("kinemaattinen") => Ok(Some(Liikkuvuus::Kinemaattinen)),
+        Some("dynaaminen") => Ok(Some(Liikkuvuus::Dynaaminen)),
+        Some(x) => Err(tietue.kentan_virhe(
+            "liikkuvuus",
+            &format!(
+                "tuntematon liikkuvuus '{}'. Odotettiin 'staattinen', 'kinemaattinen' tai 'dynaaminen'",
+                x
+            ),
+        )),
+    }
+}
+
+/// Tallentaa tekoälyn lajin ja tiedot tietueeseen
+/// # Arguments
+/// * `tietue` - Tietue, johon tekoäly tallennetaan
+/// * `aly` - Tallennettava tekoäly
+pub fn kirjoita_aly(tietue: &mut Tietue, aly: &dyn Aly) {
+    tietue.lisaa("aly", aly.nimi());
+    aly.tallenna(tietue);
+}
+
+/// Lukee tekoälyn tietueesta, jos tietueessa on kenttä `aly`
+/// # Arguments
+/// * `tietue` - Tietue, josta tekoäly luetaan
+/// * `lajit` - Rekisterit, joiden avulla tekoäly ladataan
+pub fn lue_aly(tietue: &Tietue, lajit: &Lajit) -> Result<Option<Box<dyn Aly>>, String> {
+    match tietue.anna("aly") {
+        Some(nimi) => lajit.alyt.lataa(nimi, tietue).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Lukee muodon tietueen kentästä `muoto`
+/// # Arguments
+/// * `tietue` - Tietue, josta muoto luetaan
+fn lue_muoto(tietue: &Tietue) -> Result<Muoto, String> {
+    let arvo = tietue.vaadi("muoto")?;
+    let osat: Vec<&str> = arvo.split_whitespace().collect();
+    let luku = |x: &str| {
+        x.parse::<Luku>()
+            .map_err(|_| tietue.kentan_virhe("muoto", &format!("virheellinen luku '{}'", x)))
+    };
+    match osat.as_slice() {
+        ["nelio", leveys, korkeus] => Ok(Muoto::Nelio(luku(leveys)?, luku(korkeus)?)),
+        ["ympyra", sade] => Ok(Muoto::Ympyra(luku(sade)?)),
+        ["kapseli", pituus, sade] => Ok(Muoto::Kapseli(luku(pituus)?, luku(sade)?)),
+        ["jana", pituus] => Ok(Muoto::Jana(luku(pituus)?)),
+        ["monikulmio", luvut @ ..] if luvut.len() % 2 == 0 => {
+            let mut karjet = Vec::new();
+            for pari in luvut.chunks(2) {
+                karjet.push(Vektori::new(luku(pari[0])?, luku(pari[1])?));
+            }
+            Ok(Muoto::Monikulmio(
+                Monikulmio::new(&karjet).map_err(|e| tietue.kentan_virhe("muoto", &e))?,
+            ))
+        }
+        _ => Err(tietue.kentan_virhe(
+            "muoto",
+            &format!(
+                "tuntematon muoto '{}'. Odotettiin 'nelio leveys korkeus', 'ympyra sade', \
+                 'kapseli pituus sade', 'jana pituus' tai 'monikulmio x1 y1 x2 y2 ...'",
+                arvo
+            ),
+        )),
+    }
+}
+
+/// Tallentaa kestävyyden tietueeseen. Pudotusten kappaleet ja piirtotavat tallennetaan
+/// kenttiin, joiden etuliite on `pudotus1.`, `pudotus2.` jne. pudotusten järjestyksessä.
+/// # Arguments
+/// * `tietue` - Tietue, johon kestävyys tallennetaan
+/// * `kestavyys` - Tallennettava kestävyys
+/// * `tagit` - Rekisteri, josta pudotusten tagien nimet haetaan
+pub fn kirjoita_kestavyys(tietue: &mut Tietue, kestavyys: &Kestavyys, tagit: &Tagirekisteri) {
+    tietue.lisaa("elamat", kestavyys.maksimi);
+    if kestavyys.suojausaika > Duration::new(0, 0) {
+        tietue.lisaa_kesto("suojaus", kestavyys.suojausaika);
+    }
+    let mut kuolemat = Vec::new();
+    let mut pudotukset = Vec::new();
+    for kuolema in &kestavyys.kuolemat {
+        match kuolema {
+            Kuolema::Poisto => kuolemat.push("poisto"),
+            Kuolema::Animaatio => kuolemat.push("animaatio"),
+            Kuolema::PelinLoppu => kuolemat.push("loppu"),
+            Kuolema::Pudotus {
+                kappale,
+                piirtotapa,
+            } => {
+                kuolemat.push("pudotus");
+                let mut pudotus = Tietue::new("pudotus");
+                kirjoita_kappale(&mut pudotus, kappale, tagit);
+                kirjoita_piirtotapa(&mut pudotus, piirtotapa);
+                pudotukset.push(pudotus);
+            }
+        }
+    }
+    tietue.lisaa("kuolema", kuolemat.join(" "));
+    for (i, pudotus) in pudotukset.iter().enumerate() {
+        tietue.lisaa_alitietue(&format!("pudotus{}", i + 1), pudotus);
+    }
+}
+
+/// Lukee kestävyyden tietueesta, jos tietueessa on kenttä `elamat`. Kuoleman seuraukset
+/// luetaan kentästä `kuolema`, jonka puuttuessa kuollut kappale poistetaan. Kunkin
+/// pudotuksen kappale ja piirtotapa luetaan omilla etuliitteillään.
+/// # Arguments
+/// * `tietue` - Tietue, josta kestävyys luetaan
+/// * `tagit` - Rekisteri, josta pudotusten tagit haetaan nimen perusteella
+pub fn lue_kestavyys(tietue: &Tietue, tagit: &Tagirekisteri) -> Result<Option<Kestavyys>, String> {
+    if !tietue.sisaltaa("elamat") {
+        return Ok(None);
+    }
+    let mut kestavyys = Kestavyys::new(tietue.arvo("elamat")?);
+    if tietue.sisaltaa("suojaus") {
+        kestavyys.suojausaika = tietue.kesto("suojaus")?;
+    }
+    if let Some(arvo) = tietue.anna("kuolema") {
+        let mut pudotuksia = 0;
+        kestavyys.kuolemat = arvo
+            .split_whitespace()
+            .map(|x| match x {
+                "poisto" => Ok(Kuolema::Poisto),
+                "animaatio" => Ok(Kuolema::Animaatio),
+                "loppu" => Ok(Kuolema::PelinLoppu),
+                "pudotus" => {
+                    pudotuksia += 1;
+                    lue_pudotus(&tietue.alitietue(&format!("pudotus{}", pudotuksia)), tagit)
+                }
+                _ => Err(tietue.kentan_virhe(
+                    "kuolema",
+                    &format!(
+                        "tuntematon seuraus '{}'. Odotettiin 'poisto', 'animaatio', \
+                         'pudotus' tai 'loppu'",
+                        x
+                    ),
+                )),
+            })
+            .collect::<Result<_, _>>()?;
+    }
+    Ok(Some(kestavyys))
+}
+
+/// Lukee pudotuksen kappaleen ja piirtotavan pudotuksen alitietueesta
+/// # Arguments
+/// * `tietue` - Alitietue, josta pudotus luetaan
+/// * `tagit` - Rekisteri, josta pudotettavan kappaleen tagit haetaan nimen perusteella
+fn lue_pudotus(tietue: &Tietue, tagit: &Tagirekisteri) -> Result<Kuolema, String> {
+    let kappale = lue_kappale(tietue, tagit)?;
+    let piirtotapa = match lue_piirtotapa(tietue)? {
+        Some(piirtotapa) => piirtotapa,
+        None => return Err(tietue.virhe("puuttuu kenttä 'vari' tai 'kuva'")),
+    };
+    tietue.tarkista_tuntemattomat()?;
+    Ok(Kuolema::Pudotus {
+        kappale,
+        piirtotapa,
+    })
+}
+
+/// Tallentaa vahingon tietueeseen
+/// # Arguments
+/// * `tietue` - Tietue, johon vahinko tallennetaan
+/// * `vahinko` - Tallennettava vahinko
+/// * `tagit` - Rekisteri, josta vahingoitettavien tagien nimet haetaan
+pub fn kirjoita_vahinko(tietue: &mut Tietue, vahinko: &Vahinko, tagit: &Tagirekisteri) {
+    tietue.lisaa("vahinko", vahinko.maara);
+    if !vahinko.kohteet.is_empty() {
+        tietue.lisaa("vahingoittaa", tagien_nimet(vahinko.kohteet, tagit));
+    }
+}
+
+/// Lukee vahingon tietueesta, jos tietueessa on kenttä `vahinko`. Vahingoitettavien
+/// tagit luetaan kentästä `vahingoittaa`, jonka puuttuessa vahingoitetaan kaikkia.
+/// # Arguments
+/// * `tietue` - Tietue, josta vahinko luetaan
+/// * `tagit` - Rekisteri, josta tagit haetaan nimen perusteella
+pub fn lue_vahinko(tietue: &Tietue, tagit: &Tagirekisteri) -> Result<Option<Vahinko>, String> {
+    if !tietue.sisaltaa("vahinko") {
+        return Ok(None);
+    }
+    let mut vahinko = Vahinko::new(tietue.arvo("vahinko")?);
+    vahinko.kohteet = lue_tagit(tietue, "vahingoittaa", tagit)?;
+    Ok(Some(vahinko))
+}
+
+/// Tallentaa elinajan ja kantaman tietueeseen kentillä `elinaika` ja `kantama`
+/// # Arguments
+/// * `tietue` - Tietue, johon elinaika tallennetaan
+/// * `elinaika` - Kuinka kauan kappale on vielä olemassa
+/// * `kantama` - Kuinka pitkän matkan kappale voi vielä kulkea
+pub fn kirjoita_elinaika(tietue: &mut Tietue, elinaika: Option<Duration>, kantama: Option<Luku>) {
+    if let Some(elinaika) = elinaika {
+        tietue.lisaa_kesto("elinaika", elinaika);
+    }
+    if let Some(kantama) = kantama {
+        tietue.lisaa("kantama", kantama);
+    }
+}
+
+/// Lukee elinajan ja kantaman tietueen kentistä `elinaika` ja `kantama`, jos niitä on
+/// # Arguments
+/// * `tietue` - Tietue, josta elinaika luetaan
+pub fn lue_elinaika(tietue: &Tietue) -> Result<(Option<Duration>, Option<Luku>), String> {
+    let elinaika = match tietue.sisaltaa("elinaika") {
+        true => Some(tietue.kesto("elinaika")?),
+        false => None,
+    };
+    let kantama = match tietue.sisaltaa("kantama") {
+        true => Some(tietue.arvo("kantama")?),
+        false => None,
+    };
+    Ok((elinaika, kantama))
+}
+
+/// Tallentaa törmäysmatriisin tietueeksi, jossa kunkin kerroksen kentässä ovat kerrokset,
+/// joihin se törmää. Matriisi on symmetrinen, joten rivi kirjoitetaan vain, jos aiemmin
+/// kirjoitetut rivit eivät jo kerro sitä. Antaa ei mitään, jos kaikki kerrokset törmäävät.
+/// # Arguments
+/// * `matriisi` - Tallennettava törmäysmatriisi
+/// * `tagit` - Rekisteri, josta kerrosten nimet haetaan
+fn kirjoita_tormaysmatriisi(matriisi: &Tormaysmatriisi, tagit: &Tagirekisteri) -> Option<Tietue> {
+    let nimetyt: Tagit = Tagit::kaikki()
+        .iter()
+        .filter(|x| tagit.nimi(*x).is_some())
+        .collect();
+    let nimetty_rivi = |matriisi: &Tormaysmatriisi, kerros| -> Tagit {
+        matriisi
+            .rivi(kerros)
+            .iter()
+            .filter(|x| nimetyt.sisaltaa(*x))
+            .collect()
+    };
+    let mut tietue = Tietue::new("tormaykset");
+    let mut kirjoitettu = Tormaysmatriisi::new();
+    let mut kirjoitettiinko = false;
+    for kerros in nimetyt.iter() {
+        let rivi = nimetty_rivi(matriisi, kerros);
+        if rivi != nimetty_rivi(&kirjoitettu, kerros) {
+            tietue.lisaa(
+                tagit.nimi(kerros).unwrap_or_default(),
+                tagien_nimet(rivi, tagit),
+            );
+            kirjoitettu.aseta_rivi(kerros, rivi);
+            kirjoitettiinko = true;
+        }
+    }
+    if kirjoitettiinko {
+        Some(tietue)
+    } else {
+        None
+    }
+}
+
+/// Lukee törmäysmatriisin rivit tietueesta. Kentän nimi on kerros ja arvona ovat kerrokset,
+/// joihin se törmää. Rivit luetaan kerrosten järjestyksessä, ja puuttuvat rivit säilyvät.
+/// # Arguments
+/// * `tietue` - Tietue, josta rivit luetaan
+/// * `tagit` - Rekisteri, josta kerrokset haetaan nimen perusteella
+/// * `matriisi` - Matriisi, johon rivit asetetaan
+fn lue_tormaysmatriisi(
+    tietue: &Tietue,
+    tagit: &Tagirekisteri,
+    matriisi: &mut Tormaysmatriisi,
+) -> Result<(), String> {
+    for kerros in Tagit::kaikki().iter() {
+        if let Some(nimi) = tagit.nimi(kerros) {
+            if tietue.sisaltaa(nimi) {
+                matriisi.aseta_rivi(kerros, lue_tagit(tietue, nimi, tagit)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Antaa tagien nimet välilyönnein eroteltuina
+/// # Arguments
+/// * `joukko` - Tagit, joiden nimet annetaan
+/// * `tagit` - Rekisteri, josta tagien nimet haetaan
+fn tagien_nimet(joukko: Tagit, tagit: &Tagirekisteri) -> String {
+    let nimet: Vec<&str> = joukko.iter().filter_map(|x| tagit.nimi(x)).collect();
+    nimet.join(" ")
+}
+
+/// Lukee tagit annetusta kentästä, jossa tagien nimet on eroteltu välilyönneillä.
+/// Kentän puuttuessa tageja ei ole.
+/// # Arguments
+/// * `tietue` - Tietue, josta tagit luetaan
+/// * `avain` - Kenttä, josta tagit luetaan
+/// * `tagit` - Rekisteri, josta tagit haetaan nimen perusteella
+fn lue_tagit(tietue: &Tietue, avain: &str, tagit: &Tagirekisteri) -> Result<Tagit, String> {
+    match tietue.anna(avain) {
+        None => Ok(Tagit::new()),
+        Some(arvo) => arvo
+            .split_whitespace()
+            .map(|nimi| {
+                tagit.anna(nimi).ok_or_else(|| {
+                    tietue.kentan_virhe(
+                        avain,
+                        &format!(
+                            "tuntematon tagi '{}'. Omat tagit pitää esitellä [tagit]-tietueessa",
+                            nimi
+                        ),
+                    )
+                })
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::pixels::Color;
+
+    use super::*;
+    use crate::maailma::kappale::Tagi;
+    use crate::maailma::luku::luku;
+
+    /// Lataa tekstin uuteen maailmaan
+    fn ladattu(teksti: &str) -> Result<Perusmaailma, String> {
+        let mut maailma = Perusmaailma::new();
+        lataa(&mut maailma, teksti, &Lajit::default())?;
+        Ok(maailma)
+    }
+
+    const PUDOTTAVA: &str = "
+[kappale]
+muoto = nelio 10 10
+keskipiste = 50 40
+elamat = 1
+kuolema = animaatio pudotus pudotus
+pudotus1.muoto = ympyra 2
+pudotus1.keskipiste = 0 0
+pudotus1.tagi = partikkeli
+pudotus1.vari = 0 255 0
+pudotus2.muoto = nelio 4 4
+pudotus2.sijainti = 0 0
+pudotus2.kuva = kuvat/esine.png
+";
+
+    #[test]
+    fn pudotukset_sailyvat_tallennuksessa() {
+        let maailma = ladattu(PUDOTTAVA).unwrap();
+        let kappale = &maailma.kappaleet()[0];
+        let kuolemat = &maailma
+            .anna_lisaosa::<Elinvoima>(kappale)
+            .unwrap()
+            .kestavyys
+            .kuolemat;
+        assert_eq!(kuolemat.len(), 3);
+        match &kuolemat[1] {
+            Kuolema::Pudotus {
+                kappale,
+                piirtotapa: Piirtotapa::Yksivarinen { .. },
+            } => assert!(kappale.tagit.sisaltaa(Tagi::Partikkeli)),
+            _ => panic!("Ensimmäinen pudotus puuttuu"),
+        }
+        match &kuolemat[2] {
+            Kuolema::Pudotus {
+                piirtotapa: Piirtotapa::Kuvallinen { kuvan_nimi },
+                ..
+            } => assert_eq!(kuvan_nimi, "kuvat/esine.png"),
+            _ => panic!("Toinen pudotus puuttuu"),
+        }
+
+        let teksti = tallenna(&maailma, Duration::new(0, 0));
+        assert!(teksti.contains("pudotus2.kuva = kuvat/esine.png"));
+        let uudelleen = tallenna(&ladattu(&teksti).unwrap(), Duration::new(0, 0));
+        assert_eq!(teksti, uudelleen);
+    }
+
+    #[test]
+    fn pudotuksen_virheet() {
+        let puuttuva = PUDOTTAVA.replace("animaatio pudotus pudotus", "pudotus");
+        let puuttuva = puuttuva.split("pudotus1.muoto").next().unwrap();
+        let virhe = ladattu(puuttuva).err().unwrap();
+        assert!(virhe.contains("pudotus1"), "{}", virhe);
+
+        let ylimaarainen = PUDOTTAVA.replace("pudotus pudotus", "pudotus");
+        let virhe = ladattu(&ylimaarainen).err().unwrap();
+        let odotettu = "tuntematon kenttä 'pudotus2.muoto'";
+        assert!(virhe.contains(odotettu), "{}", virhe);
+
+        let tuntematon = PUDOTTAVA.replace("pudotus1.tagi", "pudotus1.tagit");
+        let virhe = ladattu(&tuntematon).err().unwrap();
+        assert!(virhe.contains("tuntematon kenttä 'tagit'"), "{}", virhe);
+    }
+
+    /// Rakentaa maailman, jossa on rajat, oma tagi, fysiikkakappale, piirrettäviä kappaleita,
+    /// liitetty kappale ja lisäosia
+    fn rakennettu_maailma() -> Perusmaailma {
+        let mut maailma = Perusmaailma::new();
+        let mut rajat = Maailmanrajat::new(Rajaus::new(
+            Vektori::new(luku(0.0), luku(0.0)),
+            Vektori::new(luku(400.0), luku(300.0)),
+        ));
+        rajat.oletus = Some(Rajatoiminta::Kierto);
+        maailma.aseta_rajat(Some(rajat));
+        let oma = maailma.rekisteroi_tagi("laatikko").unwrap();
+
+        let muoto = Muoto::Nelio(luku(20.0), luku(10.0));
+        let vanhempi = maailma.lisaa_kappale(Kappale::new_keskipisteella(
+            muoto,
+            luku(50.0),
+            luku(60.0),
+            oma,
+        ));
+        let mut fysiikka =
+            Fysiikkakappale::new(Vektori::new(luku(3.0), luku(-1.5)), Rc::clone(&vanhempi));
+        fysiikka.aseta_massa(luku(2.5));
+        fysiikka.aseta_kimmoisuus(luku(0.5));
+        fysiikka.aseta_tavoitenopeus(Vektori::new(luku(-4.0), luku(2.0)), luku(8.0));
+        maailma.lisaa_fysiikkakappale(fysiikka);
+        maailma.lisaa_piirrettava_kappale(PiirrettavaKappale::new(
+            Rc::clone(&vanhempi),
+            Piirtotapa::Kuvallinen {
+                kuvan_nimi: "kuvat/laatikko.png".to_string(),
+            },
+        ));
+        let mut elinvoima = Elinvoima::new(Rc::clone(&vanhempi), Kestavyys::new(luku(3.0)));
+        elinvoima.aseta_elamat(luku(2.0));
+        maailma.lisaa_lisaosa(elinvoima);
+        maailma.lisaa_lisaosa(Rajakayttaytyminen::new(
+            Rc::clone(&vanhempi),
+            Rajatoiminta::Kimpoaminen,
+        ));
+
+        let muoto = Muoto::Ympyra(luku(4.0));
+        let lapsi = maailma.lisaa_kappale(Kappale::new_keskipisteella(
+            muoto,
+            luku(0.0),
+            luku(0.0),
+            Tagi::Vihollinen,
+        ));
+        maailma.lisaa_piirrettava_kappale(PiirrettavaKappale::new(
+            Rc::clone(&lapsi),
+            Piirtotapa::Yksivarinen {
+                vari: Color::RGB(0, 255, 0),
+            },
+        ));
+        let siirtyma = Vektori::new(luku(15.0), luku(0.0));
+        maailma
+            .liita(&lapsi, &vanhempi, siirtyma, luku(0.5))
+            .unwrap();
+        maailma
+    }
+
+    #[test]
+    fn maailma_sailyy_tallennuksessa() {
+        let maailma = rakennettu_maailma();
+        let teksti = tallenna(&maailma, Duration::new(0, 0));
+        let ladattu = ladattu(&teksti).unwrap();
+        assert_eq!(ladattu.kappaleet().len(), 2);
+        assert_eq!(ladattu.tagirekisteri().omat().to_vec(), vec!["laatikko"]);
+        let rajat = ladattu.rajat().unwrap();
+        assert_eq!(rajat.alue.oikea_ala, Vektori::new(luku(400.0), luku(300.0)));
+        assert!(rajat.oletus == Some(Rajatoiminta::Kierto));
+
+        let vanhempi = &ladattu.kappaleet()[0];
+        let lapsi = &ladattu.kappaleet()[1];
+        let oma = ladattu.tagirekisteri().anna("laatikko").unwrap();
+        assert!(vanhempi.borrow().tagit.sisaltaa(oma));
+        let liitetty = ladattu.anna_vanhempi(lapsi).unwrap();
+        assert!(Rc::ptr_eq(&liitetty, vanhempi));
+        let fysiikka = ladattu.anna_fysiikka(vanhempi).unwrap();
+        assert_eq!(fysiikka.anna_nopeus(), Vektori::new(luku(3.0), luku(-1.5)));
+        assert_eq!(fysiikka.anna_massa(), luku(2.5));
+        assert_eq!(fysiikka.anna_kimmoisuus(), luku(0.5));
+        let (tavoite, kiihtyvyys) = fysiikka.anna_tavoitenopeus().unwrap();
+        assert_eq!(tavoite, Vektori::new(luku(-4.0), luku(2.0)));
+        assert_eq!(kiihtyvyys, luku(8.0));
+        assert!(ladattu.anna_fysiikka(lapsi).is_none());
+        assert!(ladattu.anna_piirrettavyys(lapsi).is_some());
+        let elinvoima = ladattu.anna_lisaosa::<Elinvoima>(vanhempi).unwrap();
+        assert_eq!(elinvoima.elamat(), luku(2.0));
+        assert_eq!(elinvoima.kestavyys.maksimi, luku(3.0));
+        let kayttaytyminen = ladattu
+            .anna_lisaosa::<Rajakayttaytyminen>(vanhempi)
+            .unwrap();
+        assert!(kayttaytyminen.toiminta == Rajatoiminta::Kimpoaminen);
+
+        assert_eq!(tallenna(&ladattu, Duration::new(0, 0)), teksti);
+    }
+
+    #[test]
+    fn tuntemattomat_kentat_ja_tietueet() {
+        let kappale = "[kappale]\nmuoto = ympyra 2\nkeskipiste = 0 0\ntagi = seina\npaino = 3\n";
+        let virhe = ladattu(kappale).err().unwrap();
+        assert!(virhe.contains("tuntematon kenttä 'paino'"), "{}", virhe);
+
+        let virhe = ladattu("[maailma]\npelin_aika = 1\nnopeus = 2\n")
+            .err()
+            .unwrap();
+        assert!(virhe.contains("tuntematon kenttä 'nopeus'"), "{}", virhe);
+
+        let virhe = ladattu("[kappaleet]\nmuoto = ympyra 2\n").err().unwrap();
+        assert!(
+            virhe.contains("tuntematon tietueen laji 'kappaleet'"),
+            "{}",
+            virhe
+        );
+    }
+
+    #[test]
+    fn nimet_ja_vanhemmat() {
+        let kappale = |rivit: &str| {
+            format!(
+                "[kappale]\nmuoto = ympyra 2\nkeskipiste = 0 0\ntagi = seina\n{}",
+                rivit
+            )
+        };
+
+        // Vanhempi voi olla tiedostossa myöhemmin kuin lapsi
+        let teksti = kappale("vanhempi = a\nsiirtyma = 5 0\n") + &kappale("nimi = a\n");
+        let maailma = ladattu(&teksti).unwrap();
+        let lapsi = &maailma.kappaleet()[0];
+        let vanhempi = maailma.anna_vanhempi(lapsi).unwrap();
+        assert!(Rc::ptr_eq(&vanhempi, &maailma.kappaleet()[1]));
+        let keskipiste = lapsi.borrow().keskipisteen_sijainti();
+        assert_eq!(keskipiste, Vektori::new(luku(5.0), luku(0.0)));
+
+        let teksti = kappale("nimi = a\n") + &kappale("nimi = a\n");
+        let virhe = ladattu(&teksti).err().unwrap();
+        assert!(virhe.contains("nimi 'a' on jo käytössä"), "{}", virhe);
+
+        let virhe = ladattu(&kappale("vanhempi = b\n")).err().unwrap();
+        assert!(virhe.contains("kappaletta nimeltä 'b' ei ole"), "{}", virhe);
+
+        let virhe = ladattu(&kappale("nimi = a\nvanhempi = a\n")).err().unwrap();
+        assert!(virhe.contains("itseensä"), "{}", virhe);
+    }
+
+    #[test]
+    fn rajojen_tarkistus() {
+        let maailma = ladattu("[maailma]\nrajat = -10 0 10 20\nraja = tuhoutuminen\n").unwrap();
+        let rajat = maailma.rajat().unwrap();
+        assert_eq!(rajat.alue.vasen_yla, Vektori::new(luku(-10.0), luku(0.0)));
+        assert!(rajat.oletus == Some(Rajatoiminta::Tuhoutuminen));
+
+        for virheellinen in &["10 0 -10 20", "0 20 10 20", "0 0 10"] {
+            let teksti = format!("[maailma]\nrajat = {}\n", virheellinen);
+            let virhe = ladattu(&teksti).err().unwrap();
+            assert!(virhe.contains("rajat"), "{}", virhe);
+        }
+
+        let virhe = ladattu("[maailma]\nrajat = 0 0 10 10\nraja = pomppu\n")
+            .err()
+            .unwrap();
+        assert!(virhe.contains("raja"), "{}", virhe);
+    }
+
+    #[test]
+    fn tavoitenopeus_vaatii_kiihtyvyyden() {
+        let kappale = |rivit: &str| {
+            let teksti = format!(
+                "[kappale]\nmuoto = ympyra 2\nkeskipiste = 0 0\nnopeus = 0 0\n{}",
+                rivit
+            );
+            ladattu(&teksti)
+        };
+        let maailma = kappale("tavoitenopeus = 3 0\ntavoitekiihtyvyys = 6\n").unwrap();
+        let fysiikka = maailma.anna_fysiikka(&maailma.kappaleet()[0]).unwrap();
+        assert_eq!(
+            fysiikka.anna_tavoitenopeus(),
+            Some((Vektori::new(luku(3.0), luku(0.0)), luku(6.0)))
+        );
+
+        let virhe = kappale("tavoitenopeus = 3 0\n").err().unwrap();
+        assert!(virhe.contains("tavoitekiihtyvyys"), "{}", virhe);
+        let virhe = kappale("tavoitenopeus = 3 0\ntavoitekiihtyvyys = -1\n")
+            .err()
+            .unwrap();
+        assert!(virhe.contains("negatiivinen"), "{}", virhe);
+    }
+}
@@ -1,59 +1,76 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use crate::fysiikka::Fysiikkakappale;
 use crate::maailma::kappale::Kappale;
+use crate::maailma::kysely::Kysely;
+use crate::maailma::lisaosarekisteri::Lisaosarekisteri;
+use crate::maailma::luku::Luku;
 use crate::maailma::pelihahmo::Pelihahmo;
+use crate::maailma::ruudukko::Ruudukko;
 use crate::maailma::vektori::Vektori;
 use crate::maailma::Lisaosa;
 use crate::maailma::Pelihahmollinen;
+use crate::tiedosto::Tietue;
 
 type RcKappale = Rc<RefCell<Kappale>>;
 
 /// Sisältää tekoälyn tarvitsemat tiedot maailmasta
 pub struct TekoalyMaailma<'a> {
     /// Kappaleet
-    _kappaleet: &'a mut Vec<RcKappale>,
-    /// Pelaajan pelihahmo
-    pelihahmo: &'a mut Option<Pelihahmo>,
-    /// Fysiikan sisältämät kappaleet
-    _fysiikalliset: &'a mut Vec<Fysiikkakappale>,
+    kappaleet: &'a [RcKappale],
+    /// Alueindeksi, josta voidaan hakea lähellä olevia kappaleita
+    alueindeksi: &'a Ruudukko,
+    /// Kappaleisiin liitetyt lisäosat
+    lisaosat: &'a Lisaosarekisteri,
+    /// Pelaajien pelihahmot
+    pelihahmot: &'a mut [Pelihahmo],
 }
 
 impl<'a> TekoalyMaailma<'a> {
     /// Luo uuden tekoälyn käyttämän maailman
     /// # Arguments
-    /// * `_kappaleet` - Maailman kappaleet
-    /// * `pelihahmo` - Pelaajan ohjaama hahmo
-    /// * `_fysikaalliset` - Maailmassa olevat fysiikkakappaleet
+    /// * `kappaleet` - Maailman kappaleet
+    /// * `alueindeksi` - Maailman kappaleiden alueindeksi
+    /// * `lisaosat` - Maailman kappaleisiin liitetyt lisäosat
+    /// * `pelihahmot` - Pelaajien ohjaamat hahmot
     pub fn new(
-        _kappaleet: &'a mut Vec<RcKappale>,
-        pelihahmo: &'a mut Option<Pelihahmo>,
-        _fysiikalliset: &'a mut Vec<Fysiikkakappale>,
+        kappaleet: &'a [RcKappale],
+        alueindeksi: &'a Ruudukko,
+        lisaosat: &'a Lisaosarekisteri,
+        pelihahmot: &'a mut [Pelihahmo],
     ) -> Self {
         TekoalyMaailma {
-            _kappaleet: _kappaleet,
-            pelihahmo: pelihahmo,
-            _fysiikalliset: _fysiikalliset,
+            kappaleet,
+            alueindeksi,
+            lisaosat,
+            pelihahmot,
         }
     }
+
+    /// Antaa kappaleet, joiden rajaus on annetun etäisyyden päässä annetusta pisteestä
+    /// # Arguments
+    /// * `keskipiste` - Piste, jonka ympäriltä kappaleita haetaan
+    /// * `sade` - Etäisyys, jonka päästä kappaleita haetaan
+    pub fn kappaleet_sateella(&self, keskipiste: Vektori, sade: Luku) -> Vec<RcKappale> {
+        self.alueindeksi.sateella(keskipiste, sade)
+    }
+
+    /// Aloittaa kyselyn, jolla maailman kappaleita voidaan hakea esim. tagin, sijainnin
+    /// tai lisäosien perusteella
+    pub fn kysely(&self) -> Kysely<'_> {
+        Kysely::new(self.kappaleet, self.alueindeksi, self.lisaosat)
+    }
 }
 
 impl<'a> Pelihahmollinen for TekoalyMaailma<'a> {
-    /// Antaa pelihahmon, jos sellainen on luotu
-    fn anna_pelihahmo_mut(&mut self) -> Option<&mut Pelihahmo> {
-        match &mut self.pelihahmo {
-            None => None,
-            Some(hahmo) => Some(hahmo),
-        }
+    /// Antaa kaikki pelihahmot lisäysjärjestyksessä
+    fn pelihahmot(&self) -> &[Pelihahmo] {
+        self.pelihahmot
     }
 
-    /// Antaa pelihahmon, jos sellainen on luotu
-    fn anna_pelihahmo(&self) -> Option<&Pelihahmo> {
-        match &self.pelihahmo {
-            None => None,
-            Some(hahmo) => Some(&hahmo),
-        }
+    /// Antaa kaikki pelihahmot lisäysjärjestyksessä
+    fn pelihahmot_mut(&mut self) -> &mut [Pelihahmo] {
+        self.pelihahmot
     }
 }
 
@@ -83,6 +100,11 @@ impl Alyllinen {
     pub fn alyile(&self, maailma: &TekoalyMaailma) -> AlyToiminta {
         self.aly.alyile(maailma, &self.kappale)
     }
+
+    /// Antaa kappaleen käyttämän tekoälyn
+    pub fn anna_aly(&self) -> &dyn Aly {
+        &*self.aly
+    }
 }
 
 impl Lisaosa for Alyllinen {
@@ -100,6 +122,15 @@ pub trait Aly {
     fn alyile(&self, maailma: &TekoalyMaailma, oma_kappale: &RcKappale) -> AlyToiminta;
     
     fn box_clone(&self) -> Box<Aly>;
+
+    /// Antaa tekoälyn lajin nimen, jolla tekoäly tunnistetaan tallennettaessa ja ladattaessa
+    fn nimi(&self) -> &'static str;
+
+    /// Tallentaa tekoälyn omat tiedot annettuun tietueeseen. Tiedot luetaan latauksessa
+    /// takaisin lajin nimellä rekisteröidyn lataajan avulla.
+    /// # Arguments
+    /// * `tietue` - Tietue, johon tiedot tallennetaan
+    fn tallenna(&self, _tietue: &mut Tietue) {}
 }
 
 /// Tekoälyn mahdolliset toimintavaihtoehdot
@@ -110,7 +141,7 @@ pub enum AlyToiminta {
     Liiku { suunta: Vektori },
 }
 
-/// Tekoäly, joka ohjaa ohjattavan kappaleen liikkumaan suoraan pelaajan ohjaamaa hahmoa kohti
+/// Tekoäly, joka ohjaa ohjattavan kappaleen liikkumaan suoraan lähintä pelaajan ohjaamaa hahmoa kohti
 #[derive(Copy, Clone)]
 pub struct SeurausAly;
 
@@ -120,10 +151,12 @@ impl Aly for SeurausAly {
     /// * `maailma` - Maailma, jonka perusteella toimitaan
     /// * `oma_kappale` Tekoälyn ohjaama kappale
     fn alyile(&self, maailma: &TekoalyMaailma, oma_kappale: &RcKappale) -> AlyToiminta {
-        if let Some(pelihahmo) = maailma.anna_pelihahmo() {
+        let oma_sijainti = oma_kappale.borrow().keskipisteen_sijainti();
+        if let Some(pelihahmo) = maailma.lahin_pelihahmo(oma_sijainti) {
             let oma_suunta = (pelihahmo.anna_kappale().borrow().keskipisteen_sijainti()
-                - oma_kappale.borrow().keskipisteen_sijainti())
-            .yksikkovektori();
+                - oma_sijainti)
+                .normalisoi()
+                .unwrap_or_default();
             return AlyToiminta::Liiku { suunta: oma_suunta };
         }
         AlyToiminta::Laiskottele
@@ -132,4 +165,15 @@ impl Aly for SeurausAly {
     fn box_clone(&self) -> Box<Aly> {
         Box::new(*self)
     }
+
+    fn nimi(&self) -> &'static str {
+        "seuraus"
+    }
+}
+
+impl SeurausAly {
+    /// Lataa seurausälyn tietueesta. Seurausälyllä ei ole omia tietoja.
+    pub fn lataa(_tietue: &Tietue) -> Result<Box<dyn Aly>, String> {
+        Ok(Box::new(SeurausAly))
+    }
 }
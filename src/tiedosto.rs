@@ -0,0 +1,466 @@
+//! Sisältää yksinkertaisen ihmisluettavan tiedostomuodon, jota käytetään esim. pelimaailman
+//! tallentamiseen. Tiedosto koostuu tietueista, jotka alkavat otsikolla `[laji]` ja
+//! sisältävät `avain = arvo` -rivejä. `#`-merkistä alkaen rivin loppu on kommenttia.
+//!
+//! ```text
+//! # Pelaajan hahmo
+//! [kappale]
+//! muoto = nelio 20 20
+//! sijainti = 310 230
+//! ```
+use sdl2::pixels::Color;
+use std::cell::Cell;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::maailma::luku::Luku;
+use crate::maailma::vektori::Vektori;
+
+/// Yksittäinen tietueen kenttä
+struct Kentta {
+    /// Kentän nimi
+    avain: String,
+    /// Kentän arvo tekstinä
+    arvo: String,
+    /// Rivi, jolla kenttä on tiedostossa
+    rivi: usize,
+    /// Onko kentän arvo luettu
+    luettu: Cell<bool>,
+}
+
+/// Tietue, jolla on laji ja joukko kenttiä
+pub struct Tietue {
+    /// Tietueen laji eli otsikko
+    laji: String,
+    /// Rivi, jolla tietue alkaa tiedostossa
+    rivi: usize,
+    /// Tietueen kentät järjestyksessä
+    kentat: Vec<Kentta>,
+}
+
+impl Tietue {
+    /// Luo uuden tyhjän tietueen
+    /// # Arguments
+    /// * `laji` - Tietueen laji
+    pub fn new(laji: &str) -> Self {
+        Tietue {
+            laji: laji.to_string(),
+            rivi: 0,
+            kentat: Vec::new(),
+        }
+    }
+
+    /// Antaa tietueen lajin
+    pub fn laji(&self) -> &str {
+        &self.laji
+    }
+
+    /// Antaa rivin, jolta tietue alkaa
+    pub fn rivi(&self) -> usize {
+        self.rivi
+    }
+
+    /// Lisää tietueeseen kentän
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    /// * `arvo` - Kentän arvo
+    pub fn lisaa<T: Display>(&mut self, avain: &str, arvo: T) {
+        self.kentat.push(Kentta {
+            avain: avain.to_string(),
+            arvo: arvo.to_string(),
+            rivi: 0,
+            luettu: Cell::new(false),
+        });
+    }
+
+    /// Lisää tietueeseen vektorin
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    /// * `vektori` - Tallennettava vektori
+    pub fn lisaa_vektori(&mut self, avain: &str, vektori: Vektori) {
+        self.lisaa(avain, format!("{} {}", vektori.x, vektori.y));
+    }
+
+    /// Lisää tietueeseen keston sekunteina
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    /// * `kesto` - Tallennettava kesto
+    pub fn lisaa_kesto(&mut self, avain: &str, kesto: Duration) {
+        self.lisaa(
+            avain,
+            format!("{}.{:09}", kesto.as_secs(), kesto.subsec_nanos()),
+        );
+    }
+
+    /// Lisää tietueeseen värin
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    /// * `vari` - Tallennettava väri
+    pub fn lisaa_vari(&mut self, avain: &str, vari: Color) {
+        self.lisaa(avain, format!("{} {} {} {}", vari.r, vari.g, vari.b, vari.a));
+    }
+
+    /// Lisää toisen tietueen kentät tähän tietueeseen niin, että kenttien nimien eteen
+    /// tulee annettu etuliite ja piste, esim. `pudotus1.muoto`
+    /// # Arguments
+    /// * `etuliite` - Alitietueen kenttien etuliite
+    /// * `alitietue` - Tietue, jonka kentät lisätään
+    pub fn lisaa_alitietue(&mut self, etuliite: &str, alitietue: &Tietue) {
+        for kentta in &alitietue.kentat {
+            self.lisaa(&format!("{}.{}", etuliite, kentta.avain), &kentta.arvo);
+        }
+    }
+
+    /// Antaa tietueen, jossa ovat ilman etuliitettä ne kentät, joiden nimi alkaa annetulla
+    /// etuliitteellä ja pisteellä. Kentät merkitään tässä tietueessa luetuiksi, joten
+    /// alitietueen tuntemattomat kentät pitää tarkistaa alitietueesta.
+    /// # Arguments
+    /// * `etuliite` - Alitietueen kenttien etuliite
+    pub fn alitietue(&self, etuliite: &str) -> Tietue {
+        let alku = format!("{}.", etuliite);
+        let mut alitietue = Tietue::new(&format!("{} {}", self.laji, etuliite));
+        alitietue.rivi = self.rivi;
+        for kentta in self.kentat.iter().filter(|x| x.avain.starts_with(&alku)) {
+            kentta.luettu.set(true);
+            alitietue.kentat.push(Kentta {
+                avain: kentta.avain[alku.len()..].to_string(),
+                arvo: kentta.arvo.clone(),
+                rivi: kentta.rivi,
+                luettu: Cell::new(false),
+            });
+        }
+        alitietue
+    }
+
+    /// Antaa kentän arvon, jos kenttä on olemassa, ja merkitsee kentän luetuksi
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    pub fn anna(&self, avain: &str) -> Option<&str> {
+        self.kentta(avain).map(|x| {
+            x.luettu.set(true);
+            x.arvo.as_str()
+        })
+    }
+
+    /// Antaa kentän arvon tai virheen, jos kenttää ei ole
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    pub fn vaadi(&self, avain: &str) -> Result<&str, String> {
+        self.anna(avain)
+            .ok_or_else(|| self.virhe(&format!("puuttuu kenttä '{}'", avain)))
+    }
+
+    /// Onko tietueessa annettu kenttä. Ei merkitse kenttää luetuksi.
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    pub fn sisaltaa(&self, avain: &str) -> bool {
+        self.kentta(avain).is_some()
+    }
+
+    /// Antaa kentän arvon jäsennettynä annetuksi tyypiksi
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    pub fn arvo<T: FromStr>(&self, avain: &str) -> Result<T, String> {
+        let arvo = self.vaadi(avain)?;
+        arvo.parse::<T>()
+            .map_err(|_| self.kentan_virhe(avain, &format!("virheellinen arvo '{}'", arvo)))
+    }
+
+    /// Antaa kentän arvon välilyönnein eroteltuina lukuina. Lukujen määrän pitää olla annettu.
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    /// * `maara` - Vaadittu lukujen määrä
+    pub fn luvut<T: FromStr>(&self, avain: &str, maara: usize) -> Result<Vec<T>, String> {
+        let osat: Vec<&str> = self.vaadi(avain)?.split_whitespace().collect();
+        if osat.len() != maara {
+            return Err(self.kentan_virhe(
+                avain,
+                &format!("odotettiin {} lukua, mutta saatiin {}", maara, osat.len()),
+            ));
+        }
+        osat.iter()
+            .map(|x| {
+                x.parse::<T>()
+                    .map_err(|_| self.kentan_virhe(avain, &format!("virheellinen luku '{}'", x)))
+            })
+            .collect()
+    }
+
+    /// Antaa kentän arvon vektorina
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    pub fn vektori(&self, avain: &str) -> Result<Vektori, String> {
+        let luvut = self.luvut::<Luku>(avain, 2)?;
+        Ok(Vektori::new(luvut[0], luvut[1]))
+    }
+
+    /// Antaa kentän arvon kestona. Arvo on sekunteina desimaalilukuna.
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    pub fn kesto(&self, avain: &str) -> Result<Duration, String> {
+        let arvo = self.vaadi(avain)?;
+        let virhe = || self.kentan_virhe(avain, &format!("virheellinen kesto '{}'", arvo));
+        let mut osat = arvo.splitn(2, '.');
+        let sekunnit = osat
+            .next()
+            .unwrap()
+            .parse::<u64>()
+            .map_err(|_| virhe())?;
+        let nanot = match osat.next() {
+            Some(desimaalit) if desimaalit.len() <= 9 => {
+                format!("{:0<9}", desimaalit)
+                    .parse::<u32>()
+                    .map_err(|_| virhe())?
+            }
+            Some(_) => return Err(virhe()),
+            None => 0,
+        };
+        Ok(Duration::new(sekunnit, nanot))
+    }
+
+    /// Antaa kentän arvon värinä. Väri annetaan muodossa `r g b` tai `r g b a`.
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    pub fn vari(&self, avain: &str) -> Result<Color, String> {
+        let maara = self.vaadi(avain)?.split_whitespace().count();
+        match maara {
+            3 => {
+                let v = self.luvut::<u8>(avain, 3)?;
+                Ok(Color::RGB(v[0], v[1], v[2]))
+            }
+            _ => {
+                let v = self.luvut::<u8>(avain, 4)?;
+                Ok(Color::RGBA(v[0], v[1], v[2], v[3]))
+            }
+        }
+    }
+
+    /// Antaa virheen ensimmäisestä kentästä, jota ei ole luettu. Tällä huomataan
+    /// esim. kirjoitusvirheet kenttien nimissä.
+    pub fn tarkista_tuntemattomat(&self) -> Result<(), String> {
+        match self.kentat.iter().find(|x| !x.luettu.get()) {
+            Some(kentta) => Err(format!(
+                "Rivi {}: [{}]: tuntematon kenttä '{}'",
+                kentta.rivi, self.laji, kentta.avain
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Luo virheilmoituksen, joka kertoo tietueen sijainnin
+    /// # Arguments
+    /// * `viesti` - Virheen kuvaus
+    pub fn virhe(&self, viesti: &str) -> String {
+        format!("Rivi {}: [{}]: {}", self.rivi, self.laji, viesti)
+    }
+
+    /// Luo virheilmoituksen, joka kertoo kentän sijainnin
+    /// # Arguments
+    /// * `avain` - Kentän nimi
+    /// * `viesti` - Virheen kuvaus
+    pub fn kentan_virhe(&self, avain: &str, viesti: &str) -> String {
+        let rivi = self.kentta(avain).map_or(self.rivi, |x| x.rivi);
+        format!("Rivi {}: [{}] {}: {}", rivi, self.laji, avain, viesti)
+    }
+
+    /// Antaa kentän nimeltä
+    fn kentta(&self, avain: &str) -> Option<&Kentta> {
+        self.kentat.iter().find(|x| x.avain == avain)
+    }
+}
+
+/// Jäsentää annetun tekstin tietueiksi
+/// # Arguments
+/// * `teksti` - Jäsennettävä teksti
+pub fn lue_tietueet(teksti: &str) -> Result<Vec<Tietue>, String> {
+    let mut tietueet: Vec<Tietue> = Vec::new();
+    for (indeksi, rivi) in teksti.lines().enumerate() {
+        let rivinumero = indeksi + 1;
+        let rivi = match rivi.find('#') {
+            Some(kommentti) => &rivi[..kommentti],
+            None => rivi,
+        }
+        .trim();
+        if rivi.is_empty() {
+            continue;
+        }
+        if rivi.starts_with('[') {
+            if !rivi.ends_with(']') || rivi.len() < 3 {
+                return Err(format!("Rivi {}: virheellinen otsikko '{}'", rivinumero, rivi));
+            }
+            let mut tietue = Tietue::new(rivi[1..rivi.len() - 1].trim());
+            tietue.rivi = rivinumero;
+            tietueet.push(tietue);
+            continue;
+        }
+        let tietue = match tietueet.last_mut() {
+            Some(tietue) => tietue,
+            None => {
+                return Err(format!(
+                    "Rivi {}: kenttä '{}' ennen ensimmäistä [otsikkoa]",
+                    rivinumero, rivi
+                ))
+            }
+        };
+        let mut osat = rivi.splitn(2, '=');
+        let avain = osat.next().unwrap().trim();
+        let arvo = match osat.next() {
+            Some(arvo) => arvo.trim(),
+            None => {
+                return Err(format!(
+                    "Rivi {}: odotettiin muotoa 'avain = arvo', mutta saatiin '{}'",
+                    rivinumero, rivi
+                ))
+            }
+        };
+        if avain.is_empty() {
+            return Err(format!("Rivi {}: kentältä puuttuu nimi", rivinumero));
+        }
+        if tietue.sisaltaa(avain) {
+            return Err(format!(
+                "Rivi {}: kenttä '{}' on annettu useamman kerran",
+                rivinumero, avain
+            ));
+        }
+        tietue.kentat.push(Kentta {
+            avain: avain.to_string(),
+            arvo: arvo.to_string(),
+            rivi: rivinumero,
+            luettu: Cell::new(false),
+        });
+    }
+    Ok(tietueet)
+}
+
+/// Muuttaa annetut tietueet tekstiksi, jonka `lue_tietueet` osaa jäsentää
+/// # Arguments
+/// * `tietueet` - Kirjoitettavat tietueet
+pub fn kirjoita_tietueet(tietueet: &[Tietue]) -> String {
+    let mut teksti = String::new();
+    for tietue in tietueet {
+        teksti.push_str(&format!("[{}]\n", tietue.laji));
+        for kentta in &tietue.kentat {
+            teksti.push_str(&format!("{} = {}\n", kentta.avain, kentta.arvo));
+        }
+        teksti.push('\n');
+    }
+    teksti
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Jäsentää tekstin ja antaa virheen, jonka jäsentäminen tai ensimmäisen tietueen
+    /// kentän lukeminen aiheuttaa
+    fn lue_virhe<T>(teksti: &str, lue: impl Fn(&Tietue) -> Result<T, String>) -> String {
+        match lue_tietueet(teksti) {
+            Ok(tietueet) => match lue(&tietueet[0]) {
+                Ok(_) => panic!("virhettä ei tullut: {}", teksti),
+                Err(virhe) => virhe,
+            },
+            Err(virhe) => virhe,
+        }
+    }
+
+    /// Tarkistaa, että virheessä kerrotaan rivi ja kenttä
+    fn tarkista(virhe: &str, rivi: usize, kentta: &str) {
+        assert!(virhe.starts_with(&format!("Rivi {}:", rivi)), "{}", virhe);
+        assert!(virhe.contains(kentta), "{}", virhe);
+    }
+
+    #[test]
+    fn tietueet_luetaan_ja_kirjoitetaan() {
+        let teksti = "# kommentti\n\n[kappale]  # otsikko\nmuoto = nelio 20 10\n\
+                      sijainti=  3 -4.5 \n[maailma]\n";
+        let tietueet = lue_tietueet(teksti).unwrap();
+        assert_eq!(tietueet.len(), 2);
+        assert_eq!(tietueet[0].laji(), "kappale");
+        assert_eq!(tietueet[0].rivi(), 3);
+        assert_eq!(tietueet[0].anna("muoto"), Some("nelio 20 10"));
+        assert_eq!(
+            tietueet[0].luvut::<f32>("sijainti", 2).unwrap(),
+            vec![3.0, -4.5]
+        );
+        assert_eq!(tietueet[1].laji(), "maailma");
+
+        let kirjoitettu = kirjoita_tietueet(&tietueet);
+        assert_eq!(
+            kirjoitettu,
+            "[kappale]\nmuoto = nelio 20 10\nsijainti = 3 -4.5\n\n[maailma]\n\n"
+        );
+        assert_eq!(lue_tietueet(&kirjoitettu).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rakennevirheissa_kerrotaan_rivi() {
+        let virhe = lue_virhe("[kappale]\n\nmuoto nelio 2 2\n", |_| Ok(()));
+        tarkista(&virhe, 3, "'muoto nelio 2 2'");
+        assert!(virhe.contains("avain = arvo"), "{}", virhe);
+
+        let virhe = lue_virhe("# alku\nmuoto = ympyra 2\n[kappale]\n", |_| Ok(()));
+        tarkista(&virhe, 2, "'muoto = ympyra 2'");
+        assert!(virhe.contains("ennen ensimmäistä"), "{}", virhe);
+
+        let virhe = lue_virhe(
+            "[kappale]\nmuoto = ympyra 2\nmuoto = ympyra 3\n",
+            |_| Ok(()),
+        );
+        tarkista(&virhe, 3, "'muoto'");
+
+        for (teksti, rivi) in &[("[kappale\n", 1), ("[maailma]\n[]\n", 2)] {
+            let virhe = lue_virhe(teksti, |_| Ok(()));
+            tarkista(&virhe, *rivi, "virheellinen otsikko");
+        }
+        tarkista(&lue_virhe("[kappale]\n = 3\n", |_| Ok(())), 2, "nimi");
+    }
+
+    #[test]
+    fn arvovirheissa_kerrotaan_kentan_rivi_ja_nimi() {
+        let teksti = "[kappale]\nmassa = kolme\nnopeus = 1 x\nkeskipiste = 1 2 3\n\
+                      elinaika = 1.5s\nvari = 255 0\n";
+        tarkista(
+            &lue_virhe(teksti, |x| x.arvo::<f32>("massa")),
+            2,
+            "massa: virheellinen arvo 'kolme'",
+        );
+        tarkista(
+            &lue_virhe(teksti, |x| x.vektori("nopeus")),
+            3,
+            "nopeus: virheellinen luku 'x'",
+        );
+        tarkista(
+            &lue_virhe(teksti, |x| x.vektori("keskipiste")),
+            4,
+            "keskipiste: odotettiin 2 lukua",
+        );
+        tarkista(
+            &lue_virhe(teksti, |x| x.kesto("elinaika")),
+            5,
+            "elinaika: virheellinen kesto",
+        );
+        tarkista(
+            &lue_virhe(teksti, |x| x.vari("vari")),
+            6,
+            "vari: odotettiin 4 lukua",
+        );
+        // Puuttuvasta kentästä kerrotaan tietueen rivi
+        tarkista(
+            &lue_virhe(teksti, |x| x.vaadi("muoto").map(|_| ())),
+            1,
+            "puuttuu kenttä 'muoto'",
+        );
+    }
+
+    #[test]
+    fn lukemattomat_kentat_ovat_tuntemattomia() {
+        let tietueet = lue_tietueet("[kappale]\nmuoto = ympyra 2\npaino = 3\n").unwrap();
+        assert!(tietueet[0].sisaltaa("muoto"));
+        tietueet[0].anna("muoto");
+        let virhe = tietueet[0].tarkista_tuntemattomat().err().unwrap();
+        tarkista(&virhe, 3, "tuntematon kenttä 'paino'");
+        tietueet[0].anna("paino");
+        assert!(tietueet[0].tarkista_tuntemattomat().is_ok());
+    }
+}
@@ -0,0 +1,393 @@
+//! Maailman tilan tiivisteet, joilla voidaan todeta simulaation determinismi. Kun saman
+//! nauhoitteen kahden ajon tiivisteitä verrataan päivitys kerrallaan, löydetään ensimmäinen
+//! päivitys ja maailman osa (esim. kappale), jossa ajot eroavat toisistaan.
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hasher;
+use std::time::Duration;
+
+use crate::maailma::Perusmaailma;
+use crate::tallennus::tallenna_tietueiksi;
+use crate::tiedosto::kirjoita_tietueet;
+
+/// FNV-1a -tiivistäjä. Toisin kuin standardikirjaston oletustiivistäjän, tämän tulos
+/// on sama kaikilla alustoilla ja ajokerroilla.
+pub struct Tiivistaja(u64);
+
+impl Default for Tiivistaja {
+    fn default() -> Self {
+        Tiivistaja(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Tiivistaja {
+    fn write(&mut self, tavut: &[u8]) {
+        for tavu in tavut {
+            self.0 ^= u64::from(*tavu);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Maailman tilan tiiviste yhden päivityksen jälkeen
+#[derive(Clone)]
+pub struct Tilatiiviste {
+    /// Päivityksen järjestysnumero
+    pub askel: u64,
+    /// Koko tilan tiiviste
+    pub tiiviste: u64,
+    /// Maailman osien tiivisteet tallennusjärjestyksessä, esim. `("kappale#3", tiiviste)`
+    pub osat: Vec<(String, u64)>,
+}
+
+impl Tilatiiviste {
+    /// Laskee maailman tilan tiivisteen. Tila on sama, joka tallennetaan tallennukseen:
+    /// kappaleiden sijainnit, nopeudet ja tagit, tekoälyt, spawnerit jne. Animaatiot jätetään
+    /// pois, koska ne vanhenevat piirtämisen tahdissa eivätkä vaikuta simulaatioon.
+    /// # Arguments
+    /// * `askel` - Päivityksen järjestysnumero
+    /// * `maailma` - Maailma, jonka tila tiivistetään
+    /// * `pelin_aika` - Pelin alusta kulunut aika
+    pub fn new(askel: u64, maailma: &Perusmaailma, pelin_aika: Duration) -> Self {
+        let mut kokonainen = Tiivistaja::default();
+        let mut osat = Vec::new();
+        let mut lajien_maarat: HashMap<String, usize> = HashMap::new();
+        for tietue in tallenna_tietueiksi(maailma, pelin_aika) {
+            if tietue.laji() == "animaatio" {
+                continue;
+            }
+            let mut tiivistaja = Tiivistaja::default();
+            tiivistaja.write(kirjoita_tietueet(std::slice::from_ref(&tietue)).as_bytes());
+            let tiiviste = tiivistaja.finish();
+            kokonainen.write_u64(tiiviste);
+
+            let maara = lajien_maarat.entry(tietue.laji().to_string()).or_insert(0);
+            osat.push((format!("{}#{}", tietue.laji(), maara), tiiviste));
+            *maara += 1;
+        }
+        Tilatiiviste {
+            askel,
+            tiiviste: kokonainen.finish(),
+            osat,
+        }
+    }
+
+    /// Kirjoittaa tiivisteen yhdeksi riviksi muodossa `<askel> <tiiviste> <osa>=<tiiviste> ...`
+    pub fn kirjoita(&self) -> String {
+        let mut rivi = format!("{} {:016x}", self.askel, self.tiiviste);
+        for (osa, tiiviste) in &self.osat {
+            rivi.push_str(&format!(" {}={:016x}", osa, tiiviste));
+        }
+        rivi
+    }
+
+    /// Lukee tiivisteen rivistä, joka on kirjoitettu `kirjoita`-funktiolla
+    /// # Arguments
+    /// * `rivi` - Luettava rivi
+    pub fn lue(rivi: &str) -> Result<Self, String> {
+        let virhe = || format!("Virheellinen tiivisterivi '{}'", rivi);
+        let mut sanat = rivi.split_whitespace();
+        let askel = sanat
+            .next()
+            .and_then(|x| x.parse::<u64>().ok())
+            .ok_or_else(virhe)?;
+        let tiiviste = sanat
+            .next()
+            .and_then(|x| u64::from_str_radix(x, 16).ok())
+            .ok_or_else(virhe)?;
+        let mut osat = Vec::new();
+        for sana in sanat {
+            let mut osa = sana.splitn(2, '=');
+            let nimi = osa.next().ok_or_else(virhe)?;
+            let tiiviste = osa
+                .next()
+                .and_then(|x| u64::from_str_radix(x, 16).ok())
+                .ok_or_else(virhe)?;
+            osat.push((nimi.to_string(), tiiviste));
+        }
+        Ok(Tilatiiviste {
+            askel,
+            tiiviste,
+            osat,
+        })
+    }
+}
+
+/// Ajon tiivisteet päivityksittäin
+#[derive(Default)]
+pub struct Tiivistehistoria {
+    tiivisteet: Vec<Tilatiiviste>,
+}
+
+impl Tiivistehistoria {
+    /// Luo tyhjän historian
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Lisää päivityksen tiivisteen historian loppuun
+    /// # Arguments
+    /// * `tiiviste` - Lisättävä tiiviste
+    pub fn lisaa(&mut self, tiiviste: Tilatiiviste) {
+        self.tiivisteet.push(tiiviste);
+    }
+
+    /// Antaa tiivisteet päivitysten järjestyksessä
+    pub fn tiivisteet(&self) -> &[Tilatiiviste] {
+        &self.tiivisteet
+    }
+
+    /// Kirjoittaa historian tekstiksi, jossa on yksi tiiviste riviä kohden
+    pub fn kirjoita(&self) -> String {
+        let mut teksti = String::new();
+        for tiiviste in &self.tiivisteet {
+            teksti.push_str(&tiiviste.kirjoita());
+            teksti.push('\n');
+        }
+        teksti
+    }
+
+    /// Lukee historian tekstistä. Tyhjät rivit ohitetaan.
+    /// # Arguments
+    /// * `teksti` - Luettava teksti
+    pub fn lue(teksti: &str) -> Result<Self, String> {
+        let mut historia = Tiivistehistoria::new();
+        for (numero, rivi) in teksti.lines().enumerate() {
+            if rivi.trim().is_empty() {
+                continue;
+            }
+            let tiiviste =
+                Tilatiiviste::lue(rivi).map_err(|e| format!("Rivi {}: {}", numero + 1, e))?;
+            historia.lisaa(tiiviste);
+        }
+        Ok(historia)
+    }
+}
+
+/// Ensimmäinen kohta, jossa kaksi ajoa eroavat toisistaan
+pub struct Poikkeama {
+    /// Päivitys, jonka jälkeen tilat eroavat
+    pub askel: u64,
+    /// Maailman osa, joka eroaa ensimmäisenä, esim. `kappale#3`. Ei mitään, jos osat
+    /// ovat samat mutta kokonaiset tiivisteet eroavat.
+    pub osa: Option<String>,
+}
+
+impl fmt::Display for Poikkeama {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.osa {
+            Some(osa) => write!(f, "Ajot eroavat päivityksellä {}: {}", self.askel, osa),
+            None => write!(f, "Ajot eroavat päivityksellä {}", self.askel),
+        }
+    }
+}
+
+/// Etsii ensimmäisen päivityksen, jolla ajojen tiivisteet eroavat, ja siitä ensimmäisen
+/// eroavan maailman osan. Vain molemmissa ajoissa olevat päivitykset verrataan.
+/// # Arguments
+/// * `a` - Ensimmäisen ajon historia
+/// * `b` - Toisen ajon historia
+pub fn ensimmainen_poikkeama(a: &Tiivistehistoria, b: &Tiivistehistoria) -> Option<Poikkeama> {
+    for (x, y) in a.tiivisteet().iter().zip(b.tiivisteet()) {
+        if x.askel != y.askel {
+            return Some(Poikkeama {
+                askel: x.askel.min(y.askel),
+                osa: None,
+            });
+        }
+        if x.tiiviste == y.tiiviste {
+            continue;
+        }
+        // Osat verrataan nimien perusteella, jotta esim. ylimääräinen kappale ei siirrä vertailua
+        let osat_y: HashMap<&str, u64> = y.osat.iter().map(|(n, t)| (n.as_str(), *t)).collect();
+        let osat_x: HashMap<&str, u64> = x.osat.iter().map(|(n, t)| (n.as_str(), *t)).collect();
+        let eroava = x
+            .osat
+            .iter()
+            .find(|(nimi, tiiviste)| osat_y.get(nimi.as_str()) != Some(tiiviste))
+            .or_else(|| {
+                y.osat
+                    .iter()
+                    .find(|(nimi, _)| !osat_x.contains_key(nimi.as_str()))
+            });
+        return Some(Poikkeama {
+            askel: x.askel,
+            osa: eroava.map(|(nimi, _)| nimi.clone()),
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::fysiikka::Fysiikkakappale;
+    use crate::maailma::kappale::{Kappale, Muoto, Tagi};
+    use crate::maailma::luku::luku;
+    use crate::maailma::vektori::Vektori;
+
+    fn tiiviste(askel: u64, osat: &[(&str, u64)]) -> Tilatiiviste {
+        let mut kokonainen = Tiivistaja::default();
+        for (_, osa) in osat {
+            kokonainen.write_u64(*osa);
+        }
+        Tilatiiviste {
+            askel,
+            tiiviste: kokonainen.finish(),
+            osat: osat.iter().map(|(n, t)| (n.to_string(), *t)).collect(),
+        }
+    }
+
+    fn historia(tiivisteet: Vec<Tilatiiviste>) -> Tiivistehistoria {
+        let mut historia = Tiivistehistoria::new();
+        for tiiviste in tiivisteet {
+            historia.lisaa(tiiviste);
+        }
+        historia
+    }
+
+    #[test]
+    fn samat_ajot_eivat_poikkea() {
+        let a = historia(vec![
+            tiiviste(0, &[("kappale#0", 1), ("kappale#1", 2)]),
+            tiiviste(1, &[("kappale#0", 3), ("kappale#1", 4)]),
+        ]);
+        let b = historia(vec![
+            tiiviste(0, &[("kappale#0", 1), ("kappale#1", 2)]),
+            tiiviste(1, &[("kappale#0", 3), ("kappale#1", 4)]),
+        ]);
+        assert!(ensimmainen_poikkeama(&a, &b).is_none());
+    }
+
+    #[test]
+    fn poikkeamasta_kerrotaan_ensimmainen_eroava_osa() {
+        let a = historia(vec![
+            tiiviste(0, &[("kappale#0", 1), ("kappale#1", 2)]),
+            tiiviste(1, &[("kappale#0", 3), ("kappale#1", 4), ("spawneri#0", 5)]),
+            tiiviste(2, &[("kappale#0", 6), ("kappale#1", 7)]),
+        ]);
+        let b = historia(vec![
+            tiiviste(0, &[("kappale#0", 1), ("kappale#1", 2)]),
+            tiiviste(1, &[("kappale#0", 3), ("kappale#1", 9), ("spawneri#0", 8)]),
+            tiiviste(2, &[("kappale#0", 0), ("kappale#1", 0)]),
+        ]);
+        let poikkeama = ensimmainen_poikkeama(&a, &b).unwrap();
+        assert_eq!(poikkeama.askel, 1);
+        assert_eq!(poikkeama.osa.as_deref(), Some("kappale#1"));
+        assert_eq!(
+            poikkeama.to_string(),
+            "Ajot eroavat päivityksellä 1: kappale#1"
+        );
+    }
+
+    #[test]
+    fn ylimaarainen_osa_loytyy_kummastakin_ajosta() {
+        let a = historia(vec![tiiviste(0, &[("kappale#0", 1)])]);
+        let b = historia(vec![tiiviste(0, &[("kappale#0", 1), ("kappale#1", 2)])]);
+        let poikkeama = ensimmainen_poikkeama(&a, &b).unwrap();
+        assert_eq!(poikkeama.osa.as_deref(), Some("kappale#1"));
+        let poikkeama = ensimmainen_poikkeama(&b, &a).unwrap();
+        assert_eq!(poikkeama.osa.as_deref(), Some("kappale#1"));
+    }
+
+    #[test]
+    fn eroava_askel_ja_pelkka_kokonaistiiviste() {
+        let a = historia(vec![tiiviste(0, &[]), tiiviste(3, &[])]);
+        let b = historia(vec![tiiviste(0, &[]), tiiviste(2, &[])]);
+        let poikkeama = ensimmainen_poikkeama(&a, &b).unwrap();
+        assert_eq!(poikkeama.askel, 2);
+        assert_eq!(poikkeama.osa, None);
+
+        let mut eroava = tiiviste(0, &[("kappale#0", 1)]);
+        eroava.tiiviste += 1;
+        let a = historia(vec![tiiviste(0, &[("kappale#0", 1)])]);
+        let poikkeama = ensimmainen_poikkeama(&a, &historia(vec![eroava])).unwrap();
+        assert_eq!(poikkeama.askel, 0);
+        assert_eq!(poikkeama.osa, None);
+    }
+
+    #[test]
+    fn vain_yhteiset_paivitykset_verrataan() {
+        let a = historia(vec![tiiviste(0, &[("kappale#0", 1)])]);
+        let b = historia(vec![
+            tiiviste(0, &[("kappale#0", 1)]),
+            tiiviste(1, &[("kappale#0", 2)]),
+        ]);
+        assert!(ensimmainen_poikkeama(&a, &b).is_none());
+    }
+
+    #[test]
+    fn historia_tekstiksi_ja_takaisin() {
+        let alkuperainen = historia(vec![
+            tiiviste(0, &[("kappale#0", 0xdead_beef), ("tekoaly#0", 1)]),
+            tiiviste(1, &[]),
+        ]);
+        let teksti = alkuperainen.kirjoita();
+        assert_eq!(
+            teksti.lines().next(),
+            Some(alkuperainen.tiivisteet()[0].kirjoita().as_str())
+        );
+
+        let luettu = Tiivistehistoria::lue(&format!("\n{}\n", teksti)).unwrap();
+        assert_eq!(luettu.tiivisteet().len(), 2);
+        assert_eq!(luettu.kirjoita(), teksti);
+        assert_eq!(
+            luettu.tiivisteet()[0].osat[0],
+            ("kappale#0".to_string(), 0xdead_beef)
+        );
+        assert!(ensimmainen_poikkeama(&alkuperainen, &luettu).is_none());
+    }
+
+    #[test]
+    fn virheellinen_rivi_kerrotaan() {
+        for rivi in &["x 0", "0", "0 zz", "0 1 kappale#0", "0 1 kappale#0=g"] {
+            assert!(Tilatiiviste::lue(rivi).is_err(), "{}", rivi);
+        }
+        let virhe = Tiivistehistoria::lue("0 1\n1 2 kappale#0\n").err().unwrap();
+        assert!(virhe.starts_with("Rivi 2:"), "{}", virhe);
+    }
+
+    /// Tiivistää maailman, jossa on kaksi liikkuvaa kappaletta. Jälkimmäinen kiihtyy
+    /// annettua tavoitenopeutta kohti.
+    fn tavoitteellinen_maailma(tavoite: Option<f32>) -> Tiivistehistoria {
+        let mut maailma = Perusmaailma::new();
+        for (x, tavoite) in &[(0.0, None), (20.0, tavoite)] {
+            let kappale = maailma.lisaa_kappale(Kappale::new_keskipisteella(
+                Muoto::Ympyra(luku(5.0)),
+                luku(*x),
+                luku(0.0),
+                Tagi::Vihollinen,
+            ));
+            let nopeus = Vektori::new(luku(1.0), luku(0.0));
+            let mut fysiikka = Fysiikkakappale::new(nopeus, Rc::clone(&kappale));
+            if let Some(tavoite) = tavoite {
+                fysiikka.aseta_tavoitenopeus(Vektori::new(luku(*tavoite), luku(0.0)), luku(10.0));
+            }
+            maailma.lisaa_fysiikkakappale(fysiikka);
+        }
+        historia(vec![Tilatiiviste::new(0, &maailma, Duration::new(0, 0))])
+    }
+
+    #[test]
+    fn pelkka_tavoitenopeus_muuttaa_tiivistetta() {
+        let ilman = tavoitteellinen_maailma(None);
+        let tavoite = tavoitteellinen_maailma(Some(5.0));
+        assert!(ensimmainen_poikkeama(&tavoite, &tavoitteellinen_maailma(Some(5.0))).is_none());
+
+        // Kappaleiden sijainnit ja nopeudet ovat samat, mutta tuleva liike ei
+        for (a, b) in &[
+            (&ilman, &tavoite),
+            (&tavoite, &tavoitteellinen_maailma(Some(6.0))),
+        ] {
+            let poikkeama = ensimmainen_poikkeama(a, b).unwrap();
+            assert_eq!(poikkeama.askel, 0);
+            assert_eq!(poikkeama.osa.as_deref(), Some("kappale#1"));
+        }
+    }
+}
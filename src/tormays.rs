@@ -1,12 +1,13 @@
 use crate::fysiikka::Tormaystieto;
 use crate::maailma::kappale::Tagi;
+use crate::maailma::tagit::Tagit;
 use crate::maailma::*;
 use crate::paivitys::Paivitysaika;
 
 /// Törmäystoiminta, joka tehdään, jos törmääjällä on oikea tägi.
 pub trait Tormaystoiminta {
-    /// Koskeeko törmäystapahtuma annettau tägiä
-    fn ehto(&self, tagi: Tagi) -> bool;
+    /// Koskeeko törmäystapahtuma törmääjää, jolla on annetut tägit
+    fn ehto(&self, tagit: Tagit) -> bool;
     /// Toiminta, joka tehdään ehdon toteutuessa.
     /// # Arguments
     /// * `tormays` - Törmäyksen tiedot
@@ -20,9 +21,9 @@ pub trait Tormaystoiminta {
 /// tapahtuman funktio.
 pub struct YleinenTormays<'a> {
     /// Törmääjän tägit
-    omat_tagit: Vec<Tagi>,
+    omat_tagit: Tagit,
     /// Törmätyn tägit
-    kohteen_tagit: Vec<Tagi>,
+    kohteen_tagit: Tagit,
     /// Tapahtuma, jota kutsutaan, jos ehdot toteutuvat
     tapahtuma: &'a Fn(&Tormaystieto, &mut Perusmaailma, &Paivitysaika),
 }
@@ -39,17 +40,18 @@ impl<'a> YleinenTormays<'a> {
         tapahtuma: &'a Fn(&Tormaystieto, &mut Perusmaailma, &Paivitysaika),
     ) -> Self {
         YleinenTormays {
-            omat_tagit: omat_tagit,
-            kohteen_tagit: kohteiden_tagit,
+            omat_tagit: omat_tagit.into_iter().collect(),
+            kohteen_tagit: kohteiden_tagit.into_iter().collect(),
             tapahtuma,
         }
     }
 }
 
 impl<'a> Tormaystoiminta for YleinenTormays<'a> {
-    /// Koskeeko törmäystapahtuma annettau tägiä
-    fn ehto(&self, tagi: Tagi) -> bool {
-        self.omat_tagit.contains(&tagi)
+    /// Koskeeko törmäystapahtuma törmääjää, jolla on annetut tägit.
+    /// Riittää, että yksikin törmääjän tägeistä on haluttujen joukossa.
+    fn ehto(&self, tagit: Tagit) -> bool {
+        self.omat_tagit.leikkaa(tagit)
     }
 
     /// Toiminta, joka tehdään ehdon toteutuessa. Tarkistaa vielä onko törmäyksen kohde
@@ -60,13 +62,7 @@ impl<'a> Tormaystoiminta for YleinenTormays<'a> {
     /// * `paivitysaika` - Päivitysaika
     fn toiminta(&self, tormays: &Tormaystieto, maailma: &mut Perusmaailma, paivitysaika: &Paivitysaika) {
         // Katstaan onko mikään törmätyn kohteen tageista haluttujen joukossa
-        if self
-            .kohteen_tagit
-            .iter()
-            .skip_while(|x| !tormays.anna_tagit().contains(x))
-            .next()
-            .is_some()
-        {
+        if self.kohteen_tagit.leikkaa(tormays.anna_tagit()) {
             (self.tapahtuma)(tormays, maailma, paivitysaika);
         }
     }